@@ -1,250 +1,373 @@
-// ═══════════════════════════════════════════════════════════
-// Zephyr Abstract Syntax Tree
-// ═══════════════════════════════════════════════════════════
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum Type {
-    Int,
-    Float,
-    Bool,
-    StringT,
-    Nil,
-    Option(Box<Type>),
-    Result(Box<Type>, Box<Type>),
-    List(Box<Type>),
-    Map(Box<Type>, Box<Type>),
-    Tuple(Vec<Type>),
-    Named(String),
-    Generic(String, Vec<Type>),
-    Function(Vec<Type>, Box<Type>),
-    Inferred,
-}
-
-#[derive(Debug, Clone)]
-pub enum Expr {
-    // Literals
-    Int(i64),
-    Float(f64),
-    Bool(bool),
-    StringLit(String),
-    Nil,
-
-    // Interpolated string: parts alternate text and expr
-    InterpolatedString(Vec<StringPart>),
-
-    // Variable
-    Var(String),
-
-    // Tuple
-    Tuple(Vec<Expr>),
-
-    // List literal
-    List(Vec<Expr>),
-
-    // Map literal
-    MapLit(Vec<(Expr, Expr)>),
-
-    // Block expression
-    Block(Vec<Stmt>, Option<Box<Expr>>),
-
-    // Binary operations
-    BinOp(Box<Expr>, BinOp, Box<Expr>),
-
-    // Unary operations
-    UnaryOp(UnaryOp, Box<Expr>),
-
-    // Function call
-    Call(Box<Expr>, Vec<Expr>),
-
-    // Method call: obj.method(args)
-    MethodCall(Box<Expr>, String, Vec<Expr>),
-
-    // Field access: obj.field
-    FieldAccess(Box<Expr>, String),
-
-    // Index: obj[idx]
-    Index(Box<Expr>, Box<Expr>),
-
-    // If expression
-    If(Box<Expr>, Box<Expr>, Vec<(Expr, Expr)>, Option<Box<Expr>>),
-
-    // Match expression
-    Match(Box<Expr>, Vec<MatchArm>),
-
-    // Closure: |args| => expr  or  |args| { body }
-    Closure(Vec<(String, Option<Type>)>, Box<Expr>),
-
-    // Struct creation: MyStruct { field: val, ... }
-    StructCreate(String, Vec<(String, Expr)>),
-
-    // Enum variant: MyEnum::Variant(args)
-    EnumVariant(String, String, Vec<Expr>),
-
-    // Range: start..end
-    Range(Box<Expr>, Box<Expr>),
-
-    // Option: Some(x) / nil
-    Some(Box<Expr>),
-
-    // Result: Ok(x) / Err(x)
-    Ok(Box<Expr>),
-    Err(Box<Expr>),
-
-    // Question mark unwrap operator (like Rust's ?)
-    Question(Box<Expr>),
-
-    // box expr — heap allocation hint
-    BoxExpr(Box<Expr>),
-
-    // ref expr — reference
-    RefExpr(Box<Expr>),
-
-    // Assignment expression (for var reassignment)
-    Assign(Box<Expr>, Box<Expr>),
-
-    // Await (future use)
-    Await(Box<Expr>),
-}
-
-#[derive(Debug, Clone)]
-pub enum StringPart {
-    Literal(String),
-    Interpolated(Expr),
-}
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum BinOp {
-    Add, Sub, Mul, Div, Mod,
-    Eq, NotEq, Lt, LtEq, Gt, GtEq,
-    And, Or,
-    DotDot, // range
-}
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum UnaryOp {
-    Neg,
-    Not,
-}
-
-#[derive(Debug, Clone)]
-pub struct MatchArm {
-    pub pattern: Pattern,
-    pub guard: Option<Expr>,
-    pub body: Expr,
-}
-
-#[derive(Debug, Clone)]
-pub enum Pattern {
-    Wildcard,
-    Ident(String),
-    Int(i64),
-    Float(f64),
-    Bool(bool),
-    StringLit(String),
-    Nil,
-    Tuple(Vec<Pattern>),
-    List(Vec<Pattern>),
-    Struct(String, Vec<(String, Pattern)>),
-    EnumVariant(String, String, Vec<Pattern>),
-    Some(Box<Pattern>),
-    Ok(Box<Pattern>),
-    Err(Box<Pattern>),
-    Or(Box<Pattern>, Box<Pattern>),
-    Range(Box<Pattern>, Box<Pattern>),
-}
-
-#[derive(Debug, Clone)]
-pub enum Stmt {
-    // let x: Type = expr
-    Let(String, Option<Type>, Expr, bool), // (name, type, value, is_mutable)
-
-    // Expression statement
-    Expr(Expr),
-
-    // return expr
-    Return(Option<Expr>),
-
-    // break / continue
-    Break,
-    Continue,
-
-    // while cond { body }
-    While(Expr, Vec<Stmt>),
-
-    // for x in iter { body }
-    For(String, Expr, Vec<Stmt>),
-
-    // Function definition
-    FunDef(FunDef),
-
-    // Struct definition
-    StructDef(StructDef),
-
-    // Enum definition
-    EnumDef(EnumDef),
-
-    // impl block
-    ImplBlock(ImplBlock),
-
-    // Module
-    ModDef(String, Vec<Stmt>),
-
-    // Import
-    Import(Vec<String>),
-
-    // Type alias
-    TypeAlias(String, Vec<String>, Type),
-}
-
-#[derive(Debug, Clone)]
-pub struct FunDef {
-    pub name: String,
-    pub generics: Vec<String>,
-    pub params: Vec<Param>,
-    pub return_type: Option<Type>,
-    pub body: Vec<Stmt>,
-    pub is_pub: bool,
-}
-
-#[derive(Debug, Clone)]
-pub struct Param {
-    pub name: String,
-    pub ty: Option<Type>,
-    pub default: Option<Expr>,
-}
-
-#[derive(Debug, Clone)]
-pub struct StructDef {
-    pub name: String,
-    pub generics: Vec<String>,
-    pub fields: Vec<StructField>,
-    pub is_pub: bool,
-}
-
-#[derive(Debug, Clone)]
-pub struct StructField {
-    pub name: String,
-    pub ty: Type,
-    pub is_pub: bool,
-}
-
-#[derive(Debug, Clone)]
-pub struct EnumDef {
-    pub name: String,
-    pub generics: Vec<String>,
-    pub variants: Vec<EnumVariant>,
-    pub is_pub: bool,
-}
-
-#[derive(Debug, Clone)]
-pub struct EnumVariant {
-    pub name: String,
-    pub fields: Vec<Type>,
-}
-
-#[derive(Debug, Clone)]
-pub struct ImplBlock {
-    pub target: String,
-    pub generics: Vec<String>,
-    pub methods: Vec<FunDef>,
-}
\ No newline at end of file
+// ═══════════════════════════════════════════════════════════
+// Zephyr Abstract Syntax Tree
+// ═══════════════════════════════════════════════════════════
+
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    StringT,
+    Nil,
+    Option(Box<Type>),
+    Result(Box<Type>, Box<Type>),
+    List(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    Tuple(Vec<Type>),
+    Named(String),
+    Generic(String, Vec<Type>),
+    Function(Vec<Type>, Box<Type>),
+    Inferred,
+}
+
+/// An expression together with the source position it starts at (for
+/// operators, the position of the operator token).
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
+pub enum ExprKind {
+    // Literals
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    StringLit(String),
+    Nil,
+
+    // Interpolated string: parts alternate text and expr
+    InterpolatedString(Vec<StringPart>),
+
+    // Variable, and where the resolver found it
+    Var(String, VarRes),
+
+    // Tuple
+    Tuple(Vec<Expr>),
+
+    // List literal
+    List(Vec<Expr>),
+
+    // Map literal
+    MapLit(Vec<(Expr, Expr)>),
+    // Set literal: #{a, b}
+    SetLit(Vec<Expr>),
+
+    // Block expression
+    Block(Vec<Stmt>, Option<Box<Expr>>, Scope),
+
+    // Binary operations
+    BinOp(Box<Expr>, BinOp, Box<Expr>),
+
+    // Unary operations
+    UnaryOp(UnaryOp, Box<Expr>),
+
+    // Function call
+    Call(Box<Expr>, Vec<Expr>),
+
+    // Method call: obj.method(args)
+    MethodCall(Box<Expr>, String, Vec<Expr>),
+
+    // Field access: obj.field
+    FieldAccess(Box<Expr>, String),
+
+    // Index: obj[idx]
+    Index(Box<Expr>, Box<Expr>),
+
+    // If expression
+    If(Box<Expr>, Box<Expr>, Vec<(Expr, Expr)>, Option<Box<Expr>>),
+
+    // Match expression
+    Match(Box<Expr>, Vec<MatchArm>),
+
+    // Closure: |args| => expr  or  |args| { body }
+    Closure(Vec<(String, Option<Type>)>, Box<Expr>, Scope),
+
+    // Struct creation: MyStruct { field: val, ... }
+    StructCreate(String, Vec<(String, Expr)>),
+
+    // Enum variant: MyEnum::Variant(args)
+    EnumVariant(String, String, Vec<Expr>),
+
+    // Qualified path: geometry::area, outer::inner::f, geometry::Shape::Circle
+    Path(Vec<String>),
+
+    // Range: start..end
+    Range(Box<Expr>, Box<Expr>),
+
+    // Option: Some(x) / nil
+    Some(Box<Expr>),
+
+    // Result: Ok(x) / Err(x)
+    Ok(Box<Expr>),
+    Err(Box<Expr>),
+
+    // Question mark unwrap operator (like Rust's ?)
+    Question(Box<Expr>),
+
+    // box expr — heap allocation hint
+    BoxExpr(Box<Expr>),
+
+    // ref expr — reference
+    RefExpr(Box<Expr>),
+
+    // Assignment expression (for var reassignment)
+    Assign(Box<Expr>, Box<Expr>),
+
+    // Await (future use)
+    Await(Box<Expr>),
+}
+
+/// Where a variable lives, as worked out by `resolver.rs`. Code that was
+/// never resolved (bytecode, default arguments) stays `Dynamic`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum VarRes {
+    /// look the name up scope by scope
+    #[default]
+    Dynamic,
+    /// slot `slot` of the scope `depth` levels out; a slot that is not
+    /// defined yet falls back to the lookup by name
+    Slot { depth: u32, slot: u32 },
+}
+
+/// The scope a block, loop body, match arm or function body runs in.
+#[derive(Debug, Clone, Default)]
+pub enum Scope {
+    /// a fresh scope whose names are bound as they are defined
+    #[default]
+    Dynamic,
+    /// declares nothing, so it runs in the enclosing scope
+    Inline,
+    /// a scope holding these names in slots; the flag is set when a closure
+    /// or function made inside may keep it, so a loop needs a fresh one
+    /// every iteration
+    Slots(Arc<[String]>, bool),
+}
+
+#[derive(Debug, Clone)]
+pub enum StringPart {
+    Literal(String),
+    /// `#{expr}` or `#{expr:spec}`; see `format.rs` for the spec language
+    Interpolated(Expr, Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinOp {
+    Add, Sub, Mul, Div, Mod,
+    Eq, NotEq, Lt, LtEq, Gt, GtEq,
+    And, Or,
+    DotDot, // range
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+    pub scope: Scope,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Ident(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    StringLit(String),
+    Nil,
+    Tuple(Vec<Pattern>),
+    List(Vec<Pattern>),
+    /// `..` or `..name` inside a list pattern: whatever the other elements leave over
+    Rest(Option<String>),
+    /// `name @ pattern`: match the pattern and bind the whole value
+    Bind(String, Box<Pattern>),
+    /// `Name { field: pattern, .. }`; the flag is set when `..` ignores the remaining fields
+    Struct(String, Vec<(String, Pattern)>, bool),
+    EnumVariant(String, String, Vec<Pattern>),
+    Some(Box<Pattern>),
+    Ok(Box<Pattern>),
+    Err(Box<Pattern>),
+    Or(Box<Pattern>, Box<Pattern>),
+    Range(Box<Pattern>, Box<Pattern>),
+}
+
+/// Patterns print as source, for error messages.
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |ps: &[Pattern]| ps.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ");
+        match self {
+            Pattern::Wildcard      => write!(f, "_"),
+            Pattern::Ident(n)      => write!(f, "{}", n),
+            Pattern::Int(n)        => write!(f, "{}", n),
+            Pattern::Float(x)      => write!(f, "{}", x),
+            Pattern::Bool(b)       => write!(f, "{}", b),
+            Pattern::StringLit(s)  => write!(f, "{:?}", s),
+            Pattern::Nil           => write!(f, "nil"),
+            Pattern::Tuple(ps)     => write!(f, "({})", list(ps)),
+            Pattern::List(ps)      => write!(f, "[{}]", list(ps)),
+            Pattern::Rest(None)    => write!(f, ".."),
+            Pattern::Rest(Some(n)) => write!(f, "..{}", n),
+            Pattern::Bind(n, p)    => write!(f, "{} @ {}", n, p),
+            Pattern::Struct(name, fields, rest) => {
+                let mut fields: Vec<String> = fields.iter()
+                    .map(|(n, p)| match p {
+                        Pattern::Ident(b) if b == n => n.clone(),
+                        _ => format!("{}: {}", n, p),
+                    })
+                    .collect();
+                if *rest { fields.push("..".into()); }
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            }
+            Pattern::EnumVariant(e, v, ps) if ps.is_empty() => write!(f, "{}::{}", e, v),
+            Pattern::EnumVariant(e, v, ps) => write!(f, "{}::{}({})", e, v, list(ps)),
+            Pattern::Some(p)       => write!(f, "Some({})", p),
+            Pattern::Ok(p)         => write!(f, "Ok({})", p),
+            Pattern::Err(p)        => write!(f, "Err({})", p),
+            Pattern::Or(a, b)      => write!(f, "{} | {}", a, b),
+            Pattern::Range(a, b)   => write!(f, "{}..{}", a, b),
+        }
+    }
+}
+
+pub use crate::lexer::Span;
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Stmt { kind, span }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
+    // let x: Type = expr
+    Let(Pattern, Option<Type>, Expr, bool), // (pattern, type, value, is_mutable)
+
+    // Expression statement
+    Expr(Expr),
+
+    // return expr
+    Return(Option<Expr>),
+
+    // break / continue
+    Break,
+    Continue,
+
+    // while cond { body }
+    While(Expr, Vec<Stmt>, Scope),
+
+    // for pattern in iter { body }
+    For(Pattern, Expr, Vec<Stmt>, Scope),
+
+    // Function definition
+    FunDef(FunDef),
+
+    // Struct definition
+    StructDef(StructDef),
+
+    // Enum definition
+    EnumDef(EnumDef),
+
+    // trait declaration
+    TraitDef(TraitDef),
+
+    // impl block, inherent or `impl Trait for Type`
+    ImplBlock(ImplBlock),
+
+    // Module
+    ModDef(String, Vec<Stmt>, bool), // (name, body, is_pub)
+
+    // Import
+    Import(Vec<String>),
+
+    // Type alias
+    TypeAlias(String, Vec<String>, Type),
+}
+
+#[derive(Debug, Clone)]
+pub struct FunDef {
+    pub name: String,
+    pub generics: Vec<String>,
+    pub params: Vec<Param>,
+    pub return_type: Option<Type>,
+    pub body: Vec<Stmt>,
+    pub is_pub: bool,
+    // the parameters come first
+    pub scope: Scope,
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub ty: Option<Type>,
+    pub default: Option<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructDef {
+    pub name: String,
+    pub generics: Vec<String>,
+    pub fields: Vec<StructField>,
+    pub is_pub: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructField {
+    pub name: String,
+    pub ty: Type,
+    pub is_pub: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDef {
+    pub name: String,
+    pub generics: Vec<String>,
+    pub variants: Vec<EnumVariant>,
+    pub is_pub: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Vec<Type>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImplBlock {
+    pub trait_name: Option<String>,
+    pub target: String,
+    pub generics: Vec<String>,
+    pub methods: Vec<FunDef>,
+}
+
+/// Methods without a body are required; the rest are defaults that an
+/// impl may override.
+#[derive(Debug, Clone)]
+pub struct TraitDef {
+    pub name: String,
+    pub required: Vec<FunDef>,
+    pub defaults: Vec<FunDef>,
+    pub is_pub: bool,
+}
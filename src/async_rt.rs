@@ -8,8 +8,11 @@
 //  CREATING TASKS
 //  async_spawn(thunk)
 //      Fun -> Task
-//      Spawns a zero-argument closure as an async task.
-//      Returns a Task handle that can be awaited.
+//      Runs a zero-argument closure on a worker thread, in a fresh
+//      interpreter. Captured values and the globals it uses are copied
+//      over; refs, channels, tasks, modules and values that contain
+//      themselves cannot be.
+//      Returns a Task handle; awaiting it gives Ok(value) or Err(message).
 //
 //  AWAITING RESULTS
 //  async_await(task)
@@ -63,8 +66,14 @@ use std::thread;
 use std::time::Duration;
 use std::collections::VecDeque;
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::rc::Rc;
-use crate::interpreter::Value;
+use crate::ast::{EnumDef, Param, Scope, Span, Stmt, StmtKind, StructDef};
+use crate::bytecode::{Decoder, Encoder};
+use crate::compiler::{Constant, Op, Proto};
+use crate::interpreter::{Env, EnvInner, Interpreter, MapKey, Signal, Value, ZephyrFn};
+use crate::vm::Closure;
 
 // ── Task handle ───────────────────────────────────────────────────────────────
 
@@ -77,7 +86,7 @@ enum TaskState {
 }
 
 #[derive(Debug, Clone)]
-enum TaskResult {
+pub enum TaskResult {
     Ok(SerializableValue),
     Err(String),
 }
@@ -88,48 +97,65 @@ enum TaskResult {
 #[derive(Debug, Clone)]
 pub enum SerializableValue {
     Int(i64),
+    BigInt(num_bigint::BigInt),
     Float(f64),
     Bool(bool),
     Str(String),
     Nil,
     List(Vec<SerializableValue>),
-    Map(Vec<(String, SerializableValue)>),
+    Map(Vec<(MapKey, SerializableValue)>),
+    Set(Vec<MapKey>),
+    Regex(String),
+    DateTime(chrono::DateTime<chrono::FixedOffset>),
+    Duration(chrono::TimeDelta),
     Tuple(Vec<SerializableValue>),
     Ok(Box<SerializableValue>),
     Err(String),
     Option(Option<Box<SerializableValue>>),
+    Struct(String, Vec<(String, SerializableValue)>),
+    Enum(String, String, Vec<SerializableValue>),
 }
 
 pub fn value_to_serial(v: &Value) -> SerializableValue {
     match v {
         Value::Int(n)    => SerializableValue::Int(*n),
+        Value::BigInt(n) => SerializableValue::BigInt((**n).clone()),
         Value::Float(f)  => SerializableValue::Float(*f),
         Value::Bool(b)   => SerializableValue::Bool(*b),
-        Value::Str(s)    => SerializableValue::Str(s.clone()),
+        Value::Str(s)    => SerializableValue::Str((**s).clone()),
         Value::Nil       => SerializableValue::Nil,
         Value::Tuple(vs) => SerializableValue::Tuple(vs.iter().map(value_to_serial).collect()),
         Value::List(v)   => SerializableValue::List(v.borrow().iter().map(value_to_serial).collect()),
         Value::Map(m)    => SerializableValue::Map(
             m.borrow().iter().map(|(k, v)| (k.clone(), value_to_serial(v))).collect()
         ),
+        Value::Set(s)    => SerializableValue::Set(s.borrow().iter().cloned().collect()),
         Value::Result(std::result::Result::Ok(v))  => SerializableValue::Ok(Box::new(value_to_serial(v))),
         Value::Result(std::result::Result::Err(e)) => SerializableValue::Err(format!("{}", e)),
         Value::Option(Some(v)) => SerializableValue::Option(Some(Box::new(value_to_serial(v)))),
         Value::Option(None)    => SerializableValue::Option(None),
         // Functions and Refs can't be serialized — return Nil
         Value::Function(_) => SerializableValue::Str("<function>".into()),
+        Value::Module(m)   => SerializableValue::Str(format!("<module {}>", m.name)),
         Value::Ref(r)      => value_to_serial(&r.borrow()),
+        Value::Iter(_)     => SerializableValue::Str("<iter>".into()),
+        Value::Regex(re)   => SerializableValue::Regex(re.as_str().to_string()),
+        Value::DateTime(dt) => SerializableValue::DateTime(*dt),
+        Value::Duration(d) => SerializableValue::Duration(*d),
+        Value::Range(start, end, step) => SerializableValue::List(
+            (0..crate::iter::range_len(*start, *end, *step)).map(|i| SerializableValue::Int(start + i * step)).collect()
+        ),
         Value::Struct(name, fields) => {
-            let mut map: Vec<(String, SerializableValue)> = fields.borrow().iter()
-                .map(|(k, v)| (k.clone(), value_to_serial(v)))
+            let mut map: Vec<(MapKey, SerializableValue)> = fields.borrow().iter()
+                .map(|(k, v)| (k.as_str().into(), value_to_serial(v)))
                 .collect();
-            map.push(("__type".to_string(), SerializableValue::Str(name.clone())));
+            map.push(("__type".into(), SerializableValue::Str(name.clone())));
             SerializableValue::Map(map)
         }
         Value::Enum(_, variant, fields) => {
             let map = vec![
-                ("__variant".to_string(), SerializableValue::Str(variant.clone())),
-                ("fields".to_string(), SerializableValue::List(fields.iter().map(value_to_serial).collect())),
+                ("__variant".into(), SerializableValue::Str(variant.clone())),
+                ("fields".into(), SerializableValue::List(fields.iter().map(value_to_serial).collect())),
             ];
             SerializableValue::Map(map)
         }
@@ -139,26 +165,96 @@ pub fn value_to_serial(v: &Value) -> SerializableValue {
 pub fn serial_to_value(s: SerializableValue) -> Value {
     match s {
         SerializableValue::Int(n)    => Value::Int(n),
+        SerializableValue::BigInt(n) => Value::BigInt(Rc::new(n)),
         SerializableValue::Float(f)  => Value::Float(f),
         SerializableValue::Bool(b)   => Value::Bool(b),
-        SerializableValue::Str(s)    => Value::Str(s),
+        SerializableValue::Str(s)    => Value::str(s),
         SerializableValue::Nil       => Value::Nil,
         SerializableValue::Tuple(vs) => Value::Tuple(vs.into_iter().map(serial_to_value).collect()),
-        SerializableValue::List(vs)  => Value::List(Rc::new(RefCell::new(
+        SerializableValue::List(vs)  => Value::list(
             vs.into_iter().map(serial_to_value).collect()
-        ))),
+        ),
         SerializableValue::Map(pairs) => {
-            let mut map = std::collections::HashMap::new();
+            let mut map = indexmap::IndexMap::new();
             for (k, v) in pairs {
                 map.insert(k, serial_to_value(v));
             }
-            Value::Map(Rc::new(RefCell::new(map)))
+            Value::map(map)
         }
+        // the pattern compiled once already, so it compiles again
+        SerializableValue::Regex(pat) => match regex::Regex::new(&pat) {
+            Ok(re) => Value::Regex(Rc::new(re)),
+            Err(e) => Value::str(e.to_string()),
+        },
+        SerializableValue::DateTime(dt) => Value::DateTime(dt),
+        SerializableValue::Duration(d) => Value::Duration(d),
+        SerializableValue::Set(keys) => Value::Set(Rc::new(RefCell::new(keys.into_iter().collect()))),
         SerializableValue::Ok(v)    => Value::Result(std::result::Result::Ok(Box::new(serial_to_value(*v)))),
-        SerializableValue::Err(e)   => Value::Result(std::result::Result::Err(Box::new(Value::Str(e)))),
+        SerializableValue::Err(e)   => Value::Result(std::result::Result::Err(Box::new(Value::str(e)))),
         SerializableValue::Option(Some(v)) => Value::Option(Some(Box::new(serial_to_value(*v)))),
         SerializableValue::Option(None)    => Value::Option(None),
+        SerializableValue::Struct(name, fields) => Value::structure(name,
+            fields.into_iter().map(|(k, v)| (k, serial_to_value(v))).collect()
+        ),
+        SerializableValue::Enum(name, variant, fields) => {
+            Value::Enum(name, variant, fields.into_iter().map(serial_to_value).collect())
+        }
+    }
+}
+
+/// Like `value_to_serial`, but keeps structs and enums intact and refuses
+/// what only makes sense on the current thread.
+fn to_sendable(v: &Value) -> Result<SerializableValue, String> {
+    sendable(v, &mut Vec::new())
+}
+
+// `open` holds the lists, maps and structs being copied, to catch a value
+// that contains itself before the copy recurses forever.
+fn sendable(v: &Value, open: &mut Vec<*const ()>) -> Result<SerializableValue, String> {
+    let ptr = match v {
+        Value::List(l) => Some(Rc::as_ptr(l) as *const ()),
+        Value::Map(m) => Some(Rc::as_ptr(m) as *const ()),
+        Value::Struct(_, f) => Some(Rc::as_ptr(f) as *const ()),
+        _ => None,
+    };
+    if let Some(ptr) = ptr {
+        if open.contains(&ptr) {
+            return Err("a value that contains itself cannot be sent to another thread".into());
+        }
+        open.push(ptr);
     }
+    let result = sendable_parts(v, open);
+    if ptr.is_some() { open.pop(); }
+    result
+}
+
+fn sendable_parts(v: &Value, open: &mut Vec<*const ()>) -> Result<SerializableValue, String> {
+    let mut all = |vs: &[Value]| vs.iter().map(|v| sendable(v, open)).collect::<Result<Vec<_>, _>>();
+    Ok(match v {
+        Value::Ref(_)      => return Err("a ref cannot be shared with another thread".into()),
+        Value::Module(m)   => return Err(format!("module '{}' cannot be sent to another thread", m.name)),
+        Value::Function(_) => return Err("functions inside values cannot be sent to another thread".into()),
+        Value::Iter(_)     => return Err("an iterator cannot be sent to another thread; collect() it first".into()),
+        Value::Map(_) if get_channel_id(v).is_some() => {
+            return Err("a channel belongs to the thread that created it".into())
+        }
+        Value::Map(_) if get_task_id(v).is_some() => {
+            return Err("a task can only be awaited by the thread that spawned it".into())
+        }
+        Value::Tuple(vs) => SerializableValue::Tuple(all(vs)?),
+        Value::List(vs)  => SerializableValue::List(all(&vs.borrow())?),
+        Value::Enum(name, variant, fields) => SerializableValue::Enum(name.clone(), variant.clone(), all(fields)?),
+        Value::Map(m) => SerializableValue::Map(
+            m.borrow().iter().map(|(k, v)| Ok((k.clone(), sendable(v, open)?))).collect::<Result<_, String>>()?
+        ),
+        Value::Result(std::result::Result::Ok(v)) => SerializableValue::Ok(Box::new(sendable(v, open)?)),
+        Value::Option(Some(v)) => SerializableValue::Option(Some(Box::new(sendable(v, open)?))),
+        Value::Struct(name, fields) => SerializableValue::Struct(
+            name.clone(),
+            fields.borrow().iter().map(|(k, v)| Ok((k.clone(), sendable(v, open)?))).collect::<Result<_, String>>()?,
+        ),
+        other => value_to_serial(other),
+    })
 }
 
 /// Thread-safe task handle.
@@ -177,13 +273,6 @@ impl Task {
         matches!(*self.state.lock().unwrap(), TaskState::Done(_))
     }
 
-    pub fn result(&self) -> Option<TaskResult> {
-        match &*self.state.lock().unwrap() {
-            TaskState::Done(r) => Some(r.clone()),
-            _ => None,
-        }
-    }
-
     /// Block until this task completes and return its value.
     pub fn join(&self) -> TaskResult {
         loop {
@@ -248,10 +337,10 @@ pub fn task_to_value(task: Task) -> Value {
     TASK_REGISTRY.with(|reg| {
         reg.borrow_mut().insert(id, task);
     });
-    let mut map = std::collections::HashMap::new();
-    map.insert("__task_id".to_string(), Value::Int(id as i64));
-    map.insert("__is_task".to_string(), Value::Bool(true));
-    Value::Map(Rc::new(RefCell::new(map)))
+    let mut map = indexmap::IndexMap::new();
+    map.insert("__task_id".into(), Value::Int(id as i64));
+    map.insert("__is_task".into(), Value::Bool(true));
+    Value::map(map)
 }
 
 // Thread-local task registry (tasks are created and joined on the same thread)
@@ -262,8 +351,8 @@ thread_local! {
 fn get_task_id(val: &Value) -> Option<u64> {
     if let Value::Map(m) = val {
         let map = m.borrow();
-        if let Some(Value::Bool(true)) = map.get("__is_task") {
-            if let Some(Value::Int(id)) = map.get("__task_id") {
+        if let Some(Value::Bool(true)) = map.get(&MapKey::from("__is_task")) {
+            if let Some(Value::Int(id)) = map.get(&MapKey::from("__task_id")) {
                 return Some(*id as u64);
             }
         }
@@ -304,7 +393,7 @@ fn join_task_val(val: &Value) -> Result<Value, String> {
             // TaskResult::Err means the task closure itself returned Err (a
             // Rust-level failure, e.g. couldn't spawn the process). Wrap this
             // in a Zephyr Err so scripts can match on it.
-            TaskResult::Err(e) => Ok(Value::Result(std::result::Result::Err(Box::new(Value::Str(e))))),
+            TaskResult::Err(e) => Ok(Value::Result(std::result::Result::Err(Box::new(Value::str(e))))),
         }
     })
 }
@@ -319,8 +408,8 @@ fn join_task_timeout(val: &Value, timeout_ms: u64) -> Result<Value, String> {
         match task.join_timeout(timeout_ms) {
             // Same fix as join_task_val: deserialize directly, no extra wrapping.
             Some(TaskResult::Ok(v))  => Ok(serial_to_value(v)),
-            Some(TaskResult::Err(e)) => Ok(Value::Result(std::result::Result::Err(Box::new(Value::Str(e))))),
-            None => Ok(Value::Result(std::result::Result::Err(Box::new(Value::Str("timeout".to_string()))))),
+            Some(TaskResult::Err(e)) => Ok(Value::Result(std::result::Result::Err(Box::new(Value::str(e))))),
+            None => Ok(Value::Result(std::result::Result::Err(Box::new(Value::str("timeout".to_string()))))),
         }
     })
 }
@@ -379,27 +468,27 @@ fn new_channel_id() -> u64 {
 }
 
 fn channel_to_value(id: u64) -> Value {
-    let mut map = std::collections::HashMap::new();
-    map.insert("__channel_id".to_string(), Value::Int(id as i64));
-    map.insert("__is_channel".to_string(), Value::Bool(true));
+    let mut map = indexmap::IndexMap::new();
+    map.insert("__channel_id".into(), Value::Int(id as i64));
+    map.insert("__is_channel".into(), Value::Bool(true));
 
     let send_name = format!("channel_send_{}", id);
     let recv_name = format!("channel_recv_{}", id);
     let try_recv_name = format!("channel_try_recv_{}", id);
 
-    map.insert("send".to_string(), Value::Str(send_name));
-    map.insert("recv".to_string(), Value::Str(recv_name));
-    map.insert("try_recv".to_string(), Value::Str(try_recv_name));
-    map.insert("channel_id".to_string(), Value::Int(id as i64));
+    map.insert("send".into(), Value::str(send_name));
+    map.insert("recv".into(), Value::str(recv_name));
+    map.insert("try_recv".into(), Value::str(try_recv_name));
+    map.insert("channel_id".into(), Value::Int(id as i64));
 
-    Value::Map(Rc::new(RefCell::new(map)))
+    Value::map(map)
 }
 
 fn get_channel_id(val: &Value) -> Option<u64> {
     if let Value::Map(m) = val {
         let map = m.borrow();
-        if let Some(Value::Bool(true)) = map.get("__is_channel") {
-            if let Some(Value::Int(id)) = map.get("__channel_id") {
+        if let Some(Value::Bool(true)) = map.get(&MapKey::from("__is_channel")) {
+            if let Some(Value::Int(id)) = map.get(&MapKey::from("__channel_id")) {
                 return Some(*id as u64);
             }
         }
@@ -444,7 +533,7 @@ pub fn call_async(name: &str, args: Vec<Value>) -> Result<Value, String> {
         "async_join"     => async_await_all(args),   // alias
         "channel"        => make_channel(args, None),
         "channel_bounded"=> {
-            let cap = match args.get(0) {
+            let cap = match args.first() {
                 Some(Value::Int(n)) => Some(*n as usize),
                 _ => None,
             };
@@ -469,7 +558,7 @@ fn async_spawn(args: Vec<Value>) -> Result<Value, String> {
 
     match &args[0] {
         Value::Str(url) => {
-            let url = url.clone();
+            let url = (**url).clone();
             let task = spawn_task(move || {
                 match ureq::get(&url).call() {
                     Ok(resp) => {
@@ -481,13 +570,283 @@ fn async_spawn(args: Vec<Value>) -> Result<Value, String> {
             });
             Ok(task_to_value(task))
         }
-        Value::Function(_) => {
-            Err("async_spawn with user-defined functions: use async_spawn_http(), async_spawn_exec(), or channel-based patterns instead. User closures cannot be moved across OS thread boundaries. See examples/async_demo.zph.".into())
-        }
+        // functions need the interpreter and are spawned by spawn_function
         _ => Err("async_spawn() requires a function or URL string".into())
     }
 }
 
+// ═══════════════════════════════════════════════════════════
+// Spawning Zephyr functions
+// ═══════════════════════════════════════════════════════════
+//
+// Values are Rc-based, so a function cannot simply move to another
+// thread. Instead it is packed into a Job: its code (AST for tree-walker
+// functions, the encoded Proto for compiled ones), its captured values,
+// and every binding it names, copied recursively. Bindings are packed
+// per scope that defines them, so the worker rebuilds the same chain of
+// scopes and two functions that see different bindings under one name
+// keep seeing different values. The worker unpacks the Job into a fresh
+// Interpreter and calls the function there.
+
+/// A function in a form that can cross threads.
+enum PortableFn {
+    Native(String),
+    // `env` indexes Job::envs; None is the worker's builtins
+    Ast { name: Option<String>, params: Vec<Param>, body: Vec<Stmt>, scope: Scope, env: Option<usize>, file: Option<PathBuf> },
+    Compiled { proto: Vec<u8>, upvals: Vec<Portable>, file: Option<PathBuf> },
+}
+
+enum Portable {
+    Value(SerializableValue),
+    Fn(PortableFn),
+}
+
+/// A scope a sent function closes over, holding only the names it uses.
+/// Entry 0 is always the program's global scope.
+struct PortableEnv {
+    parent: Option<usize>,
+    vars: Vec<(String, Portable)>,
+}
+
+/// Everything a spawned function needs on the worker thread.
+struct Job {
+    entry: PortableFn,
+    envs: Vec<PortableEnv>,
+    structs: Vec<StructDef>,
+    enums: Vec<EnumDef>,
+    methods: Vec<(String, String, PortableFn)>,
+    file: Option<PathBuf>,
+}
+
+struct Packer<'a> {
+    interp: &'a Interpreter,
+    envs: Vec<PortableEnv>,
+    // scopes already packed, by address
+    env_ids: HashMap<*const RefCell<EnvInner>, usize>,
+    seen: HashSet<(usize, String)>,
+    depth: usize,
+}
+
+impl<'a> Packer<'a> {
+    fn new(interp: &'a Interpreter) -> Self {
+        let global = PortableEnv { parent: None, vars: Vec::new() };
+        let env_ids = HashMap::from([(Rc::as_ptr(&interp.global.0), 0)]);
+        Packer { interp, envs: vec![global], env_ids, seen: HashSet::new(), depth: 0 }
+    }
+
+    fn function(&mut self, f: &ZephyrFn) -> Result<PortableFn, String> {
+        self.depth += 1;
+        if self.depth > 64 {
+            return Err("functions that capture themselves cannot be sent to another thread".into());
+        }
+        let packed = match f {
+            ZephyrFn::Native(name) => PortableFn::Native(name.clone()),
+            ZephyrFn::UserDefined { name, params, body, scope, closure_env, file } => {
+                let mut names: Vec<String> = crate::compiler::mentioned_names(params, body).into_iter().collect();
+                names.sort();
+                for n in names {
+                    if let Some(owner) = owner_of(closure_env, &n) {
+                        if let Some(id) = self.env(&owner) { self.bind(id, &n, &owner)?; }
+                    }
+                }
+                PortableFn::Ast {
+                    name: name.clone(),
+                    params: params.clone(),
+                    body: (**body).clone(),
+                    scope: scope.clone(),
+                    env: self.env(closure_env),
+                    file: file.as_deref().cloned(),
+                }
+            }
+            ZephyrFn::Compiled(c) => {
+                let mut names = Vec::new();
+                global_names(&c.proto, &mut names);
+                let global = self.interp.global.clone();
+                for n in names {
+                    if global.get_local(&n).is_some() { self.bind(0, &n, &global)?; }
+                }
+                let upvals = c.upvals.iter().map(|u| self.value(&u.borrow())).collect::<Result<_, _>>()?;
+                let mut enc = Encoder::new();
+                enc.write_proto(&c.proto);
+                PortableFn::Compiled { proto: enc.finish(), upvals, file: c.file.as_deref().cloned() }
+            }
+        };
+        self.depth -= 1;
+        Ok(packed)
+    }
+
+    /// The packed form of `env` and the scopes around it. The builtins are
+    /// not packed: every interpreter has its own.
+    fn env(&mut self, env: &Env) -> Option<usize> {
+        if Rc::ptr_eq(&env.0, &self.interp.builtins.0) {
+            return None;
+        }
+        if let Some(&id) = self.env_ids.get(&Rc::as_ptr(&env.0)) {
+            return Some(id);
+        }
+        let parent = env.0.borrow().parent.clone();
+        let parent = parent.and_then(|p| self.env(&p));
+        self.envs.push(PortableEnv { parent, vars: Vec::new() });
+        self.env_ids.insert(Rc::as_ptr(&env.0), self.envs.len() - 1);
+        Some(self.envs.len() - 1)
+    }
+
+    /// Pack `name` as `owner` (packed as `id`) defines it.
+    fn bind(&mut self, id: usize, name: &str, owner: &Env) -> Result<(), String> {
+        if !self.seen.insert((id, name.to_string())) {
+            return Ok(());
+        }
+        let Some(v) = owner.get_local(name) else { return Ok(()) };
+        let packed = self.value(&v).map_err(|e| format!("cannot send '{}' to a task: {}", name, e))?;
+        self.envs[id].vars.push((name.to_string(), packed));
+        Ok(())
+    }
+
+    fn value(&mut self, v: &Value) -> Result<Portable, String> {
+        match v {
+            Value::Function(f) => self.function(f).map(Portable::Fn),
+            other => to_sendable(other).map(Portable::Value),
+        }
+    }
+}
+
+/// The scope in `env`'s chain that defines `name`.
+fn owner_of(env: &Env, name: &str) -> Option<Env> {
+    let mut env = env.clone();
+    loop {
+        if env.get_local(name).is_some() {
+            return Some(env);
+        }
+        let parent = env.0.borrow().parent.clone()?;
+        env = parent;
+    }
+}
+
+/// Names of the globals a compiled function and its nested functions use.
+fn global_names(proto: &Proto, out: &mut Vec<String>) {
+    for op in &proto.code {
+        // the head of a path such as `m::f` may name a module
+        let (Op::GetGlobal(k) | Op::SetGlobal(k) | Op::Path(k) | Op::CallPath(k, _) | Op::Variant(k, _)) = op else { continue };
+        match proto.consts.get(*k as usize) {
+            Some(Constant::Str(s)) => out.push(s.clone()),
+            Some(Constant::Names(n)) => out.push(n[0].clone()),
+            _ => {}
+        }
+    }
+    for c in &proto.consts {
+        if let Constant::Proto(p) = c { global_names(p, out); }
+    }
+}
+
+impl Job {
+    fn pack(interp: &Interpreter, f: &ZephyrFn) -> Result<Job, String> {
+        let mut packer = Packer::new(interp);
+        let entry = packer.function(f)?;
+        // methods are sent when they can be; one that cannot only fails
+        // if the task calls it
+        let mut methods = Vec::new();
+        for (type_name, table) in &interp.impl_methods {
+            for (method, func) in table {
+                if let Ok(m) = packer.function(func) {
+                    methods.push((type_name.clone(), method.clone(), m));
+                }
+                packer.depth = 0;
+            }
+        }
+        Ok(Job {
+            entry,
+            envs: packer.envs,
+            structs: interp.struct_defs.values().cloned().collect(),
+            enums: interp.enum_defs.values().cloned().collect(),
+            methods,
+            file: interp.current_file.as_deref().cloned(),
+        })
+    }
+
+    /// Rebuild the function in a new interpreter and call it.
+    fn run(self) -> Result<SerializableValue, String> {
+        let mut interp = Interpreter::new();
+        interp.current_file = self.file.map(Rc::new);
+        let global = interp.global.clone();
+        for def in self.structs {
+            let _ = interp.exec_stmt(&Stmt::new(StmtKind::StructDef(def), Span::default()), &global);
+        }
+        for def in self.enums {
+            let _ = interp.exec_stmt(&Stmt::new(StmtKind::EnumDef(def), Span::default()), &global);
+        }
+        // scopes come before the scopes inside them, so parents exist
+        let mut envs: Vec<Env> = Vec::with_capacity(self.envs.len());
+        for (i, e) in self.envs.iter().enumerate() {
+            let env = match (i, e.parent) {
+                (0, _) => global.clone(),
+                (_, Some(p)) => Env::child(&envs[p]),
+                (_, None) => Env::child(&interp.builtins),
+            };
+            envs.push(env);
+        }
+        for (env, packed) in envs.iter().zip(self.envs) {
+            for (name, value) in packed.vars {
+                env.define(&name, unpack(&interp, &envs, value)?);
+            }
+        }
+        for (type_name, method, func) in self.methods {
+            if let Value::Function(f) = unpack_fn(&interp, &envs, func)? {
+                interp.impl_methods.entry(type_name).or_default().insert(method, f);
+            }
+        }
+        let entry = unpack_fn(&interp, &envs, self.entry)?;
+        Ok(match interp.call_value(entry, vec![], &global) {
+            Ok(v) => match to_sendable(&v) {
+                Ok(v) => SerializableValue::Ok(Box::new(v)),
+                Err(e) => SerializableValue::Err(format!("task result cannot be returned: {}", e)),
+            },
+            Err(Signal::Error(e)) => SerializableValue::Err(e),
+            Err(Signal::Located(e)) => {
+                SerializableValue::Err(format!("{} (line {}, column {})", e.message, e.span.line, e.span.col))
+            }
+            Err(Signal::PropagateErr(v)) => SerializableValue::Err(format!("{}", v)),
+            Err(Signal::Return(v)) => SerializableValue::Ok(Box::new(value_to_serial(&v))),
+            Err(Signal::Break | Signal::Continue | Signal::TailCall(_)) => SerializableValue::Ok(Box::new(SerializableValue::Nil)),
+        })
+    }
+}
+
+fn unpack(interp: &Interpreter, envs: &[Env], p: Portable) -> Result<Value, String> {
+    match p {
+        Portable::Value(v) => Ok(serial_to_value(v)),
+        Portable::Fn(f) => unpack_fn(interp, envs, f),
+    }
+}
+
+fn unpack_fn(interp: &Interpreter, envs: &[Env], f: PortableFn) -> Result<Value, String> {
+    let f = match f {
+        PortableFn::Native(name) => ZephyrFn::Native(name),
+        PortableFn::Ast { name, params, body, scope, env, file } => ZephyrFn::UserDefined {
+            name,
+            params,
+            body: Rc::new(body),
+            scope,
+            closure_env: env.map_or_else(|| interp.builtins.clone(), |i| envs[i].clone()),
+            file: file.map(Rc::new),
+        },
+        PortableFn::Compiled { proto, upvals, file } => {
+            let proto = Decoder::new(&proto).read_proto().map_err(|e| e.to_string())?;
+            let upvals = upvals.into_iter()
+                .map(|u| unpack(interp, envs, u).map(|v| Rc::new(RefCell::new(v))))
+                .collect::<Result<_, _>>()?;
+            ZephyrFn::Compiled(crate::gc::track_closure(Rc::new(Closure { proto: Rc::new(proto), upvals, file: file.map(Rc::new) })))
+        }
+    };
+    Ok(Value::Function(f))
+}
+
+/// `async_spawn(fn)`: run a Zephyr function on a worker thread.
+pub fn spawn_function(interp: &Interpreter, f: &ZephyrFn) -> Result<Value, String> {
+    let job = Job::pack(interp, f)?;
+    let task = spawn_task(move || job.run());
+    Ok(task_to_value(task))
+}
+
 fn spawn_http_task(url: String) -> Task {
     spawn_task(move || {
         match ureq::get(&url).call() {
@@ -574,7 +933,7 @@ fn async_await_all(args: Vec<Value>) -> Result<Value, String> {
         results.push(join_task_val(task_val)?);
     }
 
-    Ok(Value::List(Rc::new(RefCell::new(results))))
+    Ok(Value::list(results))
 }
 
 fn async_await_any(args: Vec<Value>) -> Result<Value, String> {
@@ -604,7 +963,7 @@ fn async_await_any(args: Vec<Value>) -> Result<Value, String> {
 }
 
 fn async_sleep(args: Vec<Value>) -> Result<Value, String> {
-    let ms = match args.get(0) {
+    let ms = match args.first() {
         Some(Value::Int(n)) => *n as u64,
         Some(Value::Float(f)) => *f as u64,
         _ => return Err("async_sleep(ms) requires an integer millisecond count".into()),
@@ -637,7 +996,7 @@ fn async_map(args: Vec<Value>) -> Result<Value, String> {
     let mut tasks = Vec::new();
     for item in list {
         let task = match item {
-            Value::Str(url) => spawn_http_task(url),
+            Value::Str(url) => spawn_http_task(Rc::unwrap_or_clone(url)),
             Value::Int(n) => {
                 spawn_task(move || Ok(SerializableValue::Int(n)))
             }
@@ -648,7 +1007,7 @@ fn async_map(args: Vec<Value>) -> Result<Value, String> {
         };
         tasks.push(task_to_value(task));
     }
-    Ok(Value::List(Rc::new(RefCell::new(tasks))))
+    Ok(Value::list(tasks))
 }
 
 fn task_is_done(args: Vec<Value>) -> Result<Value, String> {
@@ -693,12 +1052,12 @@ fn channel_send(args: Vec<Value>) -> Result<Value, String> {
         Err(e) if e.contains("not found") => Err(e),
         // Capacity exceeded is a normal Zephyr Err the script can handle
         Ok(_)  => Ok(Value::Result(std::result::Result::Ok(Box::new(Value::Nil)))),
-        Err(e) => Ok(Value::Result(std::result::Result::Err(Box::new(Value::Str(e))))),
+        Err(e) => Ok(Value::Result(std::result::Result::Err(Box::new(Value::str(e))))),
     }
 }
 
 fn channel_recv(args: Vec<Value>) -> Result<Value, String> {
-    let id = get_channel_id(args.get(0).unwrap_or(&Value::Nil))
+    let id = get_channel_id(args.first().unwrap_or(&Value::Nil))
         .ok_or_else(|| "channel_recv: argument is not a Channel".to_string())?;
     let sv = CHANNEL_REGISTRY.with(|reg| {
         let reg = reg.borrow();
@@ -710,7 +1069,7 @@ fn channel_recv(args: Vec<Value>) -> Result<Value, String> {
 }
 
 fn channel_try_recv(args: Vec<Value>) -> Result<Value, String> {
-    let id = get_channel_id(args.get(0).unwrap_or(&Value::Nil))
+    let id = get_channel_id(args.first().unwrap_or(&Value::Nil))
         .ok_or_else(|| "channel_try_recv: argument is not a Channel".to_string())?;
     let maybe = CHANNEL_REGISTRY.with(|reg| {
         let reg = reg.borrow();
@@ -776,7 +1135,7 @@ pub fn call_async_http(name: &str, args: Vec<Value>) -> Result<Value, String> {
             Ok(task_to_value(spawn_exec_task(cmd)))
         }
         "async_sleep_task" => {
-            let ms = match args.get(0) {
+            let ms = match args.first() {
                 Some(Value::Int(n)) => *n as u64,
                 Some(Value::Float(f)) => *f as u64,
                 _ => return Err("async_sleep_task(ms) requires an integer".into()),
@@ -789,7 +1148,7 @@ pub fn call_async_http(name: &str, args: Vec<Value>) -> Result<Value, String> {
 
 fn require_str(args: &[Value], idx: usize, sig: &str) -> Result<String, String> {
     match args.get(idx) {
-        Some(Value::Str(s)) => Ok(s.clone()),
+        Some(Value::Str(s)) => Ok((**s).clone()),
         Some(other)         => Ok(format!("{}", other)),
         None                => Err(format!("{}: argument {} is required", sig, idx + 1)),
     }
@@ -26,8 +26,8 @@
 //
 // On startup (in main.rs, before CLI parsing), `extract_payload`
 // reads the running binary's own tail. If sentinel matches, it
-// slices out the bytecode bytes and returns the decoded AST.
-// The interpreter then runs it directly — no files, no temp dirs.
+// slices out the bytecode bytes and returns the decoded program.
+// The VM then runs it directly — no files, no temp dirs.
 //
 // OS-specific output filename
 // ───────────────────────────
@@ -39,9 +39,8 @@
 // ═══════════════════════════════════════════════════════════
 
 use std::fs;
-use std::io::{self, Read, Seek, SeekFrom, Write};
-use std::path::Path;
-use crate::ast::Stmt;
+use std::io::{self, Write};
+use crate::compiler::Proto;
 use crate::bytecode;
 
 // Magic sentinel — 8 ASCII bytes, unlikely to appear in normal binary data
@@ -131,9 +130,10 @@ fn find_payload_start(data: &[u8]) -> Option<usize> {
 // ── Extracting the payload at runtime ────────────────────────────────────
 
 /// Called at startup in main(). Reads the running binary's own bytes,
-/// checks for a payload, and if found decodes and returns the AST.
+/// checks for a payload, and if found decodes and returns the program along
+/// with the source it was compiled from.
 /// Returns None if this binary has no embedded program (normal Zephyr CLI).
-pub fn extract_payload() -> Option<Vec<Stmt>> {
+pub fn extract_payload() -> Option<(Proto, String)> {
     let self_path = std::env::current_exe().ok()?;
     let data = fs::read(&self_path).ok()?;
 
@@ -149,7 +149,7 @@ pub fn extract_payload() -> Option<Vec<Stmt>> {
     let bytecode_bytes = &data[payload_start..payload_end];
 
     match bytecode::decode(bytecode_bytes) {
-        Ok((stmts, _hash)) => Some(stmts),
+        Ok(decoded) => Some(decoded),
         Err(e) => {
             // Payload is present but corrupted — warn and fall through to CLI
             eprintln!("\x1b[33m[Zephyr warning]\x1b[0m Embedded payload is corrupt: {}", e);
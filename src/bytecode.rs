@@ -1,14 +1,31 @@
 // ═══════════════════════════════════════════════════════════
-// Zephyr Bytecode — AST serialization to .zphc files
+// Zephyr Bytecode — compiled programs in .zphc files
 // ═══════════════════════════════════════════════════════════
 //
 // .zphc file format:
 //
 //   [4 bytes]  magic: 0x5A504843  ("ZPHC")
-//   [2 bytes]  version: u16 little-endian  (current: 1)
+//   [2 bytes]  version: u16 little-endian  (current: 11)
 //   [8 bytes]  source hash: u64 (FNV-1a of original source)
-//   [4 bytes]  stmt count: u32
-//   [N bytes]  serialized statements (recursive binary encoding)
+//   [N bytes]  the original source, as a String — runtime errors quote
+//              their line from it, even when the .zph is not around
+//   [N bytes]  the program's top-level Proto (see compiler.rs)
+//
+// A Proto is:
+//
+//   Option<String>           name
+//   Vec<(String, bool)>      params (name, has default)
+//   u32                      local slot count
+//   Vec<(bool, u32)>         captures (from_local, index)
+//   Vec<Constant>            constant pool — tag byte, then the value;
+//                            nested functions are Proto constants, and
+//                            patterns and interpreter-run statements
+//                            use the AST encoding below
+//   Vec<Op>                  code — opcode byte, then 0–2 u32 operands
+//   Vec<Span>                line table — (line, col) as two u32s per op
+//
+// Every AST node (Expr and Stmt) is written as its span followed by the
+// tag byte and fields, so constants carry positions too.
 //
 // All multi-byte integers are little-endian.
 // Strings are: [4-byte length][UTF-8 bytes]
@@ -18,13 +35,15 @@
 //
 // ═══════════════════════════════════════════════════════════
 
-use std::io::{self, Read, Write};
+use std::io;
+use std::rc::Rc;
 use crate::ast::*;
+use crate::compiler::{Binding, Capture, Constant, Op, Proto};
 
 // ── Constants ─────────────────────────────────────────────────────────────────
 
 const MAGIC: u32 = 0x5A504843; // "ZPHC"
-const VERSION: u16 = 1;
+const VERSION: u16 = 11;
 
 // ── Tag bytes for each AST variant ───────────────────────────────────────────
 // Expr tags
@@ -59,6 +78,8 @@ const TAG_EXPR_BOX: u8          = 0x1C;
 const TAG_EXPR_REF: u8          = 0x1D;
 const TAG_EXPR_ASSIGN: u8       = 0x1E;
 const TAG_EXPR_AWAIT: u8        = 0x1F;
+const TAG_EXPR_PATH: u8         = 0x20;
+const TAG_EXPR_SETLIT: u8       = 0x21;
 
 // Stmt tags
 const TAG_STMT_LET: u8          = 0x40;
@@ -75,6 +96,7 @@ const TAG_STMT_IMPLBLOCK: u8    = 0x4A;
 const TAG_STMT_MODDEF: u8       = 0x4B;
 const TAG_STMT_IMPORT: u8       = 0x4C;
 const TAG_STMT_TYPEALIAS: u8    = 0x4D;
+const TAG_STMT_TRAITDEF: u8     = 0x4E;
 
 // Type tags
 const TAG_TYPE_INT: u8          = 0x80;
@@ -129,10 +151,22 @@ const TAG_PAT_OK: u8          = 0xCC;
 const TAG_PAT_ERR: u8         = 0xCD;
 const TAG_PAT_OR: u8          = 0xCE;
 const TAG_PAT_RANGE: u8       = 0xCF;
+const TAG_PAT_REST: u8        = 0xD0;
+const TAG_PAT_BIND: u8        = 0xD1;
+
+// Constant pool tags
+const TAG_CONST_INT: u8     = 0x01;
+const TAG_CONST_FLOAT: u8   = 0x02;
+const TAG_CONST_STR: u8     = 0x03;
+const TAG_CONST_NAMES: u8   = 0x04;
+const TAG_CONST_PROTO: u8   = 0x05;
+const TAG_CONST_PATTERN: u8 = 0x06;
+const TAG_CONST_STMT: u8    = 0x07;
 
 // StringPart tags
 const TAG_STRPART_LITERAL: u8 = 0x01;
 const TAG_STRPART_INTERP: u8  = 0x02;
+const TAG_STRPART_FORMAT: u8  = 0x03;
 
 // ═══════════════════════════════════════════════════════════
 // Encoder
@@ -153,18 +187,10 @@ impl Encoder {
         self.buf.push(v);
     }
 
-    fn write_u16(&mut self, v: u16) {
-        self.buf.extend_from_slice(&v.to_le_bytes());
-    }
-
     fn write_u32(&mut self, v: u32) {
         self.buf.extend_from_slice(&v.to_le_bytes());
     }
 
-    fn write_u64(&mut self, v: u64) {
-        self.buf.extend_from_slice(&v.to_le_bytes());
-    }
-
     fn write_i64(&mut self, v: i64) {
         self.buf.extend_from_slice(&v.to_le_bytes());
     }
@@ -242,13 +268,15 @@ impl Encoder {
             Pattern::Err(inner)       => { self.write_u8(TAG_PAT_ERR); self.write_pattern(inner); }
             Pattern::Or(a, b)         => { self.write_u8(TAG_PAT_OR); self.write_pattern(a); self.write_pattern(b); }
             Pattern::Range(lo, hi)    => { self.write_u8(TAG_PAT_RANGE); self.write_pattern(lo); self.write_pattern(hi); }
+            Pattern::Rest(name)       => { self.write_u8(TAG_PAT_REST); self.write_opt(name, |e, n| e.write_str(n)); }
+            Pattern::Bind(name, inner) => { self.write_u8(TAG_PAT_BIND); self.write_str(name); self.write_pattern(inner); }
             Pattern::EnumVariant(en, var, fields) => {
                 self.write_u8(TAG_PAT_ENUMVARIANT);
                 self.write_str(en);
                 self.write_str(var);
                 self.write_vec(fields, |e, p| e.write_pattern(p));
             }
-            Pattern::Struct(name, fields) => {
+            Pattern::Struct(name, fields, rest) => {
                 self.write_u8(TAG_PAT_STRUCT);
                 self.write_str(name);
                 self.write_u32(fields.len() as u32);
@@ -256,6 +284,7 @@ impl Encoder {
                     self.write_str(fname);
                     self.write_pattern(fpat);
                 }
+                self.write_bool(*rest);
             }
         }
     }
@@ -265,7 +294,12 @@ impl Encoder {
     fn write_string_part(&mut self, part: &StringPart) {
         match part {
             StringPart::Literal(s)       => { self.write_u8(TAG_STRPART_LITERAL); self.write_str(s); }
-            StringPart::Interpolated(e)  => { self.write_u8(TAG_STRPART_INTERP); self.write_expr(e); }
+            StringPart::Interpolated(e, None) => { self.write_u8(TAG_STRPART_INTERP); self.write_expr(e); }
+            StringPart::Interpolated(e, Some(spec)) => {
+                self.write_u8(TAG_STRPART_FORMAT);
+                self.write_expr(e);
+                self.write_str(spec);
+            }
         }
     }
 
@@ -313,72 +347,84 @@ impl Encoder {
 
     // ── Expressions ───────────────────────────────────────────────────────
 
+    fn write_span(&mut self, span: Span) {
+        self.write_u32(span.line as u32);
+        self.write_u32(span.col as u32);
+    }
+
     pub fn write_expr(&mut self, expr: &Expr) {
-        match expr {
-            Expr::Int(n)    => { self.write_u8(TAG_EXPR_INT); self.write_i64(*n); }
-            Expr::Float(f)  => { self.write_u8(TAG_EXPR_FLOAT); self.write_f64(*f); }
-            Expr::Bool(b)   => { self.write_u8(TAG_EXPR_BOOL); self.write_bool(*b); }
-            Expr::Nil       => self.write_u8(TAG_EXPR_NIL),
-            Expr::StringLit(s) => { self.write_u8(TAG_EXPR_STRING); self.write_str(s); }
-
-            Expr::InterpolatedString(parts) => {
+        self.write_span(expr.span);
+        match &expr.kind {
+            ExprKind::Int(n)    => { self.write_u8(TAG_EXPR_INT); self.write_i64(*n); }
+            ExprKind::Float(f)  => { self.write_u8(TAG_EXPR_FLOAT); self.write_f64(*f); }
+            ExprKind::Bool(b)   => { self.write_u8(TAG_EXPR_BOOL); self.write_bool(*b); }
+            ExprKind::Nil       => self.write_u8(TAG_EXPR_NIL),
+            ExprKind::StringLit(s) => { self.write_u8(TAG_EXPR_STRING); self.write_str(s); }
+
+            ExprKind::InterpolatedString(parts) => {
                 self.write_u8(TAG_EXPR_INTERP);
                 self.write_vec(parts, |e, p| e.write_string_part(p));
             }
 
-            Expr::Var(name) => { self.write_u8(TAG_EXPR_VAR); self.write_str(name); }
+            // resolver annotations are not stored: the compiler only sees
+            // unresolved trees, and they decode as unresolved
+            ExprKind::Var(name, _) => { self.write_u8(TAG_EXPR_VAR); self.write_str(name); }
 
-            Expr::Tuple(elems) => {
+            ExprKind::Tuple(elems) => {
                 self.write_u8(TAG_EXPR_TUPLE);
                 self.write_vec(elems, |e, x| e.write_expr(x));
             }
-            Expr::List(elems) => {
+            ExprKind::List(elems) => {
                 self.write_u8(TAG_EXPR_LIST);
                 self.write_vec(elems, |e, x| e.write_expr(x));
             }
-            Expr::MapLit(pairs) => {
+            ExprKind::MapLit(pairs) => {
                 self.write_u8(TAG_EXPR_MAPLIT);
                 self.write_u32(pairs.len() as u32);
                 for (k, v) in pairs { self.write_expr(k); self.write_expr(v); }
             }
-            Expr::Block(stmts, tail) => {
+            ExprKind::SetLit(elems) => {
+                self.write_u8(TAG_EXPR_SETLIT);
+                self.write_vec(elems, |e, x| e.write_expr(x));
+            }
+            ExprKind::Block(stmts, tail, _) => {
                 self.write_u8(TAG_EXPR_BLOCK);
                 self.write_vec(stmts, |e, s| e.write_stmt(s));
                 self.write_opt(tail, |e, t| e.write_expr(t));
             }
-            Expr::BinOp(l, op, r) => {
+            ExprKind::BinOp(l, op, r) => {
                 self.write_u8(TAG_EXPR_BINOP);
                 self.write_expr(l);
                 self.write_binop(op);
                 self.write_expr(r);
             }
-            Expr::UnaryOp(op, inner) => {
+            ExprKind::UnaryOp(op, inner) => {
                 self.write_u8(TAG_EXPR_UNARYOP);
                 self.write_unaryop(op);
                 self.write_expr(inner);
             }
-            Expr::Call(callee, args) => {
+            ExprKind::Call(callee, args) => {
                 self.write_u8(TAG_EXPR_CALL);
                 self.write_expr(callee);
                 self.write_vec(args, |e, a| e.write_expr(a));
             }
-            Expr::MethodCall(obj, method, args) => {
+            ExprKind::MethodCall(obj, method, args) => {
                 self.write_u8(TAG_EXPR_METHODCALL);
                 self.write_expr(obj);
                 self.write_str(method);
                 self.write_vec(args, |e, a| e.write_expr(a));
             }
-            Expr::FieldAccess(obj, field) => {
+            ExprKind::FieldAccess(obj, field) => {
                 self.write_u8(TAG_EXPR_FIELDACCESS);
                 self.write_expr(obj);
                 self.write_str(field);
             }
-            Expr::Index(obj, idx) => {
+            ExprKind::Index(obj, idx) => {
                 self.write_u8(TAG_EXPR_INDEX);
                 self.write_expr(obj);
                 self.write_expr(idx);
             }
-            Expr::If(cond, then, elifs, else_) => {
+            ExprKind::If(cond, then, elifs, else_) => {
                 self.write_u8(TAG_EXPR_IF);
                 self.write_expr(cond);
                 self.write_expr(then);
@@ -386,12 +432,12 @@ impl Encoder {
                 for (c, b) in elifs { self.write_expr(c); self.write_expr(b); }
                 self.write_opt(else_, |e, x| e.write_expr(x));
             }
-            Expr::Match(subj, arms) => {
+            ExprKind::Match(subj, arms) => {
                 self.write_u8(TAG_EXPR_MATCH);
                 self.write_expr(subj);
                 self.write_vec(arms, |e, a| e.write_match_arm(a));
             }
-            Expr::Closure(params, body) => {
+            ExprKind::Closure(params, body, _) => {
                 self.write_u8(TAG_EXPR_CLOSURE);
                 self.write_u32(params.len() as u32);
                 for (name, ty) in params {
@@ -400,96 +446,109 @@ impl Encoder {
                 }
                 self.write_expr(body);
             }
-            Expr::StructCreate(name, fields) => {
+            ExprKind::StructCreate(name, fields) => {
                 self.write_u8(TAG_EXPR_STRUCTCREATE);
                 self.write_str(name);
                 self.write_u32(fields.len() as u32);
                 for (fname, fval) in fields { self.write_str(fname); self.write_expr(fval); }
             }
-            Expr::EnumVariant(en, var, args) => {
+            ExprKind::EnumVariant(en, var, args) => {
                 self.write_u8(TAG_EXPR_ENUMVARIANT);
                 self.write_str(en);
                 self.write_str(var);
                 self.write_vec(args, |e, a| e.write_expr(a));
             }
-            Expr::Range(start, end) => {
+            ExprKind::Range(start, end) => {
                 self.write_u8(TAG_EXPR_RANGE);
                 self.write_expr(start);
                 self.write_expr(end);
             }
-            Expr::Some(inner)     => { self.write_u8(TAG_EXPR_SOME); self.write_expr(inner); }
-            Expr::Ok(inner)       => { self.write_u8(TAG_EXPR_OK); self.write_expr(inner); }
-            Expr::Err(inner)      => { self.write_u8(TAG_EXPR_ERR); self.write_expr(inner); }
-            Expr::Question(inner) => { self.write_u8(TAG_EXPR_QUESTION); self.write_expr(inner); }
-            Expr::BoxExpr(inner)  => { self.write_u8(TAG_EXPR_BOX); self.write_expr(inner); }
-            Expr::RefExpr(inner)  => { self.write_u8(TAG_EXPR_REF); self.write_expr(inner); }
-            Expr::Assign(target, val) => {
+            ExprKind::Some(inner)     => { self.write_u8(TAG_EXPR_SOME); self.write_expr(inner); }
+            ExprKind::Ok(inner)       => { self.write_u8(TAG_EXPR_OK); self.write_expr(inner); }
+            ExprKind::Err(inner)      => { self.write_u8(TAG_EXPR_ERR); self.write_expr(inner); }
+            ExprKind::Question(inner) => { self.write_u8(TAG_EXPR_QUESTION); self.write_expr(inner); }
+            ExprKind::BoxExpr(inner)  => { self.write_u8(TAG_EXPR_BOX); self.write_expr(inner); }
+            ExprKind::RefExpr(inner)  => { self.write_u8(TAG_EXPR_REF); self.write_expr(inner); }
+            ExprKind::Assign(target, val) => {
                 self.write_u8(TAG_EXPR_ASSIGN);
                 self.write_expr(target);
                 self.write_expr(val);
             }
-            Expr::Await(inner) => { self.write_u8(TAG_EXPR_AWAIT); self.write_expr(inner); }
+            ExprKind::Await(inner) => { self.write_u8(TAG_EXPR_AWAIT); self.write_expr(inner); }
+            ExprKind::Path(segs) => {
+                self.write_u8(TAG_EXPR_PATH);
+                self.write_vec(segs, |e, s| e.write_str(s));
+            }
         }
     }
 
     // ── Statements ────────────────────────────────────────────────────────
 
     pub fn write_stmt(&mut self, stmt: &Stmt) {
-        match stmt {
-            Stmt::Let(name, ty, val, mutable) => {
+        self.write_span(stmt.span);
+        match &stmt.kind {
+            StmtKind::Let(pat, ty, val, mutable) => {
                 self.write_u8(TAG_STMT_LET);
-                self.write_str(name);
+                self.write_pattern(pat);
                 self.write_opt(ty, |e, t| e.write_type(t));
                 self.write_expr(val);
                 self.write_bool(*mutable);
             }
-            Stmt::Expr(expr) => {
+            StmtKind::Expr(expr) => {
                 self.write_u8(TAG_STMT_EXPR);
                 self.write_expr(expr);
             }
-            Stmt::Return(expr) => {
+            StmtKind::Return(expr) => {
                 self.write_u8(TAG_STMT_RETURN);
                 self.write_opt(expr, |e, x| e.write_expr(x));
             }
-            Stmt::Break    => self.write_u8(TAG_STMT_BREAK),
-            Stmt::Continue => self.write_u8(TAG_STMT_CONTINUE),
-            Stmt::While(cond, body) => {
+            StmtKind::Break    => self.write_u8(TAG_STMT_BREAK),
+            StmtKind::Continue => self.write_u8(TAG_STMT_CONTINUE),
+            StmtKind::While(cond, body, _) => {
                 self.write_u8(TAG_STMT_WHILE);
                 self.write_expr(cond);
                 self.write_vec(body, |e, s| e.write_stmt(s));
             }
-            Stmt::For(var, iter, body) => {
+            StmtKind::For(var, iter, body, _) => {
                 self.write_u8(TAG_STMT_FOR);
-                self.write_str(var);
+                self.write_pattern(var);
                 self.write_expr(iter);
                 self.write_vec(body, |e, s| e.write_stmt(s));
             }
-            Stmt::FunDef(f) => {
+            StmtKind::FunDef(f) => {
                 self.write_u8(TAG_STMT_FUNDEF);
                 self.write_fundef(f);
             }
-            Stmt::StructDef(s) => {
+            StmtKind::StructDef(s) => {
                 self.write_u8(TAG_STMT_STRUCTDEF);
                 self.write_structdef(s);
             }
-            Stmt::EnumDef(en) => {
+            StmtKind::EnumDef(en) => {
                 self.write_u8(TAG_STMT_ENUMDEF);
                 self.write_enumdef(en);
             }
-            Stmt::ImplBlock(ib) => {
+            StmtKind::TraitDef(td) => {
+                self.write_u8(TAG_STMT_TRAITDEF);
+                self.write_str(&td.name);
+                self.write_vec(&td.required, |e, m| e.write_fundef(m));
+                self.write_vec(&td.defaults, |e, m| e.write_fundef(m));
+                self.write_bool(td.is_pub);
+            }
+            StmtKind::ImplBlock(ib) => {
                 self.write_u8(TAG_STMT_IMPLBLOCK);
                 self.write_implblock(ib);
             }
-            Stmt::ModDef(name, stmts) => {
+            StmtKind::ModDef(name, stmts, is_pub) => {
                 self.write_u8(TAG_STMT_MODDEF);
                 self.write_str(name);
                 self.write_vec(stmts, |e, s| e.write_stmt(s));
+                self.write_bool(*is_pub);
             }
-            Stmt::Import(path) => {
+            StmtKind::Import(path) => {
                 self.write_u8(TAG_STMT_IMPORT);
                 self.write_vec(path, |e, s| e.write_str(s));
             }
-            Stmt::TypeAlias(name, generics, ty) => {
+            StmtKind::TypeAlias(name, generics, ty) => {
                 self.write_u8(TAG_STMT_TYPEALIAS);
                 self.write_str(name);
                 self.write_vec(generics, |e, s| e.write_str(s));
@@ -531,10 +590,113 @@ impl Encoder {
     }
 
     fn write_implblock(&mut self, ib: &ImplBlock) {
+        self.write_opt(&ib.trait_name, |e, t| e.write_str(t));
         self.write_str(&ib.target);
         self.write_vec(&ib.generics, |e, g| e.write_str(g));
         self.write_vec(&ib.methods, |e, m| e.write_fundef(m));
     }
+
+    // ── Compiled code ─────────────────────────────────────────────────────
+
+    pub fn write_proto(&mut self, proto: &Proto) {
+        self.write_opt(&proto.name, |e, n| e.write_str(n));
+        self.write_vec(&proto.params, |e, (n, d)| { e.write_str(n); e.write_bool(*d); });
+        self.write_u32(proto.slots);
+        self.write_vec(&proto.captures, |e, c| { e.write_bool(c.from_local); e.write_u32(c.index); });
+        self.write_vec(&proto.consts, |e, c| e.write_const(c));
+        self.write_vec(&proto.code, |e, op| e.write_op(*op));
+        self.write_vec(&proto.spans, |e, s| e.write_span(*s));
+    }
+
+    fn write_const(&mut self, c: &Constant) {
+        match c {
+            Constant::Int(n)   => { self.write_u8(TAG_CONST_INT); self.write_i64(*n); }
+            Constant::Float(f) => { self.write_u8(TAG_CONST_FLOAT); self.write_f64(*f); }
+            Constant::Str(s)   => { self.write_u8(TAG_CONST_STR); self.write_str(s); }
+            Constant::Names(v) => { self.write_u8(TAG_CONST_NAMES); self.write_vec(v, |e, s| e.write_str(s)); }
+            Constant::Proto(p) => { self.write_u8(TAG_CONST_PROTO); self.write_proto(p); }
+            Constant::Pattern(pat, binds) => {
+                self.write_u8(TAG_CONST_PATTERN);
+                self.write_pattern(pat);
+                self.write_vec(binds, |e, b| { e.write_str(&b.name); e.write_u32(b.slot); e.write_bool(b.boxed); });
+            }
+            Constant::Stmt(s)  => { self.write_u8(TAG_CONST_STMT); self.write_stmt(s); }
+        }
+    }
+
+    fn write_op(&mut self, op: Op) {
+        match op {
+            Op::Const(a) => { self.write_u8(0x00); self.write_u32(a); }
+            Op::Nil => self.write_u8(0x01),
+            Op::True => self.write_u8(0x02),
+            Op::False => self.write_u8(0x03),
+            Op::Pop => self.write_u8(0x04),
+            Op::GetLocal(a) => { self.write_u8(0x05); self.write_u32(a); }
+            Op::SetLocal(a) => { self.write_u8(0x06); self.write_u32(a); }
+            Op::GetCell(a) => { self.write_u8(0x07); self.write_u32(a); }
+            Op::SetCell(a) => { self.write_u8(0x08); self.write_u32(a); }
+            Op::NewCell(a) => { self.write_u8(0x09); self.write_u32(a); }
+            Op::BoxLocal(a) => { self.write_u8(0x0A); self.write_u32(a); }
+            Op::GetUpval(a) => { self.write_u8(0x0B); self.write_u32(a); }
+            Op::SetUpval(a) => { self.write_u8(0x0C); self.write_u32(a); }
+            Op::GetGlobal(a) => { self.write_u8(0x0D); self.write_u32(a); }
+            Op::SetGlobal(a) => { self.write_u8(0x0E); self.write_u32(a); }
+            Op::DefineGlobal(a) => { self.write_u8(0x0F); self.write_u32(a); }
+            Op::Add => self.write_u8(0x10),
+            Op::Sub => self.write_u8(0x11),
+            Op::Mul => self.write_u8(0x12),
+            Op::Div => self.write_u8(0x13),
+            Op::Mod => self.write_u8(0x14),
+            Op::Eq => self.write_u8(0x15),
+            Op::NotEq => self.write_u8(0x16),
+            Op::Lt => self.write_u8(0x17),
+            Op::LtEq => self.write_u8(0x18),
+            Op::Gt => self.write_u8(0x19),
+            Op::GtEq => self.write_u8(0x1A),
+            Op::And => self.write_u8(0x1B),
+            Op::Or => self.write_u8(0x1C),
+            Op::Neg => self.write_u8(0x1D),
+            Op::Not => self.write_u8(0x1E),
+            Op::Jump(a) => { self.write_u8(0x1F); self.write_u32(a); }
+            Op::JumpIfFalse(a) => { self.write_u8(0x20); self.write_u32(a); }
+            Op::ArgGiven(a, b) => { self.write_u8(0x21); self.write_u32(a); self.write_u32(b); }
+            Op::Call(a) => { self.write_u8(0x22); self.write_u32(a); }
+            Op::CallPath(a, b) => { self.write_u8(0x23); self.write_u32(a); self.write_u32(b); }
+            Op::Method(a, b) => { self.write_u8(0x24); self.write_u32(a); self.write_u32(b); }
+            Op::Return => self.write_u8(0x25),
+            Op::Tuple(a) => { self.write_u8(0x26); self.write_u32(a); }
+            Op::List(a) => { self.write_u8(0x27); self.write_u32(a); }
+            Op::Map(a) => { self.write_u8(0x28); self.write_u32(a); }
+            Op::Concat(a) => { self.write_u8(0x29); self.write_u32(a); }
+            Op::Range => self.write_u8(0x2A),
+            Op::Struct(a) => { self.write_u8(0x2B); self.write_u32(a); }
+            Op::Variant(a, b) => { self.write_u8(0x2C); self.write_u32(a); self.write_u32(b); }
+            Op::Path(a) => { self.write_u8(0x2D); self.write_u32(a); }
+            Op::Field(a) => { self.write_u8(0x2E); self.write_u32(a); }
+            Op::SetField(a) => { self.write_u8(0x2F); self.write_u32(a); }
+            Op::Index => self.write_u8(0x30),
+            Op::SetIndex => self.write_u8(0x31),
+            Op::Closure(a) => { self.write_u8(0x32); self.write_u32(a); }
+            Op::DefMethod(a) => { self.write_u8(0x33); self.write_u32(a); }
+            Op::Some => self.write_u8(0x34),
+            Op::Ok => self.write_u8(0x35),
+            Op::Err => self.write_u8(0x36),
+            Op::Try => self.write_u8(0x37),
+            Op::Ref => self.write_u8(0x38),
+            Op::IterInit(a) => { self.write_u8(0x39); self.write_u32(a); }
+            Op::ForNext(a, b) => { self.write_u8(0x3A); self.write_u32(a); self.write_u32(b); }
+            Op::Match(a, b) => { self.write_u8(0x3B); self.write_u32(a); self.write_u32(b); }
+            Op::Exec(a) => { self.write_u8(0x3C); self.write_u32(a); }
+            Op::Fail(a) => { self.write_u8(0x3D); self.write_u32(a); }
+            Op::DefTrait(a, b) => { self.write_u8(0x3E); self.write_u32(a); self.write_u32(b); }
+            Op::ImplTrait(a) => { self.write_u8(0x3F); self.write_u32(a); }
+            Op::Destructure(a) => { self.write_u8(0x40); self.write_u32(a); }
+            Op::Set(a) => { self.write_u8(0x41); self.write_u32(a); }
+            Op::Format(a) => { self.write_u8(0x42); self.write_u32(a); }
+            Op::TailCall(a) => { self.write_u8(0x43); self.write_u32(a); }
+        }
+    }
+
 }
 
 // ═══════════════════════════════════════════════════════════
@@ -560,21 +722,11 @@ impl<'a> Decoder<'a> {
         Ok(v)
     }
 
-    fn read_u16(&mut self) -> io::Result<u16> {
-        let b = self.read_bytes(2)?;
-        Ok(u16::from_le_bytes([b[0], b[1]]))
-    }
-
     fn read_u32(&mut self) -> io::Result<u32> {
         let b = self.read_bytes(4)?;
         Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
     }
 
-    fn read_u64(&mut self) -> io::Result<u64> {
-        let b = self.read_bytes(8)?;
-        Ok(u64::from_le_bytes(b.try_into().unwrap()))
-    }
-
     fn read_i64(&mut self) -> io::Result<i64> {
         let b = self.read_bytes(8)?;
         Ok(i64::from_le_bytes(b.try_into().unwrap()))
@@ -614,7 +766,8 @@ impl<'a> Decoder<'a> {
 
     fn read_vec<T, F: Fn(&mut Self) -> io::Result<T>>(&mut self, f: F) -> io::Result<Vec<T>> {
         let count = self.read_u32()? as usize;
-        let mut v = Vec::with_capacity(count);
+        // every item takes at least a byte, so a damaged count cannot over-allocate
+        let mut v = Vec::with_capacity(count.min(self.data.len() - self.pos));
         for _ in 0..count { v.push(f(self)?); }
         Ok(v)
     }
@@ -698,7 +851,12 @@ impl<'a> Decoder<'a> {
                     let fpat = self.read_pattern()?;
                     fields.push((fname, fpat));
                 }
-                Pattern::Struct(name, fields)
+                Pattern::Struct(name, fields, self.read_bool()?)
+            }
+            TAG_PAT_REST => Pattern::Rest(self.read_opt(|d| d.read_str())?),
+            TAG_PAT_BIND => {
+                let name = self.read_str()?;
+                Pattern::Bind(name, Box::new(self.read_pattern()?))
             }
             tag => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown pattern tag: 0x{:02X}", tag)))
         })
@@ -709,7 +867,11 @@ impl<'a> Decoder<'a> {
     fn read_string_part(&mut self) -> io::Result<StringPart> {
         Ok(match self.read_u8()? {
             TAG_STRPART_LITERAL => StringPart::Literal(self.read_str()?),
-            TAG_STRPART_INTERP  => StringPart::Interpolated(self.read_expr()?),
+            TAG_STRPART_INTERP  => StringPart::Interpolated(self.read_expr()?, None),
+            TAG_STRPART_FORMAT  => {
+                let e = self.read_expr()?;
+                StringPart::Interpolated(e, Some(self.read_str()?))
+            }
             tag => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown string part tag: 0x{:02X}", tag)))
         })
     }
@@ -720,7 +882,7 @@ impl<'a> Decoder<'a> {
         let pattern = self.read_pattern()?;
         let guard = self.read_opt(|d| d.read_expr())?;
         let body = self.read_expr()?;
-        Ok(MatchArm { pattern, guard, body })
+        Ok(MatchArm { pattern, guard, body, scope: Scope::Dynamic })
     }
 
     // ── Param ─────────────────────────────────────────────────────────────
@@ -764,59 +926,67 @@ impl<'a> Decoder<'a> {
 
     // ── Expressions ───────────────────────────────────────────────────────
 
+    fn read_span(&mut self) -> io::Result<Span> {
+        let line = self.read_u32()? as usize;
+        let col = self.read_u32()? as usize;
+        Ok(Span { line, col })
+    }
+
     pub fn read_expr(&mut self) -> io::Result<Expr> {
-        Ok(match self.read_u8()? {
-            TAG_EXPR_INT    => Expr::Int(self.read_i64()?),
-            TAG_EXPR_FLOAT  => Expr::Float(self.read_f64()?),
-            TAG_EXPR_BOOL   => Expr::Bool(self.read_bool()?),
-            TAG_EXPR_NIL    => Expr::Nil,
-            TAG_EXPR_STRING => Expr::StringLit(self.read_str()?),
-            TAG_EXPR_INTERP => Expr::InterpolatedString(self.read_vec(|d| d.read_string_part())?),
-            TAG_EXPR_VAR    => Expr::Var(self.read_str()?),
-            TAG_EXPR_TUPLE  => Expr::Tuple(self.read_vec(|d| d.read_expr())?),
-            TAG_EXPR_LIST   => Expr::List(self.read_vec(|d| d.read_expr())?),
+        let span = self.read_span()?;
+        let kind = match self.read_u8()? {
+            TAG_EXPR_INT    => ExprKind::Int(self.read_i64()?),
+            TAG_EXPR_FLOAT  => ExprKind::Float(self.read_f64()?),
+            TAG_EXPR_BOOL   => ExprKind::Bool(self.read_bool()?),
+            TAG_EXPR_NIL    => ExprKind::Nil,
+            TAG_EXPR_STRING => ExprKind::StringLit(self.read_str()?),
+            TAG_EXPR_INTERP => ExprKind::InterpolatedString(self.read_vec(|d| d.read_string_part())?),
+            TAG_EXPR_VAR    => ExprKind::Var(self.read_str()?, VarRes::Dynamic),
+            TAG_EXPR_TUPLE  => ExprKind::Tuple(self.read_vec(|d| d.read_expr())?),
+            TAG_EXPR_LIST   => ExprKind::List(self.read_vec(|d| d.read_expr())?),
             TAG_EXPR_MAPLIT => {
                 let count = self.read_u32()? as usize;
                 let mut pairs = Vec::new();
                 for _ in 0..count { pairs.push((self.read_expr()?, self.read_expr()?)); }
-                Expr::MapLit(pairs)
+                ExprKind::MapLit(pairs)
             }
+            TAG_EXPR_SETLIT => ExprKind::SetLit(self.read_vec(|d| d.read_expr())?),
             TAG_EXPR_BLOCK => {
                 let stmts = self.read_vec(|d| d.read_stmt())?;
                 let tail = self.read_opt(|d| d.read_expr())?;
-                Expr::Block(stmts, tail.map(Box::new))
+                ExprKind::Block(stmts, tail.map(Box::new), Scope::Dynamic)
             }
             TAG_EXPR_BINOP => {
                 let l = self.read_expr()?;
                 let op = self.read_binop()?;
                 let r = self.read_expr()?;
-                Expr::BinOp(Box::new(l), op, Box::new(r))
+                ExprKind::BinOp(Box::new(l), op, Box::new(r))
             }
             TAG_EXPR_UNARYOP => {
                 let op = self.read_unaryop()?;
                 let inner = self.read_expr()?;
-                Expr::UnaryOp(op, Box::new(inner))
+                ExprKind::UnaryOp(op, Box::new(inner))
             }
             TAG_EXPR_CALL => {
                 let callee = self.read_expr()?;
                 let args = self.read_vec(|d| d.read_expr())?;
-                Expr::Call(Box::new(callee), args)
+                ExprKind::Call(Box::new(callee), args)
             }
             TAG_EXPR_METHODCALL => {
                 let obj = self.read_expr()?;
                 let method = self.read_str()?;
                 let args = self.read_vec(|d| d.read_expr())?;
-                Expr::MethodCall(Box::new(obj), method, args)
+                ExprKind::MethodCall(Box::new(obj), method, args)
             }
             TAG_EXPR_FIELDACCESS => {
                 let obj = self.read_expr()?;
                 let field = self.read_str()?;
-                Expr::FieldAccess(Box::new(obj), field)
+                ExprKind::FieldAccess(Box::new(obj), field)
             }
             TAG_EXPR_INDEX => {
                 let obj = self.read_expr()?;
                 let idx = self.read_expr()?;
-                Expr::Index(Box::new(obj), Box::new(idx))
+                ExprKind::Index(Box::new(obj), Box::new(idx))
             }
             TAG_EXPR_IF => {
                 let cond = self.read_expr()?;
@@ -825,12 +995,12 @@ impl<'a> Decoder<'a> {
                 let mut elifs = Vec::new();
                 for _ in 0..elif_count { elifs.push((self.read_expr()?, self.read_expr()?)); }
                 let else_ = self.read_opt(|d| d.read_expr())?;
-                Expr::If(Box::new(cond), Box::new(then), elifs, else_.map(Box::new))
+                ExprKind::If(Box::new(cond), Box::new(then), elifs, else_.map(Box::new))
             }
             TAG_EXPR_MATCH => {
                 let subj = self.read_expr()?;
                 let arms = self.read_vec(|d| d.read_match_arm())?;
-                Expr::Match(Box::new(subj), arms)
+                ExprKind::Match(Box::new(subj), arms)
             }
             TAG_EXPR_CLOSURE => {
                 let count = self.read_u32()? as usize;
@@ -841,86 +1011,99 @@ impl<'a> Decoder<'a> {
                     params.push((name, ty));
                 }
                 let body = self.read_expr()?;
-                Expr::Closure(params, Box::new(body))
+                ExprKind::Closure(params, Box::new(body), Scope::Dynamic)
             }
             TAG_EXPR_STRUCTCREATE => {
                 let name = self.read_str()?;
                 let count = self.read_u32()? as usize;
                 let mut fields = Vec::new();
                 for _ in 0..count { fields.push((self.read_str()?, self.read_expr()?)); }
-                Expr::StructCreate(name, fields)
+                ExprKind::StructCreate(name, fields)
             }
             TAG_EXPR_ENUMVARIANT => {
                 let en = self.read_str()?;
                 let var = self.read_str()?;
                 let args = self.read_vec(|d| d.read_expr())?;
-                Expr::EnumVariant(en, var, args)
+                ExprKind::EnumVariant(en, var, args)
             }
             TAG_EXPR_RANGE => {
                 let s = self.read_expr()?;
                 let e = self.read_expr()?;
-                Expr::Range(Box::new(s), Box::new(e))
-            }
-            TAG_EXPR_SOME     => Expr::Some(Box::new(self.read_expr()?)),
-            TAG_EXPR_OK       => Expr::Ok(Box::new(self.read_expr()?)),
-            TAG_EXPR_ERR      => Expr::Err(Box::new(self.read_expr()?)),
-            TAG_EXPR_QUESTION => Expr::Question(Box::new(self.read_expr()?)),
-            TAG_EXPR_BOX      => Expr::BoxExpr(Box::new(self.read_expr()?)),
-            TAG_EXPR_REF      => Expr::RefExpr(Box::new(self.read_expr()?)),
+                ExprKind::Range(Box::new(s), Box::new(e))
+            }
+            TAG_EXPR_SOME     => ExprKind::Some(Box::new(self.read_expr()?)),
+            TAG_EXPR_OK       => ExprKind::Ok(Box::new(self.read_expr()?)),
+            TAG_EXPR_ERR      => ExprKind::Err(Box::new(self.read_expr()?)),
+            TAG_EXPR_QUESTION => ExprKind::Question(Box::new(self.read_expr()?)),
+            TAG_EXPR_BOX      => ExprKind::BoxExpr(Box::new(self.read_expr()?)),
+            TAG_EXPR_REF      => ExprKind::RefExpr(Box::new(self.read_expr()?)),
             TAG_EXPR_ASSIGN   => {
                 let t = self.read_expr()?;
                 let v = self.read_expr()?;
-                Expr::Assign(Box::new(t), Box::new(v))
+                ExprKind::Assign(Box::new(t), Box::new(v))
             }
-            TAG_EXPR_AWAIT => Expr::Await(Box::new(self.read_expr()?)),
+            TAG_EXPR_AWAIT => ExprKind::Await(Box::new(self.read_expr()?)),
+            TAG_EXPR_PATH  => ExprKind::Path(self.read_vec(|d| d.read_str())?),
             tag => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown expr tag: 0x{:02X}", tag)))
-        })
+        };
+        Ok(Expr::new(kind, span))
     }
 
     // ── Statements ────────────────────────────────────────────────────────
 
     pub fn read_stmt(&mut self) -> io::Result<Stmt> {
-        Ok(match self.read_u8()? {
+        let span = self.read_span()?;
+        let kind = match self.read_u8()? {
             TAG_STMT_LET => {
-                let name = self.read_str()?;
+                let pat = self.read_pattern()?;
                 let ty = self.read_opt(|d| d.read_type())?;
                 let val = self.read_expr()?;
                 let mutable = self.read_bool()?;
-                Stmt::Let(name, ty, val, mutable)
+                StmtKind::Let(pat, ty, val, mutable)
             }
-            TAG_STMT_EXPR     => Stmt::Expr(self.read_expr()?),
-            TAG_STMT_RETURN   => Stmt::Return(self.read_opt(|d| d.read_expr())?),
-            TAG_STMT_BREAK    => Stmt::Break,
-            TAG_STMT_CONTINUE => Stmt::Continue,
+            TAG_STMT_EXPR     => StmtKind::Expr(self.read_expr()?),
+            TAG_STMT_RETURN   => StmtKind::Return(self.read_opt(|d| d.read_expr())?),
+            TAG_STMT_BREAK    => StmtKind::Break,
+            TAG_STMT_CONTINUE => StmtKind::Continue,
             TAG_STMT_WHILE => {
                 let cond = self.read_expr()?;
                 let body = self.read_vec(|d| d.read_stmt())?;
-                Stmt::While(cond, body)
+                StmtKind::While(cond, body, Scope::Dynamic)
             }
             TAG_STMT_FOR => {
-                let var = self.read_str()?;
+                let var = self.read_pattern()?;
                 let iter = self.read_expr()?;
                 let body = self.read_vec(|d| d.read_stmt())?;
-                Stmt::For(var, iter, body)
+                StmtKind::For(var, iter, body, Scope::Dynamic)
+            }
+            TAG_STMT_FUNDEF    => StmtKind::FunDef(self.read_fundef()?),
+            TAG_STMT_STRUCTDEF => StmtKind::StructDef(self.read_structdef()?),
+            TAG_STMT_ENUMDEF   => StmtKind::EnumDef(self.read_enumdef()?),
+            TAG_STMT_IMPLBLOCK => StmtKind::ImplBlock(self.read_implblock()?),
+            TAG_STMT_TRAITDEF => {
+                let name = self.read_str()?;
+                let required = self.read_vec(|d| d.read_fundef())?;
+                let defaults = self.read_vec(|d| d.read_fundef())?;
+                let is_pub = self.read_bool()?;
+                StmtKind::TraitDef(TraitDef { name, required, defaults, is_pub })
             }
-            TAG_STMT_FUNDEF    => Stmt::FunDef(self.read_fundef()?),
-            TAG_STMT_STRUCTDEF => Stmt::StructDef(self.read_structdef()?),
-            TAG_STMT_ENUMDEF   => Stmt::EnumDef(self.read_enumdef()?),
-            TAG_STMT_IMPLBLOCK => Stmt::ImplBlock(self.read_implblock()?),
             TAG_STMT_MODDEF => {
                 let name = self.read_str()?;
                 let stmts = self.read_vec(|d| d.read_stmt())?;
-                Stmt::ModDef(name, stmts)
+                let is_pub = self.read_bool()?;
+                StmtKind::ModDef(name, stmts, is_pub)
             }
-            TAG_STMT_IMPORT    => Stmt::Import(self.read_vec(|d| d.read_str())?),
+            TAG_STMT_IMPORT    => StmtKind::Import(self.read_vec(|d| d.read_str())?),
             TAG_STMT_TYPEALIAS => {
                 let name = self.read_str()?;
                 let generics = self.read_vec(|d| d.read_str())?;
                 let ty = self.read_type()?;
-                Stmt::TypeAlias(name, generics, ty)
+                StmtKind::TypeAlias(name, generics, ty)
             }
             tag => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown stmt tag: 0x{:02X}", tag)))
-        })
+        };
+        // Source positions are not stored in .zphc
+        Ok(Stmt::new(kind, span))
     }
 
     fn read_fundef(&mut self) -> io::Result<FunDef> {
@@ -930,7 +1113,7 @@ impl<'a> Decoder<'a> {
         let return_type = self.read_opt(|d| d.read_type())?;
         let body = self.read_vec(|d| d.read_stmt())?;
         let is_pub = self.read_bool()?;
-        Ok(FunDef { name, generics, params, return_type, body, is_pub })
+        Ok(FunDef { name, generics, params, return_type, body, is_pub, scope: Scope::Dynamic })
     }
 
     fn read_structdef(&mut self) -> io::Result<StructDef> {
@@ -963,11 +1146,121 @@ impl<'a> Decoder<'a> {
     }
 
     fn read_implblock(&mut self) -> io::Result<ImplBlock> {
+        let trait_name = self.read_opt(|d| d.read_str())?;
         let target = self.read_str()?;
         let generics = self.read_vec(|d| d.read_str())?;
         let methods = self.read_vec(|d| d.read_fundef())?;
-        Ok(ImplBlock { target, generics, methods })
+        Ok(ImplBlock { trait_name, target, generics, methods })
+    }
+
+    // ── Compiled code ─────────────────────────────────────────────────────
+
+    pub fn read_proto(&mut self) -> io::Result<Proto> {
+        let name = self.read_opt(|d| d.read_str())?;
+        let params = self.read_vec(|d| Ok((d.read_str()?, d.read_bool()?)))?;
+        let mut proto = Proto::new(name, params);
+        proto.slots = self.read_u32()?;
+        proto.captures = self.read_vec(|d| Ok(Capture { from_local: d.read_bool()?, index: d.read_u32()? }))?;
+        proto.consts = self.read_vec(|d| d.read_const())?;
+        proto.code = self.read_vec(|d| d.read_op())?;
+        proto.spans = self.read_vec(|d| d.read_span())?;
+        if proto.spans.len() != proto.code.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "line table does not match code length"));
+        }
+        Ok(proto)
+    }
+
+    fn read_const(&mut self) -> io::Result<Constant> {
+        Ok(match self.read_u8()? {
+            TAG_CONST_INT     => Constant::Int(self.read_i64()?),
+            TAG_CONST_FLOAT   => Constant::Float(self.read_f64()?),
+            TAG_CONST_STR     => Constant::Str(self.read_str()?),
+            TAG_CONST_NAMES   => Constant::Names(self.read_vec(|d| d.read_str())?),
+            TAG_CONST_PROTO   => Constant::Proto(Rc::new(self.read_proto()?)),
+            TAG_CONST_PATTERN => {
+                let pat = self.read_pattern()?;
+                let binds = self.read_vec(|d| Ok(Binding { name: d.read_str()?, slot: d.read_u32()?, boxed: d.read_bool()? }))?;
+                Constant::Pattern(pat, binds)
+            }
+            TAG_CONST_STMT    => Constant::Stmt(self.read_stmt()?),
+            t => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown constant tag: 0x{:02X}", t))),
+        })
+    }
+
+    fn read_op(&mut self) -> io::Result<Op> {
+        Ok(match self.read_u8()? {
+            0x00 => Op::Const(self.read_u32()?),
+            0x01 => Op::Nil,
+            0x02 => Op::True,
+            0x03 => Op::False,
+            0x04 => Op::Pop,
+            0x05 => Op::GetLocal(self.read_u32()?),
+            0x06 => Op::SetLocal(self.read_u32()?),
+            0x07 => Op::GetCell(self.read_u32()?),
+            0x08 => Op::SetCell(self.read_u32()?),
+            0x09 => Op::NewCell(self.read_u32()?),
+            0x0A => Op::BoxLocal(self.read_u32()?),
+            0x0B => Op::GetUpval(self.read_u32()?),
+            0x0C => Op::SetUpval(self.read_u32()?),
+            0x0D => Op::GetGlobal(self.read_u32()?),
+            0x0E => Op::SetGlobal(self.read_u32()?),
+            0x0F => Op::DefineGlobal(self.read_u32()?),
+            0x10 => Op::Add,
+            0x11 => Op::Sub,
+            0x12 => Op::Mul,
+            0x13 => Op::Div,
+            0x14 => Op::Mod,
+            0x15 => Op::Eq,
+            0x16 => Op::NotEq,
+            0x17 => Op::Lt,
+            0x18 => Op::LtEq,
+            0x19 => Op::Gt,
+            0x1A => Op::GtEq,
+            0x1B => Op::And,
+            0x1C => Op::Or,
+            0x1D => Op::Neg,
+            0x1E => Op::Not,
+            0x1F => Op::Jump(self.read_u32()?),
+            0x20 => Op::JumpIfFalse(self.read_u32()?),
+            0x21 => Op::ArgGiven(self.read_u32()?, self.read_u32()?),
+            0x22 => Op::Call(self.read_u32()?),
+            0x23 => Op::CallPath(self.read_u32()?, self.read_u32()?),
+            0x24 => Op::Method(self.read_u32()?, self.read_u32()?),
+            0x25 => Op::Return,
+            0x26 => Op::Tuple(self.read_u32()?),
+            0x27 => Op::List(self.read_u32()?),
+            0x28 => Op::Map(self.read_u32()?),
+            0x29 => Op::Concat(self.read_u32()?),
+            0x2A => Op::Range,
+            0x2B => Op::Struct(self.read_u32()?),
+            0x2C => Op::Variant(self.read_u32()?, self.read_u32()?),
+            0x2D => Op::Path(self.read_u32()?),
+            0x2E => Op::Field(self.read_u32()?),
+            0x2F => Op::SetField(self.read_u32()?),
+            0x30 => Op::Index,
+            0x31 => Op::SetIndex,
+            0x32 => Op::Closure(self.read_u32()?),
+            0x33 => Op::DefMethod(self.read_u32()?),
+            0x34 => Op::Some,
+            0x35 => Op::Ok,
+            0x36 => Op::Err,
+            0x37 => Op::Try,
+            0x38 => Op::Ref,
+            0x39 => Op::IterInit(self.read_u32()?),
+            0x3A => Op::ForNext(self.read_u32()?, self.read_u32()?),
+            0x3B => Op::Match(self.read_u32()?, self.read_u32()?),
+            0x3C => Op::Exec(self.read_u32()?),
+            0x3D => Op::Fail(self.read_u32()?),
+            0x3E => Op::DefTrait(self.read_u32()?, self.read_u32()?),
+            0x3F => Op::ImplTrait(self.read_u32()?),
+            0x40 => Op::Destructure(self.read_u32()?),
+            0x41 => Op::Set(self.read_u32()?),
+            0x42 => Op::Format(self.read_u32()?),
+            0x43 => Op::TailCall(self.read_u32()?),
+            t => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown opcode: 0x{:02X}", t))),
+        })
     }
+
 }
 
 // ═══════════════════════════════════════════════════════════
@@ -984,28 +1277,28 @@ fn fnv1a(data: &[u8]) -> u64 {
     hash
 }
 
-/// Serialize a parsed AST to .zphc bytes.
-pub fn encode(stmts: &[Stmt], source: &str) -> Vec<u8> {
+/// Serialize a compiled program to .zphc bytes.
+pub fn encode(program: &Proto, source: &str) -> Vec<u8> {
     let mut out = Vec::new();
 
     // Header
     out.extend_from_slice(&MAGIC.to_le_bytes());
     out.extend_from_slice(&VERSION.to_le_bytes());
     out.extend_from_slice(&fnv1a(source.as_bytes()).to_le_bytes());
-    out.extend_from_slice(&(stmts.len() as u32).to_le_bytes());
 
     // Body
     let mut enc = Encoder::new();
-    for stmt in stmts { enc.write_stmt(stmt); }
+    enc.write_str(source);
+    enc.write_proto(program);
     out.extend_from_slice(&enc.finish());
 
     out
 }
 
-/// Decode .zphc bytes back to an AST.
-/// Returns (stmts, source_hash).
-pub fn decode(data: &[u8]) -> Result<(Vec<Stmt>, u64), String> {
-    if data.len() < 18 {
+/// Decode .zphc bytes back to a compiled program.
+/// Returns (program, source it was compiled from).
+pub fn decode(data: &[u8]) -> Result<(Proto, String), String> {
+    if data.len() < 14 {
         return Err("File too short to be a valid .zphc".into());
     }
 
@@ -1021,16 +1314,156 @@ pub fn decode(data: &[u8]) -> Result<(Vec<Stmt>, u64), String> {
         return Err(format!("Unsupported bytecode version: {} (this runtime supports {})", version, VERSION));
     }
 
-    let source_hash = u64::from_le_bytes(data[6..14].try_into().unwrap());
-    let stmt_count = u32::from_le_bytes([data[14], data[15], data[16], data[17]]) as usize;
+    let mut dec = Decoder::new(&data[14..]);
+    let source = dec.read_str().map_err(|e| format!("Decode error: {}", e))?;
+    let program = dec.read_proto().map_err(|e| format!("Decode error: {}", e))?;
+    verify(&program, None).map_err(|e| format!("Invalid bytecode in {}: {}", program.name.as_deref().unwrap_or("<script>"), e))?;
 
-    let mut dec = Decoder::new(&data[18..]);
-    let mut stmts = Vec::with_capacity(stmt_count);
-    for _ in 0..stmt_count {
-        stmts.push(dec.read_stmt().map_err(|e| format!("Decode error: {}", e))?);
+    Ok((program, source))
+}
+
+// ── Verification ──────────────────────────────────────────────────────────────
+//
+// The VM trusts its input: it indexes slots, constants and upvalues and
+// pops operands without checking. A damaged file is caught here instead,
+// by checking every operand against its Proto and walking the code to make
+// sure no instruction pops more than the paths reaching it have pushed.
+
+// more locals than any real function has; also keeps a damaged count
+// from allocating the frame
+const MAX_SLOTS: u32 = 1 << 16;
+
+/// Check `proto` and the functions nested in it. `outer` is the enclosing
+/// Proto, whose slots and upvalues the captures refer to.
+fn verify(proto: &Proto, outer: Option<&Proto>) -> Result<(), String> {
+    if proto.slots > MAX_SLOTS || proto.params.len() > proto.slots as usize {
+        return Err(format!("bad slot count {}", proto.slots));
     }
+    for cap in &proto.captures {
+        let limit = match outer {
+            Some(o) if cap.from_local => o.slots as usize,
+            Some(o) => o.captures.len(),
+            None => 0,
+        };
+        if cap.index as usize >= limit {
+            return Err(format!("capture {} is out of range", cap.index));
+        }
+    }
+    for (at, op) in proto.code.iter().enumerate() {
+        check_operands(proto, op).map_err(|e| format!("{} at instruction {}", e, at))?;
+    }
+    check_stack(proto)?;
+    for c in &proto.consts {
+        if let Constant::Proto(inner) = c {
+            verify(inner, Some(proto)).map_err(|e| format!("{} in {}", e, inner.name.as_deref().unwrap_or("<closure>")))?;
+        }
+    }
+    Ok(())
+}
 
-    Ok((stmts, source_hash))
+fn check_operands(proto: &Proto, op: &Op) -> Result<(), String> {
+    let slot = |s: u32| if s < proto.slots { Ok(()) } else { Err(format!("slot {} is out of range", s)) };
+    let jump = |t: u32| if (t as usize) < proto.code.len() { Ok(()) } else { Err(format!("jump to {} is out of range", t)) };
+    let constant = |k: u32| proto.consts.get(k as usize).ok_or_else(|| format!("constant {} is out of range", k));
+    let string = |k: u32| match constant(k)? {
+        Constant::Str(_) => Ok(()),
+        _ => Err(format!("constant {} is not a name", k)),
+    };
+    let names = |k: u32, min: usize| match constant(k)? {
+        Constant::Names(n) if n.len() >= min => Ok(()),
+        _ => Err(format!("constant {} is not a path of {} or more names", k, min)),
+    };
+    let pattern = |k: u32| match constant(k)? {
+        Constant::Pattern(_, binds) => binds.iter().try_for_each(|b| slot(b.slot)),
+        _ => Err(format!("constant {} is not a pattern", k)),
+    };
+    match *op {
+        Op::Const(k) => constant(k).map(|_| ()),
+        Op::GetLocal(s) | Op::SetLocal(s) | Op::GetCell(s) | Op::SetCell(s) | Op::NewCell(s) | Op::BoxLocal(s) => slot(s),
+        Op::GetUpval(i) | Op::SetUpval(i) if i as usize >= proto.captures.len() => Err(format!("upvalue {} is out of range", i)),
+        Op::GetGlobal(k) | Op::SetGlobal(k) | Op::DefineGlobal(k) | Op::Method(k, _) | Op::Format(k)
+        | Op::Field(k) | Op::SetField(k) | Op::Fail(k) => string(k),
+        Op::Jump(t) | Op::JumpIfFalse(t) | Op::ArgGiven(_, t) => jump(t),
+        Op::CallPath(k, _) | Op::Path(k) | Op::Struct(k) => names(k, 1),
+        Op::Variant(k, _) | Op::DefMethod(k) | Op::ImplTrait(k) => names(k, 2),
+        Op::DefTrait(k, n) => names(k, n as usize + 1),
+        Op::Closure(k) => match constant(k)? {
+            Constant::Proto(_) => Ok(()),
+            _ => Err(format!("constant {} is not a function", k)),
+        },
+        Op::IterInit(s) => slot(s.saturating_add(1)),
+        Op::ForNext(s, t) => slot(s.saturating_add(1)).and(jump(t)),
+        Op::Match(k, t) => pattern(k).and(jump(t)),
+        Op::Destructure(k) => pattern(k),
+        Op::Exec(k) => match constant(k)? {
+            Constant::Stmt(_) => Ok(()),
+            _ => Err(format!("constant {} is not a statement", k)),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Values `op` pops and pushes. Ops that leave the frame push nothing.
+fn stack_effect(proto: &Proto, op: &Op) -> (usize, usize) {
+    match *op {
+        Op::Const(_) | Op::Nil | Op::True | Op::False | Op::GetLocal(_) | Op::GetCell(_) | Op::GetUpval(_)
+        | Op::GetGlobal(_) | Op::Path(_) | Op::Closure(_) => (0, 1),
+        Op::Pop | Op::SetLocal(_) | Op::SetCell(_) | Op::NewCell(_) | Op::SetUpval(_) | Op::SetGlobal(_)
+        | Op::DefineGlobal(_) | Op::JumpIfFalse(_) | Op::DefMethod(_) | Op::IterInit(_) | Op::Match(..)
+        | Op::Destructure(_) | Op::Return => (1, 0),
+        Op::BoxLocal(_) | Op::Jump(_) | Op::ArgGiven(..) | Op::ImplTrait(_) | Op::Exec(_) | Op::Fail(_) => (0, 0),
+        Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Mod | Op::Eq | Op::NotEq | Op::Lt | Op::LtEq | Op::Gt
+        | Op::GtEq | Op::And | Op::Or | Op::Range | Op::Index => (2, 1),
+        Op::Neg | Op::Not | Op::Format(_) | Op::Field(_) | Op::Some | Op::Ok | Op::Err | Op::Try | Op::Ref => (1, 1),
+        Op::SetField(_) => (2, 0),
+        Op::SetIndex => (3, 0),
+        Op::Call(n) | Op::TailCall(n) | Op::Method(_, n) => (n as usize + 1, 1),
+        Op::CallPath(_, n) | Op::Variant(_, n) | Op::Tuple(n) | Op::List(n) | Op::Set(n) | Op::Concat(n) => (n as usize, 1),
+        Op::Map(n) => (2 * n as usize, 1),
+        Op::DefTrait(_, n) => (n as usize, 0),
+        Op::Struct(k) => match &proto.consts[k as usize] {
+            Constant::Names(n) => (n.len() - 1, 1),
+            _ => (0, 1),
+        },
+        // pushes the next item, or jumps to the exit with nothing
+        Op::ForNext(..) => (0, 1),
+    }
+}
+
+/// Walk every path through the code, keeping the lowest stack height each
+/// instruction can be reached with, and fail if one could pop too much or
+/// run past the end.
+fn check_stack(proto: &Proto) -> Result<(), String> {
+    let code = &proto.code;
+    let mut lowest: Vec<Option<usize>> = vec![None; code.len()];
+    let mut work = vec![(0usize, 0usize)];
+    while let Some((at, height)) = work.pop() {
+        let Some(op) = code.get(at) else {
+            return Err("code runs past its end".into());
+        };
+        if lowest[at].is_some_and(|h| h <= height) {
+            continue;
+        }
+        lowest[at] = Some(height);
+        let (pops, pushes) = stack_effect(proto, op);
+        let after = height.checked_sub(pops)
+            .ok_or_else(|| format!("instruction {} pops {} values with {} on the stack", at, pops, height))?
+            + pushes;
+        match *op {
+            Op::Return | Op::Fail(_) => {}
+            Op::Jump(t) => work.push((t as usize, after)),
+            Op::JumpIfFalse(t) | Op::ArgGiven(_, t) | Op::Match(_, t) => {
+                work.push((t as usize, after));
+                work.push((at + 1, after));
+            }
+            Op::ForNext(_, exit) => {
+                work.push((exit as usize, height));
+                work.push((at + 1, after));
+            }
+            _ => work.push((at + 1, after)),
+        }
+    }
+    Ok(())
 }
 
 /// Check if a .zphc file is still valid for the given source.
@@ -1038,4 +1471,92 @@ pub fn is_fresh(bytecode: &[u8], source: &str) -> bool {
     if bytecode.len() < 14 { return false; }
     let stored_hash = u64::from_le_bytes(bytecode[6..14].try_into().unwrap());
     stored_hash == fnv1a(source.as_bytes())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "let a = [1, 2]\nfun f(x) { x + a[0] }\nvar got = 0\nfor i in 0..3 { got = got + f(i) }\n";
+
+    fn compiled() -> Proto {
+        let tokens = crate::lexer::Lexer::new(SRC).tokenize().unwrap();
+        let stmts = crate::parser::Parser::new(tokens).parse_program().unwrap();
+        crate::compiler::compile(&stmts).unwrap()
+    }
+
+    /// Compile SRC, change its top-level code with `damage`, and decode it.
+    fn decode_damaged(damage: impl FnOnce(&mut Proto)) -> String {
+        let mut program = compiled();
+        damage(&mut program);
+        decode(&encode(&program, SRC)).expect_err("damaged bytecode should not decode")
+    }
+
+    fn find(p: &Proto, want: fn(&Op) -> bool) -> usize {
+        p.code.iter().position(want).unwrap()
+    }
+
+    #[test]
+    fn intact_programs_decode() {
+        let (program, source) = decode(&encode(&compiled(), SRC)).unwrap();
+        assert_eq!(program.code, compiled().code);
+        assert_eq!(source, SRC);
+    }
+
+    #[test]
+    fn out_of_range_operands_are_rejected() {
+        let err = decode_damaged(|p| {
+            let k = p.consts.len() as u32;
+            p.code[0] = Op::Const(k);
+        });
+        assert!(err.starts_with("Invalid bytecode in <script>: constant"), "{}", err);
+        assert!(err.ends_with("is out of range at instruction 0"), "{}", err);
+
+        let err = decode_damaged(|p| {
+            let at = find(p, |op| matches!(op, Op::IterInit(_)));
+            p.code[at] = Op::IterInit(p.slots);
+        });
+        assert!(err.contains("out of range"), "{}", err);
+
+        let err = decode_damaged(|p| {
+            let at = find(p, |op| matches!(op, Op::Jump(_)));
+            p.code[at] = Op::Jump(p.code.len() as u32);
+        });
+        assert!(err.contains("is out of range"), "{}", err);
+
+        let err = decode_damaged(|p| p.code[0] = Op::GetUpval(0));
+        assert!(err.contains("upvalue 0 is out of range"), "{}", err);
+
+        let err = decode_damaged(|p| p.slots = u32::MAX);
+        assert!(err.contains("bad slot count"), "{}", err);
+    }
+
+    #[test]
+    fn constants_of_the_wrong_kind_are_rejected() {
+        let err = decode_damaged(|p| {
+            let at = find(p, |op| matches!(op, Op::GetGlobal(_)));
+            p.code[at] = Op::Closure(0);
+        });
+        assert!(err.contains("is not a function"), "{}", err);
+    }
+
+    #[test]
+    fn damaged_bytes_never_panic() {
+        let bytes = encode(&compiled(), SRC);
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut rand = move |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % n as u64) as usize
+        };
+        for _ in 0..2000 {
+            let mut damaged = bytes.clone();
+            for _ in 0..3 {
+                let at = 14 + rand(damaged.len() - 14);
+                damaged[at] = rand(256) as u8;
+            }
+            let _ = decode(&damaged);
+            let _ = decode(&damaged[..rand(damaged.len())]);
+        }
+    }
+}
@@ -0,0 +1,990 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr Compiler — AST to VM instructions
+// ═══════════════════════════════════════════════════════════
+//
+// Each function body becomes a Proto: a flat instruction list, a
+// constant pool and a fixed number of local slots. Names are resolved
+// here, once, into one of three places:
+//
+//   local    a slot in the current frame (params come first)
+//   upvalue  a cell captured from an enclosing function
+//   global   a name in the interpreter's global Env, looked up by name
+//            and cached per instruction site by the VM
+//
+// A local that some nested function mentions is "boxed": its slot holds
+// a shared cell instead of the value, so closures see later writes.
+//
+// Top-level `let`/`fun` stay globals so the tree-walker, imported
+// modules and natives all see the same bindings. Definitions that only
+// touch interpreter tables (struct, enum, mod, import, type) are kept
+// as AST and executed by the interpreter through Op::Exec.
+//
+// ═══════════════════════════════════════════════════════════
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::ast::*;
+
+// ── Instructions ──────────────────────────────────────────────────────────────
+
+/// Operands are slot numbers, constant-pool indices, counts or absolute
+/// jump targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Const(u32),
+    Nil,
+    True,
+    False,
+    Pop,
+
+    GetLocal(u32),
+    SetLocal(u32),      // pops
+    GetCell(u32),       // read through a boxed local
+    SetCell(u32),       // pops, writes through a boxed local
+    NewCell(u32),       // pops into a fresh cell in a boxed local
+    BoxLocal(u32),      // turn a plain local (a captured param) into a cell
+    GetUpval(u32),
+    SetUpval(u32),      // pops
+    GetGlobal(u32),     // operand: name constant
+    SetGlobal(u32),     // pops; defines the name if it does not exist yet
+    DefineGlobal(u32),  // pops
+
+    Add, Sub, Mul, Div, Mod,
+    Eq, NotEq, Lt, LtEq, Gt, GtEq,
+    And, Or,
+    Neg, Not,
+
+    Jump(u32),
+    JumpIfFalse(u32),   // pops
+    ArgGiven(u32, u32), // (param, target) — jump if the caller passed it
+
+    Call(u32),          // argc; callee sits below the args
+    CallPath(u32, u32), // (path constant, argc)
+    Method(u32, u32),   // (name constant, argc); receiver sits below the args
+    TailCall(u32),      // argc; a Call in `return f(...)`, always followed by Return
+    Return,
+
+    Tuple(u32),
+    List(u32),
+    Map(u32),           // pair count; keys and values interleaved
+    Set(u32),
+    Concat(u32),        // interpolated string pieces
+    Format(u32),        // str constant: the spec to render the top value with
+    Range,
+    Struct(u32),        // names constant: [type, field...]
+    Variant(u32, u32),  // (names constant [enum, variant], argc)
+    Path(u32),
+    Field(u32),
+    SetField(u32),      // pops receiver then value
+    Index,
+    SetIndex,           // pops receiver, index, value
+
+    Closure(u32),       // proto constant
+    DefMethod(u32),     // names constant [type, method]; pops the function
+    DefTrait(u32, u32), // (names constant [trait, defaults..., required...], default count); pops the defaults
+    ImplTrait(u32),     // names constant [trait, type, methods...]; checks the impl, adds defaults
+
+    Some,
+    Ok,
+    Err,
+    Try,
+    Ref,
+
+    IterInit(u32),      // pops an iterable into slots n (items) and n+1 (position)
+    ForNext(u32, u32),  // (slot, exit) — push the next item or jump to exit
+    Match(u32, u32),    // (pattern constant, target) — pops; binds or jumps
+    Destructure(u32),   // pattern constant — pops; binds or fails
+    Exec(u32),          // statement constant, run by the interpreter
+    Fail(u32),          // raise the string constant as a runtime error
+}
+
+// ── Functions and constants ──────────────────────────────────────────────────
+
+#[derive(Debug)]
+pub struct Proto {
+    pub name: Option<String>,
+    // (name, has_default)
+    pub params: Vec<(String, bool)>,
+    pub slots: u32,
+    pub captures: Vec<Capture>,
+    pub code: Vec<Op>,
+    // source position of each instruction, parallel to `code`
+    pub spans: Vec<Span>,
+    pub consts: Vec<Constant>,
+    // resolved global cells, indexed like `consts`; filled by the VM
+    pub globals: RefCell<Vec<Option<Rc<RefCell<crate::interpreter::Value>>>>>,
+}
+
+/// Where a closure's upvalue comes from when it is created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capture {
+    pub from_local: bool, // a boxed local of the enclosing frame, or one of its upvalues
+    pub index: u32,
+}
+
+#[derive(Debug)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Names(Vec<String>),
+    Proto(Rc<Proto>),
+    Pattern(Pattern, Vec<Binding>),
+    Stmt(Stmt),
+}
+
+/// A name a pattern binds, and the local slot it lands in.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: String,
+    pub slot: u32,
+    pub boxed: bool,
+}
+
+impl Proto {
+    pub fn new(name: Option<String>, params: Vec<(String, bool)>) -> Self {
+        Proto {
+            name,
+            params,
+            slots: 0,
+            captures: Vec::new(),
+            code: Vec::new(),
+            spans: Vec::new(),
+            consts: Vec::new(),
+            globals: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+/// Compile a whole program. The result runs as the script's top frame.
+pub fn compile(stmts: &[Stmt]) -> Result<Proto, String> {
+    let mut c = Compiler { fns: Vec::new() };
+    c.fns.push(FnState::new(Proto::new(None, Vec::new()), captured_names(stmts), true));
+    c.body(stmts, false)?;
+    c.emit(Op::Nil);
+    c.emit(Op::Return);
+    Ok(c.fns.pop().unwrap().proto)
+}
+
+// ── Compiler state ────────────────────────────────────────────────────────────
+
+struct Local {
+    name: String,
+    slot: u32,
+    boxed: bool,
+}
+
+struct Loop {
+    continue_to: usize,
+    breaks: Vec<usize>,
+}
+
+struct FnState {
+    proto: Proto,
+    scopes: Vec<Vec<Local>>,
+    // upvalue names, parallel to proto.captures
+    upvals: Vec<String>,
+    // names mentioned inside nested functions; locals with these names are boxed
+    captured: HashSet<String>,
+    loops: Vec<Loop>,
+    // the top frame: names outside any block are globals
+    script: bool,
+    // position of the node being compiled, recorded for each emitted op
+    span: Span,
+}
+
+impl FnState {
+    fn new(proto: Proto, captured: HashSet<String>, script: bool) -> Self {
+        FnState { proto, scopes: Vec::new(), upvals: Vec::new(), captured, loops: Vec::new(), script, span: Span::default() }
+    }
+
+    fn find_local(&self, name: &str) -> Option<&Local> {
+        self.scopes.iter().rev().flat_map(|s| s.iter().rev()).find(|l| l.name == name)
+    }
+}
+
+enum Resolved {
+    Local(u32, bool),
+    Upval(u32),
+    Global,
+}
+
+struct Compiler {
+    fns: Vec<FnState>,
+}
+
+impl Compiler {
+    fn cur(&mut self) -> &mut FnState {
+        self.fns.last_mut().unwrap()
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        let f = self.cur();
+        f.proto.code.push(op);
+        f.proto.spans.push(f.span);
+        f.proto.code.len() - 1
+    }
+
+    fn here(&mut self) -> u32 {
+        self.cur().proto.code.len() as u32
+    }
+
+    /// Point the jump at `at` to the current position.
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        let code = &mut self.cur().proto.code;
+        code[at] = match code[at] {
+            Op::Jump(_)          => Op::Jump(target),
+            Op::JumpIfFalse(_)   => Op::JumpIfFalse(target),
+            Op::ArgGiven(p, _)   => Op::ArgGiven(p, target),
+            Op::ForNext(s, _)    => Op::ForNext(s, target),
+            Op::Match(p, _)      => Op::Match(p, target),
+            other => other,
+        };
+    }
+
+    fn constant(&mut self, c: Constant) -> u32 {
+        let consts = &mut self.cur().proto.consts;
+        consts.push(c);
+        (consts.len() - 1) as u32
+    }
+
+    /// Strings are pooled so repeated uses of a global share a cache entry.
+    fn str_const(&mut self, s: &str) -> u32 {
+        let consts = &self.cur().proto.consts;
+        if let Some(i) = consts.iter().position(|c| matches!(c, Constant::Str(x) if x == s)) {
+            return i as u32;
+        }
+        self.constant(Constant::Str(s.to_string()))
+    }
+
+    fn names_const(&mut self, names: Vec<String>) -> u32 {
+        self.constant(Constant::Names(names))
+    }
+
+    fn alloc_slot(&mut self) -> u32 {
+        let f = self.cur();
+        f.proto.slots += 1;
+        f.proto.slots - 1
+    }
+
+    fn begin_scope(&mut self) {
+        self.cur().scopes.push(Vec::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.cur().scopes.pop();
+    }
+
+    fn at_global_scope(&self) -> bool {
+        let f = self.fns.last().unwrap();
+        f.script && f.scopes.is_empty()
+    }
+
+    /// Add a local to the innermost scope and return (slot, boxed).
+    fn declare(&mut self, name: &str) -> (u32, bool) {
+        let slot = self.alloc_slot();
+        let f = self.cur();
+        let boxed = f.captured.contains(name);
+        f.scopes.last_mut().unwrap().push(Local { name: name.to_string(), slot, boxed });
+        (slot, boxed)
+    }
+
+    /// Pop the value on top of the stack into a freshly declared local.
+    fn init_local(&mut self, slot: u32, boxed: bool) {
+        self.emit(if boxed { Op::NewCell(slot) } else { Op::SetLocal(slot) });
+    }
+
+    /// The local `name` already has in the innermost scope. Binding it
+    /// again there updates that variable, as it does in the tree-walker,
+    /// so closures that captured it see the new value.
+    fn rebinding(&mut self, name: &str) -> Option<(u32, bool)> {
+        let l = self.cur().scopes.last()?.iter().find(|l| l.name == name)?;
+        Some((l.slot, l.boxed))
+    }
+
+    /// Pop the value on top of the stack into `slot`.
+    fn store_local(&mut self, slot: u32, boxed: bool) {
+        self.emit(if boxed { Op::SetCell(slot) } else { Op::SetLocal(slot) });
+    }
+
+    /// Pop the value on top of the stack into a new binding for `name`.
+    fn define(&mut self, name: &str) {
+        if self.at_global_scope() {
+            let k = self.str_const(name);
+            self.emit(Op::DefineGlobal(k));
+        } else if let Some((slot, boxed)) = self.rebinding(name) {
+            self.store_local(slot, boxed);
+        } else {
+            let (slot, boxed) = self.declare(name);
+            self.init_local(slot, boxed);
+        }
+    }
+
+    /// Bind the pattern to the value on top of the stack, failing at
+    /// runtime if it does not fit.
+    fn bind_pattern(&mut self, pat: &Pattern) {
+        if let Pattern::Ident(name) = pat {
+            return self.define(name);
+        }
+        let mut names = Vec::new();
+        pattern_names(pat, &mut names);
+        if self.at_global_scope() {
+            // destructure into scratch slots, then publish each as a global
+            let binds: Vec<Binding> = names.iter()
+                .map(|n| Binding { name: n.clone(), slot: self.alloc_slot(), boxed: false })
+                .collect();
+            let k = self.constant(Constant::Pattern(pat.clone(), binds.clone()));
+            self.emit(Op::Destructure(k));
+            for b in binds {
+                self.emit(Op::GetLocal(b.slot));
+                let k = self.str_const(&b.name);
+                self.emit(Op::DefineGlobal(k));
+            }
+            return;
+        }
+        // names bound again go through scratch slots into their variables
+        let mut rebound = Vec::new();
+        let binds = names.iter().map(|n| match self.rebinding(n) {
+            Some(local) => {
+                let slot = self.alloc_slot();
+                rebound.push((slot, local));
+                Binding { name: n.clone(), slot, boxed: false }
+            }
+            None => {
+                let (slot, boxed) = self.declare(n);
+                Binding { name: n.clone(), slot, boxed }
+            }
+        }).collect();
+        let k = self.constant(Constant::Pattern(pat.clone(), binds));
+        self.emit(Op::Destructure(k));
+        for (scratch, (slot, boxed)) in rebound {
+            self.emit(Op::GetLocal(scratch));
+            self.store_local(slot, boxed);
+        }
+    }
+
+    fn resolve(&mut self, depth: usize, name: &str) -> Resolved {
+        let f = &self.fns[depth];
+        if let Some(l) = f.find_local(name) {
+            return Resolved::Local(l.slot, l.boxed);
+        }
+        if let Some(i) = f.upvals.iter().position(|n| n == name) {
+            return Resolved::Upval(i as u32);
+        }
+        if depth == 0 {
+            return Resolved::Global;
+        }
+        let capture = match self.resolve(depth - 1, name) {
+            Resolved::Local(slot, true) => Capture { from_local: true, index: slot },
+            Resolved::Upval(i)          => Capture { from_local: false, index: i },
+            // unboxed locals are never mentioned by nested functions
+            Resolved::Local(_, false) | Resolved::Global => return Resolved::Global,
+        };
+        let f = &mut self.fns[depth];
+        f.upvals.push(name.to_string());
+        f.proto.captures.push(capture);
+        Resolved::Upval((f.upvals.len() - 1) as u32)
+    }
+
+    fn load(&mut self, name: &str) {
+        let depth = self.fns.len() - 1;
+        let op = match self.resolve(depth, name) {
+            Resolved::Local(slot, false) => Op::GetLocal(slot),
+            Resolved::Local(slot, true)  => Op::GetCell(slot),
+            Resolved::Upval(i)           => Op::GetUpval(i),
+            Resolved::Global             => Op::GetGlobal(self.str_const(name)),
+        };
+        self.emit(op);
+    }
+
+    fn store(&mut self, name: &str) {
+        let depth = self.fns.len() - 1;
+        let op = match self.resolve(depth, name) {
+            Resolved::Local(slot, false) => Op::SetLocal(slot),
+            Resolved::Local(slot, true)  => Op::SetCell(slot),
+            Resolved::Upval(i)           => Op::SetUpval(i),
+            Resolved::Global             => Op::SetGlobal(self.str_const(name)),
+        };
+        self.emit(op);
+    }
+
+    // ── Statements ────────────────────────────────────────────────────────────
+
+    /// Compile a statement list. With `keep_last`, the value of a trailing
+    /// expression statement (or nil) is left on the stack, which is what a
+    /// function body evaluates to.
+    fn body(&mut self, stmts: &[Stmt], keep_last: bool) -> Result<(), String> {
+        for (i, stmt) in stmts.iter().enumerate() {
+            let last = i + 1 == stmts.len();
+            match &stmt.kind {
+                StmtKind::Expr(e) if last && keep_last => { self.expr(e)?; }
+                _ => self.stmt(stmt)?,
+            }
+        }
+        if keep_last && !matches!(stmts.last().map(|s| &s.kind), Some(StmtKind::Expr(_))) {
+            self.emit(Op::Nil);
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), String> {
+        let outer = std::mem::replace(&mut self.cur().span, stmt.span);
+        let result = self.stmt_node(stmt);
+        self.cur().span = outer;
+        result
+    }
+
+    fn stmt_node(&mut self, stmt: &Stmt) -> Result<(), String> {
+        match &stmt.kind {
+            StmtKind::Let(pat, _, value, _) => {
+                self.expr(value)?;
+                self.bind_pattern(pat);
+            }
+
+            StmtKind::Expr(e) => {
+                self.expr(e)?;
+                self.emit(Op::Pop);
+            }
+
+            StmtKind::Return(value) => {
+                match value {
+                    Some(Expr { kind: ExprKind::Call(callee, args), .. }) if !matches!(callee.kind, ExprKind::Path(_)) => {
+                        self.expr(callee)?;
+                        let n = self.exprs(args)?;
+                        self.emit(Op::TailCall(n));
+                    }
+                    Some(e) => self.expr(e)?,
+                    None => { self.emit(Op::Nil); }
+                }
+                self.emit(Op::Return);
+            }
+
+            StmtKind::Break => {
+                let at = self.emit(Op::Jump(0));
+                match self.cur().loops.last_mut() {
+                    Some(l) => l.breaks.push(at),
+                    None => return Err("'break' outside of a loop".into()),
+                }
+            }
+
+            StmtKind::Continue => {
+                let target = match self.cur().loops.last() {
+                    Some(l) => l.continue_to as u32,
+                    None => return Err("'continue' outside of a loop".into()),
+                };
+                self.emit(Op::Jump(target));
+            }
+
+            StmtKind::While(cond, body, _) => {
+                let start = self.here() as usize;
+                self.expr(cond)?;
+                let exit = self.emit(Op::JumpIfFalse(0));
+                self.loop_body(start, body, |_| {})?;
+                self.emit(Op::Jump(start as u32));
+                self.patch(exit);
+                self.end_loop();
+            }
+
+            StmtKind::For(var, iter, body, _) => {
+                self.expr(iter)?;
+                let items = self.alloc_slot();
+                self.alloc_slot(); // position
+                self.emit(Op::IterInit(items));
+                let next = self.emit(Op::ForNext(items, 0));
+                self.loop_body(next, body, |c| c.bind_pattern(var))?;
+                self.emit(Op::Jump(next as u32));
+                self.patch(next);
+                self.end_loop();
+            }
+
+            StmtKind::FunDef(fun) => {
+                if self.at_global_scope() {
+                    self.function(fun)?;
+                    let k = self.str_const(&fun.name);
+                    self.emit(Op::DefineGlobal(k));
+                } else {
+                    // declare first so the body can call itself
+                    let (slot, boxed) = match self.rebinding(&fun.name) {
+                        Some(local) => local,
+                        None => {
+                            let (slot, boxed) = self.declare(&fun.name);
+                            if boxed {
+                                self.emit(Op::Nil);
+                                self.emit(Op::NewCell(slot));
+                            }
+                            (slot, boxed)
+                        }
+                    };
+                    self.function(fun)?;
+                    self.store_local(slot, boxed);
+                }
+            }
+
+            StmtKind::TraitDef(def) => {
+                for method in &def.defaults {
+                    self.function(method)?;
+                }
+                let names = std::iter::once(&def.name)
+                    .chain(def.defaults.iter().chain(&def.required).map(|m| &m.name))
+                    .cloned()
+                    .collect();
+                let k = self.names_const(names);
+                self.emit(Op::DefTrait(k, def.defaults.len() as u32));
+            }
+
+            StmtKind::ImplBlock(block) => {
+                for method in &block.methods {
+                    self.function(method)?;
+                    let k = self.names_const(vec![block.target.clone(), method.name.clone()]);
+                    self.emit(Op::DefMethod(k));
+                }
+                if let Some(trait_name) = &block.trait_name {
+                    let mut names = vec![trait_name.clone(), block.target.clone()];
+                    names.extend(block.methods.iter().map(|m| m.name.clone()));
+                    let k = self.names_const(names);
+                    self.emit(Op::ImplTrait(k));
+                }
+            }
+
+            StmtKind::StructDef(_) | StmtKind::EnumDef(_) | StmtKind::ModDef(..)
+            | StmtKind::Import(_) | StmtKind::TypeAlias(..) => {
+                let k = self.constant(Constant::Stmt(stmt.clone()));
+                self.emit(Op::Exec(k));
+            }
+        }
+        Ok(())
+    }
+
+    /// Body of a while/for loop, in a fresh scope per iteration. `bind`
+    /// declares the loop variable from the value on top of the stack.
+    fn loop_body(&mut self, continue_to: usize, body: &[Stmt], bind: impl FnOnce(&mut Self)) -> Result<(), String> {
+        self.cur().loops.push(Loop { continue_to, breaks: Vec::new() });
+        self.begin_scope();
+        bind(self);
+        let result = self.body(body, false);
+        self.end_scope();
+        result
+    }
+
+    fn end_loop(&mut self) {
+        let l = self.cur().loops.pop().unwrap();
+        for at in l.breaks { self.patch(at); }
+    }
+
+    /// Compile a named function and leave the closure on the stack.
+    fn function(&mut self, fun: &FunDef) -> Result<(), String> {
+        let params: Vec<(String, Option<&Expr>)> = fun.params.iter()
+            .map(|p| (p.name.clone(), p.default.as_ref()))
+            .collect();
+        self.closure(Some(fun.name.clone()), &params, |c| c.body(&fun.body, true), &fun.body, None)
+    }
+
+    /// Compile a function body into a new Proto and emit Op::Closure for it.
+    fn closure(
+        &mut self,
+        name: Option<String>,
+        params: &[(String, Option<&Expr>)],
+        compile_body: impl FnOnce(&mut Self) -> Result<(), String>,
+        body: &[Stmt],
+        tail: Option<&Expr>,
+    ) -> Result<(), String> {
+        let mut captured = captured_names(body);
+        if let Some(e) = tail { collect_expr(e, false, &mut captured); }
+        for (_, default) in params {
+            if let Some(d) = default { collect_expr(d, false, &mut captured); }
+        }
+        let proto = Proto::new(name, params.iter().map(|(n, d)| (n.clone(), d.is_some())).collect());
+        let span = self.cur().span;
+        self.fns.push(FnState::new(proto, captured, false));
+        self.cur().span = span;
+        self.begin_scope();
+
+        let mut boxed_params = Vec::new();
+        for (name, _) in params {
+            let (slot, boxed) = self.declare(name);
+            if boxed { boxed_params.push(slot); }
+        }
+        for (i, (_, default)) in params.iter().enumerate() {
+            if let Some(d) = default {
+                let skip = self.emit(Op::ArgGiven(i as u32, 0));
+                self.expr(d)?;
+                self.emit(Op::SetLocal(i as u32));
+                self.patch(skip);
+            }
+        }
+        for slot in boxed_params { self.emit(Op::BoxLocal(slot)); }
+
+        compile_body(self)?;
+        self.emit(Op::Return);
+
+        let f = self.fns.pop().unwrap();
+        let k = self.constant(Constant::Proto(Rc::new(f.proto)));
+        self.emit(Op::Closure(k));
+        Ok(())
+    }
+
+    // ── Expressions ───────────────────────────────────────────────────────────
+
+    fn exprs(&mut self, exprs: &[Expr]) -> Result<u32, String> {
+        for e in exprs { self.expr(e)?; }
+        Ok(exprs.len() as u32)
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<(), String> {
+        let outer = std::mem::replace(&mut self.cur().span, expr.span);
+        let result = self.expr_node(expr);
+        self.cur().span = outer;
+        result
+    }
+
+    fn expr_node(&mut self, expr: &Expr) -> Result<(), String> {
+        match &expr.kind {
+            ExprKind::Int(n) => {
+                let k = self.constant(Constant::Int(*n));
+                self.emit(Op::Const(k));
+            }
+            ExprKind::Float(f) => {
+                let k = self.constant(Constant::Float(*f));
+                self.emit(Op::Const(k));
+            }
+            ExprKind::StringLit(s) => {
+                let k = self.str_const(s);
+                self.emit(Op::Const(k));
+            }
+            ExprKind::Bool(true)  => { self.emit(Op::True); }
+            ExprKind::Bool(false) => { self.emit(Op::False); }
+            ExprKind::Nil         => { self.emit(Op::Nil); }
+
+            ExprKind::InterpolatedString(parts) => {
+                for part in parts {
+                    match part {
+                        StringPart::Literal(s) => {
+                            let k = self.str_const(s);
+                            self.emit(Op::Const(k));
+                        }
+                        StringPart::Interpolated(e, None) => self.expr(e)?,
+                        StringPart::Interpolated(e, Some(spec)) => {
+                            self.expr(e)?;
+                            let k = self.str_const(spec);
+                            self.emit(Op::Format(k));
+                        }
+                    }
+                }
+                self.emit(Op::Concat(parts.len() as u32));
+            }
+
+            ExprKind::Var(name, _) => self.load(name),
+
+            ExprKind::Tuple(elems) => {
+                let n = self.exprs(elems)?;
+                self.emit(Op::Tuple(n));
+            }
+            ExprKind::List(elems) => {
+                let n = self.exprs(elems)?;
+                self.emit(Op::List(n));
+            }
+            ExprKind::MapLit(pairs) => {
+                for (k, v) in pairs {
+                    self.expr(k)?;
+                    self.expr(v)?;
+                }
+                self.emit(Op::Map(pairs.len() as u32));
+            }
+            ExprKind::SetLit(elems) => {
+                let n = self.exprs(elems)?;
+                self.emit(Op::Set(n));
+            }
+
+            ExprKind::Block(stmts, tail, _) => {
+                self.begin_scope();
+                self.body(stmts, false)?;
+                match tail {
+                    Some(e) => self.expr(e)?,
+                    None => { self.emit(Op::Nil); }
+                }
+                self.end_scope();
+            }
+
+            ExprKind::BinOp(l, op, r) => {
+                self.expr(l)?;
+                self.expr(r)?;
+                self.emit(match op {
+                    BinOp::Add => Op::Add, BinOp::Sub => Op::Sub, BinOp::Mul => Op::Mul,
+                    BinOp::Div => Op::Div, BinOp::Mod => Op::Mod,
+                    BinOp::Eq => Op::Eq, BinOp::NotEq => Op::NotEq,
+                    BinOp::Lt => Op::Lt, BinOp::LtEq => Op::LtEq,
+                    BinOp::Gt => Op::Gt, BinOp::GtEq => Op::GtEq,
+                    BinOp::And => Op::And, BinOp::Or => Op::Or,
+                    BinOp::DotDot => Op::Range,
+                });
+            }
+
+            ExprKind::UnaryOp(op, e) => {
+                self.expr(e)?;
+                self.emit(match op { UnaryOp::Neg => Op::Neg, UnaryOp::Not => Op::Not });
+            }
+
+            ExprKind::Assign(target, value) => {
+                match &target.kind {
+                    ExprKind::Var(name, _) => {
+                        self.expr(value)?;
+                        self.store(name);
+                    }
+                    ExprKind::Index(obj, idx) => {
+                        self.expr(value)?;
+                        self.expr(idx)?;
+                        self.expr(obj)?;
+                        self.emit(Op::SetIndex);
+                    }
+                    ExprKind::FieldAccess(obj, field) => {
+                        self.expr(value)?;
+                        self.expr(obj)?;
+                        let k = self.str_const(field);
+                        self.emit(Op::SetField(k));
+                    }
+                    _ => return Err("Invalid assignment target".into()),
+                }
+                self.emit(Op::Nil);
+            }
+
+            ExprKind::Call(callee, args) => {
+                if let ExprKind::Path(segs) = &callee.kind {
+                    let n = self.exprs(args)?;
+                    let k = self.names_const(segs.clone());
+                    self.emit(Op::CallPath(k, n));
+                } else {
+                    self.expr(callee)?;
+                    let n = self.exprs(args)?;
+                    self.emit(Op::Call(n));
+                }
+            }
+
+            ExprKind::MethodCall(obj, method, args) => {
+                self.expr(obj)?;
+                let n = self.exprs(args)?;
+                let k = self.str_const(method);
+                self.emit(Op::Method(k, n));
+            }
+
+            ExprKind::FieldAccess(obj, field) => {
+                self.expr(obj)?;
+                let k = self.str_const(field);
+                self.emit(Op::Field(k));
+            }
+
+            ExprKind::Index(obj, idx) => {
+                self.expr(obj)?;
+                self.expr(idx)?;
+                self.emit(Op::Index);
+            }
+
+            ExprKind::If(cond, then, elifs, els) => {
+                let mut ends = Vec::new();
+                let branches = std::iter::once((cond.as_ref(), then.as_ref()))
+                    .chain(elifs.iter().map(|(c, b)| (c, b)));
+                for (c, b) in branches {
+                    self.expr(c)?;
+                    let next = self.emit(Op::JumpIfFalse(0));
+                    self.expr(b)?;
+                    ends.push(self.emit(Op::Jump(0)));
+                    self.patch(next);
+                }
+                match els {
+                    Some(e) => self.expr(e)?,
+                    None => { self.emit(Op::Nil); }
+                }
+                for at in ends { self.patch(at); }
+            }
+
+            ExprKind::Match(subject, arms) => {
+                self.expr(subject)?;
+                let slot = self.alloc_slot();
+                self.emit(Op::SetLocal(slot));
+                let mut ends = Vec::new();
+                for arm in arms {
+                    self.begin_scope();
+                    let mut names = Vec::new();
+                    pattern_names(&arm.pattern, &mut names);
+                    let binds = names.iter().map(|n| {
+                        let (slot, boxed) = self.declare(n);
+                        Binding { name: n.clone(), slot, boxed }
+                    }).collect();
+                    let k = self.constant(Constant::Pattern(arm.pattern.clone(), binds));
+                    self.emit(Op::GetLocal(slot));
+                    let next = self.emit(Op::Match(k, 0));
+                    let guard_fail = match &arm.guard {
+                        Some(g) => {
+                            self.expr(g)?;
+                            Some(self.emit(Op::JumpIfFalse(0)))
+                        }
+                        None => None,
+                    };
+                    self.expr(&arm.body)?;
+                    ends.push(self.emit(Op::Jump(0)));
+                    self.patch(next);
+                    if let Some(at) = guard_fail { self.patch(at); }
+                    self.end_scope();
+                }
+                let k = self.str_const("Non-exhaustive match");
+                self.emit(Op::Fail(k));
+                for at in ends { self.patch(at); }
+            }
+
+            ExprKind::Closure(params, body, _) => {
+                let params: Vec<(String, Option<&Expr>)> = params.iter().map(|(n, _)| (n.clone(), None)).collect();
+                self.closure(None, &params, |c| c.expr(body), &[], Some(body))?;
+            }
+
+            ExprKind::StructCreate(name, fields) => {
+                let mut names = vec![name.clone()];
+                for (fname, fexpr) in fields {
+                    self.expr(fexpr)?;
+                    names.push(fname.clone());
+                }
+                let k = self.names_const(names);
+                self.emit(Op::Struct(k));
+            }
+
+            ExprKind::EnumVariant(enum_name, variant, args) => {
+                let n = self.exprs(args)?;
+                let k = self.names_const(vec![enum_name.clone(), variant.clone()]);
+                self.emit(Op::Variant(k, n));
+            }
+
+            ExprKind::Path(segs) => {
+                let k = self.names_const(segs.clone());
+                self.emit(Op::Path(k));
+            }
+
+            ExprKind::Range(start, end) => {
+                self.expr(start)?;
+                self.expr(end)?;
+                self.emit(Op::Range);
+            }
+
+            ExprKind::Some(e) => { self.expr(e)?; self.emit(Op::Some); }
+            ExprKind::Ok(e)   => { self.expr(e)?; self.emit(Op::Ok); }
+            ExprKind::Err(e)  => { self.expr(e)?; self.emit(Op::Err); }
+            ExprKind::Question(e) => { self.expr(e)?; self.emit(Op::Try); }
+            ExprKind::RefExpr(e)  => { self.expr(e)?; self.emit(Op::Ref); }
+            ExprKind::BoxExpr(e) | ExprKind::Await(e) => self.expr(e)?,
+        }
+        Ok(())
+    }
+}
+
+// ── Capture analysis ──────────────────────────────────────────────────────────
+
+/// Every name mentioned inside a function nested in `body`. Coarse — it
+/// ignores shadowing — but a spurious entry only costs a cell.
+fn captured_names(body: &[Stmt]) -> HashSet<String> {
+    let mut out = HashSet::new();
+    for s in body { collect_stmt(s, false, &mut out); }
+    out
+}
+
+/// Every name a function mentions anywhere in its defaults or body.
+pub(crate) fn mentioned_names(params: &[Param], body: &[Stmt]) -> HashSet<String> {
+    let mut out = HashSet::new();
+    for p in params {
+        if let Some(d) = &p.default { collect_expr(d, true, &mut out); }
+    }
+    for s in body { collect_stmt(s, true, &mut out); }
+    out
+}
+
+fn collect_stmt(stmt: &Stmt, nested: bool, out: &mut HashSet<String>) {
+    match &stmt.kind {
+        StmtKind::Let(_, _, e, _) | StmtKind::Expr(e) => collect_expr(e, nested, out),
+        StmtKind::Return(Some(e)) => collect_expr(e, nested, out),
+        StmtKind::While(c, body, _) => {
+            collect_expr(c, nested, out);
+            for s in body { collect_stmt(s, nested, out); }
+        }
+        StmtKind::For(_, e, body, _) => {
+            collect_expr(e, nested, out);
+            for s in body { collect_stmt(s, nested, out); }
+        }
+        StmtKind::FunDef(f) => collect_fun(f, out),
+        StmtKind::ImplBlock(b) => for m in &b.methods { collect_fun(m, out) },
+        StmtKind::TraitDef(t) => for m in &t.defaults { collect_fun(m, out) },
+        _ => {}
+    }
+}
+
+fn collect_fun(f: &FunDef, out: &mut HashSet<String>) {
+    for p in &f.params {
+        if let Some(d) = &p.default { collect_expr(d, true, out); }
+    }
+    for s in &f.body { collect_stmt(s, true, out); }
+}
+
+fn collect_expr(expr: &Expr, nested: bool, out: &mut HashSet<String>) {
+    let go = |e: &Expr, out: &mut HashSet<String>| collect_expr(e, nested, out);
+    match &expr.kind {
+        ExprKind::Var(name, _) => if nested { out.insert(name.clone()); },
+        ExprKind::Closure(_, body, _) => collect_expr(body, true, out),
+        ExprKind::InterpolatedString(parts) => for p in parts {
+            if let StringPart::Interpolated(e, _) = p { go(e, out) }
+        },
+        ExprKind::Tuple(es) | ExprKind::List(es) | ExprKind::SetLit(es) => for e in es { go(e, out) },
+        // `m::f(x)` parses as a variant; the head may name a module
+        ExprKind::EnumVariant(head, _, es) => {
+            if nested { out.insert(head.clone()); }
+            for e in es { go(e, out) }
+        }
+        ExprKind::Path(segs) => if nested { out.insert(segs[0].clone()); },
+        ExprKind::MapLit(pairs) => for (k, v) in pairs { go(k, out); go(v, out); },
+        ExprKind::Block(stmts, tail, _) => {
+            for s in stmts { collect_stmt(s, nested, out); }
+            if let Some(t) = tail { go(t, out); }
+        }
+        ExprKind::BinOp(l, _, r) | ExprKind::Index(l, r) | ExprKind::Range(l, r) | ExprKind::Assign(l, r) => { go(l, out); go(r, out); }
+        ExprKind::UnaryOp(_, e) | ExprKind::FieldAccess(e, _) | ExprKind::Some(e) | ExprKind::Ok(e) | ExprKind::Err(e)
+        | ExprKind::Question(e) | ExprKind::BoxExpr(e) | ExprKind::RefExpr(e) | ExprKind::Await(e) => go(e, out),
+        ExprKind::Call(f, args) | ExprKind::MethodCall(f, _, args) => {
+            go(f, out);
+            for a in args { go(a, out); }
+        }
+        ExprKind::If(c, t, elifs, els) => {
+            go(c, out);
+            go(t, out);
+            for (c, b) in elifs { go(c, out); go(b, out); }
+            if let Some(e) = els { go(e, out); }
+        }
+        ExprKind::Match(subject, arms) => {
+            go(subject, out);
+            for arm in arms {
+                if let Some(g) = &arm.guard { go(g, out); }
+                go(&arm.body, out);
+            }
+        }
+        ExprKind::StructCreate(_, fields) => for (_, e) in fields { go(e, out) },
+        ExprKind::Int(_) | ExprKind::Float(_) | ExprKind::Bool(_) | ExprKind::StringLit(_) | ExprKind::Nil => {}
+    }
+}
+
+/// Names a pattern binds, in first-seen order.
+pub(crate) fn pattern_names(pat: &Pattern, out: &mut Vec<String>) {
+    match pat {
+        Pattern::Ident(name) if !out.contains(name) => out.push(name.clone()),
+        Pattern::Tuple(ps) | Pattern::List(ps) | Pattern::EnumVariant(_, _, ps) => {
+            for p in ps { pattern_names(p, out); }
+        }
+        Pattern::Struct(_, fields, _) => for (_, p) in fields { pattern_names(p, out) },
+        Pattern::Rest(Some(name)) if !out.contains(name) => out.push(name.clone()),
+        Pattern::Bind(name, p) => {
+            if !out.contains(name) { out.push(name.clone()); }
+            pattern_names(p, out);
+        }
+        Pattern::Some(p) | Pattern::Ok(p) | Pattern::Err(p) => pattern_names(p, out),
+        Pattern::Or(a, b) | Pattern::Range(a, b) => {
+            pattern_names(a, out);
+            pattern_names(b, out);
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,432 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr DateTime — wall-clock time, durations and timers
+// ═══════════════════════════════════════════════════════════
+//
+// QUICK REFERENCE
+//
+//   now(offset?)                          DateTime, UTC unless an offset is given
+//   datetime(y, mo, d, h?, mi?, s?, off?) DateTime from its parts
+//   from_timestamp(secs, offset?)         DateTime from Unix seconds (Int or Float)
+//   parse_datetime(s, fmt?)               Result<DateTime, String>
+//   format_datetime(dt, fmt)              String
+//   to_utc(dt)  with_offset(dt, off)      the same instant in another zone
+//   timestamp(dt)  timestamp_ms(dt)       Unix seconds / milliseconds
+//
+//   days(n) hours(n) minutes(n) seconds(n) millis(n)     Duration
+//
+//   instant()                             Int, nanoseconds on a monotonic clock
+//   elapsed_ms(start)                     Float, milliseconds since `start`
+//
+// An offset is "Z", "UTC", "+HH:MM", "+HHMM" or "+HH". A DateTime keeps
+// its offset; comparisons and equality look only at the instant.
+//
+// Without a format, parse_datetime takes RFC 3339 / ISO 8601
+// ("2024-05-01T12:00:00+02:00", "2024-05-01 12:00:00", "2024-05-01").
+// Formats are strftime patterns ("%Y-%m-%d %H:%M"); "iso" / "rfc3339" and
+// "rfc2822" name the standard layouts. A pattern without a zone reads as UTC.
+//
+// Arithmetic:  dt + dur   dt - dur   dt - dt → Duration
+//              dur + dur  dur - dur  dur * n  -dur
+//
+// Methods on DateTime: year month day hour minute second millisecond
+// weekday (1 = Monday) ordinal offset format(fmt) to_utc() with_offset(off)
+// timestamp() timestamp_ms() to_iso()
+// Methods on Duration: total_seconds() total_ms() abs()
+//
+//   let start = instant()
+//   let name = "report-#{now().format(\"%Y%m%d-%H%M%S\")}.json"
+//   let due = parse_datetime("2024-05-01").unwrap() + days(30)
+//   if now() > due { println("overdue by", now() - due) }
+//   println("took", elapsed_ms(start), "ms")
+//
+// ═══════════════════════════════════════════════════════════
+
+use std::fmt::Write;
+use std::sync::OnceLock;
+use std::time::Instant;
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat, TimeDelta, Timelike, Utc};
+use crate::ast::BinOp;
+use crate::interpreter::Value;
+
+// ── Registration ──────────────────────────────────────────────────────────────
+
+pub fn datetime_functions() -> Vec<&'static str> {
+    vec![
+        "now",
+        "datetime",
+        "from_timestamp",
+        "parse_datetime",
+        "format_datetime",
+        "to_utc",
+        "with_offset",
+        "timestamp",
+        "timestamp_ms",
+        "days",
+        "hours",
+        "minutes",
+        "seconds",
+        "millis",
+        "instant",
+        "elapsed_ms",
+    ]
+}
+
+// ── Dispatch ──────────────────────────────────────────────────────────────────
+
+pub fn call_datetime(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    match name {
+        "now"             => now(args),
+        "datetime"        => datetime(args),
+        "from_timestamp"  => from_timestamp(args),
+        "parse_datetime"  => parse_datetime(args),
+        "format_datetime" => {
+            let dt = datetime_arg(&args, 0, "format_datetime(dt, fmt)")?;
+            let fmt = str_arg(&args, 1, "format_datetime(dt, fmt)")?;
+            format_with(&dt, fmt).map(Value::str)
+        }
+        "to_utc"          => Ok(Value::DateTime(datetime_arg(&args, 0, "to_utc(dt)")?.with_timezone(&utc()))),
+        "with_offset"     => {
+            let dt = datetime_arg(&args, 0, "with_offset(dt, offset)")?;
+            let off = parse_offset(str_arg(&args, 1, "with_offset(dt, offset)")?)?;
+            Ok(Value::DateTime(dt.with_timezone(&off)))
+        }
+        "timestamp"       => Ok(Value::Int(datetime_arg(&args, 0, "timestamp(dt)")?.timestamp())),
+        "timestamp_ms"    => Ok(Value::Int(datetime_arg(&args, 0, "timestamp_ms(dt)")?.timestamp_millis())),
+        "days"            => duration_of(&args, name, 86_400_000.0),
+        "hours"           => duration_of(&args, name, 3_600_000.0),
+        "minutes"         => duration_of(&args, name, 60_000.0),
+        "seconds"         => duration_of(&args, name, 1_000.0),
+        "millis"          => duration_of(&args, name, 1.0),
+        "instant"         => Ok(Value::Int(origin().elapsed().as_nanos() as i64)),
+        "elapsed_ms"      => match args.first() {
+            Some(Value::Int(start)) => {
+                let now = origin().elapsed().as_nanos() as i64;
+                Ok(Value::Float((now - start) as f64 / 1e6))
+            }
+            _ => Err("elapsed_ms(start) requires an Int from instant()".into()),
+        },
+        _ => Err(format!("Unknown datetime function '{}'", name)),
+    }
+}
+
+/// Everything `call_method` answers, for REPL completion.
+pub const METHODS: &[&str] = &[
+    "year", "month", "day", "hour", "minute", "second", "millisecond", "weekday", "ordinal",
+    "offset", "timestamp", "timestamp_ms", "to_iso", "to_utc", "with_offset", "format",
+    "total_seconds", "total_ms", "abs",
+];
+
+/// Methods on DateTime and Duration values.
+pub fn call_method(obj: &Value, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    match obj {
+        Value::DateTime(dt) => {
+            let int = |n: u32| Ok(Value::Int(n as i64));
+            match method {
+                "year"         => Ok(Value::Int(dt.year() as i64)),
+                "month"        => int(dt.month()),
+                "day"          => int(dt.day()),
+                "hour"         => int(dt.hour()),
+                "minute"       => int(dt.minute()),
+                "second"       => int(dt.second()),
+                "millisecond"  => int(dt.timestamp_subsec_millis()),
+                "weekday"      => int(dt.weekday().number_from_monday()),
+                "ordinal"      => int(dt.ordinal()),
+                "offset"       => Ok(Value::str(dt.offset().to_string())),
+                "timestamp"    => Ok(Value::Int(dt.timestamp())),
+                "timestamp_ms" => Ok(Value::Int(dt.timestamp_millis())),
+                "to_iso"       => Ok(Value::str(iso(dt))),
+                "to_utc"       => Ok(Value::DateTime(dt.with_timezone(&utc()))),
+                "with_offset"  => {
+                    let off = parse_offset(str_arg(&args, 0, "with_offset(offset)")?)?;
+                    Ok(Value::DateTime(dt.with_timezone(&off)))
+                }
+                "format"       => format_with(dt, str_arg(&args, 0, "format(fmt)")?).map(Value::str),
+                _ => Err(format!("No method '{}' on type DateTime", method)),
+            }
+        }
+        Value::Duration(d) => match method {
+            "total_seconds" => Ok(Value::Float(d.num_microseconds().map_or(d.num_milliseconds() as f64 / 1e3, |us| us as f64 / 1e6))),
+            "total_ms"      => Ok(Value::Int(d.num_milliseconds())),
+            "abs"           => Ok(Value::Duration(d.abs())),
+            _ => Err(format!("No method '{}' on type Duration", method)),
+        },
+        other => Err(format!("No method '{}' on {}", method, other)),
+    }
+}
+
+/// `+`, `-` and `*` where a DateTime or Duration is involved.
+pub fn arith(l: &Value, op: &BinOp, r: &Value) -> Result<Value, String> {
+    let overflow = || format!("DateTime arithmetic overflowed: {} {} {}", l, op_symbol(op), r);
+    match (l, op, r) {
+        (Value::DateTime(t), BinOp::Add, Value::Duration(d)) | (Value::Duration(d), BinOp::Add, Value::DateTime(t)) => {
+            t.checked_add_signed(*d).map(Value::DateTime).ok_or_else(overflow)
+        }
+        (Value::DateTime(t), BinOp::Sub, Value::Duration(d)) => t.checked_sub_signed(*d).map(Value::DateTime).ok_or_else(overflow),
+        (Value::DateTime(a), BinOp::Sub, Value::DateTime(b)) => Ok(Value::Duration(a.signed_duration_since(*b))),
+        (Value::Duration(a), BinOp::Add, Value::Duration(b)) => a.checked_add(b).map(Value::Duration).ok_or_else(overflow),
+        (Value::Duration(a), BinOp::Sub, Value::Duration(b)) => a.checked_sub(b).map(Value::Duration).ok_or_else(overflow),
+        (Value::Duration(d), BinOp::Mul, Value::Int(n)) | (Value::Int(n), BinOp::Mul, Value::Duration(d)) => {
+            i32::try_from(*n).ok().and_then(|n| d.checked_mul(n)).map(Value::Duration).ok_or_else(overflow)
+        }
+        _ => Err(format!("Cannot apply '{}' to {} and {}", op_symbol(op), l, r)),
+    }
+}
+
+/// How a Duration prints: `1d2h30m`, `1.5s`, `250ms`, `0s`.
+pub fn fmt_duration(d: &TimeDelta) -> String {
+    let ms = d.num_milliseconds();
+    let sign = if ms < 0 { "-" } else { "" };
+    let ms = ms.unsigned_abs();
+    if ms < 1000 {
+        return if ms == 0 { "0s".into() } else { format!("{}{}ms", sign, ms) };
+    }
+    let mut out = sign.to_string();
+    for (n, unit) in [(ms / 86_400_000, "d"), (ms / 3_600_000 % 24, "h"), (ms / 60_000 % 60, "m")] {
+        if n != 0 {
+            write!(out, "{}{}", n, unit).ok();
+        }
+    }
+    let (secs, frac) = ((ms / 1000) % 60, ms % 1000);
+    match (secs, frac) {
+        (0, 0) => {}
+        (s, 0) => { write!(out, "{}s", s).ok(); }
+        (s, f) => { write!(out, "{}.{}s", s, format!("{:03}", f).trim_end_matches('0')).ok(); }
+    }
+    out
+}
+
+/// RFC 3339, with `Z` for UTC and only as many fractional digits as needed.
+pub fn iso(dt: &DateTime<FixedOffset>) -> String {
+    dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+// ── Functions ─────────────────────────────────────────────────────────────────
+
+fn now(args: Vec<Value>) -> Result<Value, String> {
+    let off = match args.first() {
+        Some(Value::Str(s)) => parse_offset(s)?,
+        Some(other) => return Err(format!("now(offset) — offset must be a String, got {}", other)),
+        None => utc(),
+    };
+    Ok(Value::DateTime(Utc::now().with_timezone(&off)))
+}
+
+fn datetime(args: Vec<Value>) -> Result<Value, String> {
+    let sig = "datetime(year, month, day, hour?, minute?, second?, offset?)";
+    let part = |i: usize| -> Result<i64, String> {
+        match args.get(i) {
+            Some(Value::Int(n)) => Ok(*n),
+            None if i >= 3 => Ok(0),
+            Some(other) => Err(format!("{} — argument {} must be an Int, got {}", sig, i + 1, other)),
+            None => Err(format!("{} — argument {} is required", sig, i + 1)),
+        }
+    };
+    let (y, mo, d, h, mi) = (part(0)?, part(1)?, part(2)?, part(3)?, part(4)?);
+    let secs = match args.get(5) {
+        Some(Value::Int(n)) => *n as f64,
+        Some(Value::Float(f)) => *f,
+        Some(other) => return Err(format!("{} — second must be a number, got {}", sig, other)),
+        None => 0.0,
+    };
+    let off = match args.get(6) {
+        Some(Value::Str(s)) => parse_offset(s)?,
+        Some(other) => return Err(format!("{} — offset must be a String, got {}", sig, other)),
+        None => utc(),
+    };
+    let invalid = || format!("datetime: {}-{:02}-{:02} {:02}:{:02}:{:02} is not a valid time", y, mo, d, h, mi, secs as i64);
+    let fit = |n: i64| u32::try_from(n).map_err(|_| invalid());
+    let date = NaiveDate::from_ymd_opt(i32::try_from(y).map_err(|_| invalid())?, fit(mo)?, fit(d)?).ok_or_else(invalid)?;
+    if !(0.0..60.0).contains(&secs) {
+        return Err(invalid());
+    }
+    let nanos = (secs.fract() * 1e9).round() as u32;
+    let naive = date.and_hms_nano_opt(fit(h)?, fit(mi)?, secs as u32, nanos).ok_or_else(invalid)?;
+    naive.and_local_timezone(off).single().map(Value::DateTime).ok_or_else(invalid)
+}
+
+fn from_timestamp(args: Vec<Value>) -> Result<Value, String> {
+    let sig = "from_timestamp(secs, offset?)";
+    let dt = match args.first() {
+        Some(Value::Int(n)) => DateTime::from_timestamp(*n, 0),
+        Some(Value::Float(f)) => DateTime::from_timestamp(f.floor() as i64, (f.fract().abs() * 1e9).round().min(999_999_999.0) as u32),
+        Some(other) => return Err(format!("{} — secs must be a number, got {}", sig, other)),
+        None => return Err(format!("{} — argument 1 is required", sig)),
+    }
+    .ok_or_else(|| format!("from_timestamp: {} is out of range", args[0]))?;
+    let off = match args.get(1) {
+        Some(Value::Str(s)) => parse_offset(s)?,
+        Some(other) => return Err(format!("{} — offset must be a String, got {}", sig, other)),
+        None => utc(),
+    };
+    Ok(Value::DateTime(dt.with_timezone(&off)))
+}
+
+fn parse_datetime(args: Vec<Value>) -> Result<Value, String> {
+    let sig = "parse_datetime(text, fmt?)";
+    let text = str_arg(&args, 0, sig)?.trim();
+    let parsed = match args.get(1) {
+        Some(Value::Str(fmt)) => parse_with(text, fmt),
+        Some(other) => return Err(format!("{} — fmt must be a String, got {}", sig, other)),
+        None => parse_iso(text),
+    };
+    Ok(Value::Result(match parsed {
+        Ok(dt) => Ok(Box::new(Value::DateTime(dt))),
+        Err(e) => Err(Box::new(Value::str(e))),
+    }))
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+fn parse_iso(text: &str) -> Result<DateTime<FixedOffset>, String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Ok(dt);
+    }
+    for fmt in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(text, fmt) {
+            return Ok(naive.and_utc().fixed_offset());
+        }
+    }
+    match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        Ok(date) => Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().fixed_offset()),
+        Err(_) => Err(format!("'{}' is not an ISO 8601 date or time", text)),
+    }
+}
+
+fn parse_with(text: &str, fmt: &str) -> Result<DateTime<FixedOffset>, String> {
+    match fmt {
+        "iso" | "rfc3339" => return DateTime::parse_from_rfc3339(text).map_err(|e| format!("'{}' is not RFC 3339: {}", text, e)),
+        "rfc2822" => return DateTime::parse_from_rfc2822(text).map_err(|e| format!("'{}' is not RFC 2822: {}", text, e)),
+        _ => {}
+    }
+    let zone_err = match DateTime::parse_from_str(text, fmt) {
+        Ok(dt) => return Ok(dt),
+        Err(e) => e,
+    };
+    if let Ok(naive) = NaiveDateTime::parse_from_str(text, fmt) {
+        return Ok(naive.and_utc().fixed_offset());
+    }
+    match NaiveDate::parse_from_str(text, fmt) {
+        Ok(date) => Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().fixed_offset()),
+        Err(_) => Err(format!("'{}' does not match '{}': {}", text, fmt, zone_err)),
+    }
+}
+
+fn format_with(dt: &DateTime<FixedOffset>, fmt: &str) -> Result<String, String> {
+    match fmt {
+        "iso" | "rfc3339" => return Ok(iso(dt)),
+        "rfc2822" => return Ok(dt.to_rfc2822()),
+        _ => {}
+    }
+    let mut out = String::new();
+    write!(out, "{}", dt.format(fmt)).map_err(|_| format!("Invalid datetime format '{}'", fmt))?;
+    Ok(out)
+}
+
+fn parse_offset(s: &str) -> Result<FixedOffset, String> {
+    if matches!(s, "Z" | "z" | "UTC" | "utc") {
+        return Ok(utc());
+    }
+    let bad = || format!("Invalid UTC offset '{}' (use Z, +HH:MM, +HHMM or +HH)", s);
+    let (sign, rest) = match s.as_bytes().first() {
+        Some(b'+') => (1, &s[1..]),
+        Some(b'-') => (-1, &s[1..]),
+        _ => return Err(bad()),
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) || !matches!(digits.len(), 2 | 4) || rest.len() > 5 {
+        return Err(bad());
+    }
+    let hours: i32 = digits[..2].parse().map_err(|_| bad())?;
+    let mins: i32 = if digits.len() == 4 { digits[2..].parse().map_err(|_| bad())? } else { 0 };
+    if mins >= 60 {
+        return Err(bad());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + mins * 60)).ok_or_else(bad)
+}
+
+fn duration_of(args: &[Value], name: &str, ms_per_unit: f64) -> Result<Value, String> {
+    let ms = match args.first() {
+        Some(Value::Int(n)) => *n as f64 * ms_per_unit,
+        Some(Value::Float(f)) => f * ms_per_unit,
+        Some(other) => return Err(format!("{}(n) requires a number, got {}", name, other)),
+        None => return Err(format!("{}(n) requires 1 argument", name)),
+    };
+    let micros = (ms * 1e3).round();
+    if !micros.is_finite() || micros.abs() >= i64::MAX as f64 {
+        return Err(format!("{}({}) is out of range", name, args[0]));
+    }
+    Ok(Value::Duration(TimeDelta::microseconds(micros as i64)))
+}
+
+fn datetime_arg(args: &[Value], idx: usize, sig: &str) -> Result<DateTime<FixedOffset>, String> {
+    match args.get(idx) {
+        Some(Value::DateTime(dt)) => Ok(*dt),
+        Some(other) => Err(format!("{} — argument {} must be a DateTime, got {}", sig, idx + 1, other)),
+        None => Err(format!("{} — argument {} is required", sig, idx + 1)),
+    }
+}
+
+fn str_arg<'a>(args: &'a [Value], idx: usize, sig: &str) -> Result<&'a str, String> {
+    match args.get(idx) {
+        Some(Value::Str(s)) => Ok(s),
+        Some(other) => Err(format!("{} — argument {} must be a String, got {}", sig, idx + 1, other)),
+        None => Err(format!("{} — argument {} is required", sig, idx + 1)),
+    }
+}
+
+fn utc() -> FixedOffset {
+    FixedOffset::east_opt(0).expect("zero offset")
+}
+
+// the monotonic clock's zero: the first time anyone asked for it
+fn origin() -> Instant {
+    static ORIGIN: OnceLock<Instant> = OnceLock::new();
+    *ORIGIN.get_or_init(Instant::now)
+}
+
+fn op_symbol(op: &BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        _ => "?",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::eval;
+
+    fn show(src: &str) -> String {
+        format!("{}", eval(src).unwrap())
+    }
+
+    #[test]
+    fn arithmetic_crosses_day_boundaries() {
+        let src = "let t = datetime(2024, 2, 28, 23, 30)\n[t + hours(1), t - days(59), (t + minutes(90)) - t, -seconds(1.5)]";
+        assert_eq!(show(src), "[2024-02-29T00:30:00Z, 2023-12-31T23:30:00Z, 1h30m, -1.5s]");
+        assert_eq!(show("days(1) * 3 + millis(250)"), "3d0.25s");
+    }
+
+    #[test]
+    fn offsets_are_kept_but_comparisons_use_the_instant() {
+        let src = "let p = parse_datetime(\"2024-05-01T12:00:00+02:00\").unwrap()\n\
+                   [p, p.to_utc(), p == p.to_utc(), p.offset(), p.with_offset(\"-0130\").hour()]";
+        assert_eq!(show(src), "[2024-05-01T12:00:00+02:00, 2024-05-01T10:00:00Z, true, +02:00, 8]");
+    }
+
+    #[test]
+    fn formats_and_parses_with_patterns() {
+        let src = "let p = parse_datetime(\"01/05/2024 12:00\", \"%d/%m/%Y %H:%M\").unwrap()\n\
+                   [p, format_datetime(p, \"%Y%m%d %z\"), p.weekday()]";
+        assert_eq!(show(src), "[2024-05-01T12:00:00Z, 20240501 +0000, 3]");
+    }
+
+    #[test]
+    fn impossible_dates_and_bad_offsets_are_errors() {
+        assert!(show("parse_datetime(\"30/02/2024\", \"%d/%m/%Y\")").starts_with("Err('30/02/2024' does not match '%d/%m/%Y'"));
+        assert_eq!(eval("datetime(2024, 2, 30)").unwrap_err(), "datetime: 2024-02-30 00:00:00 is not a valid time");
+        assert_eq!(eval("now(\"+25\")").unwrap_err(), "Invalid UTC offset '+25' (use Z, +HH:MM, +HHMM or +HH)");
+        assert_eq!(eval("datetime(2024, 1, 1) + 1").unwrap_err(), "Cannot apply '+' to 2024-01-01T00:00:00Z and 1");
+    }
+}
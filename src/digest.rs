@@ -0,0 +1,104 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr Digest — checksums and cryptographic hashes
+// ═══════════════════════════════════════════════════════════
+//
+// QUICK REFERENCE
+//
+//   sha256(data)              String, lowercase hex
+//   sha1(data)                String, lowercase hex
+//   md5(data)                 String, lowercase hex
+//   crc32(data)               Int
+//   hmac_sha256(key, data)    String, lowercase hex
+//
+// `data` and `key` are a String (hashed as UTF-8) or a List of byte
+// Ints, such as the one file_read_bytes returns:
+//
+//   let sum = sha256(file_read_bytes("release.tar.gz").unwrap())
+//   let sig = hmac_sha256(secret, body)
+//
+// sha1 and md5 are here for checksums and old protocols; use sha256 for
+// anything that has to resist tampering.
+//
+// ═══════════════════════════════════════════════════════════
+
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use crate::interpreter::Value;
+
+// ── Registration ──────────────────────────────────────────────────────────────
+
+pub fn digest_functions() -> Vec<&'static str> {
+    vec!["sha256", "sha1", "md5", "crc32", "hmac_sha256"]
+}
+
+// ── Dispatch ──────────────────────────────────────────────────────────────────
+
+pub fn call_digest(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    match name {
+        "sha256" => Ok(hex(&Sha256::digest(bytes_arg(&args, 0, "sha256(data)")?))),
+        "sha1"   => Ok(hex(&Sha1::digest(bytes_arg(&args, 0, "sha1(data)")?))),
+        "md5"    => Ok(hex(&Md5::digest(bytes_arg(&args, 0, "md5(data)")?))),
+        "crc32"  => Ok(Value::Int(crc32fast::hash(&bytes_arg(&args, 0, "crc32(data)")?) as i64)),
+        "hmac_sha256" => {
+            let key = bytes_arg(&args, 0, "hmac_sha256(key, data)")?;
+            let data = bytes_arg(&args, 1, "hmac_sha256(key, data)")?;
+            let mut mac = Hmac::<Sha256>::new_from_slice(&key).map_err(|e| format!("hmac_sha256: {}", e))?;
+            mac.update(&data);
+            Ok(hex(&mac.finalize().into_bytes()))
+        }
+        _ => Err(format!("Unknown digest function '{}'", name)),
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+fn hex(bytes: &[u8]) -> Value {
+    Value::str(bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+fn bytes_arg(args: &[Value], idx: usize, sig: &str) -> Result<Vec<u8>, String> {
+    match args.get(idx) {
+        Some(Value::Str(s)) => Ok(s.as_bytes().to_vec()),
+        Some(Value::List(v)) => v.borrow().iter().map(|b| match b {
+            Value::Int(n) if (0..=255).contains(n) => Ok(*n as u8),
+            other => Err(format!("{} — byte lists hold Ints from 0 to 255, found {}", sig, other)),
+        }).collect(),
+        Some(other) => Err(format!("{} — argument {} must be a String or a List of bytes, got {}", sig, idx + 1, other)),
+        None => Err(format!("{} — argument {} is required", sig, idx + 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::eval;
+
+    fn show(src: &str) -> String {
+        format!("{}", eval(src).unwrap())
+    }
+
+    #[test]
+    fn known_digests() {
+        assert_eq!(show("md5(\"\")"), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(show("sha1(\"abc\")"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(show("crc32(\"123456789\")"), "3421780262");
+        assert_eq!(
+            show("hmac_sha256(\"key\", \"The quick brown fox jumps over the lazy dog\")"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn strings_hash_as_their_utf8_bytes() {
+        assert_eq!(show("sha256(\"abc\") == sha256([97, 98, 99])"), "true");
+        assert_eq!(show("md5(\"é\") == md5([195, 169])"), "true");
+    }
+
+    #[test]
+    fn bad_data_is_an_error() {
+        assert_eq!(eval("sha256([1, 256])").unwrap_err(), "sha256(data) — byte lists hold Ints from 0 to 255, found 256");
+        assert_eq!(eval("crc32(1.5)").unwrap_err(), "crc32(data) — argument 1 must be a String or a List of bytes, got 1.5");
+        assert_eq!(eval("hmac_sha256(\"key\")").unwrap_err(), "hmac_sha256(key, data) — argument 2 is required");
+    }
+}
@@ -0,0 +1,230 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr Formatting — the spec after `:` in `#{value:spec}`
+// ═══════════════════════════════════════════════════════════
+//
+//   [[fill]align][sign][#][0][width][.precision][type]
+//
+//   align      <  left   >  right   ^  centre   (fill is any one character)
+//   sign       +  always show the sign of a number
+//   #          0x / 0b / 0o prefix for x, X, b and o
+//   0          pad numbers with zeros after the sign and prefix
+//   width      minimum width in characters
+//   precision  digits after the point for numbers, max length for the rest
+//   type       x X  hex     b  binary     o  octal     e E  exponent
+//
+// Numbers align right by default and everything else left.
+//
+//   "#{price:.2}"   "#{name:<20}"   "#{n:08}"   "#{n:#x}"   "#{ratio:+.1e}"
+//
+// `format(template, args...)` fills `{}` / `{0}` / `{:spec}` / `{1:spec}`
+// placeholders from its arguments; `{{` and `}}` are literal braces.
+//
+// ═══════════════════════════════════════════════════════════
+
+use crate::interpreter::Value;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormatSpec {
+    pub fill: char,
+    pub align: Option<char>,
+    pub plus: bool,
+    pub alternate: bool,
+    pub zero: bool,
+    pub width: usize,
+    pub precision: Option<usize>,
+    pub kind: Option<char>,
+}
+
+impl FormatSpec {
+    pub fn parse(spec: &str) -> Result<FormatSpec, String> {
+        let chars: Vec<char> = spec.chars().collect();
+        let mut out = FormatSpec { fill: ' ', ..FormatSpec::default() };
+        let mut i = 0;
+        let is_align = |c: Option<&char>| matches!(c, Some('<' | '>' | '^'));
+
+        if is_align(chars.get(1)) {
+            out.fill = chars[0];
+            out.align = Some(chars[1]);
+            i = 2;
+        } else if is_align(chars.first()) {
+            out.align = Some(chars[0]);
+            i = 1;
+        }
+        if chars.get(i) == Some(&'+') { out.plus = true; i += 1; }
+        else if chars.get(i) == Some(&'-') { i += 1; }
+        if chars.get(i) == Some(&'#') { out.alternate = true; i += 1; }
+        if chars.get(i) == Some(&'0') { out.zero = true; i += 1; }
+
+        let digits = |i: &mut usize| -> Option<usize> {
+            let start = *i;
+            while chars.get(*i).is_some_and(|c| c.is_ascii_digit()) { *i += 1; }
+            chars[start..*i].iter().collect::<String>().parse().ok()
+        };
+        out.width = digits(&mut i).unwrap_or(0);
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            out.precision = Some(digits(&mut i).ok_or_else(|| format!("Format spec '{}' needs digits after '.'", spec))?);
+        }
+        if let Some(&c) = chars.get(i) {
+            if !matches!(c, 'x' | 'X' | 'b' | 'o' | 'e' | 'E') {
+                return Err(format!("Unknown format type '{}' in '{}' (use x, X, b, o, e or E)", c, spec));
+            }
+            out.kind = Some(c);
+            i += 1;
+        }
+        if i < chars.len() {
+            return Err(format!("Invalid format spec '{}'", spec));
+        }
+        Ok(out)
+    }
+}
+
+/// Render a value under a spec such as `>8.2`.
+pub fn format_value(val: &Value, spec: &str) -> Result<String, String> {
+    let spec = FormatSpec::parse(spec)?;
+    let numeric = matches!(val, Value::Int(_) | Value::BigInt(_) | Value::Float(_));
+
+    let (sign, body) = match (val, spec.kind) {
+        (Value::Int(_) | Value::BigInt(_), Some(k @ ('x' | 'X' | 'b' | 'o'))) => {
+            let text = val.to_string();
+            let (neg, digits) = match text.strip_prefix('-') {
+                Some(d) => (true, d.to_string()),
+                None => (false, text),
+            };
+            let magnitude: num_bigint::BigUint = digits.parse().map_err(|_| format!("Cannot format {}", val))?;
+            let body = match k {
+                'x' => format!("{:x}", magnitude),
+                'X' => format!("{:X}", magnitude),
+                'b' => format!("{:b}", magnitude),
+                _ => format!("{:o}", magnitude),
+            };
+            (sign_of(neg, spec.plus), body)
+        }
+        (_, Some('x' | 'X' | 'b' | 'o')) => {
+            return Err(format!("Format type '{}' needs an Int, got {}", spec.kind.unwrap_or('x'), val));
+        }
+        (Value::Int(_) | Value::BigInt(_) | Value::Float(_), Some(k @ ('e' | 'E'))) => {
+            let f = to_float(val);
+            let text = match spec.precision {
+                Some(p) => format!("{:.*e}", p, f.abs()),
+                None => format!("{:e}", f.abs()),
+            };
+            let text = if k == 'E' { text.to_uppercase() } else { text };
+            (sign_of(f.is_sign_negative() && f != 0.0, spec.plus), text)
+        }
+        (_, Some(k)) => return Err(format!("Format type '{}' needs a number, got {}", k, val)),
+        (Value::Float(_) | Value::Int(_) | Value::BigInt(_), None) if spec.precision.is_some() => {
+            let f = to_float(val);
+            let text = format!("{:.*}", spec.precision.unwrap_or(0), f.abs());
+            (sign_of(f.is_sign_negative() && text.chars().any(|c| c.is_ascii_digit() && c != '0'), spec.plus), text)
+        }
+        (Value::Int(_) | Value::BigInt(_) | Value::Float(_), None) => {
+            let text = val.to_string();
+            match text.strip_prefix('-') {
+                Some(d) => ("-", d.to_string()),
+                None => (sign_of(false, spec.plus), text),
+            }
+        }
+        (_, None) => {
+            let text = val.to_string();
+            let text = match spec.precision {
+                Some(p) => text.chars().take(p).collect(),
+                None => text,
+            };
+            ("", text)
+        }
+    };
+
+    // The radix prefix sits with the sign, so zeros go between it and the digits.
+    let prefix = match (spec.alternate, spec.kind) {
+        (true, Some('x' | 'X')) => "0x",
+        (true, Some('b')) => "0b",
+        (true, Some('o')) => "0o",
+        _ => "",
+    };
+    let sign = format!("{}{}", sign, prefix);
+    let len = sign.chars().count() + body.chars().count();
+    let pad = spec.width.saturating_sub(len);
+    if spec.zero && numeric && spec.align.is_none() {
+        return Ok(format!("{}{}{}", sign, "0".repeat(pad), body));
+    }
+    let text = format!("{}{}", sign, body);
+    let fill = |n: usize| spec.fill.to_string().repeat(n);
+    Ok(match spec.align.unwrap_or(if numeric { '>' } else { '<' }) {
+        '<' => format!("{}{}", text, fill(pad)),
+        '^' => format!("{}{}{}", fill(pad / 2), text, fill(pad - pad / 2)),
+        _ => format!("{}{}", fill(pad), text),
+    })
+}
+
+fn sign_of(negative: bool, plus: bool) -> &'static str {
+    match (negative, plus) {
+        (true, _) => "-",
+        (false, true) => "+",
+        (false, false) => "",
+    }
+}
+
+fn to_float(val: &Value) -> f64 {
+    match val {
+        Value::Float(f) => *f,
+        other => other.to_string().parse().unwrap_or(f64::NAN),
+    }
+}
+
+/// `format("{} is {:>5}", a, b)`: fill a template built at runtime.
+pub fn format_template(template: &str, args: &[Value]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    let mut next = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => { chars.next(); out.push('{'); }
+            '}' if chars.peek() == Some(&'}') => { chars.next(); out.push('}'); }
+            '}' => return Err("format(): unmatched '}' in template (write '}}' for a literal brace)".into()),
+            '{' => {
+                let mut field = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => return Err("format(): unclosed '{' in template".into()),
+                    }
+                }
+                let (index, spec) = field.split_once(':').unwrap_or((&field, ""));
+                let index = if index.is_empty() {
+                    next += 1;
+                    next - 1
+                } else {
+                    index.trim().parse::<usize>().map_err(|_| format!("format(): '{{{}}}' is not a placeholder", field))?
+                };
+                let arg = args.get(index).ok_or_else(|| {
+                    format!("format(): placeholder {} needs {} argument(s) after the template, got {}", index, index + 1, args.len())
+                })?;
+                out.push_str(&format_value(arg, spec)?);
+            }
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_padding_goes_after_sign_and_prefix() {
+        assert_eq!(format_value(&Value::Int(255), "#010x").unwrap(), "0x000000ff");
+        assert_eq!(format_value(&Value::Int(-255), "#010x").unwrap(), "-0x00000ff");
+        assert_eq!(format_value(&Value::Int(5), "#06b").unwrap(), "0b0101");
+        assert_eq!(format_value(&Value::Int(-7), "+05").unwrap(), "-0007");
+    }
+
+    #[test]
+    fn prefixed_numbers_align_as_one_piece() {
+        assert_eq!(format_value(&Value::Int(255), "#8x").unwrap(), "    0xff");
+        assert_eq!(format_value(&Value::Int(8), "<#6o").unwrap(), "0o10  ");
+        assert_eq!(format_value(&Value::Int(255), "#X").unwrap(), "0xFF");
+    }
+}
@@ -0,0 +1,450 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr GC — cycle collection for reference-counted values
+// ═══════════════════════════════════════════════════════════
+//
+// QUICK REFERENCE
+//
+//   gc_collect()      run a full collection now; Int, the objects freed
+//   gc_stats()        Map with the collector's counters:
+//                       collections   collections run so far
+//                       full          how many of those were full
+//                       freed         objects freed by them, in total
+//                       last_freed    objects freed by the latest one
+//                       young         objects made since the latest one
+//                       old           objects that have survived one
+//
+// Values are reference counted, which frees everything except cycles: a
+// function holds the scope it was defined in and that scope holds the
+// function, a list can hold itself. Every Env, List, Map, Struct and
+// capturing compiled closure is registered here when it is made, and a
+// collection goes:
+//
+//   1. From the registered objects, find every object reachable through
+//      them (variable cells and refs included) and count, per object,
+//      the references the others hold to it.
+//   2. An object with more strong references than that count is held
+//      from outside the heap (a Rust local, the VM stack, the
+//      interpreter), so it is live, as is everything it reaches.
+//   3. The rest are only held by each other: garbage. Emptying them
+//      breaks the cycles and lets reference counting free them.
+//
+// Contents the collector cannot look into (an iterator, a module, a
+// cell that is borrowed right now) are not counted, which only makes the
+// objects they hold look live. A collection is therefore safe at any
+// point, and runs from inside allocation.
+//
+// Most cycles die young, so every YOUNG_LIMIT new objects only those are
+// collected, with the older objects they reach taken as live. Survivors
+// become old, and once the old objects have doubled since the last full
+// collection, everything is collected.
+//
+// ═══════════════════════════════════════════════════════════
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::rc::{Rc, Weak};
+use indexmap::IndexMap;
+use crate::interpreter::{EnvInner, MapKey, Value, ZephyrFn};
+use crate::vm::Closure;
+
+type EnvCell = RefCell<EnvInner>;
+type ListCell = RefCell<Vec<Value>>;
+type MapCell = RefCell<IndexMap<MapKey, Value>>;
+type FieldsCell = RefCell<HashMap<String, Value>>;
+
+// new objects that trigger a young collection
+const YOUNG_LIMIT: usize = 10_000;
+// old objects before the first full collection, and at the least after
+const MIN_OLD_LIMIT: usize = 10_000;
+
+enum Tracked {
+    Env(Weak<EnvCell>),
+    List(Weak<ListCell>),
+    Map(Weak<MapCell>),
+    Struct(Weak<FieldsCell>),
+    Closure(Weak<Closure>),
+}
+
+// An object found during a collection. The collector holds exactly one
+// strong reference to each, which it leaves out of the counts.
+enum Node {
+    Env(Rc<EnvCell>),
+    List(Rc<ListCell>),
+    Map(Rc<MapCell>),
+    Struct(Rc<FieldsCell>),
+    Closure(Rc<Closure>),
+    Cell(Rc<RefCell<Value>>),
+}
+
+struct Heap {
+    young: Vec<Tracked>,
+    old: Vec<Tracked>,
+    old_limit: usize,
+    collections: usize,
+    full: usize,
+    freed: usize,
+    last_freed: usize,
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = const { RefCell::new(Heap {
+        young: Vec::new(),
+        old: Vec::new(),
+        old_limit: MIN_OLD_LIMIT,
+        collections: 0,
+        full: 0,
+        freed: 0,
+        last_freed: 0,
+    }) };
+}
+
+// ── Registration ──────────────────────────────────────────────────────────────
+
+pub fn gc_functions() -> Vec<&'static str> {
+    vec!["gc_collect", "gc_stats"]
+}
+
+pub fn track_env(rc: Rc<EnvCell>) -> Rc<EnvCell> {
+    track(Tracked::Env(Rc::downgrade(&rc)));
+    rc
+}
+
+pub fn track_list(rc: Rc<ListCell>) -> Rc<ListCell> {
+    track(Tracked::List(Rc::downgrade(&rc)));
+    rc
+}
+
+pub fn track_map(rc: Rc<MapCell>) -> Rc<MapCell> {
+    track(Tracked::Map(Rc::downgrade(&rc)));
+    rc
+}
+
+pub fn track_struct(rc: Rc<FieldsCell>) -> Rc<FieldsCell> {
+    track(Tracked::Struct(Rc::downgrade(&rc)));
+    rc
+}
+
+/// A closure that captured nothing cannot be part of a cycle, so only
+/// the others are registered.
+pub fn track_closure(rc: Rc<Closure>) -> Rc<Closure> {
+    if !rc.upvals.is_empty() {
+        track(Tracked::Closure(Rc::downgrade(&rc)));
+    }
+    rc
+}
+
+fn track(t: Tracked) {
+    let due = HEAP.with(|h| {
+        let mut h = h.borrow_mut();
+        h.young.push(t);
+        h.young.len() >= YOUNG_LIMIT
+    });
+    if due {
+        collect(false);
+    }
+}
+
+// ── Dispatch ──────────────────────────────────────────────────────────────────
+
+pub fn call_gc(name: &str, _args: Vec<Value>) -> Result<Value, String> {
+    match name {
+        "gc_collect" => Ok(Value::Int(collect(true) as i64)),
+        "gc_stats" => {
+            let stats = HEAP.with(|h| {
+                let h = h.borrow();
+                [
+                    ("collections", h.collections),
+                    ("full", h.full),
+                    ("freed", h.freed),
+                    ("last_freed", h.last_freed),
+                    ("young", h.young.len()),
+                    ("old", h.old.len()),
+                ]
+            });
+            Ok(Value::map(stats.into_iter()
+                .map(|(k, n)| (MapKey::Str(k.to_string()), Value::Int(n as i64)))
+                .collect()))
+        }
+        _ => Err(format!("Unknown gc function '{}'", name)),
+    }
+}
+
+// ── Collection ────────────────────────────────────────────────────────────────
+
+/// Free the registered objects that are only reachable from garbage
+/// cycles: all of them when `full`, else the young ones. Returns how
+/// many were freed.
+pub fn collect(full: bool) -> usize {
+    let candidates = HEAP.with(|h| {
+        let mut h = h.borrow_mut();
+        let mut candidates = std::mem::take(&mut h.young);
+        if full {
+            candidates.append(&mut h.old);
+        }
+        candidates
+    });
+    let mut graph = Graph::default();
+    let mut registered = Vec::with_capacity(candidates.len());
+    for t in candidates {
+        if let Some(node) = t.upgrade() {
+            let i = graph.add(node);
+            registered.push((t, i));
+        }
+    }
+    graph.trace(if full { usize::MAX } else { graph.nodes.len() });
+    let live = graph.mark();
+
+    let mut freed = 0;
+    for (i, node) in graph.nodes.iter().enumerate() {
+        if !live[i] {
+            node.clear();
+            freed += usize::from(!matches!(node, Node::Cell(_)));
+        }
+    }
+    registered.retain(|(_, i)| live[*i]);
+    // dropping the graph releases the collector's references, and with
+    // them the garbage
+    drop(graph);
+
+    let escalate = HEAP.with(|h| {
+        let mut h = h.borrow_mut();
+        h.old.extend(registered.into_iter().map(|(t, _)| t));
+        if full {
+            h.old_limit = MIN_OLD_LIMIT.max(2 * h.old.len());
+            h.full += 1;
+        }
+        h.collections += 1;
+        h.freed += freed;
+        h.last_freed = freed;
+        h.old.len() >= h.old_limit
+    });
+    if escalate && !full {
+        return freed + collect(true);
+    }
+    freed
+}
+
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    index: HashMap<usize, usize, BuildHasherDefault<AddrHasher>>,
+    // references to each node from inside the graph
+    internal: Vec<usize>,
+    // the nodes node i refers to are edges[first_edge[i]..first_edge[i + 1]]
+    edges: Vec<usize>,
+    first_edge: Vec<usize>,
+    // old objects reached from young ones: not looked into, and live
+    pinned: Vec<bool>,
+}
+
+impl Graph {
+    fn add(&mut self, node: Node) -> usize {
+        let next = self.nodes.len();
+        let i = *self.index.entry(node.addr()).or_insert(next);
+        if i == next {
+            self.nodes.push(node);
+            self.internal.push(0);
+            self.pinned.push(false);
+        }
+        i
+    }
+
+    /// Follow references out of every node, adding the nodes they lead
+    /// to, until there are no new ones. Objects past `candidates` that
+    /// are not cells are old, and are left as they are.
+    fn trace(&mut self, candidates: usize) {
+        let mut i = 0;
+        let mut found = Vec::new();
+        while i < self.nodes.len() {
+            self.first_edge.push(self.edges.len());
+            if i >= candidates && !matches!(self.nodes[i], Node::Cell(_)) {
+                self.pinned[i] = true;
+            } else {
+                self.nodes[i].children(&mut found);
+            }
+            for child in found.drain(..) {
+                let j = self.add(child);
+                self.internal[j] += 1;
+                self.edges.push(j);
+            }
+            i += 1;
+        }
+        self.first_edge.push(self.edges.len());
+    }
+
+    /// Which nodes are reachable from a node held outside the graph.
+    fn mark(&self) -> Vec<bool> {
+        let mut live = vec![false; self.nodes.len()];
+        let mut stack: Vec<usize> = (0..self.nodes.len())
+            .filter(|&i| self.pinned[i] || self.nodes[i].strong_count() - 1 > self.internal[i])
+            .collect();
+        while let Some(i) = stack.pop() {
+            if !live[i] {
+                live[i] = true;
+                let out = &self.edges[self.first_edge[i]..self.first_edge[i + 1]];
+                stack.extend(out.iter().copied().filter(|&j| !live[j]));
+            }
+        }
+        live
+    }
+}
+
+// Node addresses only need their bits mixed, not a keyed hash.
+#[derive(Default)]
+struct AddrHasher(u64);
+
+impl Hasher for AddrHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_usize(b as usize);
+        }
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.0 = (self.0 ^ n as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(32);
+    }
+}
+
+impl Tracked {
+    fn upgrade(&self) -> Option<Node> {
+        Some(match self {
+            Tracked::Env(w) => Node::Env(w.upgrade()?),
+            Tracked::List(w) => Node::List(w.upgrade()?),
+            Tracked::Map(w) => Node::Map(w.upgrade()?),
+            Tracked::Struct(w) => Node::Struct(w.upgrade()?),
+            Tracked::Closure(w) => Node::Closure(w.upgrade()?),
+        })
+    }
+}
+
+impl Node {
+    fn addr(&self) -> usize {
+        let ptr = match self {
+            Node::Env(rc) => Rc::as_ptr(rc) as *const (),
+            Node::List(rc) => Rc::as_ptr(rc) as *const (),
+            Node::Map(rc) => Rc::as_ptr(rc) as *const (),
+            Node::Struct(rc) => Rc::as_ptr(rc) as *const (),
+            Node::Closure(rc) => Rc::as_ptr(rc) as *const (),
+            Node::Cell(rc) => Rc::as_ptr(rc) as *const (),
+        };
+        ptr as usize
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Env(rc) => Rc::strong_count(rc),
+            Node::List(rc) => Rc::strong_count(rc),
+            Node::Map(rc) => Rc::strong_count(rc),
+            Node::Struct(rc) => Rc::strong_count(rc),
+            Node::Closure(rc) => Rc::strong_count(rc),
+            Node::Cell(rc) => Rc::strong_count(rc),
+        }
+    }
+
+    /// The objects this one holds references to. A cell that is mutably
+    /// borrowed right now is skipped, leaving what it holds looking live.
+    fn children(&self, out: &mut Vec<Node>) {
+        match self {
+            Node::Env(rc) => if let Ok(env) = rc.try_borrow() {
+                out.extend(env.vars.values().map(|cell| Node::Cell(cell.clone())));
+                env.slots.iter().flatten().for_each(|v| value_children(v, out));
+                out.extend(env.parent.iter().map(|p| Node::Env(p.0.clone())));
+            },
+            Node::List(rc) => if let Ok(items) = rc.try_borrow() {
+                items.iter().for_each(|v| value_children(v, out));
+            },
+            Node::Map(rc) => if let Ok(entries) = rc.try_borrow() {
+                entries.values().for_each(|v| value_children(v, out));
+            },
+            Node::Struct(rc) => if let Ok(fields) = rc.try_borrow() {
+                fields.values().for_each(|v| value_children(v, out));
+            },
+            Node::Closure(rc) => out.extend(rc.upvals.iter().map(|cell| Node::Cell(cell.clone()))),
+            Node::Cell(rc) => if let Ok(v) = rc.try_borrow() {
+                value_children(&v, out);
+            },
+        }
+    }
+
+    /// Drop everything a garbage object holds.
+    fn clear(&self) {
+        match self {
+            Node::Env(rc) => if let Ok(mut env) = rc.try_borrow_mut() {
+                env.vars.clear();
+                env.slots.clear();
+                env.parent = None;
+            },
+            Node::List(rc) => if let Ok(mut items) = rc.try_borrow_mut() {
+                items.clear();
+            },
+            Node::Map(rc) => if let Ok(mut entries) = rc.try_borrow_mut() {
+                entries.clear();
+            },
+            Node::Struct(rc) => if let Ok(mut fields) = rc.try_borrow_mut() {
+                fields.clear();
+            },
+            // its cells are garbage too, and get emptied on their own
+            Node::Closure(_) => {}
+            Node::Cell(rc) => if let Ok(mut v) = rc.try_borrow_mut() {
+                *v = Value::Nil;
+            },
+        }
+    }
+}
+
+fn value_children(v: &Value, out: &mut Vec<Node>) {
+    match v {
+        Value::List(rc) => out.push(Node::List(rc.clone())),
+        Value::Map(rc) => out.push(Node::Map(rc.clone())),
+        Value::Struct(_, rc) => out.push(Node::Struct(rc.clone())),
+        Value::Ref(rc) => out.push(Node::Cell(rc.clone())),
+        Value::Function(ZephyrFn::UserDefined { closure_env, .. }) => out.push(Node::Env(closure_env.0.clone())),
+        Value::Function(ZephyrFn::Compiled(rc)) => out.push(Node::Closure(rc.clone())),
+        Value::Tuple(items) | Value::Enum(_, _, items) => items.iter().for_each(|v| value_children(v, out)),
+        Value::Option(Some(v)) | Value::Result(Ok(v) | Err(v)) => value_children(v, out),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::eval;
+
+    fn show(src: &str) -> String {
+        format!("{}", eval(src).unwrap())
+    }
+
+    #[test]
+    fn values_that_hold_themselves_are_freed() {
+        let src = "fun make() {\n let a = [1]\n a.push(a)\n let m = {}\n m[\"me\"] = m\n}\n\
+                   for i in 0..10 { make() }\n[gc_collect(), gc_collect()]";
+        assert_eq!(show(src), "[20, 0]");
+    }
+
+    #[test]
+    fn scopes_held_by_their_own_functions_are_freed() {
+        let src = "fun make() {\n fun down(n) { if n == 0 { return 0 }\n down(n - 1) }\n down(3)\n}\n\
+                   for i in 0..10 { make() }\ngc_collect()";
+        assert_eq!(show(src), "10");
+    }
+
+    #[test]
+    fn reachable_cycles_survive() {
+        let src = "let keep = [1]\nkeep.push(keep)\nlet m = {\"k\": keep}\nm[\"self\"] = m\n\
+                   let freed = gc_collect()\n[freed, len(keep[1]), len(m[\"self\"])]";
+        assert_eq!(show(src), "[0, 2, 2]");
+    }
+
+    #[test]
+    fn stats_count_each_collection() {
+        let src = "let before = gc_stats()\nvar l = [0]\nl[0] = l\nl = nil\n\
+                   let freed = gc_collect()\nlet after = gc_stats()\n\
+                   [freed, after[\"full\"] - before[\"full\"], after[\"freed\"] - before[\"freed\"], after[\"last_freed\"]]";
+        assert_eq!(show(src), "[1, 1, 1, 1]");
+    }
+}
@@ -1,875 +1,2190 @@
-// ═══════════════════════════════════════════════════════════
-// Zephyr Interpreter — tree-walking evaluator
-// ═══════════════════════════════════════════════════════════
-
-use std::collections::HashMap;
-use std::rc::Rc;
-use std::cell::RefCell;
-use std::fmt;
-
-use crate::ast::*;
-use crate::stdlib;
-
-// ── Values ────────────────────────────────────────────────────────────────────
-
-#[derive(Clone, Debug)]
-pub enum Value {
-    Int(i64),
-    Float(f64),
-    Bool(bool),
-    Str(String),
-    Nil,
-    Tuple(Vec<Value>),
-    List(Rc<RefCell<Vec<Value>>>),
-    Map(Rc<RefCell<HashMap<String, Value>>>),
-    Struct(String, Rc<RefCell<HashMap<String, Value>>>),
-    Enum(String, String, Vec<Value>),   // type_name, variant, fields
-    Option(Option<Box<Value>>),          // Some(v) or None→Nil
-    Result(std::result::Result<Box<Value>, Box<Value>>),
-    Function(ZephyrFn),
-    Ref(Rc<RefCell<Value>>),
-}
-
-#[derive(Clone, Debug)]
-pub enum ZephyrFn {
-    UserDefined {
-        name: Option<String>,
-        params: Vec<Param>,
-        body: Vec<Stmt>,
-        closure_env: Env,
-    },
-    Native(String),
-}
-
-impl fmt::Display for Value {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Value::Int(n)    => write!(f, "{}", n),
-            Value::Float(n)  => {
-                if n.fract() == 0.0 { write!(f, "{:.1}", n) } else { write!(f, "{}", n) }
-            }
-            Value::Bool(b)   => write!(f, "{}", b),
-            Value::Str(s)    => write!(f, "{}", s),
-            Value::Nil       => write!(f, "nil"),
-            Value::Tuple(v)  => {
-                write!(f, "(")?;
-                for (i, x) in v.iter().enumerate() {
-                    if i > 0 { write!(f, ", ")?; }
-                    write!(f, "{}", x)?;
-                }
-                write!(f, ")")
-            }
-            Value::List(v)   => {
-                write!(f, "[")?;
-                for (i, x) in v.borrow().iter().enumerate() {
-                    if i > 0 { write!(f, ", ")?; }
-                    write!(f, "{}", x)?;
-                }
-                write!(f, "]")
-            }
-            Value::Map(m)    => {
-                write!(f, "{{")?;
-                for (i, (k, v)) in m.borrow().iter().enumerate() {
-                    if i > 0 { write!(f, ", ")?; }
-                    write!(f, "{}: {}", k, v)?;
-                }
-                write!(f, "}}")
-            }
-            Value::Struct(name, fields) => {
-                write!(f, "{} {{", name)?;
-                for (i, (k, v)) in fields.borrow().iter().enumerate() {
-                    if i > 0 { write!(f, ", ")?; }
-                    write!(f, "{}: {}", k, v)?;
-                }
-                write!(f, "}}")
-            }
-            Value::Enum(_, variant, fields) => {
-                if fields.is_empty() {
-                    write!(f, "{}", variant)
-                } else {
-                    write!(f, "{}(", variant)?;
-                    for (i, x) in fields.iter().enumerate() {
-                        if i > 0 { write!(f, ", ")?; }
-                        write!(f, "{}", x)?;
-                    }
-                    write!(f, ")")
-                }
-            }
-            Value::Option(Some(v)) => write!(f, "Some({})", v),
-            Value::Option(None)    => write!(f, "nil"),
-            Value::Result(Ok(v))   => write!(f, "Ok({})", v),
-            Value::Result(Err(e))  => write!(f, "Err({})", e),
-            Value::Function(ZephyrFn::UserDefined { name, .. }) => {
-                write!(f, "<fun {}>", name.as_deref().unwrap_or("<closure>"))
-            }
-            Value::Function(ZephyrFn::Native(n)) => write!(f, "<native {}>", n),
-            Value::Ref(r) => write!(f, "ref({})", r.borrow()),
-        }
-    }
-}
-
-impl PartialEq for Value {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Value::Int(a), Value::Int(b))     => a == b,
-            (Value::Float(a), Value::Float(b)) => a == b,
-            (Value::Int(a), Value::Float(b))   => (*a as f64) == *b,
-            (Value::Float(a), Value::Int(b))   => *a == (*b as f64),
-            (Value::Bool(a), Value::Bool(b))   => a == b,
-            (Value::Str(a), Value::Str(b))     => a == b,
-            (Value::Nil, Value::Nil)           => true,
-            (Value::Option(None), Value::Nil)  => true,
-            (Value::Nil, Value::Option(None))  => true,
-            (Value::Option(a), Value::Option(b)) => a == b,
-            (Value::Tuple(a), Value::Tuple(b)) => a == b,
-            _                                   => false,
-        }
-    }
-}
-
-// ── Environment ───────────────────────────────────────────────────────────────
-
-#[derive(Clone, Debug)]
-pub struct Env(Rc<RefCell<EnvInner>>);
-
-#[derive(Debug)]
-struct EnvInner {
-    vars: HashMap<String, Rc<RefCell<Value>>>,
-    parent: Option<Env>,
-}
-
-impl Env {
-    pub fn new() -> Self {
-        Env(Rc::new(RefCell::new(EnvInner {
-            vars: HashMap::new(),
-            parent: None,
-        })))
-    }
-
-    pub fn child(parent: &Env) -> Self {
-        Env(Rc::new(RefCell::new(EnvInner {
-            vars: HashMap::new(),
-            parent: Some(parent.clone()),
-        })))
-    }
-
-    pub fn define(&self, name: &str, val: Value) {
-        self.0.borrow_mut().vars.insert(name.to_string(), Rc::new(RefCell::new(val)));
-    }
-
-    pub fn set(&self, name: &str, val: Value) -> bool {
-        let mut inner = self.0.borrow_mut();
-        if let Some(cell) = inner.vars.get(name) {
-            *cell.borrow_mut() = val;
-            return true;
-        }
-        if let Some(parent) = &inner.parent.clone() {
-            return parent.set(name, val);
-        }
-        false
-    }
-
-    pub fn get(&self, name: &str) -> Option<Value> {
-        let inner = self.0.borrow();
-        if let Some(cell) = inner.vars.get(name) {
-            return Some(cell.borrow().clone());
-        }
-        if let Some(parent) = &inner.parent {
-            return parent.get(name);
-        }
-        None
-    }
-}
-
-// ── Control flow signals ──────────────────────────────────────────────────────
-
-#[derive(Debug)]
-pub enum Signal {
-    Return(Value),
-    Break,
-    Continue,
-    Error(String),
-    PropagateErr(Value), // for ? operator
-}
-
-impl From<String> for Signal {
-    fn from(s: String) -> Self { Signal::Error(s) }
-}
-
-type EvalResult = std::result::Result<Value, Signal>;
-
-// ── Interpreter ───────────────────────────────────────────────────────────────
-
-pub struct Interpreter {
-    pub global: Env,
-    // struct/enum definitions
-    pub struct_defs: HashMap<String, StructDef>,
-    pub enum_defs: HashMap<String, EnumDef>,
-    pub impl_methods: HashMap<String, HashMap<String, ZephyrFn>>,
-    pub modules: HashMap<String, Env>,
-}
-
-impl Interpreter {
-    pub fn new() -> Self {
-        let global = Env::new();
-        stdlib::register(&global);
-        Interpreter {
-            global,
-            struct_defs: HashMap::new(),
-            enum_defs: HashMap::new(),
-            impl_methods: HashMap::new(),
-            modules: HashMap::new(),
-        }
-    }
-
-    pub fn run(&mut self, stmts: &[Stmt]) -> EvalResult {
-        let env = self.global.clone();
-        self.exec_block(stmts, &env)
-    }
-
-    fn exec_block(&mut self, stmts: &[Stmt], env: &Env) -> EvalResult {
-        let mut last = Value::Nil;
-        for stmt in stmts {
-            last = self.exec_stmt(stmt, env)?;
-        }
-        Ok(last)
-    }
-
-    fn exec_stmt(&mut self, stmt: &Stmt, env: &Env) -> EvalResult {
-        match stmt {
-            Stmt::Let(name, _ty, expr, _mutable) => {
-                let val = self.eval_expr(expr, env)?;
-                env.define(name, val);
-                Ok(Value::Nil)
-            }
-
-            Stmt::Expr(expr) => self.eval_expr(expr, env),
-
-            Stmt::Return(expr) => {
-                let val = if let Some(e) = expr.as_ref() { self.eval_expr(e, env)? } else { Value::Nil };
-                Err(Signal::Return(val))
-            }
-
-            Stmt::Break    => Err(Signal::Break),
-            Stmt::Continue => Err(Signal::Continue),
-
-            Stmt::While(cond, body) => {
-                loop {
-                    let c = self.eval_expr(cond, env)?;
-                    if !is_truthy(&c) { break; }
-                    let loop_env = Env::child(env);
-                    match self.exec_block(body, &loop_env) {
-                        Ok(_) => {}
-                        Err(Signal::Break) => break,
-                        Err(Signal::Continue) => continue,
-                        Err(e) => return Err(e),
-                    }
-                }
-                Ok(Value::Nil)
-            }
-
-            Stmt::For(var, iter_expr, body) => {
-                let iter_val = self.eval_expr(iter_expr, env)?;
-                let items = self.value_to_iter(iter_val)?;
-                for item in items {
-                    let loop_env = Env::child(env);
-                    loop_env.define(var, item);
-                    match self.exec_block(body, &loop_env) {
-                        Ok(_) => {}
-                        Err(Signal::Break) => break,
-                        Err(Signal::Continue) => continue,
-                        Err(e) => return Err(e),
-                    }
-                }
-                Ok(Value::Nil)
-            }
-
-            Stmt::FunDef(fun) => {
-                let func = Value::Function(ZephyrFn::UserDefined {
-                    name: Some(fun.name.clone()),
-                    params: fun.params.clone(),
-                    body: fun.body.clone(),
-                    closure_env: env.clone(),
-                });
-                env.define(&fun.name, func);
-                Ok(Value::Nil)
-            }
-
-            Stmt::StructDef(def) => {
-                self.struct_defs.insert(def.name.clone(), def.clone());
-                Ok(Value::Nil)
-            }
-
-            Stmt::EnumDef(def) => {
-                self.enum_defs.insert(def.name.clone(), def.clone());
-                // Register variant constructors as functions in env
-                for variant in &def.variants {
-                    let enum_name = def.name.clone();
-                    let var_name = variant.name.clone();
-                    let arity = variant.fields.len();
-                    if arity == 0 {
-                        env.define(&format!("{}::{}", enum_name, var_name),
-                            Value::Enum(enum_name.clone(), var_name.clone(), vec![]));
-                    }
-                    // Constructors for variants with fields are handled by EnumVariant expr
-                }
-                Ok(Value::Nil)
-            }
-
-            Stmt::ImplBlock(block) => {
-                let methods = self.impl_methods.entry(block.target.clone()).or_default();
-                for method in &block.methods {
-                    methods.insert(method.name.clone(), ZephyrFn::UserDefined {
-                        name: Some(method.name.clone()),
-                        params: method.params.clone(),
-                        body: method.body.clone(),
-                        closure_env: env.clone(),
-                    });
-                }
-                Ok(Value::Nil)
-            }
-
-            Stmt::ModDef(name, stmts) => {
-                let mod_env = Env::child(env);
-                self.exec_block(stmts, &mod_env)?;
-                self.modules.insert(name.clone(), mod_env.clone());
-                env.define(name, Value::Nil); // placeholder
-                Ok(Value::Nil)
-            }
-
-            Stmt::Import(path) => {
-                // Simple module import — just note it for now
-                // Full module system would load files from disk
-                let _path_str = path.join(".");
-                Ok(Value::Nil)
-            }
-
-            Stmt::TypeAlias(_, _, _) => Ok(Value::Nil), // type aliases are for type checking
-        }
-    }
-
-    // ── Expression evaluation ─────────────────────────────────────────────────
-
-    pub fn eval_expr(&mut self, expr: &Expr, env: &Env) -> EvalResult {
-        match expr {
-            Expr::Int(n)    => Ok(Value::Int(*n)),
-            Expr::Float(f)  => Ok(Value::Float(*f)),
-            Expr::Bool(b)   => Ok(Value::Bool(*b)),
-            Expr::Nil       => Ok(Value::Nil),
-            Expr::StringLit(s) => Ok(Value::Str(s.clone())),
-
-            Expr::InterpolatedString(parts) => {
-                let mut result = String::new();
-                for part in parts {
-                    match part {
-                        crate::ast::StringPart::Literal(s) => result.push_str(s),
-                        crate::ast::StringPart::Interpolated(e) => {
-                            let v = self.eval_expr(&e, env)?;
-                            result.push_str(&format!("{}", v));
-                        }
-                    }
-                }
-                Ok(Value::Str(result))
-            }
-
-            Expr::Var(name) => {
-                env.get(name)
-                    .or_else(|| self.global.get(name))
-                    .ok_or_else(|| Signal::Error(format!("Undefined variable '{}'", name)))
-            }
-
-            Expr::Tuple(elems) => {
-                let vals: std::result::Result<Vec<_>, _> = elems.iter().map(|e| self.eval_expr(e, env)).collect();
-                Ok(Value::Tuple(vals?))
-            }
-
-            Expr::List(elems) => {
-                let vals: std::result::Result<Vec<_>, _> = elems.iter().map(|e| self.eval_expr(e, env)).collect();
-                Ok(Value::List(Rc::new(RefCell::new(vals?))))
-            }
-
-            Expr::MapLit(pairs) => {
-                let mut map = HashMap::new();
-                for (k, v) in pairs {
-                    let kv = self.eval_expr(k, env)?;
-                    let vv = self.eval_expr(v, env)?;
-                    map.insert(format!("{}", kv), vv);
-                }
-                Ok(Value::Map(Rc::new(RefCell::new(map))))
-            }
-
-            Expr::Block(stmts, tail) => {
-                let block_env = Env::child(env);
-                for s in stmts { self.exec_stmt(s, &block_env)?; }
-                if let Some(e) = tail {
-                    self.eval_expr(e, &block_env)
-                } else {
-                    Ok(Value::Nil)
-                }
-            }
-
-            Expr::BinOp(left, op, right) => {
-                let l = self.eval_expr(left, env)?;
-                let r = self.eval_expr(right, env)?;
-                eval_binop(l, op, r)
-            }
-
-            Expr::UnaryOp(op, expr) => {
-                let val = self.eval_expr(expr, env)?;
-                match op {
-                    UnaryOp::Neg => match val {
-                        Value::Int(n)   => Ok(Value::Int(-n)),
-                        Value::Float(f) => Ok(Value::Float(-f)),
-                        other => Err(Signal::Error(format!("Cannot negate {:?}", other)))
-                    }
-                    UnaryOp::Not => Ok(Value::Bool(!is_truthy(&val)))
-                }
-            }
-
-            Expr::Assign(target, value) => {
-                let val = self.eval_expr(value, env)?;
-                match target.as_ref() {
-                    Expr::Var(name) => {
-                        if !env.set(name, val.clone()) {
-                            env.define(name, val);
-                        }
-                        Ok(Value::Nil)
-                    }
-                    Expr::Index(obj_expr, idx_expr) => {
-                        let idx = self.eval_expr(idx_expr, env)?;
-                        let obj = self.eval_expr(obj_expr, env)?;
-                        match &obj {
-                            Value::List(v) => {
-                                let i = match require_int(&idx) { Ok(n) => n as usize, Err(e) => return Err(e) };
-                                let mut list = v.borrow_mut();
-                                if i < list.len() {
-                                    list[i] = val;
-                                    Ok(Value::Nil)
-                                } else {
-                                    Err(Signal::Error(format!("Index {} out of bounds", i)))
-                                }
-                            }
-                            Value::Map(m) => {
-                                m.borrow_mut().insert(format!("{}", idx), val);
-                                Ok(Value::Nil)
-                            }
-                            _ => Err(Signal::Error("Cannot index-assign this value".into()))
-                        }
-                    }
-                    Expr::FieldAccess(obj_expr, field) => {
-                        let obj = self.eval_expr(obj_expr, env)?;
-                        if let Value::Struct(_, fields) = obj {
-                            fields.borrow_mut().insert(field.clone(), val);
-                            Ok(Value::Nil)
-                        } else {
-                            Err(Signal::Error("Cannot assign field on non-struct".into()))
-                        }
-                    }
-                    _ => Err(Signal::Error("Invalid assignment target".into()))
-                }
-            }
-
-            Expr::Call(callee_expr, args) => {
-                let callee = self.eval_expr(callee_expr, env)?;
-                let arg_vals: std::result::Result<Vec<_>, _> = args.iter().map(|a| self.eval_expr(a, env)).collect();
-                let arg_vals = arg_vals?;
-                self.call_value(callee, arg_vals, env)
-            }
-
-            Expr::MethodCall(obj_expr, method, args) => {
-                let obj = self.eval_expr(obj_expr, env)?;
-                let arg_vals: std::result::Result<Vec<_>, _> = args.iter().map(|a| self.eval_expr(a, env)).collect();
-                let arg_vals = arg_vals?;
-                self.call_method(obj, method, arg_vals, env)
-            }
-
-            Expr::FieldAccess(obj_expr, field) => {
-                let obj = self.eval_expr(obj_expr, env)?;
-                match &obj {
-                    Value::Struct(_, fields) => {
-                        fields.borrow().get(field)
-                            .cloned()
-                            .ok_or_else(|| Signal::Error(format!("No field '{}'", field)))
-                    }
-                    Value::Tuple(elems) => {
-                        let idx: usize = field.parse()
-                            .map_err(|_| Signal::Error(format!("Invalid tuple index '{}'", field)))?;
-                        elems.get(idx).cloned()
-                            .ok_or_else(|| Signal::Error(format!("Tuple index {} out of bounds", idx)))
-                    }
-                    _ => Err(Signal::Error(format!("Cannot access field '{}' on {:?}", field, obj)))
-                }
-            }
-
-            Expr::Index(obj_expr, idx_expr) => {
-                let obj = self.eval_expr(obj_expr, env)?;
-                let idx = self.eval_expr(idx_expr, env)?;
-                match &obj {
-                    Value::List(v) => {
-                        let i = match require_int(&idx) { Ok(n) => n as usize, Err(e) => return Err(e) };
-                        v.borrow().get(i).cloned()
-                            .ok_or_else(|| Signal::Error(format!("Index {} out of bounds", i)))
-                    }
-                    Value::Str(s) => {
-                        let i = match require_int(&idx) { Ok(n) => n as usize, Err(e) => return Err(e) };
-                        s.chars().nth(i)
-                            .map(|c| Value::Str(c.to_string()))
-                            .ok_or_else(|| Signal::Error(format!("String index {} out of bounds", i)))
-                    }
-                    Value::Map(m) => {
-                        let key = format!("{}", idx);
-                        m.borrow().get(&key).cloned()
-                            .ok_or_else(|| Signal::Error(format!("Key '{}' not found", key)))
-                    }
-                    _ => Err(Signal::Error("Cannot index this value".into()))
-                }
-            }
-
-            Expr::If(cond, then_expr, elif_branches, else_expr) => {
-                let cond_val = self.eval_expr(cond, env)?;
-                if is_truthy(&cond_val) {
-                    self.eval_expr(then_expr, env)
-                } else {
-                    for (elif_cond, elif_body) in elif_branches {
-                        let cv = self.eval_expr(elif_cond, env)?;
-                        if is_truthy(&cv) {
-                            return self.eval_expr(elif_body, env);
-                        }
-                    }
-                    if let Some(e) = else_expr {
-                        self.eval_expr(e, env)
-                    } else {
-                        Ok(Value::Nil)
-                    }
-                }
-            }
-
-            Expr::Match(subject, arms) => {
-                let val = self.eval_expr(subject, env)?;
-                for arm in arms {
-                    let match_env = Env::child(env);
-                    if match_pattern(&arm.pattern, &val, &match_env)? {
-                        if let Some(guard) = &arm.guard {
-                            let gv = self.eval_expr(guard, &match_env)?;
-                            if !is_truthy(&gv) { continue; }
-                        }
-                        return self.eval_expr(&arm.body, &match_env);
-                    }
-                }
-                Err(Signal::Error("Non-exhaustive match".into()))
-            }
-
-            Expr::Closure(params, body) => {
-                Ok(Value::Function(ZephyrFn::UserDefined {
-                    name: None,
-                    params: params.iter().map(|(n, t)| Param {
-                        name: n.clone(), ty: t.clone(), default: None
-                    }).collect(),
-                    body: vec![Stmt::Return(Some(*body.clone()))],
-                    closure_env: env.clone(),
-                }))
-            }
-
-            Expr::StructCreate(name, field_exprs) => {
-                let mut fields = HashMap::new();
-                for (fname, fexpr) in field_exprs {
-                    fields.insert(fname.clone(), self.eval_expr(fexpr, env)?);
-                }
-                Ok(Value::Struct(name.clone(), Rc::new(RefCell::new(fields))))
-            }
-
-            Expr::EnumVariant(enum_name, variant, args) => {
-                let vals: std::result::Result<Vec<_>, _> = args.iter().map(|a| self.eval_expr(a, env)).collect();
-                Ok(Value::Enum(enum_name.clone(), variant.clone(), vals?))
-            }
-
-            Expr::Range(start, end) => {
-                let s = require_int(&self.eval_expr(start, env)?)?;
-                let e = require_int(&self.eval_expr(end, env)?)?;
-                let list: Vec<Value> = (s..e).map(Value::Int).collect();
-                Ok(Value::List(Rc::new(RefCell::new(list))))
-            }
-
-            Expr::Some(inner) => {
-                let v = self.eval_expr(inner, env)?;
-                Ok(Value::Option(Some(Box::new(v))))
-            }
-
-            Expr::Ok(inner) => {
-                let v = self.eval_expr(inner, env)?;
-                Ok(Value::Result(std::result::Result::Ok(Box::new(v))))
-            }
-
-            Expr::Err(inner) => {
-                let v = self.eval_expr(inner, env)?;
-                Ok(Value::Result(std::result::Result::Err(Box::new(v))))
-            }
-
-            Expr::Question(inner) => {
-                let val = self.eval_expr(inner, env)?;
-                match val {
-                    Value::Result(std::result::Result::Ok(v)) => Ok(*v),
-                    Value::Result(std::result::Result::Err(e)) => Err(Signal::PropagateErr(*e)),
-                    Value::Option(Some(v)) => Ok(*v),
-                    Value::Option(None) | Value::Nil =>
-                        Err(Signal::PropagateErr(Value::Str("None".into()))),
-                    other => Ok(other),
-                }
-            }
-
-            Expr::BoxExpr(inner) => {
-                // In our interpreter, box just returns the value (GC handles memory)
-                self.eval_expr(inner, env)
-            }
-
-            Expr::RefExpr(inner) => {
-                let val = self.eval_expr(inner, env)?;
-                Ok(Value::Ref(Rc::new(RefCell::new(val))))
-            }
-
-            Expr::Await(inner) => {
-                // Await is not supported in the tree-walking interpreter
-                self.eval_expr(inner, env)
-            }
-        }
-    }
-
-    fn call_value(&mut self, callee: Value, args: Vec<Value>, env: &Env) -> EvalResult {
-        match callee {
-            Value::Function(ZephyrFn::Native(name)) => {
-                stdlib::call_native(&name, args, env)
-                    .map_err(|e| Signal::Error(e))
-            }
-            Value::Function(ZephyrFn::UserDefined { params, body, closure_env, .. }) => {
-                let call_env = Env::child(&closure_env);
-                for (i, param) in params.iter().enumerate() {
-                    let val = if i < args.len() {
-                        args[i].clone()
-                    } else if let Some(default) = &param.default {
-                        self.eval_expr(default, env)?
-                    } else {
-                        return Err(Signal::Error(format!("Missing argument '{}'", param.name)));
-                    };
-                    call_env.define(&param.name, val);
-                }
-                match self.exec_block(&body, &call_env) {
-                    Ok(v) => Ok(v),
-                    Err(Signal::Return(v)) => Ok(v),
-                    Err(Signal::PropagateErr(e)) => Ok(Value::Result(std::result::Result::Err(Box::new(e)))),
-                    Err(e) => Err(e),
-                }
-            }
-            other => Err(Signal::Error(format!("'{}' is not a function", other)))
-        }
-    }
-
-    fn call_method(&mut self, obj: Value, method: &str, mut args: Vec<Value>, env: &Env) -> EvalResult {
-        // Check impl methods first
-        let type_name = value_type_name(&obj);
-        if let Some(methods) = self.impl_methods.get(&type_name).cloned() {
-            if let Some(func) = methods.get(method).cloned() {
-                let mut all_args = vec![obj];
-                all_args.append(&mut args);
-                return self.call_value(Value::Function(func), all_args, env);
-            }
-        }
-
-        // Built-in methods
-        stdlib::call_builtin_method(obj, method, args, env)
-            .map_err(|e| Signal::Error(e))
-    }
-
-    fn value_to_iter(&self, val: Value) -> std::result::Result<Vec<Value>, Signal> {
-        match val {
-            Value::List(v) => Ok(v.borrow().clone()),
-            Value::Str(s)  => Ok(s.chars().map(|c| Value::Str(c.to_string())).collect()),
-            other => Err(Signal::Error(format!("'{}' is not iterable", other)))
-        }
-    }
-}
-
-// ── Binary operations ─────────────────────────────────────────────────────────
-
-fn eval_binop(l: Value, op: &BinOp, r: Value) -> EvalResult {
-    match op {
-        BinOp::Add => match (&l, &r) {
-            (Value::Int(a), Value::Int(b))     => Ok(Value::Int(a + b)),
-            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
-            (Value::Int(a), Value::Float(b))   => Ok(Value::Float(*a as f64 + b)),
-            (Value::Float(a), Value::Int(b))   => Ok(Value::Float(a + *b as f64)),
-            (Value::Str(a), Value::Str(b))     => Ok(Value::Str(format!("{}{}", a, b))),
-            (Value::Str(a), b)                 => Ok(Value::Str(format!("{}{}", a, b))),
-            (Value::List(a), Value::List(b)) => {
-                let mut combined = a.borrow().clone();
-                combined.extend(b.borrow().clone());
-                Ok(Value::List(Rc::new(RefCell::new(combined))))
-            }
-            _ => Err(Signal::Error(format!("Cannot add {} and {}", l, r)))
-        },
-        BinOp::Sub => numeric_op(&l, &r, |a, b| a - b, |a, b| a - b),
-        BinOp::Mul => match (&l, &r) {
-            (Value::Str(s), Value::Int(n)) => Ok(Value::Str(s.repeat(*n as usize))),
-            _ => numeric_op(&l, &r, |a, b| a * b, |a, b| a * b),
-        },
-        BinOp::Div => match (&l, &r) {
-            (_, Value::Int(0)) => Err(Signal::Error("Division by zero".into())),
-            (_, Value::Float(f)) if *f == 0.0 => Err(Signal::Error("Division by zero".into())),
-            _ => numeric_op(&l, &r, |a, b| a / b, |a, b| a / b),
-        },
-        BinOp::Mod => numeric_op(&l, &r, |a, b| a % b, |a, b| a % b),
-        BinOp::Eq    => Ok(Value::Bool(l == r)),
-        BinOp::NotEq => Ok(Value::Bool(l != r)),
-        BinOp::Lt    => compare_op(&l, &r, |o| o == std::cmp::Ordering::Less),
-        BinOp::LtEq  => compare_op(&l, &r, |o| o != std::cmp::Ordering::Greater),
-        BinOp::Gt    => compare_op(&l, &r, |o| o == std::cmp::Ordering::Greater),
-        BinOp::GtEq  => compare_op(&l, &r, |o| o != std::cmp::Ordering::Less),
-        BinOp::And   => Ok(Value::Bool(is_truthy(&l) && is_truthy(&r))),
-        BinOp::Or    => {
-            if is_truthy(&l) { Ok(l) } else { Ok(r) }
-        }
-        BinOp::DotDot => Err(Signal::Error("Range not valid in this context".into())),
-    }
-}
-
-fn numeric_op(l: &Value, r: &Value, int_op: impl Fn(i64, i64) -> i64, float_op: impl Fn(f64, f64) -> f64) -> EvalResult {
-    match (l, r) {
-        (Value::Int(a), Value::Int(b))     => Ok(Value::Int(int_op(*a, *b))),
-        (Value::Float(a), Value::Float(b)) => Ok(Value::Float(float_op(*a, *b))),
-        (Value::Int(a), Value::Float(b))   => Ok(Value::Float(float_op(*a as f64, *b))),
-        (Value::Float(a), Value::Int(b))   => Ok(Value::Float(float_op(*a, *b as f64))),
-        _ => Err(Signal::Error(format!("Type error in numeric operation: {} and {}", l, r)))
-    }
-}
-
-fn compare_op(l: &Value, r: &Value, pred: impl Fn(std::cmp::Ordering) -> bool) -> EvalResult {
-    let ord = match (l, r) {
-        (Value::Int(a), Value::Int(b))     => a.cmp(b),
-        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal),
-        (Value::Int(a), Value::Float(b))   => (*a as f64).partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal),
-        (Value::Float(a), Value::Int(b))   => a.partial_cmp(&(*b as f64)).unwrap_or(std::cmp::Ordering::Equal),
-        (Value::Str(a), Value::Str(b))     => a.cmp(b),
-        _ => return Err(Signal::Error(format!("Cannot compare {} and {}", l, r)))
-    };
-    Ok(Value::Bool(pred(ord)))
-}
-
-// ── Pattern matching ──────────────────────────────────────────────────────────
-
-fn match_pattern(pat: &Pattern, val: &Value, env: &Env) -> std::result::Result<bool, Signal> {
-    match pat {
-        Pattern::Wildcard  => Ok(true),
-        Pattern::Nil       => Ok(matches!(val, Value::Nil | Value::Option(None))),
-        Pattern::Bool(b)   => Ok(val == &Value::Bool(*b)),
-        Pattern::Int(n)    => Ok(val == &Value::Int(*n)),
-        Pattern::Float(f)  => Ok(val == &Value::Float(*f)),
-        Pattern::StringLit(s) => Ok(val == &Value::Str(s.clone())),
-
-        Pattern::Ident(name) => {
-            env.define(name, val.clone());
-            Ok(true)
-        }
-
-        Pattern::Tuple(pats) => {
-            if let Value::Tuple(elems) = val {
-                if elems.len() != pats.len() { return Ok(false); }
-                for (p, e) in pats.iter().zip(elems.iter()) {
-                    if !match_pattern(p, e, env)? { return Ok(false); }
-                }
-                Ok(true)
-            } else { Ok(false) }
-        }
-
-        Pattern::List(pats) => {
-            if let Value::List(elems) = val {
-                let elems = elems.borrow();
-                if elems.len() != pats.len() { return Ok(false); }
-                for (p, e) in pats.iter().zip(elems.iter()) {
-                    if !match_pattern(p, e, env)? { return Ok(false); }
-                }
-                Ok(true)
-            } else { Ok(false) }
-        }
-
-        Pattern::EnumVariant(enum_name, variant, field_pats) => {
-            if let Value::Enum(en, vn, fields) = val {
-                if en != enum_name || vn != variant { return Ok(false); }
-                if fields.len() != field_pats.len() { return Ok(false); }
-                for (p, f) in field_pats.iter().zip(fields.iter()) {
-                    if !match_pattern(p, f, env)? { return Ok(false); }
-                }
-                Ok(true)
-            } else { Ok(false) }
-        }
-
-        Pattern::Some(inner) => match val {
-            Value::Option(Some(v)) => match_pattern(inner, v, env),
-            _ => Ok(false)
-        }
-
-        Pattern::Ok(inner) => match val {
-            Value::Result(std::result::Result::Ok(v)) => match_pattern(inner, v, env),
-            _ => Ok(false)
-        }
-
-        Pattern::Err(inner) => match val {
-            Value::Result(std::result::Result::Err(e)) => match_pattern(inner, e, env),
-            _ => Ok(false)
-        }
-
-        Pattern::Or(a, b) => {
-            if match_pattern(a, val, env)? { Ok(true) }
-            else { match_pattern(b, val, env) }
-        }
-
-        Pattern::Range(lo, hi) => {
-            if let (Pattern::Int(lo), Pattern::Int(hi)) = (lo.as_ref(), hi.as_ref()) {
-                if let Value::Int(n) = val {
-                    return Ok(n >= lo && n < hi);
-                }
-            }
-            Ok(false)
-        }
-
-        Pattern::Struct(_, _) => Ok(false), // TODO: struct pattern matching
-    }
-}
-
-// ── Helpers ───────────────────────────────────────────────────────────────────
-
-pub fn is_truthy(val: &Value) -> bool {
-    match val {
-        Value::Bool(b)        => *b,
-        Value::Nil            => false,
-        Value::Option(None)   => false,
-        Value::Int(0)         => false,
-        Value::Str(s)         => !s.is_empty(),
-        _                     => true,
-    }
-}
-
-pub fn require_int(val: &Value) -> std::result::Result<i64, Signal> {
-    match val {
-        Value::Int(n)   => Ok(*n),
-        Value::Float(f) => Ok(*f as i64),
-        other => Err(Signal::Error(format!("Expected integer, got {}", other)))
-    }
-}
-
-pub fn value_type_name(val: &Value) -> String {
-    match val {
-        Value::Int(_)    => "Int".into(),
-        Value::Float(_)  => "Float".into(),
-        Value::Bool(_)   => "Bool".into(),
-        Value::Str(_)    => "String".into(),
-        Value::Nil       => "Nil".into(),
-        Value::List(_)   => "List".into(),
-        Value::Map(_)    => "Map".into(),
-        Value::Tuple(_)  => "Tuple".into(),
-        Value::Struct(n, _) => n.clone(),
-        Value::Enum(n, _, _) => n.clone(),
-        Value::Option(_) => "Option".into(),
-        Value::Result(_) => "Result".into(),
-        Value::Function(_) => "Function".into(),
-        Value::Ref(_)    => "Ref".into(),
-    }
-}
\ No newline at end of file
+// ═══════════════════════════════════════════════════════════
+// Zephyr Interpreter — tree-walking evaluator
+// ═══════════════════════════════════════════════════════════
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use indexmap::{IndexMap, IndexSet};
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::ast::*;
+use crate::gc;
+use crate::iter;
+use crate::stdlib;
+
+// ── Values ────────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Int(i64),
+    // an Int that no longer fits in 64 bits; arithmetic moves between the
+    // two on its own, so a BigInt never holds a value an Int could
+    BigInt(Rc<BigInt>),
+    Float(f64),
+    Bool(bool),
+    // shared, and never changed once another value can see it
+    Str(Rc<String>),
+    Nil,
+    Tuple(Vec<Value>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<IndexMap<MapKey, Value>>>),
+    Set(Rc<RefCell<IndexSet<MapKey>>>),
+    Struct(String, Rc<RefCell<HashMap<String, Value>>>),
+    Enum(String, String, Vec<Value>),   // type_name, variant, fields
+    Option(Option<Box<Value>>),          // Some(v) or None→Nil
+    Result(std::result::Result<Box<Value>, Box<Value>>),
+    Function(ZephyrFn),
+    Ref(Rc<RefCell<Value>>),
+    Module(Rc<Module>),
+    Range(i64, i64, i64),                // start, end (exclusive), step
+    Iter(crate::iter::IterRef),
+    Regex(Rc<regex::Regex>),
+    DateTime(chrono::DateTime<chrono::FixedOffset>),
+    Duration(chrono::TimeDelta),
+}
+
+#[derive(Clone, Debug)]
+pub enum ZephyrFn {
+    UserDefined {
+        name: Option<String>,
+        params: Vec<Param>,
+        // shared, so that a call can tell whether it is calling itself
+        body: Rc<Vec<Stmt>>,
+        // the scope a call runs in, parameters first
+        scope: Scope,
+        closure_env: Env,
+        // source file the function was defined in, for error locations
+        file: Option<Rc<PathBuf>>,
+    },
+    Native(String),
+    // compiled by crate::compiler and run on the VM
+    Compiled(Rc<crate::vm::Closure>),
+}
+
+/// A namespace created by `mod name { ... }` or by importing a file.
+#[derive(Debug)]
+pub struct Module {
+    pub name: String,
+    pub env: Env,
+    // every top-level item → (kind, is_pub)
+    pub items: HashMap<String, (ItemKind, bool)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemKind { Fun, Struct, Enum, Mod }
+
+impl Module {
+    fn new(name: String, env: Env, body: &[Stmt]) -> Self {
+        let mut items = HashMap::new();
+        for stmt in body {
+            match &stmt.kind {
+                StmtKind::FunDef(f)        => { items.insert(f.name.clone(), (ItemKind::Fun, f.is_pub)); }
+                StmtKind::StructDef(s)     => { items.insert(s.name.clone(), (ItemKind::Struct, s.is_pub)); }
+                StmtKind::EnumDef(e)       => { items.insert(e.name.clone(), (ItemKind::Enum, e.is_pub)); }
+                StmtKind::ModDef(n, _, p)  => { items.insert(n.clone(), (ItemKind::Mod, *p)); }
+                _ => {}
+            }
+        }
+        Module { name, env, items }
+    }
+
+    /// Look up a top-level item, enforcing `pub`.
+    fn item(&self, name: &str) -> std::result::Result<ItemKind, Signal> {
+        match self.items.get(name) {
+            Some((kind, true))  => Ok(*kind),
+            Some((_, false))    => Err(Signal::Error(format!("'{}' is private to module '{}'", name, self.name))),
+            None                => Err(Signal::Error(format!("Module '{}' has no item '{}'", self.name, name))),
+        }
+    }
+}
+
+/// A declared trait: the methods an impl has to provide, and the default
+/// bodies it gets for the rest.
+#[derive(Debug, Clone)]
+pub struct Trait {
+    pub required: Vec<String>,
+    pub defaults: HashMap<String, ZephyrFn>,
+}
+
+// What a qualified path such as `geometry::Shape::Circle` refers to
+pub(crate) enum PathItem {
+    Value(Value),
+    Variant(String, String),
+    Struct(String),
+}
+
+/// A map key or set element. Only values with a stable identity can be keys,
+/// and they keep their type: `{1: "a"}` and `{"1": "a"}` are different maps.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MapKey {
+    Int(i64),
+    BigInt(BigInt),
+    Bool(bool),
+    Str(String),
+    Tuple(Vec<MapKey>),
+    Enum(String, String, Vec<MapKey>),
+}
+
+impl MapKey {
+    pub fn from_value(v: &Value) -> std::result::Result<MapKey, String> {
+        let all = |vs: &[Value]| vs.iter().map(MapKey::from_value).collect::<std::result::Result<Vec<_>, _>>();
+        Ok(match v {
+            Value::Int(n) => MapKey::Int(*n),
+            Value::BigInt(n) => MapKey::BigInt((**n).clone()),
+            Value::Bool(b) => MapKey::Bool(*b),
+            Value::Str(s) => MapKey::Str((**s).clone()),
+            Value::Tuple(items) => MapKey::Tuple(all(items)?),
+            Value::Enum(ty, variant, fields) => MapKey::Enum(ty.clone(), variant.clone(), all(fields)?),
+            other => return Err(format!("{} cannot be a map key or set element (use Int, Bool, String, a tuple or an enum)", value_type_name(other))),
+        })
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            MapKey::Int(n) => Value::Int(*n),
+            MapKey::BigInt(n) => Value::BigInt(Rc::new(n.clone())),
+            MapKey::Bool(b) => Value::Bool(*b),
+            MapKey::Str(s) => Value::str(s.clone()),
+            MapKey::Tuple(items) => Value::Tuple(items.iter().map(MapKey::to_value).collect()),
+            MapKey::Enum(ty, variant, fields) => {
+                Value::Enum(ty.clone(), variant.clone(), fields.iter().map(MapKey::to_value).collect())
+            }
+        }
+    }
+}
+
+impl From<&str> for MapKey {
+    fn from(s: &str) -> Self { MapKey::Str(s.to_string()) }
+}
+
+impl From<String> for MapKey {
+    fn from(s: String) -> Self { MapKey::Str(s) }
+}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapKey::Str(s) => write!(f, "{}", s),
+            other => write!(f, "{}", other.to_value()),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(n)    => write!(f, "{}", n),
+            Value::BigInt(n) => write!(f, "{}", n),
+            Value::Float(n)  => {
+                if n.fract() == 0.0 { write!(f, "{:.1}", n) } else { write!(f, "{}", n) }
+            }
+            Value::Bool(b)   => write!(f, "{}", b),
+            Value::Str(s)    => write!(f, "{}", s),
+            Value::Nil       => write!(f, "nil"),
+            Value::Tuple(v)  => {
+                write!(f, "(")?;
+                for (i, x) in v.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}", x)?;
+                }
+                write!(f, ")")
+            }
+            Value::List(v)   => {
+                write!(f, "[")?;
+                for (i, x) in v.borrow().iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}", x)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(m)    => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in m.borrow().iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}: {}", k, v)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Set(s)    => {
+                write!(f, "#{{")?;
+                for (i, x) in s.borrow().iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}", x)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Struct(name, fields) => {
+                write!(f, "{} {{", name)?;
+                for (i, (k, v)) in fields.borrow().iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}: {}", k, v)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Enum(_, variant, fields) => {
+                if fields.is_empty() {
+                    write!(f, "{}", variant)
+                } else {
+                    write!(f, "{}(", variant)?;
+                    for (i, x) in fields.iter().enumerate() {
+                        if i > 0 { write!(f, ", ")?; }
+                        write!(f, "{}", x)?;
+                    }
+                    write!(f, ")")
+                }
+            }
+            Value::Option(Some(v)) => write!(f, "Some({})", v),
+            Value::Option(None)    => write!(f, "nil"),
+            Value::Result(Ok(v))   => write!(f, "Ok({})", v),
+            Value::Result(Err(e))  => write!(f, "Err({})", e),
+            Value::Function(ZephyrFn::UserDefined { name, .. }) => {
+                write!(f, "<fun {}>", name.as_deref().unwrap_or("<closure>"))
+            }
+            Value::Function(ZephyrFn::Native(n)) => write!(f, "<native {}>", n),
+            Value::Function(ZephyrFn::Compiled(c)) => {
+                write!(f, "<fun {}>", c.proto.name.as_deref().unwrap_or("<closure>"))
+            }
+            Value::Ref(r) => write!(f, "ref({})", r.borrow()),
+            Value::Module(m) => write!(f, "<module {}>", m.name),
+            Value::Range(start, end, 1) => write!(f, "{}..{}", start, end),
+            Value::Range(start, end, step) => write!(f, "range({}, {}, {})", start, end, step),
+            Value::Iter(_) => write!(f, "<iter>"),
+            Value::Regex(re) => write!(f, "<regex {}>", re.as_str()),
+            Value::DateTime(dt) => write!(f, "{}", crate::datetime::iso(dt)),
+            Value::Duration(d) => write!(f, "{}", crate::datetime::fmt_duration(d)),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b))     => a == b,
+            (Value::BigInt(a), Value::BigInt(b)) => a == b,
+            (Value::BigInt(a), Value::Float(b)) | (Value::Float(b), Value::BigInt(a)) => a.to_f64() == Some(*b),
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Int(a), Value::Float(b))   => (*a as f64) == *b,
+            (Value::Float(a), Value::Int(b))   => *a == (*b as f64),
+            (Value::Bool(a), Value::Bool(b))   => a == b,
+            (Value::Str(a), Value::Str(b))     => a == b,
+            (Value::Nil, Value::Nil)           => true,
+            (Value::Option(None), Value::Nil)  => true,
+            (Value::Nil, Value::Option(None))  => true,
+            (Value::Option(a), Value::Option(b)) => a == b,
+            (Value::Tuple(a), Value::Tuple(b)) => a == b,
+            // same elements, in any order
+            (Value::Set(a), Value::Set(b))     => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            (Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
+            (Value::Range(a, b, c), Value::Range(x, y, z)) => (a, b, c) == (x, y, z),
+            (Value::Iter(a), Value::Iter(b))   => Rc::ptr_eq(a, b),
+            (Value::Regex(a), Value::Regex(b)) => a.as_str() == b.as_str(),
+            (Value::DateTime(a), Value::DateTime(b)) => a == b,
+            (Value::Duration(a), Value::Duration(b)) => a == b,
+            _                                   => false,
+        }
+    }
+}
+
+// Lists, maps and structs are made through these, so that the cycle
+// collector knows about every one of them.
+impl Value {
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::List(gc::track_list(Rc::new(RefCell::new(items))))
+    }
+
+    pub fn str(s: impl Into<String>) -> Value {
+        Value::Str(Rc::new(s.into()))
+    }
+
+    pub fn map(entries: IndexMap<MapKey, Value>) -> Value {
+        Value::Map(gc::track_map(Rc::new(RefCell::new(entries))))
+    }
+
+    pub fn structure(type_name: String, fields: HashMap<String, Value>) -> Value {
+        Value::Struct(type_name, gc::track_struct(Rc::new(RefCell::new(fields))))
+    }
+}
+
+// ── Environment ───────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug)]
+pub struct Env(pub(crate) Rc<RefCell<EnvInner>>);
+
+/// A scope's variables. Names the resolver placed live in `slots`, in the
+/// order of `names`; anything else is bound by name in `vars`. Lookups by
+/// name see both, so a resolved scope still works for code that was not
+/// resolved.
+#[derive(Debug)]
+pub(crate) struct EnvInner {
+    pub(crate) vars: HashMap<String, Rc<RefCell<Value>>>,
+    // None until the name is defined
+    pub(crate) slots: Vec<Option<Value>>,
+    pub(crate) names: Option<Arc<[String]>>,
+    pub(crate) parent: Option<Env>,
+}
+
+impl EnvInner {
+    fn slot_of(&self, name: &str) -> Option<usize> {
+        self.names.as_ref()?.iter().position(|n| n == name)
+    }
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Env(gc::track_env(Rc::new(RefCell::new(EnvInner {
+            vars: HashMap::new(),
+            slots: Vec::new(),
+            names: None,
+            parent: None,
+        }))))
+    }
+
+    pub fn child(parent: &Env) -> Self {
+        Env(gc::track_env(Rc::new(RefCell::new(EnvInner {
+            vars: HashMap::new(),
+            slots: Vec::new(),
+            names: None,
+            parent: Some(parent.clone()),
+        }))))
+    }
+
+    /// A child scope with a slot for each of `names`.
+    pub fn with_slots(parent: &Env, names: &Arc<[String]>) -> Self {
+        Env(gc::track_env(Rc::new(RefCell::new(EnvInner {
+            vars: HashMap::new(),
+            slots: vec![None; names.len()],
+            names: Some(names.clone()),
+            parent: Some(parent.clone()),
+        }))))
+    }
+
+    /// The scope `scope` describes, inside this one.
+    pub(crate) fn enter(&self, scope: &Scope) -> Env {
+        match scope {
+            Scope::Dynamic => Env::child(self),
+            Scope::Inline => self.clone(),
+            Scope::Slots(names, _) => Env::with_slots(self, names),
+        }
+    }
+
+    /// Forget everything defined here, so a loop can run its next
+    /// iteration in the same scope.
+    pub(crate) fn clear(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.slots.fill(None);
+        inner.vars.clear();
+    }
+
+    pub fn define(&self, name: &str, val: Value) {
+        // Redefining reuses the existing cell, so the VM's cached global
+        // lookups stay valid.
+        let mut inner = self.0.borrow_mut();
+        if let Some(i) = inner.slot_of(name) {
+            inner.slots[i] = Some(val);
+        } else if let Some(cell) = inner.vars.get(name) {
+            *cell.borrow_mut() = val;
+        } else {
+            inner.vars.insert(name.to_string(), Rc::new(RefCell::new(val)));
+        }
+    }
+
+    pub fn set(&self, name: &str, val: Value) -> bool {
+        let mut inner = self.0.borrow_mut();
+        if let Some(i) = inner.slot_of(name) {
+            if inner.slots[i].is_some() {
+                inner.slots[i] = Some(val);
+                return true;
+            }
+        } else if let Some(cell) = inner.vars.get(name) {
+            *cell.borrow_mut() = val;
+            return true;
+        }
+        if let Some(parent) = &inner.parent.clone() {
+            return parent.set(name, val);
+        }
+        false
+    }
+
+    /// The storage cell behind `name`, searching parent scopes. Slots have
+    /// no cell of their own; this is only asked of the global scope.
+    pub fn cell(&self, name: &str) -> Option<Rc<RefCell<Value>>> {
+        let inner = self.0.borrow();
+        if let Some(cell) = inner.vars.get(name) {
+            return Some(cell.clone());
+        }
+        inner.parent.as_ref().and_then(|p| p.cell(name))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let inner = self.0.borrow();
+        if let Some(i) = inner.slot_of(name) {
+            if let Some(v) = &inner.slots[i] {
+                return Some(v.clone());
+            }
+        } else if let Some(cell) = inner.vars.get(name) {
+            return Some(cell.borrow().clone());
+        }
+        if let Some(parent) = &inner.parent {
+            return parent.get(name);
+        }
+        None
+    }
+
+    /// `name` as defined in this scope itself, not its parents.
+    pub(crate) fn get_local(&self, name: &str) -> Option<Value> {
+        let inner = self.0.borrow();
+        match inner.slot_of(name) {
+            Some(i) => inner.slots[i].clone(),
+            None => inner.vars.get(name).map(|cell| cell.borrow().clone()),
+        }
+    }
+
+    /// The names defined in this scope itself, not its parents.
+    pub fn names(&self) -> Vec<String> {
+        let inner = self.0.borrow();
+        let slots = inner.names.iter().flat_map(|names| names.iter().zip(&inner.slots));
+        let set = slots.filter(|(_, v)| v.is_some()).map(|(n, _)| n.clone());
+        set.chain(inner.vars.keys().filter(|n| !n.starts_with(TYPE_KEY)).cloned()).collect()
+    }
+
+    /// A resolved variable, if its slot has been defined.
+    pub(crate) fn get_slot(&self, depth: u32, slot: u32) -> Option<Value> {
+        let inner = self.0.borrow();
+        if depth == 0 {
+            return inner.slots.get(slot as usize)?.clone();
+        }
+        inner.parent.as_ref()?.get_slot(depth - 1, slot)
+    }
+
+    /// Assign a resolved variable; hands the value back if its slot has
+    /// not been defined.
+    pub(crate) fn set_slot(&self, depth: u32, slot: u32, val: Value) -> std::result::Result<(), Value> {
+        if depth > 0 {
+            return match &self.0.borrow().parent {
+                Some(parent) => parent.set_slot(depth - 1, slot, val),
+                None => Err(val),
+            };
+        }
+        match self.0.borrow_mut().slots.get_mut(slot as usize) {
+            Some(s @ Some(_)) => {
+                *s = Some(val);
+                Ok(())
+            }
+            _ => Err(val),
+        }
+    }
+
+    /// Leave nil in the variable `s = s + x` is about to store over, if it
+    /// still holds `s`, so that nothing else shares the string and the
+    /// concatenation can grow it in place.
+    pub(crate) fn release(&self, name: &str, res: VarRes, s: &Rc<String>) {
+        let release = |v: &mut Value| {
+            if matches!(v, Value::Str(held) if Rc::ptr_eq(held, s)) {
+                *v = Value::Nil;
+            }
+        };
+        if let VarRes::Slot { depth, slot } = res {
+            let mut env = self.clone();
+            for _ in 0..depth {
+                let parent = env.0.borrow().parent.clone();
+                match parent {
+                    Some(p) => env = p,
+                    None => return,
+                }
+            }
+            let mut inner = env.0.borrow_mut();
+            if let Some(Some(v)) = inner.slots.get_mut(slot as usize) {
+                return release(v);
+            }
+        }
+        // The same search `set` makes, so it finds the variable that
+        // assignment goes on to write.
+        let mut env = self.clone();
+        loop {
+            let parent = {
+                let mut inner = env.0.borrow_mut();
+                if let Some(i) = inner.slot_of(name) {
+                    if let Some(v) = &mut inner.slots[i] {
+                        return release(v);
+                    }
+                } else if let Some(cell) = inner.vars.get(name) {
+                    return release(&mut cell.borrow_mut());
+                }
+                inner.parent.clone()
+            };
+            match parent {
+                Some(p) => env = p,
+                None => return,
+            }
+        }
+    }
+}
+
+/// The scope all iterations of a loop share, when nothing made in the body
+/// can hold on to it.
+fn loop_scope(scope: &Scope, env: &Env) -> Option<Env> {
+    matches!(scope, Scope::Slots(_, false)).then(|| env.enter(scope))
+}
+
+fn iteration_env(shared: &Option<Env>, scope: &Scope, env: &Env) -> Env {
+    match shared {
+        Some(loop_env) => {
+            loop_env.clear();
+            loop_env.clone()
+        }
+        None => env.enter(scope),
+    }
+}
+
+// ── Control flow signals ──────────────────────────────────────────────────────
+
+#[derive(Debug)]
+pub enum Signal {
+    Return(Value),
+    Break,
+    Continue,
+    Error(String),
+    // an Error that has been given a source position
+    Located(Box<RuntimeError>),
+    PropagateErr(Value), // for ? operator
+    // `return f(...)` where f is the function running: the arguments for
+    // the next round. Never leaves the call it was raised in.
+    TailCall(Vec<Value>),
+}
+
+/// A runtime error, where it was raised, and the Zephyr functions it
+/// unwound through.
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    pub file: Option<Rc<PathBuf>>,
+    pub span: Span,
+    // innermost first
+    pub frames: Vec<TraceFrame>,
+    // position reached in the frame being unwound, until we learn whose it is
+    site: Option<(Option<Rc<PathBuf>>, Span)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceFrame {
+    pub name: String,
+    pub file: Option<Rc<PathBuf>>,
+    pub span: Span,
+}
+
+impl RuntimeError {
+    /// Record that the error left the function `name`.
+    pub(crate) fn leave_frame(&mut self, name: &str) {
+        if let Some((file, span)) = self.site.take() {
+            self.frames.push(TraceFrame { name: name.to_string(), file, span });
+        }
+    }
+
+    /// The message, its location with the source line underlined, and the
+    /// call stack. `source` is the text of `self.file`, when available.
+    pub fn render(&self, source: Option<&str>) -> String {
+        let mut out = self.message.clone();
+        let file = display_file(self.file.as_deref());
+        out.push_str(&format!("\n  --> {}:{}:{}", file, self.span.line, self.span.col));
+        let line = source.and_then(|src| src.lines().nth(self.span.line.wrapping_sub(1)));
+        if let Some(line) = line {
+            let gutter = self.span.line.to_string().len();
+            let caret: String = line.chars().take(self.span.col.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            out.push_str(&format!("\n{:w$} |", "", w = gutter));
+            out.push_str(&format!("\n{} | {}", self.span.line, line));
+            out.push_str(&format!("\n{:w$} | {}^", "", caret, w = gutter));
+        }
+        if !self.frames.is_empty() {
+            out.push_str("\ncall stack (most recent call last):");
+            if let Some((file, span)) = &self.site {
+                out.push_str(&format!("\n  <script> at {}:{}:{}", display_file(file.as_deref()), span.line, span.col));
+            }
+            // a runaway recursion is summarised by its two ends
+            let n = self.frames.len();
+            for (i, f) in self.frames.iter().rev().enumerate() {
+                if n > 2 * TRACE_ENDS && i == TRACE_ENDS {
+                    out.push_str(&format!("\n  ... {} more calls ...", n - 2 * TRACE_ENDS));
+                }
+                if n > 2 * TRACE_ENDS && (TRACE_ENDS..n - TRACE_ENDS).contains(&i) {
+                    continue;
+                }
+                out.push_str(&format!("\n  {} at {}:{}:{}", f.name, display_file(f.file.as_deref()), f.span.line, f.span.col));
+            }
+        }
+        out
+    }
+}
+
+// frames shown at each end of a long call stack
+const TRACE_ENDS: usize = 10;
+
+fn display_file(file: Option<&PathBuf>) -> String {
+    file.map(|f| f.display().to_string()).unwrap_or_else(|| "<input>".to_string())
+}
+
+impl From<String> for Signal {
+    fn from(s: String) -> Self { Signal::Error(s) }
+}
+
+pub(crate) type EvalResult = std::result::Result<Value, Signal>;
+
+// ── Interpreter ───────────────────────────────────────────────────────────────
+
+pub struct Interpreter {
+    pub global: Env,
+    // natives; the parent of the program's globals and of every imported module
+    pub builtins: Env,
+    // struct/enum definitions, keyed by module-qualified name (`geometry::Point`)
+    pub struct_defs: HashMap<String, StructDef>,
+    pub enum_defs: HashMap<String, EnumDef>,
+    pub impl_methods: HashMap<String, HashMap<String, ZephyrFn>>,
+    pub traits: HashMap<String, Trait>,
+    // file imports, keyed by canonical path
+    pub modules: HashMap<String, Rc<Module>>,
+    // file being executed — imports and error locations resolve relative to it
+    pub(crate) current_file: Option<Rc<PathBuf>>,
+    // files whose top level is currently running, for circular import detection
+    loading: Vec<PathBuf>,
+    // path of the module being defined, which qualifies the types it declares
+    module_path: String,
+    // Zephyr calls in progress, in the tree-walker and the VM together
+    pub(crate) depth: usize,
+    pub max_depth: usize,
+    // stack segments the calls in progress have moved onto
+    stack_segments: usize,
+    // body of the user function running now, to spot self tail calls
+    current_fn: Option<Rc<Vec<Stmt>>>,
+}
+
+/// Calls that may be in progress at once before a call fails with a
+/// recursion error. Scripts can change it with `set_recursion_limit`.
+///
+/// A tree-walker call takes about 10 KB of stack in a release build, so
+/// the default depth costs about 100 MB. A debug build takes about 100 KB
+/// per call and reaches STACK_LIMIT at around 5000 calls instead.
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+// Zephyr recursion is Rust recursion, so each call makes sure this much
+// stack is left, and moves to a fresh segment of STACK_SEGMENT when not.
+// However high the recursion limit, the segments stop at STACK_LIMIT.
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+const STACK_LIMIT: usize = 512 * 1024 * 1024;
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let builtins = Env::new();
+        stdlib::register(&builtins);
+        Interpreter {
+            global: Env::child(&builtins),
+            builtins,
+            struct_defs: HashMap::new(),
+            enum_defs: HashMap::new(),
+            impl_methods: HashMap::new(),
+            traits: HashMap::new(),
+            modules: HashMap::new(),
+            current_file: None,
+            loading: Vec::new(),
+            module_path: String::new(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            stack_segments: 0,
+            current_fn: None,
+        }
+    }
+
+    /// Record the path of the program about to be run, so that `import`
+    /// can resolve sibling files and detect a module importing its entry point.
+    pub fn set_source_path(&mut self, path: &str) {
+        let path = PathBuf::from(path);
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        self.loading = vec![canonical];
+        self.current_file = Some(Rc::new(path));
+    }
+
+    pub fn run(&mut self, mut stmts: Vec<Stmt>) -> EvalResult {
+        let env = self.global.clone();
+        crate::resolver::resolve(&mut stmts, &|name| env.get(name).is_some());
+        self.exec_block(&stmts, &env)
+    }
+
+    fn exec_block(&mut self, stmts: &[Stmt], env: &Env) -> EvalResult {
+        let mut last = Value::Nil;
+        for stmt in stmts {
+            last = self.exec_stmt(stmt, env)?;
+        }
+        Ok(last)
+    }
+
+    /// Attach a position to a plain error. The innermost position wins;
+    /// an already located error only gets its position in the current
+    /// frame filled in.
+    pub(crate) fn locate(&self, sig: Signal, span: Span) -> Signal {
+        match sig {
+            Signal::Error(message) => Signal::Located(Box::new(RuntimeError {
+                message,
+                file: self.current_file.clone(),
+                span,
+                frames: Vec::new(),
+                site: Some((self.current_file.clone(), span)),
+            })),
+            Signal::Located(mut e) if e.site.is_none() => {
+                e.site = Some((self.current_file.clone(), span));
+                Signal::Located(e)
+            }
+            other => other,
+        }
+    }
+
+    pub(crate) fn exec_stmt(&mut self, stmt: &Stmt, env: &Env) -> EvalResult {
+        self.exec_stmt_kind(stmt, env).map_err(|sig| self.locate(sig, stmt.span))
+    }
+
+    fn exec_stmt_kind(&mut self, stmt: &Stmt, env: &Env) -> EvalResult {
+        match &stmt.kind {
+            StmtKind::Let(pat, _ty, expr, _mutable) => {
+                let val = self.eval_expr(expr, env)?;
+                bind_pattern(pat, val, env)?;
+                Ok(Value::Nil)
+            }
+
+            StmtKind::Expr(expr) => self.eval_expr(expr, env),
+
+            StmtKind::Return(Some(Expr { kind: ExprKind::Call(callee, args), .. })) if self.is_self_call(callee, env) => {
+                let arg_vals: std::result::Result<Vec<_>, _> = args.iter().map(|a| self.eval_expr(a, env)).collect();
+                Err(Signal::TailCall(arg_vals?))
+            }
+
+            StmtKind::Return(expr) => {
+                let val = if let Some(e) = expr.as_ref() { self.eval_expr(e, env)? } else { Value::Nil };
+                Err(Signal::Return(val))
+            }
+
+            StmtKind::Break    => Err(Signal::Break),
+            StmtKind::Continue => Err(Signal::Continue),
+
+            StmtKind::While(cond, body, scope) => {
+                let shared = loop_scope(scope, env);
+                loop {
+                    let c = self.eval_expr(cond, env)?;
+                    if !is_truthy(&c) { break; }
+                    let loop_env = iteration_env(&shared, scope, env);
+                    match self.exec_block(body, &loop_env) {
+                        Ok(_) => {}
+                        Err(Signal::Break) => break,
+                        Err(Signal::Continue) => continue,
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(Value::Nil)
+            }
+
+            StmtKind::For(pat, iter_expr, body, scope) => {
+                let iter_val = self.eval_expr(iter_expr, env)?;
+                let items = iter::iter_of(self, iter_val, env)?;
+                let shared = loop_scope(scope, env);
+                while let Some(item) = iter::next(&items, self, env)? {
+                    let loop_env = iteration_env(&shared, scope, env);
+                    bind_pattern(pat, item, &loop_env)?;
+                    match self.exec_block(body, &loop_env) {
+                        Ok(_) => {}
+                        Err(Signal::Break) => break,
+                        Err(Signal::Continue) => continue,
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(Value::Nil)
+            }
+
+            StmtKind::FunDef(fun) => {
+                let func = Value::Function(ZephyrFn::UserDefined {
+                    name: Some(fun.name.clone()),
+                    params: fun.params.clone(),
+                    body: Rc::new(fun.body.clone()),
+                    scope: fun.scope.clone(),
+                    closure_env: env.clone(),
+                    file: self.current_file.clone(),
+                });
+                env.define(&fun.name, func);
+                Ok(Value::Nil)
+            }
+
+            StmtKind::StructDef(def) => {
+                let name = self.declare_type(&def.name, env);
+                self.struct_defs.insert(name.clone(), StructDef { name, ..def.clone() });
+                Ok(Value::Nil)
+            }
+
+            StmtKind::EnumDef(def) => {
+                let name = self.declare_type(&def.name, env);
+                self.enum_defs.insert(name.clone(), EnumDef { name: name.clone(), ..def.clone() });
+                // Register variant constructors as functions in env
+                for variant in &def.variants {
+                    let enum_name = name.clone();
+                    let var_name = variant.name.clone();
+                    let arity = variant.fields.len();
+                    if arity == 0 {
+                        env.define(&format!("{}::{}", enum_name, var_name),
+                            Value::Enum(enum_name.clone(), var_name.clone(), vec![]));
+                    }
+                    // Constructors for variants with fields are handled by EnumVariant expr
+                }
+                Ok(Value::Nil)
+            }
+
+            StmtKind::TraitDef(def) => {
+                let defaults = def.defaults.iter()
+                    .map(|m| (m.name.clone(), self.method_fn(m, env)))
+                    .collect();
+                let required = def.required.iter().map(|m| m.name.clone()).collect();
+                self.traits.insert(def.name.clone(), Trait { required, defaults });
+                Ok(Value::Nil)
+            }
+
+            StmtKind::ImplBlock(block) => {
+                let target = resolve_type(&block.target, env);
+                for method in &block.methods {
+                    let f = self.method_fn(method, env);
+                    self.impl_methods.entry(target.clone()).or_default().insert(method.name.clone(), f);
+                }
+                if let Some(trait_name) = &block.trait_name {
+                    let provided: Vec<String> = block.methods.iter().map(|m| m.name.clone()).collect();
+                    self.impl_trait(trait_name, &target, &provided)?;
+                }
+                Ok(Value::Nil)
+            }
+
+            StmtKind::ModDef(name, stmts, _) => {
+                let mod_env = Env::child(env);
+                let outer = std::mem::take(&mut self.module_path);
+                self.module_path = if outer.is_empty() { name.clone() } else { format!("{}::{}", outer, name) };
+                let result = self.exec_block(stmts, &mod_env);
+                self.module_path = outer;
+                result?;
+                let module = Module::new(name.clone(), mod_env, stmts);
+                env.define(name, Value::Module(Rc::new(module)));
+                Ok(Value::Nil)
+            }
+
+            StmtKind::Import(path) => {
+                let module = self.import_module(path)?;
+                if let Some(last) = path.last() {
+                    env.define(last, Value::Module(module.clone()));
+                }
+                for (name, val) in self.exported_bindings(&module) {
+                    env.define(&name, val);
+                }
+                Ok(Value::Nil)
+            }
+
+            StmtKind::TypeAlias(_, _, _) => Ok(Value::Nil), // type aliases are for type checking
+        }
+    }
+
+    // ── Expression evaluation ─────────────────────────────────────────────────
+
+    pub fn eval_expr(&mut self, expr: &Expr, env: &Env) -> EvalResult {
+        self.eval_expr_kind(expr, env).map_err(|sig| self.locate(sig, expr.span))
+    }
+
+    /// `name = name + a + b ...`, or `None` for any other assignment.
+    /// When `name` holds a string, the operands are all evaluated first and
+    /// the variable then lets go of it, so it is appended to in place
+    /// rather than copied; adding to a string can't fail, so nothing can
+    /// tell the order apart.
+    fn append_assign(&mut self, name: &str, res: VarRes, value: &Expr, env: &Env) -> std::result::Result<Option<Value>, Signal> {
+        let mut left = value;
+        while let ExprKind::BinOp(l, BinOp::Add, _) = &left.kind {
+            left = l;
+        }
+        if std::ptr::eq(left, value) || !matches!(&left.kind, ExprKind::Var(n, _) if n == name) {
+            return Ok(None);
+        }
+        let first = self.eval_expr(left, env)?;
+        let Value::Str(s) = &first else {
+            return self.add_onto(value, first, env).map(Some);
+        };
+        let mut rights = Vec::new();
+        self.eval_addends(value, &mut rights, env)?;
+        env.release(name, res, s);
+        Ok(Some(rights.iter().fold(first, concat)))
+    }
+
+    /// Evaluate a chain of `+` whose leftmost operand is already known.
+    fn add_onto(&mut self, expr: &Expr, first: Value, env: &Env) -> EvalResult {
+        match &expr.kind {
+            ExprKind::BinOp(l, BinOp::Add, r) => {
+                let l = self.add_onto(l, first, env)?;
+                let r = self.eval_expr(r, env)?;
+                eval_binop(l, &BinOp::Add, r).map_err(|sig| self.locate(sig, expr.span))
+            }
+            _ => Ok(first),
+        }
+    }
+
+    /// The right-hand operands of a chain of `+`, left to right.
+    fn eval_addends(&mut self, expr: &Expr, out: &mut Vec<Value>, env: &Env) -> std::result::Result<(), Signal> {
+        if let ExprKind::BinOp(l, BinOp::Add, r) = &expr.kind {
+            self.eval_addends(l, out, env)?;
+            out.push(self.eval_expr(r, env)?);
+        }
+        Ok(())
+    }
+
+    fn eval_expr_kind(&mut self, expr: &Expr, env: &Env) -> EvalResult {
+        match &expr.kind {
+            ExprKind::Int(n)    => Ok(Value::Int(*n)),
+            ExprKind::Float(f)  => Ok(Value::Float(*f)),
+            ExprKind::Bool(b)   => Ok(Value::Bool(*b)),
+            ExprKind::Nil       => Ok(Value::Nil),
+            ExprKind::StringLit(s) => Ok(Value::str(s.clone())),
+
+            ExprKind::InterpolatedString(parts) => {
+                let mut result = String::new();
+                for part in parts {
+                    match part {
+                        crate::ast::StringPart::Literal(s) => result.push_str(s),
+                        crate::ast::StringPart::Interpolated(e, None) => {
+                            match self.eval_expr(e, env)? {
+                                Value::Str(s) => result.push_str(&s),
+                                v => { let _ = fmt::Write::write_fmt(&mut result, format_args!("{}", v)); }
+                            }
+                        }
+                        crate::ast::StringPart::Interpolated(e, Some(spec)) => {
+                            let v = self.eval_expr(e, env)?;
+                            result.push_str(&crate::format::format_value(&v, spec).map_err(Signal::Error)?);
+                        }
+                    }
+                }
+                Ok(Value::str(result))
+            }
+
+            ExprKind::Var(name, res) => {
+                if let VarRes::Slot { depth, slot } = res {
+                    if let Some(v) = env.get_slot(*depth, *slot) {
+                        return Ok(v);
+                    }
+                }
+                env.get(name)
+                    .or_else(|| self.builtins.get(name))
+                    .ok_or_else(|| Signal::Error(format!("Undefined variable '{}'", name)))
+            }
+
+            ExprKind::Tuple(elems) => {
+                let vals: std::result::Result<Vec<_>, _> = elems.iter().map(|e| self.eval_expr(e, env)).collect();
+                Ok(Value::Tuple(vals?))
+            }
+
+            ExprKind::List(elems) => {
+                let vals: std::result::Result<Vec<_>, _> = elems.iter().map(|e| self.eval_expr(e, env)).collect();
+                Ok(Value::list(vals?))
+            }
+
+            ExprKind::MapLit(pairs) => {
+                let mut map = IndexMap::new();
+                for (k, v) in pairs {
+                    let kv = self.eval_expr(k, env)?;
+                    let vv = self.eval_expr(v, env)?;
+                    map.insert(MapKey::from_value(&kv).map_err(Signal::Error)?, vv);
+                }
+                Ok(Value::map(map))
+            }
+
+            ExprKind::SetLit(elems) => {
+                let vals: std::result::Result<Vec<_>, _> = elems.iter().map(|e| self.eval_expr(e, env)).collect();
+                set_of(vals?).map_err(Signal::Error)
+            }
+
+            ExprKind::Block(stmts, tail, scope) => {
+                let block_env = env.enter(scope);
+                for s in stmts { self.exec_stmt(s, &block_env)?; }
+                if let Some(e) = tail {
+                    self.eval_expr(e, &block_env)
+                } else {
+                    Ok(Value::Nil)
+                }
+            }
+
+            ExprKind::BinOp(left, op, right) => {
+                let l = self.eval_expr(left, env)?;
+                let r = self.eval_expr(right, env)?;
+                eval_binop(l, op, r)
+            }
+
+            ExprKind::UnaryOp(op, expr) => {
+                let val = self.eval_expr(expr, env)?;
+                eval_unary(op, val)
+            }
+
+            ExprKind::Assign(target, value) => {
+                let appended = match &target.kind {
+                    ExprKind::Var(name, res) => self.append_assign(name, *res, value, env)?,
+                    _ => None,
+                };
+                let val = match appended {
+                    Some(val) => val,
+                    None => self.eval_expr(value, env)?,
+                };
+                match &target.kind {
+                    ExprKind::Var(name, res) => {
+                        let val = match res {
+                            VarRes::Slot { depth, slot } => match env.set_slot(*depth, *slot, val) {
+                                Ok(()) => return Ok(Value::Nil),
+                                Err(val) => val,
+                            },
+                            VarRes::Dynamic => val,
+                        };
+                        if !env.set(name, val.clone()) {
+                            env.define(name, val);
+                        }
+                        Ok(Value::Nil)
+                    }
+                    ExprKind::Index(obj_expr, idx_expr) => {
+                        let idx = self.eval_expr(idx_expr, env)?;
+                        let obj = self.eval_expr(obj_expr, env)?;
+                        set_index(&obj, &idx, val)?;
+                        Ok(Value::Nil)
+                    }
+                    ExprKind::FieldAccess(obj_expr, field) => {
+                        let obj = self.eval_expr(obj_expr, env)?;
+                        set_field(&obj, field, val)?;
+                        Ok(Value::Nil)
+                    }
+                    _ => Err(Signal::Error("Invalid assignment target".into()))
+                }
+            }
+
+            ExprKind::Call(callee_expr, args) => {
+                let callee = match &callee_expr.kind {
+                    ExprKind::Path(segs) => self.resolve_path(segs, env)?,
+                    _ => PathItem::Value(self.eval_expr(callee_expr, env)?),
+                };
+                let arg_vals: std::result::Result<Vec<_>, _> = args.iter().map(|a| self.eval_expr(a, env)).collect();
+                let arg_vals = arg_vals?;
+                match callee {
+                    PathItem::Value(f) => self.call_value(f, arg_vals, env),
+                    PathItem::Variant(e, v) => Ok(Value::Enum(e, v, arg_vals)),
+                    PathItem::Struct(s) => Err(Signal::Error(format!("Struct '{}' is created with '{} {{ ... }}', not called", s, s))),
+                }
+            }
+
+            ExprKind::MethodCall(obj_expr, method, args) => {
+                let obj = self.eval_expr(obj_expr, env)?;
+                let arg_vals: std::result::Result<Vec<_>, _> = args.iter().map(|a| self.eval_expr(a, env)).collect();
+                let arg_vals = arg_vals?;
+                if let Value::Module(m) = &obj {
+                    return match self.module_member(m, std::slice::from_ref(method))? {
+                        PathItem::Value(f) => self.call_value(f, arg_vals, env),
+                        _ => Err(Signal::Error(format!("'{}' in module '{}' is not callable", method, m.name))),
+                    };
+                }
+                self.call_method(obj, method, arg_vals, env)
+            }
+
+            ExprKind::FieldAccess(obj_expr, field) => {
+                let obj = self.eval_expr(obj_expr, env)?;
+                self.get_field(&obj, field)
+            }
+
+            ExprKind::Index(obj_expr, idx_expr) => {
+                let obj = self.eval_expr(obj_expr, env)?;
+                let idx = self.eval_expr(idx_expr, env)?;
+                get_index(&obj, &idx)
+            }
+
+            ExprKind::If(cond, then_expr, elif_branches, else_expr) => {
+                let cond_val = self.eval_expr(cond, env)?;
+                if is_truthy(&cond_val) {
+                    self.eval_expr(then_expr, env)
+                } else {
+                    for (elif_cond, elif_body) in elif_branches {
+                        let cv = self.eval_expr(elif_cond, env)?;
+                        if is_truthy(&cv) {
+                            return self.eval_expr(elif_body, env);
+                        }
+                    }
+                    if let Some(e) = else_expr {
+                        self.eval_expr(e, env)
+                    } else {
+                        Ok(Value::Nil)
+                    }
+                }
+            }
+
+            ExprKind::Match(subject, arms) => {
+                let val = self.eval_expr(subject, env)?;
+                for arm in arms {
+                    let match_env = env.enter(&arm.scope);
+                    if match_pattern(&arm.pattern, &val, &match_env)? {
+                        if let Some(guard) = &arm.guard {
+                            let gv = self.eval_expr(guard, &match_env)?;
+                            if !is_truthy(&gv) { continue; }
+                        }
+                        return self.eval_expr(&arm.body, &match_env);
+                    }
+                }
+                Err(Signal::Error("Non-exhaustive match".into()))
+            }
+
+            ExprKind::Closure(params, body, scope) => {
+                Ok(Value::Function(ZephyrFn::UserDefined {
+                    name: None,
+                    params: params.iter().map(|(n, t)| Param {
+                        name: n.clone(), ty: t.clone(), default: None
+                    }).collect(),
+                    body: Rc::new(vec![Stmt::new(StmtKind::Return(Some(*body.clone())), body.span)]),
+                    scope: scope.clone(),
+                    closure_env: env.clone(),
+                    file: self.current_file.clone(),
+                }))
+            }
+
+            ExprKind::StructCreate(name, field_exprs) => {
+                let type_name = self.struct_type_name(name, env)?;
+                let mut fields = HashMap::new();
+                for (fname, fexpr) in field_exprs {
+                    fields.insert(fname.clone(), self.eval_expr(fexpr, env)?);
+                }
+                Ok(Value::structure(type_name, fields))
+            }
+
+            ExprKind::EnumVariant(enum_name, variant, args) => {
+                let vals: std::result::Result<Vec<_>, _> = args.iter().map(|a| self.eval_expr(a, env)).collect();
+                if let Some(Value::Module(m)) = env.get(enum_name) {
+                    return match self.module_member(&m, std::slice::from_ref(variant))? {
+                        PathItem::Value(f) => self.call_value(f, vals?, env),
+                        _ => Err(Signal::Error(format!("'{}' in module '{}' is not callable", variant, m.name))),
+                    };
+                }
+                Ok(Value::Enum(resolve_type(enum_name, env), variant.clone(), vals?))
+            }
+
+            ExprKind::Path(segs) => match self.resolve_path(segs, env)? {
+                PathItem::Value(v) => Ok(v),
+                PathItem::Variant(e, v) => Ok(Value::Enum(e, v, vec![])),
+                PathItem::Struct(s) => Err(Signal::Error(format!("Struct '{}' is a type, not a value", s))),
+            },
+
+            ExprKind::Range(start, end) => {
+                let s = require_int(&self.eval_expr(start, env)?)?;
+                let e = require_int(&self.eval_expr(end, env)?)?;
+                Ok(Value::Range(s, e, 1))
+            }
+
+            ExprKind::Some(inner) => {
+                let v = self.eval_expr(inner, env)?;
+                Ok(Value::Option(Some(Box::new(v))))
+            }
+
+            ExprKind::Ok(inner) => {
+                let v = self.eval_expr(inner, env)?;
+                Ok(Value::Result(std::result::Result::Ok(Box::new(v))))
+            }
+
+            ExprKind::Err(inner) => {
+                let v = self.eval_expr(inner, env)?;
+                Ok(Value::Result(std::result::Result::Err(Box::new(v))))
+            }
+
+            ExprKind::Question(inner) => {
+                let val = self.eval_expr(inner, env)?;
+                match val {
+                    Value::Result(std::result::Result::Ok(v)) => Ok(*v),
+                    Value::Result(std::result::Result::Err(e)) => Err(Signal::PropagateErr(*e)),
+                    Value::Option(Some(v)) => Ok(*v),
+                    Value::Option(None) | Value::Nil =>
+                        Err(Signal::PropagateErr(Value::str("None"))),
+                    other => Ok(other),
+                }
+            }
+
+            ExprKind::BoxExpr(inner) => {
+                // In our interpreter, box just returns the value (GC handles memory)
+                self.eval_expr(inner, env)
+            }
+
+            ExprKind::RefExpr(inner) => {
+                let val = self.eval_expr(inner, env)?;
+                Ok(Value::Ref(Rc::new(RefCell::new(val))))
+            }
+
+            ExprKind::Await(inner) => {
+                // Await is not supported in the tree-walking interpreter
+                self.eval_expr(inner, env)
+            }
+        }
+    }
+
+    pub(crate) fn get_field(&self, obj: &Value, field: &str) -> EvalResult {
+        match obj {
+            Value::Struct(_, fields) => {
+                fields.borrow().get(field)
+                    .cloned()
+                    .ok_or_else(|| Signal::Error(format!("No field '{}'", field)))
+            }
+            Value::Tuple(elems) => {
+                let idx: usize = field.parse()
+                    .map_err(|_| Signal::Error(format!("Invalid tuple index '{}'", field)))?;
+                elems.get(idx).cloned()
+                    .ok_or_else(|| Signal::Error(format!("Tuple index {} out of bounds", idx)))
+            }
+            Value::Module(m) => match self.module_member(m, &[field.to_string()])? {
+                PathItem::Value(v) => Ok(v),
+                _ => Err(Signal::Error(format!("'{}' in module '{}' is a type, not a value", field, m.name))),
+            },
+            _ => Err(Signal::Error(format!("Cannot access field '{}' on {:?}", field, obj)))
+        }
+    }
+
+    /// The struct a literal creates: `Point` as declared in scope, or
+    /// `geometry::Point` resolved through its module.
+    pub(crate) fn struct_type_name(&self, name: &str, env: &Env) -> std::result::Result<String, Signal> {
+        if !name.contains("::") {
+            let resolved = resolve_type(name, env);
+            if !self.struct_defs.contains_key(&resolved) {
+                return Err(Signal::Error(format!("Unknown struct '{}'", name)));
+            }
+            return Ok(resolved);
+        }
+        let segs: Vec<String> = name.split("::").map(String::from).collect();
+        match self.resolve_path(&segs, env)? {
+            PathItem::Struct(s) => Ok(s),
+            _ => Err(Signal::Error(format!("'{}' is not a struct", name))),
+        }
+    }
+
+    /// Register a struct or enum declared in `env` under its qualified name,
+    /// and make the bare name refer to it there.
+    fn declare_type(&self, name: &str, env: &Env) -> String {
+        let qualified = if self.module_path.is_empty() { name.to_string() } else { format!("{}::{}", self.module_path, name) };
+        env.define(&type_key(name), Value::str(qualified.clone()));
+        qualified
+    }
+
+    pub(crate) fn call_value(&mut self, callee: Value, args: Vec<Value>, env: &Env) -> EvalResult {
+        match callee {
+            Value::Function(ZephyrFn::Native(name)) => {
+                stdlib::call_native(self, &name, args, env)
+            }
+            Value::Function(ZephyrFn::UserDefined { name, params, body, scope, closure_env, file }) => {
+                if self.depth >= self.max_depth {
+                    return Err(self.recursion_error());
+                }
+                self.depth += 1;
+                let outer_file = std::mem::replace(&mut self.current_file, file);
+                let outer_fn = self.current_fn.replace(body.clone());
+                let mut result = self.with_stack(|interp| interp.call_user_fn(&params, &body, &scope, &closure_env, args));
+                if let Err(Signal::Located(e)) = &mut result {
+                    e.leave_frame(name.as_deref().unwrap_or("<closure>"));
+                }
+                self.current_file = outer_file;
+                self.current_fn = outer_fn;
+                self.depth -= 1;
+                result
+            }
+            Value::Function(ZephyrFn::Compiled(closure)) => {
+                self.with_stack(|interp| crate::vm::call_closure(interp, closure, args))
+            }
+            other => Err(Signal::Error(format!("'{}' is not a function", other)))
+        }
+    }
+
+    /// Run `f` with at least STACK_RED_ZONE of stack left, on a new segment
+    /// if need be; a call that would take the segments past STACK_LIMIT
+    /// fails with a recursion error instead.
+    fn with_stack(&mut self, f: impl FnOnce(&mut Self) -> EvalResult) -> EvalResult {
+        if stacker::remaining_stack().is_some_and(|left| left >= STACK_RED_ZONE) {
+            return f(self);
+        }
+        if self.stack_segments >= STACK_LIMIT / STACK_SEGMENT {
+            return Err(Signal::Error(format!(
+                "Recursion limit exceeded: call depth {} has used up the {} MB of stack a program may take",
+                self.depth, STACK_LIMIT >> 20
+            )));
+        }
+        self.stack_segments += 1;
+        let result = stacker::grow(STACK_SEGMENT, || f(self));
+        self.stack_segments -= 1;
+        result
+    }
+
+    fn call_user_fn(&mut self, params: &[Param], body: &[Stmt], scope: &Scope, closure_env: &Env, mut args: Vec<Value>) -> EvalResult {
+        // a self tail call starts the body over with new arguments
+        loop {
+            let call_env = closure_env.enter(scope);
+            for (i, param) in params.iter().enumerate() {
+                let val = if i < args.len() {
+                    args[i].clone()
+                } else if let Some(default) = &param.default {
+                    // defaults see the parameters before them
+                    self.eval_expr(default, &call_env)?
+                } else {
+                    return Err(Signal::Error(format!("Missing argument '{}'", param.name)));
+                };
+                call_env.define(&param.name, val);
+            }
+            return match self.exec_block(body, &call_env) {
+                Ok(v) => Ok(v),
+                Err(Signal::Return(v)) => Ok(v),
+                Err(Signal::TailCall(next)) => {
+                    args = next;
+                    continue;
+                }
+                Err(Signal::PropagateErr(e)) => Ok(Value::Result(std::result::Result::Err(Box::new(e)))),
+                Err(e) => Err(e),
+            };
+        }
+    }
+
+    /// Whether `callee` names the user function that is running, making
+    /// `return callee(...)` a tail call that can reuse the current call.
+    fn is_self_call(&self, callee: &Expr, env: &Env) -> bool {
+        let (ExprKind::Var(name, _), Some(current)) = (&callee.kind, &self.current_fn) else { return false };
+        matches!(env.get(name), Some(Value::Function(ZephyrFn::UserDefined { body, .. })) if Rc::ptr_eq(&body, current))
+    }
+
+    /// The error for a call that would go past `max_depth`.
+    pub(crate) fn recursion_error(&self) -> Signal {
+        Signal::Error(format!(
+            "Recursion limit exceeded: call depth {} is over the limit of {} (raise it with set_recursion_limit)",
+            self.depth + 1, self.max_depth
+        ))
+    }
+
+    fn method_fn(&self, method: &FunDef, env: &Env) -> ZephyrFn {
+        ZephyrFn::UserDefined {
+            name: Some(method.name.clone()),
+            params: method.params.clone(),
+            body: Rc::new(method.body.clone()),
+            scope: method.scope.clone(),
+            closure_env: env.clone(),
+            file: self.current_file.clone(),
+        }
+    }
+
+    /// Finish `impl Trait for Type` once the block's own methods are in
+    /// place: check them against the trait and fill in its defaults.
+    pub(crate) fn impl_trait(&mut self, trait_name: &str, target: &str, provided: &[String]) -> std::result::Result<(), String> {
+        let tr = self.traits.get(trait_name).cloned()
+            .ok_or_else(|| format!("Unknown trait '{}'", trait_name))?;
+        if let Some(extra) = provided.iter().find(|m| !tr.required.contains(m) && !tr.defaults.contains_key(*m)) {
+            return Err(format!("Method '{}' is not a member of trait '{}'", extra, trait_name));
+        }
+        let missing: Vec<&str> = tr.required.iter()
+            .filter(|m| !provided.contains(m))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("impl {} for {} is missing {}", trait_name, target,
+                missing.iter().map(|m| format!("'{}'", m)).collect::<Vec<_>>().join(", ")));
+        }
+        // a default never replaces a method the type already has
+        let methods = self.impl_methods.entry(target.to_string()).or_default();
+        for (name, f) in tr.defaults {
+            methods.entry(name).or_insert(f);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn call_method(&mut self, obj: Value, method: &str, mut args: Vec<Value>, env: &Env) -> EvalResult {
+        // Check impl methods first
+        let type_name = value_type_name(&obj);
+        if let Some(methods) = self.impl_methods.get(&type_name).cloned() {
+            if let Some(func) = methods.get(method).cloned() {
+                let mut all_args = vec![obj];
+                all_args.append(&mut args);
+                return self.call_value(Value::Function(func), all_args, env);
+            }
+        }
+
+        // Built-in methods
+        stdlib::call_builtin_method(self, obj, method, args, env)
+    }
+
+    // ── Modules ───────────────────────────────────────────────────────────────
+
+    /// Load `import a.b.c` from disk, executing the file at most once.
+    fn import_module(&mut self, path: &[String]) -> std::result::Result<Rc<Module>, Signal> {
+        let dotted = path.join(".");
+        let file = self.resolve_import(path)?;
+        let key = file.to_string_lossy().into_owned();
+
+        if let Some(module) = self.modules.get(&key) {
+            return Ok(module.clone());
+        }
+
+        if let Some(start) = self.loading.iter().position(|p| *p == file) {
+            let cycle: Vec<String> = self.loading[start..].iter()
+                .chain(std::iter::once(&file))
+                .map(|p| display_path(p))
+                .collect();
+            return Err(Signal::Error(format!("Circular import of '{}': {}", dotted, cycle.join(" -> "))));
+        }
+
+        let source = std::fs::read_to_string(&file)
+            .map_err(|e| Signal::Error(format!("Cannot read module '{}' ({}): {}", dotted, display_path(&file), e)))?;
+        let tokens = crate::lexer::Lexer::new(&source).tokenize()
+            .map_err(|e| Signal::Error(format!("Lex error in module '{}': {}", dotted, e)))?;
+        let mut stmts = crate::parser::Parser::new(tokens).parse_program()
+            .map_err(|e| Signal::Error(format!("Parse error in module '{}': {}", dotted, e)))?;
+        let builtins = self.builtins.clone();
+        crate::resolver::resolve(&mut stmts, &|name| builtins.get(name).is_some());
+
+        // a module sees the natives and its own imports, never its importer's globals
+        let mod_env = Env::child(&self.builtins);
+        self.loading.push(file.clone());
+        let prev_file = self.current_file.replace(Rc::new(file.clone()));
+        let prev_fn = self.current_fn.take();
+        let prev_path = std::mem::replace(&mut self.module_path, path.join("::"));
+        let result = self.exec_block(&stmts, &mod_env);
+        self.current_file = prev_file;
+        self.current_fn = prev_fn;
+        self.module_path = prev_path;
+        self.loading.pop();
+
+        match result {
+            Ok(_) | Err(Signal::Return(_)) => {}
+            Err(Signal::Error(e)) => return Err(Signal::Error(format!("In module '{}': {}", dotted, e))),
+            Err(Signal::Located(mut e)) => {
+                e.message = format!("In module '{}': {}", dotted, e.message);
+                e.leave_frame(&format!("<module {}>", dotted));
+                return Err(Signal::Located(e));
+            }
+            Err(e) => return Err(e),
+        }
+
+        let module = Rc::new(Module::new(dotted, mod_env, &stmts));
+        self.modules.insert(key, module.clone());
+        Ok(module)
+    }
+
+    /// Names an `import` brings into scope: pub functions, modules, structs
+    /// and enums, plus the unit variants of pub enums (`Color::Red`).
+    fn exported_bindings(&self, module: &Module) -> Vec<(String, Value)> {
+        let mut out = Vec::new();
+        for (name, (kind, is_pub)) in &module.items {
+            if !is_pub { continue; }
+            match kind {
+                ItemKind::Fun | ItemKind::Mod => {
+                    if let Some(v) = module.env.get(name) { out.push((name.clone(), v)); }
+                }
+                ItemKind::Enum => {
+                    let Some(def) = self.enum_defs.get(&resolve_type(name, &module.env)) else { continue };
+                    for variant in def.variants.iter().filter(|v| v.fields.is_empty()) {
+                        let key = format!("{}::{}", name, variant.name);
+                        if let Some(v) = module.env.get(&key) { out.push((key, v)); }
+                    }
+                    if let Some(v) = module.env.get(&type_key(name)) { out.push((type_key(name), v)); }
+                }
+                ItemKind::Struct => {
+                    if let Some(v) = module.env.get(&type_key(name)) { out.push((type_key(name), v)); }
+                }
+            }
+        }
+        out
+    }
+
+    /// Resolve `a::b::c`. A leading name that is not a module is treated as
+    /// `Enum::Variant`, which is what the path meant before modules existed.
+    pub(crate) fn resolve_path(&self, segs: &[String], env: &Env) -> std::result::Result<PathItem, Signal> {
+        match env.get(&segs[0]).or_else(|| self.builtins.get(&segs[0])) {
+            Some(Value::Module(m)) if segs.len() > 1 => self.module_member(&m, &segs[1..]),
+            _ if segs.len() == 2 => Ok(PathItem::Variant(resolve_type(&segs[0], env), segs[1].clone())),
+            _ => Err(Signal::Error(format!("'{}' is not a module", segs[0]))),
+        }
+    }
+
+    pub(crate) fn module_member(&self, module: &Rc<Module>, rest: &[String]) -> std::result::Result<PathItem, Signal> {
+        let name = &rest[0];
+        let kind = module.item(name)?;
+        let qualified = format!("{}::{}", module.name, name);
+        match (kind, rest.len()) {
+            (ItemKind::Fun, 1) | (ItemKind::Mod, 1) => module.env.get(name)
+                .map(PathItem::Value)
+                .ok_or_else(|| Signal::Error(format!("'{}' is not initialised", qualified))),
+            (ItemKind::Mod, _) => match module.env.get(name) {
+                Some(Value::Module(inner)) => self.module_member(&inner, &rest[1..]),
+                _ => Err(Signal::Error(format!("'{}' is not initialised", qualified))),
+            },
+            (ItemKind::Struct, 1) => Ok(PathItem::Struct(resolve_type(name, &module.env))),
+            (ItemKind::Enum, 2) => {
+                let variant = &rest[1];
+                let enum_name = resolve_type(name, &module.env);
+                let known = self.enum_defs.get(&enum_name)
+                    .is_some_and(|def| def.variants.iter().any(|v| &v.name == variant));
+                if known {
+                    Ok(PathItem::Variant(enum_name, variant.clone()))
+                } else {
+                    Err(Signal::Error(format!("Enum '{}' has no variant '{}'", qualified, variant)))
+                }
+            }
+            (ItemKind::Enum, 1) => Err(Signal::Error(format!("Enum '{}' needs a variant, e.g. {}::Variant", qualified, qualified))),
+            _ => Err(Signal::Error(format!("Cannot resolve '{}::{}'", qualified, rest[1..].join("::")))),
+        }
+    }
+
+    fn resolve_import(&self, path: &[String]) -> std::result::Result<PathBuf, Signal> {
+        resolve_module_file(self.current_file.as_deref().map(PathBuf::as_path), path).map_err(Signal::Error)
+    }
+
+}
+
+// ── Binary operations ─────────────────────────────────────────────────────────
+
+pub(crate) fn eval_binop(l: Value, op: &BinOp, r: Value) -> EvalResult {
+    match op {
+        BinOp::Add => match (&l, &r) {
+            (Value::Int(_) | Value::BigInt(_) | Value::Float(_), Value::Int(_) | Value::BigInt(_) | Value::Float(_)) => {
+                numeric_op(&l, &r, i64::checked_add, |a, b| a + b, |a, b| a + b)
+            }
+            (Value::Str(_), _) => Ok(concat(l, &r)),
+            (Value::List(a), Value::List(b)) => {
+                let mut combined = a.borrow().clone();
+                combined.extend(b.borrow().clone());
+                Ok(Value::list(combined))
+            }
+            (Value::DateTime(_) | Value::Duration(_), _) | (_, Value::DateTime(_) | Value::Duration(_)) => {
+                crate::datetime::arith(&l, op, &r).map_err(Signal::Error)
+            }
+            _ => Err(Signal::Error(format!("Cannot add {} and {}", l, r)))
+        },
+        BinOp::Sub => match (&l, &r) {
+            (Value::DateTime(_) | Value::Duration(_), _) => crate::datetime::arith(&l, op, &r).map_err(Signal::Error),
+            _ => numeric_op(&l, &r, i64::checked_sub, |a, b| a - b, |a, b| a - b),
+        },
+        BinOp::Mul => match (&l, &r) {
+            (Value::Str(s), Value::Int(n)) => Ok(Value::str(s.repeat(*n as usize))),
+            (Value::Duration(_), _) | (_, Value::Duration(_)) => crate::datetime::arith(&l, op, &r).map_err(Signal::Error),
+            _ => numeric_op(&l, &r, i64::checked_mul, |a, b| a * b, |a, b| a * b),
+        },
+        BinOp::Div => match (&l, &r) {
+            (_, Value::Int(0)) => Err(Signal::Error("Division by zero".into())),
+            (_, Value::Float(f)) if *f == 0.0 => Err(Signal::Error("Division by zero".into())),
+            _ => numeric_op(&l, &r, i64::checked_div, |a, b| a / b, |a, b| a / b),
+        },
+        BinOp::Mod => match (&l, &r) {
+            (_, Value::Int(0)) => Err(Signal::Error("Modulo by zero".into())),
+            _ => numeric_op(&l, &r, i64::checked_rem, |a, b| a % b, |a, b| a % b),
+        },
+        BinOp::Eq    => Ok(Value::Bool(l == r)),
+        BinOp::NotEq => Ok(Value::Bool(l != r)),
+        BinOp::Lt    => compare_op(&l, &r, |o| o == std::cmp::Ordering::Less),
+        BinOp::LtEq  => compare_op(&l, &r, |o| o != std::cmp::Ordering::Greater),
+        BinOp::Gt    => compare_op(&l, &r, |o| o == std::cmp::Ordering::Greater),
+        BinOp::GtEq  => compare_op(&l, &r, |o| o != std::cmp::Ordering::Less),
+        BinOp::And   => Ok(Value::Bool(is_truthy(&l) && is_truthy(&r))),
+        BinOp::Or    => {
+            if is_truthy(&l) { Ok(l) } else { Ok(r) }
+        }
+        BinOp::DotDot => Err(Signal::Error("Range not valid in this context".into())),
+    }
+}
+
+/// `l + r` for a string `l`. A string nothing else holds grows in place.
+fn concat(l: Value, r: &Value) -> Value {
+    use std::fmt::Write;
+    let Value::Str(mut s) = l else { unreachable!("concat of a non-string") };
+    match Rc::get_mut(&mut s) {
+        Some(text) => match r {
+            Value::Str(t) => text.push_str(t),
+            other => { let _ = write!(text, "{}", other); }
+        },
+        None => s = Rc::new(format!("{}{}", s, r)),
+    }
+    Value::Str(s)
+}
+
+/// Integer ops are checked; when the result overflows an Int it is redone
+/// with `big_op` and comes back as a BigInt.
+fn numeric_op(
+    l: &Value,
+    r: &Value,
+    int_op: impl Fn(i64, i64) -> Option<i64>,
+    big_op: impl Fn(&BigInt, &BigInt) -> BigInt,
+    float_op: impl Fn(f64, f64) -> f64,
+) -> EvalResult {
+    match (l, r) {
+        (Value::Int(a), Value::Int(b)) => Ok(match int_op(*a, *b) {
+            Some(n) => Value::Int(n),
+            None => int_value(big_op(&BigInt::from(*a), &BigInt::from(*b))),
+        }),
+        (Value::Float(a), Value::Float(b)) => Ok(Value::Float(float_op(*a, *b))),
+        (Value::Float(a), b) | (b, Value::Float(a)) if to_big(b).is_some() => {
+            let b = to_float(b);
+            Ok(Value::Float(if matches!(l, Value::Float(_)) { float_op(*a, b) } else { float_op(b, *a) }))
+        }
+        _ => match (to_big(l), to_big(r)) {
+            (Some(a), Some(b)) => Ok(int_value(big_op(&a, &b))),
+            _ => Err(Signal::Error(format!("Type error in numeric operation: {} and {}", l, r))),
+        },
+    }
+}
+
+/// An integer result: an Int when it fits in 64 bits, a BigInt otherwise.
+pub(crate) fn int_value(n: BigInt) -> Value {
+    match n.to_i64() {
+        Some(i) => Value::Int(i),
+        None => Value::BigInt(Rc::new(n)),
+    }
+}
+
+/// Int and BigInt as an arbitrary-precision integer; None for anything else.
+pub(crate) fn to_big(v: &Value) -> Option<BigInt> {
+    match v {
+        Value::Int(n) => Some(BigInt::from(*n)),
+        Value::BigInt(n) => Some((**n).clone()),
+        _ => None,
+    }
+}
+
+fn to_float(v: &Value) -> f64 {
+    match v {
+        Value::Int(n) => *n as f64,
+        Value::BigInt(n) => n.to_f64().unwrap_or(f64::NAN),
+        Value::Float(f) => *f,
+        _ => f64::NAN,
+    }
+}
+
+/// How two values order under `<`: numerically across Int, BigInt and Float,
+/// or within one comparable type. None when they cannot be compared.
+pub(crate) fn value_order(l: &Value, r: &Value) -> Option<std::cmp::Ordering> {
+    Some(match (l, r) {
+        (Value::Int(a), Value::Int(b))     => a.cmp(b),
+        (Value::Int(_) | Value::BigInt(_), Value::Int(_) | Value::BigInt(_)) => to_big(l).cmp(&to_big(r)),
+        (Value::BigInt(_), Value::Float(_)) | (Value::Float(_), Value::BigInt(_)) => {
+            to_float(l).partial_cmp(&to_float(r)).unwrap_or(std::cmp::Ordering::Equal)
+        }
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal),
+        (Value::Int(a), Value::Float(b))   => (*a as f64).partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal),
+        (Value::Float(a), Value::Int(b))   => a.partial_cmp(&(*b as f64)).unwrap_or(std::cmp::Ordering::Equal),
+        (Value::Str(a), Value::Str(b))     => a.cmp(b),
+        (Value::DateTime(a), Value::DateTime(b)) => a.cmp(b),
+        (Value::Duration(a), Value::Duration(b)) => a.cmp(b),
+        _ => return None,
+    })
+}
+
+fn compare_op(l: &Value, r: &Value, pred: impl Fn(std::cmp::Ordering) -> bool) -> EvalResult {
+    let ord = value_order(l, r).ok_or_else(|| Signal::Error(format!("Cannot compare {} and {}", l, r)))?;
+    Ok(Value::Bool(pred(ord)))
+}
+
+pub(crate) fn eval_unary(op: &UnaryOp, val: Value) -> EvalResult {
+    match op {
+        UnaryOp::Neg => match val {
+            Value::Int(n)   => Ok(n.checked_neg().map(Value::Int).unwrap_or_else(|| int_value(-BigInt::from(n)))),
+            Value::BigInt(n) => Ok(int_value(-&*n)),
+            Value::Float(f) => Ok(Value::Float(-f)),
+            Value::Duration(d) => Ok(Value::Duration(-d)),
+            other => Err(Signal::Error(format!("Cannot negate {:?}", other)))
+        }
+        UnaryOp::Not => Ok(Value::Bool(!is_truthy(&val)))
+    }
+}
+
+/// A Set of `items`, which must all be usable as map keys.
+pub(crate) fn set_of(items: impl IntoIterator<Item = Value>) -> std::result::Result<Value, String> {
+    let set = items.into_iter().map(|v| MapKey::from_value(&v)).collect::<std::result::Result<IndexSet<_>, _>>()?;
+    Ok(Value::Set(Rc::new(RefCell::new(set))))
+}
+
+// ── Indexing ──────────────────────────────────────────────────────────────────
+
+pub(crate) fn get_index(obj: &Value, idx: &Value) -> EvalResult {
+    match obj {
+        Value::List(v) => {
+            let i = require_int(idx)? as usize;
+            v.borrow().get(i).cloned()
+                .ok_or_else(|| Signal::Error(format!("Index {} out of bounds", i)))
+        }
+        Value::Str(s) => {
+            let i = require_int(idx)? as usize;
+            s.chars().nth(i)
+                .map(|c| Value::str(c.to_string()))
+                .ok_or_else(|| Signal::Error(format!("String index {} out of bounds", i)))
+        }
+        Value::Map(m) => {
+            let key = MapKey::from_value(idx).map_err(Signal::Error)?;
+            m.borrow().get(&key).cloned()
+                .ok_or_else(|| Signal::Error(format!("Key '{}' not found", key)))
+        }
+        Value::Range(start, end, step) => {
+            let i = require_int(idx)?;
+            if i < 0 || i >= crate::iter::range_len(*start, *end, *step) {
+                return Err(Signal::Error(format!("Index {} out of bounds", i)));
+            }
+            Ok(Value::Int(start + i * step))
+        }
+        _ => Err(Signal::Error("Cannot index this value".into()))
+    }
+}
+
+pub(crate) fn set_index(obj: &Value, idx: &Value, val: Value) -> std::result::Result<(), Signal> {
+    match obj {
+        Value::List(v) => {
+            let i = require_int(idx)? as usize;
+            let mut list = v.borrow_mut();
+            if i < list.len() {
+                list[i] = val;
+                Ok(())
+            } else {
+                Err(Signal::Error(format!("Index {} out of bounds", i)))
+            }
+        }
+        Value::Map(m) => {
+            m.borrow_mut().insert(MapKey::from_value(idx).map_err(Signal::Error)?, val);
+            Ok(())
+        }
+        _ => Err(Signal::Error("Cannot index-assign this value".into()))
+    }
+}
+
+pub(crate) fn set_field(obj: &Value, field: &str, val: Value) -> std::result::Result<(), Signal> {
+    if let Value::Struct(_, fields) = obj {
+        fields.borrow_mut().insert(field.to_string(), val);
+        Ok(())
+    } else {
+        Err(Signal::Error("Cannot assign field on non-struct".into()))
+    }
+}
+
+// ── Pattern matching ──────────────────────────────────────────────────────────
+
+/// Bind a pattern that has to match, such as the one in `for (k, v) in m`.
+pub(crate) fn bind_pattern(pat: &Pattern, val: Value, env: &Env) -> std::result::Result<(), Signal> {
+    match pat {
+        Pattern::Ident(name) => { env.define(name, val); Ok(()) }
+        _ if match_pattern(pat, &val, env)? => Ok(()),
+        _ => Err(Signal::Error(format!("Cannot destructure {} as {}", val, pat))),
+    }
+}
+
+pub(crate) fn match_pattern(pat: &Pattern, val: &Value, env: &Env) -> std::result::Result<bool, Signal> {
+    match pat {
+        Pattern::Wildcard  => Ok(true),
+        Pattern::Nil       => Ok(matches!(val, Value::Nil | Value::Option(None))),
+        Pattern::Bool(b)   => Ok(val == &Value::Bool(*b)),
+        Pattern::Int(n)    => Ok(val == &Value::Int(*n)),
+        Pattern::Float(f)  => Ok(val == &Value::Float(*f)),
+        Pattern::StringLit(s) => Ok(val == &Value::str(s.clone())),
+
+        Pattern::Ident(name) => {
+            env.define(name, val.clone());
+            Ok(true)
+        }
+
+        Pattern::Tuple(pats) => {
+            if let Value::Tuple(elems) = val {
+                if elems.len() != pats.len() { return Ok(false); }
+                for (p, e) in pats.iter().zip(elems.iter()) {
+                    if !match_pattern(p, e, env)? { return Ok(false); }
+                }
+                Ok(true)
+            } else { Ok(false) }
+        }
+
+        Pattern::List(pats) => {
+            if let Value::List(elems) = val {
+                let elems = elems.borrow();
+                let Some(at) = pats.iter().position(|p| matches!(p, Pattern::Rest(_))) else {
+                    if elems.len() != pats.len() { return Ok(false); }
+                    for (p, e) in pats.iter().zip(elems.iter()) {
+                        if !match_pattern(p, e, env)? { return Ok(false); }
+                    }
+                    return Ok(true);
+                };
+                // `[a, ..rest, z]`: fixed elements at both ends, the rest in between
+                let tail = pats.len() - at - 1;
+                if elems.len() < at + tail { return Ok(false); }
+                let split = elems.len() - tail;
+                for (p, e) in pats[..at].iter().zip(&elems[..at]) {
+                    if !match_pattern(p, e, env)? { return Ok(false); }
+                }
+                for (p, e) in pats[at + 1..].iter().zip(&elems[split..]) {
+                    if !match_pattern(p, e, env)? { return Ok(false); }
+                }
+                if let Pattern::Rest(Some(name)) = &pats[at] {
+                    env.define(name, Value::list(elems[at..split].to_vec()));
+                }
+                Ok(true)
+            } else { Ok(false) }
+        }
+
+        Pattern::Rest(name) => {
+            if let Some(name) = name { env.define(name, val.clone()); }
+            Ok(true)
+        }
+
+        Pattern::Bind(name, inner) => {
+            if !match_pattern(inner, val, env)? { return Ok(false); }
+            env.define(name, val.clone());
+            Ok(true)
+        }
+
+        Pattern::EnumVariant(enum_name, variant, field_pats) => {
+            if let Value::Enum(en, vn, fields) = val {
+                if vn != variant || *en != resolve_type(enum_name, env) { return Ok(false); }
+                if fields.len() != field_pats.len() { return Ok(false); }
+                for (p, f) in field_pats.iter().zip(fields.iter()) {
+                    if !match_pattern(p, f, env)? { return Ok(false); }
+                }
+                Ok(true)
+            } else { Ok(false) }
+        }
+
+        Pattern::Some(inner) => match val {
+            Value::Option(Some(v)) => match_pattern(inner, v, env),
+            _ => Ok(false)
+        }
+
+        Pattern::Ok(inner) => match val {
+            Value::Result(std::result::Result::Ok(v)) => match_pattern(inner, v, env),
+            _ => Ok(false)
+        }
+
+        Pattern::Err(inner) => match val {
+            Value::Result(std::result::Result::Err(e)) => match_pattern(inner, e, env),
+            _ => Ok(false)
+        }
+
+        Pattern::Or(a, b) => {
+            if match_pattern(a, val, env)? { Ok(true) }
+            else { match_pattern(b, val, env) }
+        }
+
+        Pattern::Range(lo, hi) => {
+            if let (Pattern::Int(lo), Pattern::Int(hi)) = (lo.as_ref(), hi.as_ref()) {
+                if let Value::Int(n) = val {
+                    return Ok(n >= lo && n < hi);
+                }
+            }
+            Ok(false)
+        }
+
+        Pattern::Struct(name, field_pats, rest) => {
+            let Value::Struct(type_name, fields) = val else { return Ok(false) };
+            if *type_name != resolve_type(name, env) { return Ok(false); }
+            let fields = fields.borrow();
+            if !rest {
+                let mut missing: Vec<&str> = fields.keys()
+                    .filter(|f| !field_pats.iter().any(|(n, _)| n == *f))
+                    .map(String::as_str)
+                    .collect();
+                if !missing.is_empty() {
+                    missing.sort();
+                    return Err(Signal::Error(format!(
+                        "Pattern {} does not mention field(s) {}; add '..' to ignore them", pat, missing.join(", ")
+                    )));
+                }
+            }
+            for (f, p) in field_pats {
+                let Some(v) = fields.get(f) else {
+                    return Err(Signal::Error(format!("Struct '{}' has no field '{}'", name, f)));
+                };
+                if !match_pattern(p, v, env)? { return Ok(false); }
+            }
+            Ok(true)
+        }
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+// Prefix of the env entries that map a type's bare name to its qualified
+// one. The space keeps them apart from anything a program can bind.
+const TYPE_KEY: &str = "type ";
+
+fn type_key(name: &str) -> String {
+    format!("{}{}", TYPE_KEY, name)
+}
+
+/// What the struct or enum `name` refers to in `env`: `Point` declared in
+/// `mod geometry` is `geometry::Point`. Names not declared in scope, like
+/// `Int` in `impl Show for Int`, stay as they are.
+pub(crate) fn resolve_type(name: &str, env: &Env) -> String {
+    match env.get(&type_key(name)) {
+        Some(Value::Str(q)) => q.as_ref().clone(),
+        _ => name.to_string(),
+    }
+}
+
+/// Map `a.b.c` to `a/b/c.zph`, looking next to the importing file first
+/// and then in each directory listed in ZEPHYR_PATH.
+pub fn resolve_module_file(importer: Option<&Path>, path: &[String]) -> std::result::Result<PathBuf, String> {
+    let mut rel = PathBuf::new();
+    for seg in path { rel.push(seg); }
+    rel.set_extension("zph");
+
+    let base = importer
+        .and_then(|f| f.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let mut dirs = vec![base];
+    if let Some(search) = std::env::var_os("ZEPHYR_PATH") {
+        dirs.extend(std::env::split_paths(&search));
+    }
+
+    let mut tried = Vec::new();
+    for dir in dirs {
+        let candidate = dir.join(&rel);
+        if candidate.is_file() {
+            return Ok(candidate.canonicalize().unwrap_or(candidate));
+        }
+        tried.push(display_path(&candidate));
+    }
+    Err(format!("Module '{}' not found (searched: {})", path.join("."), tried.join(", ")))
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+pub fn is_truthy(val: &Value) -> bool {
+    match val {
+        Value::Bool(b)        => *b,
+        Value::Nil            => false,
+        Value::Option(None)   => false,
+        Value::Int(0)         => false,
+        Value::Str(s)         => !s.is_empty(),
+        _                     => true,
+    }
+}
+
+pub fn require_int(val: &Value) -> std::result::Result<i64, Signal> {
+    match val {
+        Value::Int(n)   => Ok(*n),
+        Value::Float(f) => Ok(*f as i64),
+        Value::BigInt(n) => Err(Signal::Error(format!("Integer {} is too large here", n))),
+        other => Err(Signal::Error(format!("Expected integer, got {}", other)))
+    }
+}
+
+pub fn value_type_name(val: &Value) -> String {
+    match val {
+        Value::Int(_) | Value::BigInt(_) => "Int".into(),
+        Value::Float(_)  => "Float".into(),
+        Value::Bool(_)   => "Bool".into(),
+        Value::Str(_)    => "String".into(),
+        Value::Nil       => "Nil".into(),
+        Value::List(_)   => "List".into(),
+        Value::Map(_)    => "Map".into(),
+        Value::Set(_)    => "Set".into(),
+        Value::Tuple(_)  => "Tuple".into(),
+        Value::Struct(n, _) => n.clone(),
+        Value::Enum(n, _, _) => n.clone(),
+        Value::Option(_) => "Option".into(),
+        Value::Result(_) => "Result".into(),
+        Value::Function(_) => "Function".into(),
+        Value::Ref(_)    => "Ref".into(),
+        Value::Module(_) => "Module".into(),
+        Value::Range(..) => "Range".into(),
+        Value::Iter(_)   => "Iter".into(),
+        Value::Regex(_)  => "Regex".into(),
+        Value::DateTime(_) => "DateTime".into(),
+        Value::Duration(_) => "Duration".into(),
+    }
+}
+
+/// Run `src` on the tree-walker: the value of its last statement, or the
+/// error message. For the unit tests of the native modules.
+#[cfg(test)]
+pub(crate) fn eval(src: &str) -> std::result::Result<Value, String> {
+    let tokens = crate::lexer::Lexer::new(src).tokenize()?;
+    let stmts = crate::parser::Parser::new(tokens).parse_program()?;
+    match Interpreter::new().run(stmts) {
+        Ok(v) => Ok(v),
+        Err(Signal::Error(e)) => Err(e),
+        Err(Signal::Located(e)) => Err(e.message),
+        Err(other) => Err(format!("{:?}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(src: &str) -> Interpreter {
+        let tokens = crate::lexer::Lexer::new(src).tokenize().unwrap();
+        let stmts = crate::parser::Parser::new(tokens).parse_program().unwrap();
+        let mut interp = Interpreter::new();
+        interp.run(stmts).unwrap();
+        interp
+    }
+
+    /// Write `files` under a fresh directory and return it.
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zephyr-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (path, src) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, src).unwrap();
+        }
+        dir
+    }
+
+    /// Run `main.zph` in `dir`; the error message if it fails.
+    fn run_main(dir: &Path) -> std::result::Result<Interpreter, String> {
+        let path = dir.join("main.zph");
+        let src = std::fs::read_to_string(&path).unwrap();
+        let tokens = crate::lexer::Lexer::new(&src).tokenize().unwrap();
+        let stmts = crate::parser::Parser::new(tokens).parse_program().unwrap();
+        let mut interp = Interpreter::new();
+        interp.set_source_path(path.to_str().unwrap());
+        match interp.run(stmts) {
+            Ok(_) => Ok(interp),
+            Err(Signal::Error(e)) => Err(e),
+            Err(Signal::Located(e)) => Err(e.message),
+            Err(other) => Err(format!("{:?}", other)),
+        }
+    }
+
+    fn global(interp: &Interpreter, name: &str) -> String {
+        format!("{}", interp.global.get(name).unwrap())
+    }
+
+    #[test]
+    fn appending_in_place_leaves_other_holders_alone() {
+        let src = "var a = \"x\"\nvar b = a\nfor i in 0..3 { a = a + i }\n\
+                   var s = \"ab\"\ns = s + s + \"|\" + s\n\
+                   fun grow() {\n var t = \"\"\n var seen = []\n let look = || t\n\
+                   for i in 0..3 { t = t + i + look().len() \n seen.push(t) }\n [t, seen] }\n\
+                   var n = 1\nn = n + 2 + 3\n\
+                   let got = [a, b, s, grow(), n]\n";
+        let interp = run(src);
+        assert_eq!(format!("{}", interp.global.get("got").unwrap()), "[x012, x, abab|ab, [001224, [00, 0012, 001224]], 6]");
+    }
+
+    #[test]
+    fn imports_resolve_next_to_the_importing_file() {
+        let dir = project("import", &[
+            ("main.zph", "import util.strings\nlet got = [shout(\"hi\"), twice(2)]\n"),
+            ("util/strings.zph", "import helpers\npub fun shout(s) { s + bang() }\npub fun twice(n) { double(n) }\nfun bang() { \"!\" }\n"),
+            ("util/helpers.zph", "pub fun double(n) { n * 2 }\n"),
+        ]);
+        let interp = run_main(&dir).unwrap();
+        assert_eq!(global(&interp, "got"), "[hi!, 4]");
+        assert_eq!(interp.modules.len(), 2);
+
+        std::fs::write(dir.join("main.zph"), "import util.strings\nbang()\n").unwrap();
+        assert_eq!(run_main(&dir).err().unwrap(), "Undefined variable 'bang'");
+    }
+
+    #[test]
+    fn imports_fall_back_to_zephyr_path() {
+        let lib = project("zpath-lib", &[("shared.zph", "pub fun answer() { 42 }\n")]);
+        let dir = project("zpath", &[("main.zph", "import shared\nlet got = answer()\n")]);
+        let missing = run_main(&dir).err().unwrap();
+        assert!(missing.starts_with("Module 'shared' not found"), "{}", missing);
+
+        std::env::set_var("ZEPHYR_PATH", &lib);
+        let result = run_main(&dir);
+        std::env::remove_var("ZEPHYR_PATH");
+        assert_eq!(global(&result.unwrap(), "got"), "42");
+    }
+
+    #[test]
+    fn imported_modules_do_not_see_the_importers_globals() {
+        let dir = project("isolated", &[
+            ("main.zph", "let secret = 42\nimport lib.util\nlet got = [peek(), count([1, 2])]\n"),
+            ("lib/util.zph", "pub fun peek() { secret }\npub fun count(xs) { len(xs) }\n"),
+        ]);
+        assert_eq!(run_main(&dir).err().unwrap(), "Undefined variable 'secret'");
+
+        std::fs::write(dir.join("main.zph"), "import lib.util\nlet got = count([1, 2])\n").unwrap();
+        assert_eq!(global(&run_main(&dir).unwrap(), "got"), "2");
+    }
+
+    #[test]
+    fn circular_imports_are_reported() {
+        let dir = project("cycle", &[
+            ("main.zph", "import a\n"),
+            ("a.zph", "import b\npub fun f() { 1 }\n"),
+            ("b.zph", "import a\n"),
+        ]);
+        let err = run_main(&dir).err().unwrap();
+        assert!(err.contains("Circular import of 'a'"), "{}", err);
+        assert!(err.contains("a.zph -> ") && err.ends_with("a.zph"), "{}", err);
+    }
+
+    #[test]
+    fn module_paths_reach_only_pub_items() {
+        let dir = project("paths", &[
+            ("main.zph", "import util.strings\nmod outer {\n pub mod inner {\n pub fun f() { 7 }\n }\n}\n\
+                          let got = [strings::shout(\"yo\"), strings.shout(\"!\"), outer::inner::f()]\n"),
+            ("util/strings.zph", "pub fun shout(s) { s + bang() }\nfun bang() { \"!\" }\n"),
+        ]);
+        assert_eq!(global(&run_main(&dir).unwrap(), "got"), "[yo!, !!, 7]");
+
+        let private = [
+            ("import util.strings\nstrings::bang()\n", "'bang' is private to module 'util.strings'"),
+            ("mod m {\n fun hidden() { 1 }\n}\nm::hidden()\n", "'hidden' is private to module 'm'"),
+            ("mod m {\n mod inner {\n pub fun f() { 1 }\n }\n}\nm::inner::f()\n", "'inner' is private to module 'm'"),
+            ("mod m {\n enum E { A }\n}\nm::E::A\n", "'E' is private to module 'm'"),
+        ];
+        for (src, err) in private {
+            std::fs::write(dir.join("main.zph"), src).unwrap();
+            assert_eq!(run_main(&dir).err().unwrap(), err, "{}", src);
+        }
+    }
+
+    #[test]
+    fn same_named_types_in_different_modules_stay_apart() {
+        let interp = run("mod a {\n pub struct P { x: Int }\n impl P { pub fun who(self) { \"a\" } }\n\
+                          pub enum E { One, Two(Int) }\n\
+                          pub fun show(e) { match e { E::Two(n) => n, E::One => 1, _ => 0 } }\n}\n\
+                          mod b {\n pub struct P { y: Int }\n impl P { pub fun who(self) { \"b\" } }\n pub enum E { One }\n}\n\
+                          struct P { z: Int }\n\
+                          let got = [a::P { x: 1 }.who(), b::P { y: 1 }.who(), a::show(a::E::Two(5)), a::show(b::E::One)]\n\
+                          let top = match P { z: 3 } { P { z } => z }\n");
+        assert_eq!(global(&interp, "got"), "[a, b, 5, 0]");
+        assert_eq!(global(&interp, "top"), "3");
+        assert!(interp.struct_defs.contains_key("a::P") && interp.struct_defs.contains_key("b::P"));
+        assert!(interp.enum_defs.contains_key("a::E") && interp.enum_defs.contains_key("b::E"));
+        assert!(interp.impl_methods.contains_key("a::P") && !interp.impl_methods.contains_key("P"));
+    }
+
+    #[test]
+    fn private_structs_stay_inside_their_module() {
+        let interp = run("mod m {\n struct Secret { z: Int }\n pub fun make() { Secret { z: 1 } }\n}\nlet s = m::make()\n");
+        assert_eq!(global(&interp, "s"), "m::Secret {z: 1}");
+
+        let dir = project("private", &[
+            ("main.zph", "mod m {\n struct Secret { z: Int }\n}\nSecret { z: 1 }\n"),
+            ("shapes.zph", "pub struct Shown { a: Int }\nstruct Hidden { b: Int }\n"),
+        ]);
+        assert_eq!(run_main(&dir).err().unwrap(), "Unknown struct 'Secret'");
+        std::fs::write(dir.join("main.zph"), "mod m {\n struct Secret { z: Int }\n}\nm::Secret { z: 1 }\n").unwrap();
+        assert_eq!(run_main(&dir).err().unwrap(), "'Secret' is private to module 'm'");
+
+        std::fs::write(dir.join("main.zph"), "import shapes\nlet s = [Shown { a: 1 }, shapes::Shown { a: 2 }]\n").unwrap();
+        assert_eq!(global(&run_main(&dir).unwrap(), "s"), "[shapes::Shown {a: 1}, shapes::Shown {a: 2}]");
+        std::fs::write(dir.join("main.zph"), "import shapes\nHidden { b: 1 }\n").unwrap();
+        assert_eq!(run_main(&dir).err().unwrap(), "Unknown struct 'Hidden'");
+        std::fs::write(dir.join("main.zph"), "import shapes\nshapes::Hidden { b: 1 }\n").unwrap();
+        assert_eq!(run_main(&dir).err().unwrap(), "'Hidden' is private to module 'shapes'");
+    }
+
+    #[test]
+    fn recursion_stops_at_the_stack_limit_whatever_the_depth_limit() {
+        let src = "fun d(n) {\n if n == 0 { return 0 }\n 1 + d(n - 1)\n}\nset_recursion_limit(1000000)\nd(1000000)\n";
+        let tokens = crate::lexer::Lexer::new(src).tokenize().unwrap();
+        let stmts = crate::parser::Parser::new(tokens).parse_program().unwrap();
+        let mut interp = Interpreter::new();
+        // start one segment short of the limit, so the test stays small
+        interp.stack_segments = STACK_LIMIT / STACK_SEGMENT - 1;
+        let Err(Signal::Located(e)) = interp.run(stmts) else { panic!("expected the stack limit error") };
+        assert!(e.message.starts_with("Recursion limit exceeded: call depth"), "{}", e.message);
+        assert!(e.message.ends_with("has used up the 512 MB of stack a program may take"), "{}", e.message);
+        assert_eq!((interp.depth, interp.stack_segments), (0, STACK_LIMIT / STACK_SEGMENT - 1));
+    }
+}
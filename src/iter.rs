@@ -0,0 +1,321 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr Iterators — the protocol behind `for` and lazy adapters
+// ═══════════════════════════════════════════════════════════
+//
+// Everything `for` can loop over is turned into an `Iter`:
+//
+//   List, Tuple, Set the items as they were when the loop started
+//   String           its characters
+//   Map              (key, value) tuples
+//   Range            a..b or range(a, b, step), counted without allocating
+//   Iter             the iterator itself, which the loop consumes
+//   struct / enum    stepped by calling its `next()` method, which returns
+//                    Some(item) or nil; a type with an `iter()` method
+//                    instead is iterated through whatever that returns
+//
+// Adapters wrap an iterator and do no work until they are stepped:
+//
+//   it.take(n)   it.skip(n)   it.step_by(n)   it.enumerate()
+//   it.zip(other)   it.chain(other)   it.map(f)   it.filter(f)
+//
+// `iter`, `take`, `skip`, `step_by`, `zip` and `chain` also work on
+// lists, tuples, strings, maps, sets and ranges, and give an iterator
+// back; a struct or enum with `next()` or `iter()` gets every iterator
+// method.
+// `next()` steps an iterator by hand and returns an Option; `collect()`
+// runs it into a List. `count`, `find`, `any`, `all` and `reduce` consume
+// it, stopping early where they can. On a range, `map` and `filter` are
+// adapters too, and `find`, `any`, `all` and `reduce` step it the same way.
+//
+// ═══════════════════════════════════════════════════════════
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::interpreter::{is_truthy, require_int, value_type_name, Env, EvalResult, Interpreter, MapKey, Signal, Value};
+
+#[derive(Debug)]
+pub enum Iter {
+    Items(std::vec::IntoIter<Value>),
+    Chars(Rc<String>, usize),           // text, byte position
+    Range(i64, i64, i64),           // next, end, step
+    User(Value),
+    Map(IterRef, Value),
+    Filter(IterRef, Value),
+    Take(IterRef, usize),
+    Skip(IterRef, usize),
+    StepBy(IterRef, usize, bool),   // step, whether the first item is out
+    Enumerate(IterRef, i64),
+    Zip(IterRef, IterRef),
+    Chain(IterRef, IterRef),
+    Done,
+}
+
+pub type IterRef = Rc<RefCell<Iter>>;
+
+/// Methods that turn any iterable into an iterator.
+pub const ADAPTERS: &[&str] = &["iter", "take", "skip", "step_by", "zip", "chain"];
+
+/// Everything `iter_method` answers; user types that can be iterated get all of it.
+pub const METHODS: &[&str] = &[
+    "iter", "next", "take", "skip", "step_by", "enumerate", "zip", "chain", "map", "filter",
+    "collect", "to_list", "count", "find", "any", "all", "reduce",
+];
+
+fn wrap(it: Iter) -> Value {
+    Value::Iter(Rc::new(RefCell::new(it)))
+}
+
+// ── Ranges ────────────────────────────────────────────────────────────────────
+
+/// Does a range at `n` still have items before `end`?
+pub fn range_has(n: i64, end: i64, step: i64) -> bool {
+    if step > 0 { n < end } else { n > end }
+}
+
+pub fn range_len(start: i64, end: i64, step: i64) -> i64 {
+    let (span, step) = if step > 0 {
+        (end as i128 - start as i128, step as i128)
+    } else {
+        (start as i128 - end as i128, -(step as i128))
+    };
+    if span <= 0 { 0 } else { ((span + step - 1) / step).min(i64::MAX as i128) as i64 }
+}
+
+/// Every item of a range, or an error when there are too many to hold.
+pub fn range_items(start: i64, end: i64, step: i64) -> Result<Vec<Value>, String> {
+    let len = range_len(start, end, step);
+    let mut items = Vec::new();
+    usize::try_from(len).ok()
+        .and_then(|n| items.try_reserve_exact(n).ok())
+        .ok_or_else(|| format!("range of {} items is too large to collect", len))?;
+    items.extend((0..len).map(|i| Value::Int(start + i * step)));
+    Ok(items)
+}
+
+/// The item after `n`, or `end` once the step would overflow.
+pub fn range_step(n: i64, end: i64, step: i64) -> i64 {
+    n.checked_add(step).unwrap_or(end)
+}
+
+// ── Starting and stepping ─────────────────────────────────────────────────────
+
+/// Start iterating over `v`.
+pub fn iter_of(interp: &mut Interpreter, v: Value, env: &Env) -> Result<IterRef, Signal> {
+    let it = match v {
+        Value::Iter(it) => return Ok(it),
+        Value::List(items) => Iter::Items(items.borrow().clone().into_iter()),
+        Value::Tuple(items) => Iter::Items(items.into_iter()),
+        Value::Str(s) => Iter::Chars(s, 0),
+        Value::Map(m) => {
+            let entries: Vec<Value> = m.borrow().iter()
+                .map(|(k, v)| Value::Tuple(vec![k.to_value(), v.clone()]))
+                .collect();
+            Iter::Items(entries.into_iter())
+        }
+        Value::Set(s) => Iter::Items(s.borrow().iter().map(MapKey::to_value).collect::<Vec<_>>().into_iter()),
+        Value::Range(start, end, step) => Iter::Range(start, end, step),
+        Value::Struct(..) | Value::Enum(..) if has_method(interp, &v, "next") => Iter::User(v),
+        Value::Struct(..) | Value::Enum(..) if has_method(interp, &v, "iter") => {
+            let type_name = value_type_name(&v);
+            let inner = interp.call_method(v, "iter", vec![], env)?;
+            if matches!(inner, Value::Struct(..) | Value::Enum(..)) && !has_method(interp, &inner, "next") {
+                return Err(Signal::Error(format!("{}.iter() returned {}, which has no next() method", type_name, inner)));
+            }
+            return iter_of(interp, inner, env);
+        }
+        Value::Struct(..) | Value::Enum(..) => {
+            return Err(Signal::Error(format!(
+                "'{}' is not iterable: give {} a next() method returning an Option",
+                v, value_type_name(&v)
+            )));
+        }
+        other => return Err(Signal::Error(format!("'{}' is not iterable", other))),
+    };
+    Ok(Rc::new(RefCell::new(it)))
+}
+
+fn has_method(interp: &Interpreter, v: &Value, name: &str) -> bool {
+    interp.impl_methods.get(&value_type_name(v)).is_some_and(|m| m.contains_key(name))
+}
+
+/// Step an iterator. Its state is taken out while it runs, so a callback
+/// that reaches the same iterator finds it exhausted rather than borrowed.
+pub fn next(it: &IterRef, interp: &mut Interpreter, env: &Env) -> Result<Option<Value>, Signal> {
+    let mut state = std::mem::replace(&mut *it.borrow_mut(), Iter::Done);
+    let result = step(&mut state, interp, env);
+    *it.borrow_mut() = state;
+    result
+}
+
+fn step(state: &mut Iter, interp: &mut Interpreter, env: &Env) -> Result<Option<Value>, Signal> {
+    Ok(match state {
+        Iter::Items(items) => items.next(),
+        Iter::Chars(s, pos) => match s[*pos..].chars().next() {
+            Some(c) => {
+                *pos += c.len_utf8();
+                Some(Value::str(c.to_string()))
+            }
+            None => None,
+        },
+        Iter::Range(n, end, step) => {
+            if !range_has(*n, *end, *step) { return Ok(None); }
+            let v = *n;
+            *n = range_step(v, *end, *step);
+            Some(Value::Int(v))
+        }
+        Iter::User(obj) => match interp.call_method(obj.clone(), "next", vec![], env)? {
+            Value::Option(Some(v)) => Some(*v),
+            Value::Option(None) | Value::Nil => None,
+            other => {
+                return Err(Signal::Error(format!(
+                    "{}.next() must return an Option, got {}", value_type_name(obj), other
+                )));
+            }
+        },
+        Iter::Map(inner, f) => match next(inner, interp, env)? {
+            Some(v) => Some(interp.call_value(f.clone(), vec![v], env)?),
+            None => None,
+        },
+        Iter::Filter(inner, f) => loop {
+            match next(inner, interp, env)? {
+                Some(v) => if is_truthy(&interp.call_value(f.clone(), vec![v.clone()], env)?) { break Some(v) },
+                None => break None,
+            }
+        },
+        Iter::Take(inner, n) => {
+            if *n == 0 { return Ok(None); }
+            *n -= 1;
+            next(inner, interp, env)?
+        }
+        Iter::Skip(inner, n) => {
+            while *n > 0 {
+                *n -= 1;
+                if next(inner, interp, env)?.is_none() { return Ok(None); }
+            }
+            next(inner, interp, env)?
+        }
+        Iter::StepBy(inner, by, started) => {
+            if *started {
+                for _ in 1..*by {
+                    if next(inner, interp, env)?.is_none() { return Ok(None); }
+                }
+            }
+            *started = true;
+            next(inner, interp, env)?
+        }
+        Iter::Enumerate(inner, i) => match next(inner, interp, env)? {
+            Some(v) => {
+                *i += 1;
+                Some(Value::Tuple(vec![Value::Int(*i - 1), v]))
+            }
+            None => None,
+        },
+        Iter::Zip(a, b) => match next(a, interp, env)? {
+            Some(x) => next(b, interp, env)?.map(|y| Value::Tuple(vec![x, y])),
+            None => None,
+        },
+        Iter::Chain(a, b) => match next(a, interp, env)? {
+            Some(x) => Some(x),
+            None => next(b, interp, env)?,
+        },
+        Iter::Done => None,
+    })
+}
+
+/// Run an iterator to the end.
+pub fn collect(it: &IterRef, interp: &mut Interpreter, env: &Env) -> Result<Vec<Value>, Signal> {
+    let mut out = Vec::new();
+    while let Some(v) = next(it, interp, env)? {
+        out.push(v);
+    }
+    Ok(out)
+}
+
+// ── Methods ───────────────────────────────────────────────────────────────────
+
+/// Iterator methods, and the ADAPTERS on other iterables.
+pub fn iter_method(interp: &mut Interpreter, obj: Value, method: &str, args: Vec<Value>, env: &Env) -> EvalResult {
+    let type_name = value_type_name(&obj);
+    let it = iter_of(interp, obj, env)?;
+    let mut args = args.into_iter();
+    let mut arg = |what: &str| args.next().ok_or_else(|| Signal::Error(format!("{}() requires {}", method, what)));
+    let count = |v: Value| -> Result<usize, Signal> {
+        let n = require_int(&v)?;
+        usize::try_from(n).map_err(|_| Signal::Error(format!("{}() requires a non-negative count, got {}", method, n)))
+    };
+
+    match method {
+        "iter" => Ok(Value::Iter(it)),
+        "next" => Ok(Value::Option(next(&it, interp, env)?.map(Box::new))),
+        "take" => Ok(wrap(Iter::Take(it, count(arg("a count")?)?))),
+        "skip" => Ok(wrap(Iter::Skip(it, count(arg("a count")?)?))),
+        "step_by" => match count(arg("a step")?)? {
+            0 => Err(Signal::Error("step_by() step cannot be 0".into())),
+            by => Ok(wrap(Iter::StepBy(it, by, false))),
+        },
+        "enumerate" => Ok(wrap(Iter::Enumerate(it, 0))),
+        "zip" | "chain" => {
+            let other = arg("another iterable")?;
+            let other = iter_of(interp, other, env)?;
+            Ok(wrap(if method == "zip" { Iter::Zip(it, other) } else { Iter::Chain(it, other) }))
+        }
+        "map" => Ok(wrap(Iter::Map(it, arg("a function")?))),
+        "filter" => Ok(wrap(Iter::Filter(it, arg("a function")?))),
+        "collect" | "to_list" => Ok(Value::list(collect(&it, interp, env)?)),
+        "count" => {
+            let mut n = 0;
+            while next(&it, interp, env)?.is_some() { n += 1; }
+            Ok(Value::Int(n))
+        }
+        "find" | "any" | "all" => {
+            let f = arg("a function")?;
+            let want = method != "all";
+            while let Some(v) = next(&it, interp, env)? {
+                if is_truthy(&interp.call_value(f.clone(), vec![v.clone()], env)?) == want {
+                    return Ok(if method == "find" { Value::Option(Some(Box::new(v))) } else { Value::Bool(want) });
+                }
+            }
+            Ok(if method == "find" { Value::Option(None) } else { Value::Bool(!want) })
+        }
+        "reduce" => {
+            let f = arg("a function")?;
+            let mut acc = match arg("") {
+                Ok(init) => init,
+                Err(_) => next(&it, interp, env)?
+                    .ok_or_else(|| Signal::Error("reduce() of an empty iterator with no initial value".into()))?,
+            };
+            while let Some(v) = next(&it, interp, env)? {
+                acc = interp.call_value(f.clone(), vec![acc, v], env)?;
+            }
+            Ok(acc)
+        }
+        _ => Err(Signal::Error(format!("No method '{}' on type {}", method, type_name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::eval;
+
+    fn show(src: &str) -> String {
+        format!("{}", eval(src).unwrap())
+    }
+
+    #[test]
+    fn callbacks_on_huge_ranges_stop_early() {
+        let huge = "(0..9223372036854775807)";
+        assert_eq!(show(&format!("{}.filter(|x| x % 3 == 0).take(3).collect()", huge)), "[0, 3, 6]");
+        assert_eq!(show(&format!("{}.map(|x| x * x).skip(2).next()", huge)), "Some(4)");
+        assert_eq!(show(&format!("{}.find(|x| x > 10)", huge)), "Some(11)");
+        assert_eq!(show(&format!("[{0}.any(|x| x == 5), {0}.all(|x| x < 5)]", huge)), "[true, false]");
+        assert_eq!(show("(1..5).reduce(|a, b| a + b)"), "10");
+    }
+
+    #[test]
+    fn collecting_a_huge_range_is_an_error() {
+        let err = "range of 9223372036854775807 items is too large to collect";
+        assert_eq!(eval("(0..9223372036854775807).collect()").unwrap_err(), err);
+        assert_eq!(eval("(0..9223372036854775807).sort_by(|x| -x)").unwrap_err(), err);
+        assert_eq!(show("(0..10).step_by(4).collect()"), "[0, 4, 8]");
+    }
+}
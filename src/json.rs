@@ -30,10 +30,9 @@
 //
 // ═══════════════════════════════════════════════════════════
 
-use std::collections::HashMap;
 use std::rc::Rc;
-use std::cell::RefCell;
-use crate::interpreter::Value;
+use indexmap::IndexMap;
+use crate::interpreter::{MapKey, Value};
 
 // ── Registration ──────────────────────────────────────────────────────────────
 
@@ -123,7 +122,7 @@ fn json_stringify(args: Vec<Value>) -> Result<Value, String> {
     let val = args.into_iter().next()
         .ok_or_else(|| "json_stringify(v) requires 1 argument".to_string())?;
     match serialize(&val, 0, false) {
-        Ok(s)  => Ok(ok_val(Value::Str(s))),
+        Ok(s)  => Ok(ok_val(Value::str(s))),
         Err(e) => Ok(err_val(e)),
     }
 }
@@ -148,7 +147,7 @@ fn json_pretty(args: Vec<Value>) -> Result<Value, String> {
     let val = args.into_iter().next()
         .ok_or_else(|| "json_pretty(v) requires 1 argument".to_string())?;
     match serialize(&val, 0, true) {
-        Ok(s)  => Ok(ok_val(Value::Str(s))),
+        Ok(s)  => Ok(ok_val(Value::str(s))),
         Err(e) => Ok(err_val(e)),
     }
 }
@@ -164,7 +163,7 @@ fn json_pretty(args: Vec<Value>) -> Result<Value, String> {
 ///   let name = json_get(data, "name")   // Value::Str or Value::Nil
 ///   let age  = json_get(data, "age")    // Value::Int or Value::Nil
 fn json_get(args: Vec<Value>) -> Result<Value, String> {
-    let val = args.get(0).cloned().unwrap_or(Value::Nil);
+    let val = args.first().cloned().unwrap_or(Value::Nil);
     let key = require_str(&args, 1, "json_get(v, key)")?;
     Ok(get_key(&val, &key))
 }
@@ -182,7 +181,7 @@ fn json_get(args: Vec<Value>) -> Result<Value, String> {
 ///   // {"users": [{"name": "Alice"}, {"name": "Bob"}]}
 ///   let first_name = json_get_path(data, "users.0.name")
 fn json_get_path(args: Vec<Value>) -> Result<Value, String> {
-    let val  = args.get(0).cloned().unwrap_or(Value::Nil);
+    let val  = args.first().cloned().unwrap_or(Value::Nil);
     let path = require_str(&args, 1, "json_get_path(v, path)")?;
     let mut current = val;
     for segment in path.split('.') {
@@ -219,13 +218,13 @@ fn json_set(args: Vec<Value>) -> Result<Value, String> {
     match &args[0] {
         Value::Map(m) => {
             let mut cloned = m.borrow().clone();
-            cloned.insert(key, new_val);
-            Ok(Value::Map(Rc::new(RefCell::new(cloned))))
+            cloned.insert(key.into(), new_val);
+            Ok(Value::map(cloned))
         }
         _ => {
-            let mut map = HashMap::new();
-            map.insert(key, new_val);
-            Ok(Value::Map(Rc::new(RefCell::new(map))))
+            let mut map = IndexMap::new();
+            map.insert(key.into(), new_val);
+            Ok(Value::map(map))
         }
     }
 }
@@ -240,8 +239,8 @@ fn json_set(args: Vec<Value>) -> Result<Value, String> {
 ///   }
 fn json_has(args: Vec<Value>) -> Result<Value, String> {
     let key = require_str(&args, 1, "json_has(v, key)")?;
-    match args.get(0) {
-        Some(Value::Map(m)) => Ok(Value::Bool(m.borrow().contains_key(&key))),
+    match args.first() {
+        Some(Value::Map(m)) => Ok(Value::Bool(m.borrow().contains_key(&MapKey::from(key)))),
         _ => Ok(Value::Bool(false)),
     }
 }
@@ -249,34 +248,34 @@ fn json_has(args: Vec<Value>) -> Result<Value, String> {
 // ── Type checks ───────────────────────────────────────────────────────────────
 
 fn json_is_object(args: Vec<Value>) -> Result<Value, String> {
-    Ok(Value::Bool(matches!(args.get(0), Some(Value::Map(_)))))
+    Ok(Value::Bool(matches!(args.first(), Some(Value::Map(_)))))
 }
 
 fn json_is_array(args: Vec<Value>) -> Result<Value, String> {
-    Ok(Value::Bool(matches!(args.get(0), Some(Value::List(_)))))
+    Ok(Value::Bool(matches!(args.first(), Some(Value::List(_)))))
 }
 
 fn json_is_string(args: Vec<Value>) -> Result<Value, String> {
-    Ok(Value::Bool(matches!(args.get(0), Some(Value::Str(_)))))
+    Ok(Value::Bool(matches!(args.first(), Some(Value::Str(_)))))
 }
 
 fn json_is_number(args: Vec<Value>) -> Result<Value, String> {
-    Ok(Value::Bool(matches!(args.get(0), Some(Value::Int(_)) | Some(Value::Float(_)))))
+    Ok(Value::Bool(matches!(args.first(), Some(Value::Int(_)) | Some(Value::Float(_)))))
 }
 
 fn json_is_null(args: Vec<Value>) -> Result<Value, String> {
-    Ok(Value::Bool(matches!(args.get(0), Some(Value::Nil) | None)))
+    Ok(Value::Bool(matches!(args.first(), Some(Value::Nil) | None)))
 }
 
 fn json_is_bool(args: Vec<Value>) -> Result<Value, String> {
-    Ok(Value::Bool(matches!(args.get(0), Some(Value::Bool(_)))))
+    Ok(Value::Bool(matches!(args.first(), Some(Value::Bool(_)))))
 }
 
 // ── Inspection ────────────────────────────────────────────────────────────────
 
 /// json_keys(v: Value) -> Result<List<String>, String>
 ///
-/// Returns the keys of a JSON object as a List of strings.
+/// Returns the keys of a JSON object as a List, in insertion order.
 ///
 /// Example:
 ///   let keys = json_keys(data)
@@ -285,16 +284,10 @@ fn json_is_bool(args: Vec<Value>) -> Result<Value, String> {
 ///       Err(e) => println(e)
 ///   }
 fn json_keys(args: Vec<Value>) -> Result<Value, String> {
-    match args.get(0) {
+    match args.first() {
         Some(Value::Map(m)) => {
-            let mut keys: Vec<Value> = m.borrow().keys()
-                .map(|k| Value::Str(k.clone()))
-                .collect();
-            keys.sort_by(|a, b| {
-                if let (Value::Str(a), Value::Str(b)) = (a, b) { a.cmp(b) }
-                else { std::cmp::Ordering::Equal }
-            });
-            Ok(ok_val(Value::List(Rc::new(RefCell::new(keys)))))
+            let keys = m.borrow().keys().map(MapKey::to_value).collect();
+            Ok(ok_val(Value::list(keys)))
         }
         Some(other) => Ok(err_val(format!("json_keys() expects an object, got {}", crate::interpreter::value_type_name(other)))),
         None        => Ok(err_val("json_keys() requires 1 argument".into())),
@@ -305,10 +298,10 @@ fn json_keys(args: Vec<Value>) -> Result<Value, String> {
 ///
 /// Returns the values of a JSON object as a List.
 fn json_values(args: Vec<Value>) -> Result<Value, String> {
-    match args.get(0) {
+    match args.first() {
         Some(Value::Map(m)) => {
             let vals: Vec<Value> = m.borrow().values().cloned().collect();
-            Ok(ok_val(Value::List(Rc::new(RefCell::new(vals)))))
+            Ok(ok_val(Value::list(vals)))
         }
         Some(other) => Ok(err_val(format!("json_values() expects an object, got {}", crate::interpreter::value_type_name(other)))),
         None        => Ok(err_val("json_values() requires 1 argument".into())),
@@ -319,7 +312,7 @@ fn json_values(args: Vec<Value>) -> Result<Value, String> {
 ///
 /// Returns the number of elements in a JSON array or object.
 fn json_len(args: Vec<Value>) -> Result<Value, String> {
-    match args.get(0) {
+    match args.first() {
         Some(Value::List(v)) => Ok(ok_val(Value::Int(v.borrow().len() as i64))),
         Some(Value::Map(m))  => Ok(ok_val(Value::Int(m.borrow().len() as i64))),
         Some(other) => Ok(err_val(format!("json_len() expects array or object, got {}", crate::interpreter::value_type_name(other)))),
@@ -416,7 +409,7 @@ fn parse_string(src: &str, pos: &mut usize) -> Result<Value, String> {
                     }
                 }
             }
-            c => {
+            _ => {
                 // Decode UTF-8 manually to handle multi-byte chars
                 let s = &src[*pos..];
                 let ch = s.chars().next().unwrap();
@@ -425,7 +418,7 @@ fn parse_string(src: &str, pos: &mut usize) -> Result<Value, String> {
             }
         }
     }
-    Ok(Value::Str(result))
+    Ok(Value::str(result))
 }
 
 fn parse_number(src: &str, pos: &mut usize) -> Result<Value, String> {
@@ -472,12 +465,12 @@ fn parse_number(src: &str, pos: &mut usize) -> Result<Value, String> {
             .map(Value::Float)
             .map_err(|_| format!("Invalid float: {}", num_str))
     } else {
-        // Try i64 first, fall back to f64 for large numbers
+        // Try i64 first, fall back to a BigInt for large numbers
         if let Ok(n) = num_str.parse::<i64>() {
             Ok(Value::Int(n))
         } else {
-            num_str.parse::<f64>()
-                .map(Value::Float)
+            num_str.parse::<num_bigint::BigInt>()
+                .map(crate::interpreter::int_value)
                 .map_err(|_| format!("Invalid number: {}", num_str))
         }
     }
@@ -490,7 +483,7 @@ fn parse_array(src: &str, pos: &mut usize) -> Result<Value, String> {
 
     if src.as_bytes().get(*pos) == Some(&b']') {
         *pos += 1;
-        return Ok(Value::List(Rc::new(RefCell::new(items))));
+        return Ok(Value::list(items));
     }
 
     loop {
@@ -503,17 +496,17 @@ fn parse_array(src: &str, pos: &mut usize) -> Result<Value, String> {
             _ => return Err(format!("Expected ',' or ']' in array at position {}", pos)),
         }
     }
-    Ok(Value::List(Rc::new(RefCell::new(items))))
+    Ok(Value::list(items))
 }
 
 fn parse_object(src: &str, pos: &mut usize) -> Result<Value, String> {
     expect_byte(src, pos, b'{')?;
     skip_ws(src, pos);
-    let mut map = HashMap::new();
+    let mut map = IndexMap::new();
 
     if src.as_bytes().get(*pos) == Some(&b'}') {
         *pos += 1;
-        return Ok(Value::Map(Rc::new(RefCell::new(map))));
+        return Ok(Value::map(map));
     }
 
     loop {
@@ -521,7 +514,7 @@ fn parse_object(src: &str, pos: &mut usize) -> Result<Value, String> {
 
         // Key must be a string
         let key = match parse_string(src, pos) {
-            Ok(Value::Str(s)) => s,
+            Ok(Value::Str(s)) => MapKey::Str(Rc::unwrap_or_clone(s)),
             Ok(_) => return Err("Object key must be a string".into()),
             Err(e) => return Err(e),
         };
@@ -540,7 +533,7 @@ fn parse_object(src: &str, pos: &mut usize) -> Result<Value, String> {
             _ => return Err(format!("Expected ',' or '}}' in object at position {}", pos)),
         }
     }
-    Ok(Value::Map(Rc::new(RefCell::new(map))))
+    Ok(Value::map(map))
 }
 
 // ═══════════════════════════════════════════════════════════
@@ -557,6 +550,7 @@ fn serialize(val: &Value, depth: usize, pretty: bool) -> Result<String, String>
         Value::Nil                     => Ok("null".into()),
         Value::Bool(b)                 => Ok(b.to_string()),
         Value::Int(n)                  => Ok(n.to_string()),
+        Value::BigInt(n)               => Ok(n.to_string()),
         Value::Float(f) => {
             if f.is_nan() || f.is_infinite() {
                 Ok("null".into()) // JSON has no NaN/Infinity
@@ -590,12 +584,24 @@ fn serialize(val: &Value, depth: usize, pretty: bool) -> Result<String, String>
             if map.is_empty() {
                 return Ok("{}".into());
             }
-            // Sort keys for deterministic output
-            let mut keys: Vec<&String> = map.keys().collect();
-            keys.sort();
+            // JSON keys are strings: Int and Bool keys are written as their
+            // text, and anything else has no JSON spelling. Keys come out in
+            // insertion order.
+            let mut keys = Vec::with_capacity(map.len());
+            let mut seen = std::collections::HashSet::new();
+            for (key, v) in map.iter() {
+                let text = match key {
+                    MapKey::Str(s) => s.clone(),
+                    MapKey::Int(_) | MapKey::BigInt(_) | MapKey::Bool(_) => key.to_string(),
+                    other => return Err(format!("Cannot serialize map key {} to JSON", other)),
+                };
+                if !seen.insert(text.clone()) {
+                    return Err(format!("Cannot serialize map to JSON: two keys are both written as \"{}\"", text));
+                }
+                keys.push((text, v));
+            }
             let mut out = format!("{{{}", nl);
-            for (i, key) in keys.iter().enumerate() {
-                let v = &map[*key];
+            for (i, (key, v)) in keys.iter().enumerate() {
                 out.push_str(&inner_indent);
                 out.push_str(&escape_string(key));
                 out.push(':');
@@ -609,6 +615,12 @@ fn serialize(val: &Value, depth: usize, pretty: bool) -> Result<String, String>
             Ok(out)
         }
 
+        Value::Set(s) => {
+            // Sets serialize as arrays
+            let items = s.borrow().iter().map(MapKey::to_value).collect();
+            serialize(&Value::list(items), depth, pretty)
+        }
+
         Value::Tuple(v) => {
             // Tuples serialize as arrays
             let mut out = format!("[{}", nl);
@@ -690,7 +702,18 @@ fn serialize(val: &Value, depth: usize, pretty: bool) -> Result<String, String>
 
         Value::Ref(r) => serialize(&r.borrow(), depth, pretty),
 
+        Value::Range(start, end, step) => {
+            let len = crate::iter::range_len(*start, *end, *step);
+            let items: Vec<String> = (0..len).map(|i| (start + i * step).to_string()).collect();
+            Ok(format!("[{}]", items.join(",")))
+        }
+
         Value::Function(_) => Err("Functions cannot be serialized to JSON".into()),
+        Value::Module(_)   => Err("Modules cannot be serialized to JSON".into()),
+        Value::Iter(_)     => Err("Iterators cannot be serialized to JSON; collect() them first".into()),
+        Value::Regex(_)    => Err("A Regex cannot be serialized to JSON; store its pattern string instead".into()),
+        Value::DateTime(dt) => Ok(escape_string(&crate::datetime::iso(dt))),
+        Value::Duration(_) => Err("A Duration cannot be serialized to JSON; use total_ms() or total_seconds()".into()),
     }
 }
 
@@ -720,14 +743,14 @@ fn escape_string(s: &str) -> String {
 
 fn get_key(val: &Value, key: &str) -> Value {
     match val {
-        Value::Map(m) => m.borrow().get(key).cloned().unwrap_or(Value::Nil),
+        Value::Map(m) => m.borrow().get(&MapKey::from(key)).cloned().unwrap_or(Value::Nil),
         _             => Value::Nil,
     }
 }
 
 fn require_str(args: &[Value], idx: usize, sig: &str) -> Result<String, String> {
     match args.get(idx) {
-        Some(Value::Str(s)) => Ok(s.clone()),
+        Some(Value::Str(s)) => Ok((**s).clone()),
         Some(other)         => Err(format!("{}: argument {} must be a String, got {}", sig, idx + 1, crate::interpreter::value_type_name(other))),
         None                => Err(format!("{}: argument {} is required", sig, idx + 1)),
     }
@@ -738,5 +761,5 @@ fn ok_val(v: Value) -> Value {
 }
 
 fn err_val(msg: String) -> Value {
-    Value::Result(std::result::Result::Err(Box::new(Value::Str(msg))))
+    Value::Result(std::result::Result::Err(Box::new(Value::str(msg))))
 }
\ No newline at end of file
@@ -5,6 +5,7 @@
 mod lexer;
 mod ast;
 mod parser;
+mod resolver;
 mod interpreter;
 mod stdlib;
 mod net;
@@ -12,41 +13,47 @@ mod json;
 mod process;
 mod zfs;
 mod async_rt;
+mod iter;
+mod format;
+mod math;
+mod regexp;
+mod datetime;
+mod random;
+mod digest;
+mod gc;
 mod bytecode;
+mod compiler;
+mod vm;
 mod bundle;
+mod typeck;
+mod repl;
 
 use std::env;
 use std::fs;
-use std::io::{self, Write, BufRead};
 use std::path::Path;
-use interpreter::{Interpreter, Signal};
+use std::rc::Rc;
+use interpreter::{EvalResult, Interpreter, RuntimeError, Signal};
 
 fn main() {
     // ── Bundled-binary check ───────────────────────────────────────────────
     // Before doing anything else, check if this binary has a Zephyr bytecode
     // payload appended to it. If so, extract and run it immediately — this
     // binary IS the program, not the Zephyr CLI.
-    if let Some(stmts) = bundle::extract_payload() {
+    if let Some((program, source)) = bundle::extract_payload() {
         let mut interp = Interpreter::new();
-        match interp.run(&stmts) {
-            Ok(_) | Err(Signal::Return(_)) => std::process::exit(0),
-            Err(Signal::Error(e)) => {
-                eprintln!("\x1b[31m[error]\x1b[0m {}", e);
-                std::process::exit(1);
-            }
-            Err(Signal::PropagateErr(v)) => {
-                eprintln!("\x1b[31m[unhandled error]\x1b[0m {}", v);
-                std::process::exit(1);
-            }
-            Err(Signal::Break) | Err(Signal::Continue) => std::process::exit(0),
+        let result = vm::run_program(&mut interp, Rc::new(program));
+        if let Err(e) = outcome(result, None, &source) {
+            eprintln!("\x1b[31m[Zephyr error]\x1b[0m {}", e);
+            std::process::exit(1);
         }
+        std::process::exit(0);
     }
 
     // ── Normal Zephyr CLI ──────────────────────────────────────────────────
     let args: Vec<String> = env::args().collect();
 
     match args.get(1).map(|s| s.as_str()) {
-        Some("repl") | None => repl(),
+        Some("repl") | None => repl::repl(),
 
         Some("run") => {
             let file = args.get(2).expect("Usage: zephyr run <file.zph>");
@@ -71,12 +78,10 @@ fn main() {
             eprintln!("  zephyr run <file.zph>          Run a source file");
             eprintln!("  zephyr compile <file.zph>      Compile to bytecode + native executable");
             eprintln!("  zephyr compile -o <out> <file> Specify output path (no extension)");
-            eprintln!("  zephyr check <file.zph>        Parse-check without running");
+            eprintln!("  zephyr check <file.zph>        Type-check without running");
             eprintln!("  zephyr repl                    Start interactive REPL");
             std::process::exit(1);
         }
-
-        _ => repl(),
     }
 }
 
@@ -130,10 +135,14 @@ fn compile_command(args: &[String]) {
     });
 
     let stmts = parse_source(&source, &input);
+    let program = compiler::compile(&stmts).unwrap_or_else(|e| {
+        eprintln!("\x1b[31m[compile error]\x1b[0m in {}: {}", input, e);
+        std::process::exit(1);
+    });
 
     // 1. Write .zphc bytecode
     let zphc_path = format!("{}.zphc", stem);
-    let encoded = bytecode::encode(&stmts, &source);
+    let encoded = bytecode::encode(&program, &source);
     fs::write(&zphc_path, &encoded).unwrap_or_else(|e| {
         eprintln!("\x1b[31m[Zephyr]\x1b[0m Cannot write '{}': {}", zphc_path, e);
         std::process::exit(1);
@@ -177,6 +186,20 @@ fn check_file(path: &str) {
         eprintln!("\x1b[31m[parse error]\x1b[0m in {}: {}", path, e);
         std::process::exit(1);
     });
+
+    let diags = typeck::check_program(&ast, Some(Path::new(path)));
+    let errors = diags.iter().filter(|d| d.severity == typeck::Severity::Error).count();
+    for d in &diags {
+        let label = match d.severity {
+            typeck::Severity::Error   => "\x1b[31m[type error]\x1b[0m",
+            typeck::Severity::Warning => "\x1b[33m[warning]\x1b[0m",
+        };
+        eprintln!("{} {}:{}:{}: {}", label, path, d.span.line, d.span.col, d.message);
+    }
+    if errors > 0 {
+        eprintln!("\x1b[31m✗\x1b[0m {} — {} error(s), {} warning(s)", path, errors, diags.len() - errors);
+        std::process::exit(1);
+    }
     println!("\x1b[32m✓\x1b[0m {} — OK ({} top-level statements)", path, ast.len());
 }
 
@@ -202,23 +225,17 @@ fn run_bytecode_file(path: &str) {
         }
     }
 
-    let (stmts, _hash) = bytecode::decode(&data).unwrap_or_else(|e| {
+    let (program, source) = bytecode::decode(&data).unwrap_or_else(|e| {
         eprintln!("\x1b[31m[bytecode error]\x1b[0m {}", e);
         std::process::exit(1);
     });
 
     let mut interp = Interpreter::new();
-    match interp.run(&stmts) {
-        Ok(_) | Err(Signal::Return(_)) => {}
-        Err(Signal::Error(e)) => {
-            eprintln!("\x1b[31m[runtime error]\x1b[0m {}", e);
-            std::process::exit(1);
-        }
-        Err(Signal::PropagateErr(v)) => {
-            eprintln!("\x1b[31m[unhandled error]\x1b[0m {}", v);
-            std::process::exit(1);
-        }
-        Err(Signal::Break) | Err(Signal::Continue) => {}
+    interp.set_source_path(path);
+    let result = vm::run_program(&mut interp, Rc::new(program));
+    if let Err(e) = outcome(result, Some(path), &source) {
+        eprintln!("\x1b[31m[Zephyr error]\x1b[0m {}", e);
+        std::process::exit(1);
     }
 }
 
@@ -259,138 +276,35 @@ fn run_source(source: &str, filename: &str) -> Result<(), String> {
     let mut parser = parser::Parser::new(tokens);
     let ast = parser.parse_program().map_err(|e| format!("Parse error in {}: {}", filename, e))?;
     let mut interp = Interpreter::new();
-    match interp.run(&ast) {
+    interp.set_source_path(filename);
+    let result = interp.run(ast);
+    outcome(result, Some(filename), source)
+}
+
+/// How a program run ended, the same way for both engines. `path` and
+/// `source` are the program's own file (None when bundled) and its text.
+fn outcome(result: EvalResult, path: Option<&str>, source: &str) -> Result<(), String> {
+    match result {
         Ok(_)                        => Ok(()),
         Err(Signal::Return(_))       => Ok(()),
         Err(Signal::Error(e))        => Err(format!("Runtime error: {}", e)),
+        Err(Signal::Located(e))      => Err(format!("Runtime error: {}", describe_error(&e, path, source))),
         Err(Signal::PropagateErr(v)) => Err(format!("Unhandled error: {}", v)),
         Err(Signal::Break)           => Err("break outside loop".into()),
         Err(Signal::Continue)        => Err("continue outside loop".into()),
+        Err(Signal::TailCall(_))     => Ok(()),
     }
 }
 
-// ═══════════════════════════════════════════════════════════
-// REPL
-// ═══════════════════════════════════════════════════════════
-
-fn repl() {
-    println!("\x1b[36m");
-    println!("  ███████╗███████╗██████╗ ██╗  ██╗██╗   ██╗██████╗ ");
-    println!("  ╚══███╔╝██╔════╝██╔══██╗██║  ██║╚██╗ ██╔╝██╔══██╗");
-    println!("    ███╔╝ █████╗  ██████╔╝███████║ ╚████╔╝ ██████╔╝");
-    println!("   ███╔╝  ██╔══╝  ██╔═══╝ ██╔══██║  ╚██╔╝  ██╔══██╗");
-    println!("  ███████╗███████╗██║     ██║  ██║   ██║   ██║  ██║");
-    println!("  ╚══════╝╚══════╝╚═╝     ╚═╝  ╚═╝   ╚═╝   ╚═╝  ╚═╝");
-    println!("\x1b[0m");
-    println!("  \x1b[90mThe Zephyr Programming Language v0.9.9\x1b[0m");
-    println!("  \x1b[90mType :help for help, :quit to exit\x1b[0m");
-    println!();
-
-    let mut interp = Interpreter::new();
-    let stdin = io::stdin();
-    let mut input_buffer = String::new();
-    let mut multiline = false;
-    let mut brace_depth: i32 = 0;
-
-    loop {
-        let prompt = if multiline { "  ... " } else { "\x1b[36mzph>\x1b[0m " };
-        print!("{}", prompt);
-        io::stdout().flush().unwrap();
-
-        let mut line = String::new();
-        match stdin.lock().read_line(&mut line) {
-            Ok(0) => break,
-            Ok(_) => {}
-            Err(e) => { eprintln!("Read error: {}", e); break; }
-        }
-
-        let trimmed = line.trim();
-        match trimmed {
-            ":quit" | ":q" | ":exit" => { println!("Goodbye!"); break; }
-            ":help" | ":h"           => { print_help(); continue; }
-            ":clear" => { print!("\x1b[2J\x1b[H"); io::stdout().flush().unwrap(); continue; }
-            "" if !multiline => continue,
-            _ => {}
-        }
-
-        for ch in trimmed.chars() {
-            match ch { '{' => brace_depth += 1, '}' => brace_depth -= 1, _ => {} }
-        }
-
-        input_buffer.push_str(&line);
-
-        if brace_depth > 0 { multiline = true; continue; }
-        multiline = false;
-        brace_depth = 0;
-        let source = input_buffer.clone();
-        input_buffer.clear();
-
-        let tokens = match lexer::Lexer::new(&source).tokenize() {
-            Ok(t) => t,
-            Err(e) => { eprintln!("\x1b[31m[lex error]\x1b[0m {}", e); continue; }
-        };
-        let ast = match parser::Parser::new(tokens).parse_program() {
-            Ok(a) => a,
-            Err(e) => { eprintln!("\x1b[31m[parse error]\x1b[0m {}", e); continue; }
-        };
-
-        match interp.run(&ast) {
-            Ok(interpreter::Value::Nil) => {}
-            Ok(val)                      => println!("\x1b[32m=> {}\x1b[0m", val),
-            Err(Signal::Return(v))       => println!("\x1b[32m=> {}\x1b[0m", v),
-            Err(Signal::Error(e))        => eprintln!("\x1b[31m[runtime error]\x1b[0m {}", e),
-            Err(Signal::PropagateErr(v)) => eprintln!("\x1b[31m[error propagated]\x1b[0m {}", v),
-            Err(Signal::Break)           => eprintln!("\x1b[33m[warning]\x1b[0m break outside loop"),
-            Err(Signal::Continue)        => eprintln!("\x1b[33m[warning]\x1b[0m continue outside loop"),
+/// Render a located runtime error with the offending source line. Errors in
+/// the program itself quote `source` — for bytecode, the text embedded at
+/// compile time — and errors inside imported modules read the module's file.
+fn describe_error(e: &RuntimeError, path: Option<&str>, source: &str) -> String {
+    match (&e.file, path) {
+        (Some(file), Some(path)) if file.as_path() != Path::new(path) => {
+            e.render(fs::read_to_string(file.as_path()).ok().as_deref())
         }
+        (Some(file), None) => e.render(fs::read_to_string(file.as_path()).ok().as_deref()),
+        _ => e.render(Some(source)),
     }
 }
-
-fn print_help() {
-    println!();
-    println!("  \x1b[1mZephyr Language Quick Reference\x1b[0m");
-    println!();
-    println!("  \x1b[33mCLI commands:\x1b[0m");
-    println!("    zephyr run <file.zph>          Run source file");
-    println!("    zephyr <file.zph>              Shorthand for run");
-    println!("    zephyr <file.zphc>             Run compiled bytecode");
-    println!("    zephyr compile <file.zph>      Compile → .zphc + native executable");
-    println!("    zephyr compile -o <stem> <f>   Custom output name (no extension)");
-    println!("    zephyr check <file.zph>        Parse-check without running");
-    println!("    zephyr repl                    Start REPL");
-    println!();
-    println!("  \x1b[33mVariables:\x1b[0m");
-    println!("    let x = 42          // immutable");
-    println!("    var y = \"hello\"     // mutable");
-    println!();
-    println!("  \x1b[33mFunctions:\x1b[0m");
-    println!("    fun add(a: Int, b: Int) -> Int {{ a + b }}");
-    println!("    fun greet(name) {{ println(\"Hello, #{{name}}!\") }}");
-    println!();
-    println!("  \x1b[33mTypes:\x1b[0m  Int, Float, Bool, String, Nil");
-    println!("  \x1b[33mNullable:\x1b[0m  Option<T>,  Result<T, E>");
-    println!();
-    println!("  \x1b[33mControl flow:\x1b[0m");
-    println!("    if x > 0 {{ ... }} elif x == 0 {{ ... }} else {{ ... }}");
-    println!("    while cond {{ ... }}     for i in 0..10 {{ ... }}");
-    println!("    match val {{ pattern => expr, _ => fallback }}");
-    println!();
-    println!("  \x1b[33mCollections:\x1b[0m");
-    println!("    let list  = [1, 2, 3]");
-    println!("    let map   = {{\"key\": value}}");
-    println!("    let tuple = (1, \"hello\", true)");
-    println!();
-    println!("  \x1b[33mStructs & Enums:\x1b[0m");
-    println!("    struct Point {{ x: Int, y: Int }}");
-    println!("    enum Shape {{ Circle(Float), Rect(Float, Float) }}");
-    println!();
-    println!("  \x1b[33mAsync:\x1b[0m");
-    println!("    let t  = async_http_get(url)       // spawn task");
-    println!("    let r  = async_await(t)             // await result");
-    println!("    let rs = async_await_all([t1, t2])  // await all");
-    println!("    let ch = channel()                  // create channel");
-    println!("    channel_send(ch, value)  channel_recv(ch)");
-    println!();
-    println!("  \x1b[33mREPL commands:\x1b[0m  :help  :clear  :quit");
-    println!();
-}
\ No newline at end of file
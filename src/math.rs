@@ -0,0 +1,340 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr Math — numeric functions and constants
+// ═══════════════════════════════════════════════════════════
+//
+// QUICK REFERENCE
+//
+// CONSTANTS
+//   PI  E  TAU  INF  NAN                       Float
+//
+// BASICS
+//   abs(x)  min(a, b, ...)  max(a, b, ...)      keep Int when every argument is one
+//   clamp(x, lo, hi)                            same
+//   sign(x)                                     -1, 0 or 1 (Int for Int, Float for Float)
+//   floor(x)  ceil(x)  trunc(x)  round(x)       Int
+//   round(x, digits)                            Float rounded to `digits` places
+//
+// POWERS AND LOGS                               Float
+//   sqrt(x)  cbrt(x)  pow(base, exp)  hypot(x, y)
+//   exp(x)  ln(x)  log(x)  log(x, base)  log2(x)  log10(x)
+//
+// TRIGONOMETRY (radians)                        Float
+//   sin  cos  tan  asin  acos  atan  atan2(y, x)
+//   sinh  cosh  tanh  degrees(rad)  radians(deg)
+//
+// INTEGERS                                      Int
+//   gcd(a, b)  lcm(a, b)
+//   idiv(a, b)      floor division:  idiv(-7, 2) == -4
+//   imod(a, b)      floor modulo, takes the sign of b:  imod(-7, 2) == 1
+//   fdiv(a, b)      true division, always Float:  fdiv(7, 2) == 3.5
+//
+// CHECKS                                        Bool
+//   is_nan(x)  is_finite(x)  is_inf(x)
+//
+// Int and BigInt arguments are accepted wherever a Float is and converted
+// with to_f64. Arguments outside a function's domain — sqrt(-1), ln(0),
+// asin(2) — are errors rather than NaN, and so are NaN or infinite values
+// where an Int has to come out.
+//
+// ═══════════════════════════════════════════════════════════
+
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+use crate::interpreter::{int_value, to_big, Value};
+
+// ── Registration ──────────────────────────────────────────────────────────────
+
+pub fn math_functions() -> Vec<&'static str> {
+    vec![
+        "abs", "min", "max", "clamp", "sign",
+        "floor", "ceil", "trunc", "round",
+        "sqrt", "cbrt", "pow", "hypot",
+        "exp", "ln", "log", "log2", "log10",
+        "sin", "cos", "tan", "asin", "acos", "atan", "atan2",
+        "sinh", "cosh", "tanh", "degrees", "radians",
+        "gcd", "lcm", "idiv", "imod", "fdiv",
+        "is_nan", "is_finite", "is_inf",
+    ]
+}
+
+pub fn math_constants() -> Vec<(&'static str, f64)> {
+    vec![
+        ("PI", std::f64::consts::PI),
+        ("E", std::f64::consts::E),
+        ("TAU", std::f64::consts::TAU),
+        ("INF", f64::INFINITY),
+        ("NAN", f64::NAN),
+    ]
+}
+
+// ── Dispatch ──────────────────────────────────────────────────────────────────
+
+pub fn call_math(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    let x = || float_arg(&args, 0, name);
+    let y = || float_arg(&args, 1, name);
+    match name {
+        "abs" => match arg(&args, 0, name)? {
+            Value::Float(f) => Ok(Value::Float(f.abs())),
+            n => Ok(int_value(to_big(n).ok_or_else(|| not_a_number(name, n))?.abs())),
+        },
+        "min" | "max" => extreme(name, args),
+        "clamp" => clamp(args),
+        "sign" => match arg(&args, 0, name)? {
+            Value::Float(f) if f.is_nan() => Ok(Value::Float(f64::NAN)),
+            Value::Float(f) => Ok(Value::Float(if *f == 0.0 { 0.0 } else { f.signum() })),
+            n => Ok(Value::Int(to_big(n).ok_or_else(|| not_a_number(name, n))?.signum().to_i64().unwrap_or(0))),
+        },
+
+        "floor" => to_int(name, &args, f64::floor),
+        "ceil"  => to_int(name, &args, f64::ceil),
+        "trunc" => to_int(name, &args, f64::trunc),
+        "round" if args.len() > 1 => {
+            let digits = match &args[1] {
+                Value::Int(d) => i32::try_from(*d).map_err(|_| format!("round(x, digits) — {} digits is out of range", d))?,
+                other => return Err(format!("round(x, digits) — digits must be an Int, got {}", other)),
+            };
+            let scale = 10f64.powi(digits);
+            Ok(Value::Float((x()? * scale).round() / scale))
+        }
+        "round" => to_int(name, &args, f64::round),
+
+        "sqrt" => {
+            let x = x()?;
+            if x < 0.0 { return Err(format!("sqrt({}) — square root of a negative number", x)); }
+            Ok(Value::Float(x.sqrt()))
+        }
+        "cbrt" => Ok(Value::Float(x()?.cbrt())),
+        "pow" => {
+            let (base, exp) = (x()?, y()?);
+            if base == 0.0 && exp < 0.0 {
+                return Err(format!("pow({}, {}) — zero cannot be raised to a negative power", base, exp));
+            }
+            if base < 0.0 && exp.fract() != 0.0 && exp.is_finite() {
+                return Err(format!("pow({}, {}) — a negative base needs an integer exponent", base, exp));
+            }
+            Ok(Value::Float(base.powf(exp)))
+        }
+        "hypot" => Ok(Value::Float(x()?.hypot(y()?))),
+
+        "exp"   => Ok(Value::Float(x()?.exp())),
+        "ln"    => Ok(Value::Float(positive(name, x()?)?.ln())),
+        "log2"  => Ok(Value::Float(positive(name, x()?)?.log2())),
+        "log10" => Ok(Value::Float(positive(name, x()?)?.log10())),
+        "log" if args.len() > 1 => {
+            let (x, base) = (positive(name, x()?)?, y()?);
+            if base <= 0.0 || base == 1.0 {
+                return Err(format!("log({}, {}) — the base must be positive and not 1", x, base));
+            }
+            Ok(Value::Float(x.log(base)))
+        }
+        "log" => Ok(Value::Float(positive(name, x()?)?.ln())),
+
+        "sin"  => Ok(Value::Float(x()?.sin())),
+        "cos"  => Ok(Value::Float(x()?.cos())),
+        "tan"  => Ok(Value::Float(x()?.tan())),
+        "asin" => Ok(Value::Float(unit(name, x()?)?.asin())),
+        "acos" => Ok(Value::Float(unit(name, x()?)?.acos())),
+        "atan" => Ok(Value::Float(x()?.atan())),
+        "atan2" => Ok(Value::Float(x()?.atan2(y()?))),
+        "sinh" => Ok(Value::Float(x()?.sinh())),
+        "cosh" => Ok(Value::Float(x()?.cosh())),
+        "tanh" => Ok(Value::Float(x()?.tanh())),
+        "degrees" => Ok(Value::Float(x()?.to_degrees())),
+        "radians" => Ok(Value::Float(x()?.to_radians())),
+
+        "gcd" => {
+            let (a, b) = (int_arg(&args, 0, name)?, int_arg(&args, 1, name)?);
+            Ok(int_value(gcd(a, b)))
+        }
+        "lcm" => {
+            let (a, b) = (int_arg(&args, 0, name)?, int_arg(&args, 1, name)?);
+            if a.is_zero() || b.is_zero() {
+                return Ok(Value::Int(0));
+            }
+            let g = gcd(a.clone(), b.clone());
+            Ok(int_value((a * b).abs() / g))
+        }
+        "idiv" | "imod" => {
+            let (a, b) = (int_arg(&args, 0, name)?, int_arg(&args, 1, name)?);
+            if b.is_zero() {
+                return Err(format!("{}({}, 0) — division by zero", name, a));
+            }
+            let (q, r) = (&a / &b, &a % &b);
+            // truncated → floored: step down when the remainder and divisor disagree in sign
+            let adjust = !r.is_zero() && (r.is_negative() != b.is_negative());
+            Ok(int_value(match (name, adjust) {
+                ("idiv", true) => q - 1,
+                ("idiv", false) => q,
+                (_, true) => r + b,
+                (_, false) => r,
+            }))
+        }
+        "fdiv" => {
+            let (a, b) = (x()?, y()?);
+            if b == 0.0 {
+                return Err(format!("fdiv({}, 0) — division by zero", a));
+            }
+            Ok(Value::Float(a / b))
+        }
+
+        "is_nan"    => Ok(Value::Bool(x()?.is_nan())),
+        "is_finite" => Ok(Value::Bool(x()?.is_finite())),
+        "is_inf"    => Ok(Value::Bool(x()?.is_infinite())),
+
+        _ => Err(format!("Unknown math function '{}'", name)),
+    }
+}
+
+/// Int, BigInt or Float as an f64.
+pub fn to_f64(val: &Value) -> Result<f64, String> {
+    match val {
+        Value::Int(n)   => Ok(*n as f64),
+        Value::BigInt(n) => Ok(n.to_f64().unwrap_or(f64::INFINITY)),
+        Value::Float(f) => Ok(*f),
+        other => Err(format!("Expected number, got {}", other))
+    }
+}
+
+// ── Functions ─────────────────────────────────────────────────────────────────
+
+fn extreme(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    // min(list) works on the list's elements
+    let args = match args.as_slice() {
+        [Value::List(items)] => items.borrow().clone(),
+        _ => args,
+    };
+    if args.is_empty() {
+        return Err(format!("{}() requires numbers or a non-empty List", name));
+    }
+    if let Some(ints) = args.iter().map(to_big).collect::<Option<Vec<_>>>() {
+        let best = if name == "min" { ints.into_iter().min() } else { ints.into_iter().max() };
+        return Ok(int_value(best.unwrap_or_default()));
+    }
+    let mut best = float_arg(&args, 0, name)?;
+    for i in 1..args.len() {
+        let f = float_arg(&args, i, name)?;
+        best = if name == "min" { best.min(f) } else { best.max(f) };
+    }
+    Ok(Value::Float(best))
+}
+
+fn clamp(args: Vec<Value>) -> Result<Value, String> {
+    if let (Some(x), Some(lo), Some(hi)) = (args.first().and_then(to_big), args.get(1).and_then(to_big), args.get(2).and_then(to_big)) {
+        if lo > hi {
+            return Err(format!("clamp(x, {}, {}) — lo is greater than hi", lo, hi));
+        }
+        return Ok(int_value(x.clamp(lo, hi)));
+    }
+    let (x, lo, hi) = (float_arg(&args, 0, "clamp")?, float_arg(&args, 1, "clamp")?, float_arg(&args, 2, "clamp")?);
+    if lo.is_nan() || hi.is_nan() || lo > hi {
+        return Err(format!("clamp(x, {}, {}) — lo must not be greater than hi", lo, hi));
+    }
+    Ok(Value::Float(x.clamp(lo, hi)))
+}
+
+/// floor / ceil / trunc / round: Ints pass through, Floats are rounded
+/// with `f` and must land on a real integer.
+fn to_int(name: &str, args: &[Value], f: fn(f64) -> f64) -> Result<Value, String> {
+    match arg(args, 0, name)? {
+        n @ (Value::Int(_) | Value::BigInt(_)) => Ok(n.clone()),
+        Value::Float(x) => {
+            let r = f(*x);
+            match r.to_i64() {
+                Some(i) => Ok(Value::Int(i)),
+                None => BigInt::from_f64(r)
+                    .map(int_value)
+                    .ok_or_else(|| format!("{}({}) — cannot turn {} into an Int", name, x, x)),
+            }
+        }
+        other => Err(not_a_number(name, other)),
+    }
+}
+
+fn gcd(mut a: BigInt, mut b: BigInt) -> BigInt {
+    while !b.is_zero() {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+    a.abs()
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+fn positive(name: &str, x: f64) -> Result<f64, String> {
+    if x > 0.0 || x.is_nan() {
+        Ok(x)
+    } else {
+        Err(format!("{}({}) — logarithm of a number that is not positive", name, x))
+    }
+}
+
+fn unit(name: &str, x: f64) -> Result<f64, String> {
+    if (-1.0..=1.0).contains(&x) || x.is_nan() {
+        Ok(x)
+    } else {
+        Err(format!("{}({}) — argument must be between -1 and 1", name, x))
+    }
+}
+
+fn arg<'a>(args: &'a [Value], idx: usize, name: &str) -> Result<&'a Value, String> {
+    args.get(idx).ok_or_else(|| format!("{}() — argument {} is required", name, idx + 1))
+}
+
+fn float_arg(args: &[Value], idx: usize, name: &str) -> Result<f64, String> {
+    let v = arg(args, idx, name)?;
+    to_f64(v).map_err(|_| not_a_number(name, v))
+}
+
+fn int_arg(args: &[Value], idx: usize, name: &str) -> Result<BigInt, String> {
+    let v = arg(args, idx, name)?;
+    to_big(v).ok_or_else(|| format!("{}() — argument {} must be an Int, got {}", name, idx + 1, v))
+}
+
+fn not_a_number(name: &str, v: &Value) -> String {
+    format!("{}() expects a number, got {}", name, v)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::eval;
+
+    fn show(src: &str) -> String {
+        format!("{}", eval(src).unwrap())
+    }
+
+    #[test]
+    fn integer_division_floors() {
+        assert_eq!(show("[idiv(-7, 2), imod(-7, 2), imod(7, -2), fdiv(7, 2)]"), "[-4, 1, -1, 3.5]");
+        assert_eq!(show("[gcd(12, 18), lcm(4, 6), gcd(0, 5)]"), "[6, 12, 5]");
+    }
+
+    #[test]
+    fn ints_stay_ints_where_they_can() {
+        assert_eq!(show("[abs(-3), max(1, 7, 2), clamp(15, 0, 10), sign(-2), sign(0.5)]"), "[3, 7, 10, -1, 1.0]");
+        assert_eq!(show("[floor(pow(10, 20)), round(2.5), round(3.14159, 2)]"), "[100000000000000000000, 3, 3.14]");
+    }
+
+    #[test]
+    fn floats_and_constants() {
+        assert_eq!(show("[log(100, 10), sin(PI / 2), degrees(PI), hypot(3, 4)]"), "[2.0, 1.0, 180.0, 5.0]");
+        assert_eq!(show("[is_nan(NAN), is_inf(-INF), is_finite(INF - 1)]"), "[true, true, false]");
+    }
+
+    #[test]
+    fn arguments_outside_the_domain_are_errors() {
+        assert_eq!(eval("sqrt(-1)").unwrap_err(), "sqrt(-1) — square root of a negative number");
+        assert_eq!(eval("ln(0)").unwrap_err(), "ln(0) — logarithm of a number that is not positive");
+        assert_eq!(eval("asin(2)").unwrap_err(), "asin(2) — argument must be between -1 and 1");
+        assert_eq!(eval("pow(0, -1)").unwrap_err(), "pow(0, -1) — zero cannot be raised to a negative power");
+        assert_eq!(eval("log(8, 1)").unwrap_err(), "log(8, 1) — the base must be positive and not 1");
+    }
+
+    #[test]
+    fn impossible_ints_are_errors() {
+        assert_eq!(eval("floor(NAN)").unwrap_err(), "floor(NaN) — cannot turn NaN into an Int");
+        assert_eq!(eval("idiv(1, 0)").unwrap_err(), "idiv(1, 0) — division by zero");
+        assert_eq!(eval("clamp(1, 5, 0)").unwrap_err(), "clamp(x, 5, 0) — lo is greater than hi");
+        assert_eq!(eval("gcd(1.5, 2)").unwrap_err(), "gcd() — argument 1 must be an Int, got 1.5");
+    }
+}
@@ -1,7 +1,5 @@
-use std::collections::HashMap;
-use std::rc::Rc;
-use std::cell::RefCell;
-use crate::interpreter::Value;
+use indexmap::IndexMap;
+use crate::interpreter::{MapKey, Value};
 
 // ── Registration ──────────────────────────────────────────────────────────────
 
@@ -61,7 +59,7 @@ fn net_http_get(args: Vec<Value>) -> Result<Value, String> {
     match ureq::get(&url).call() {
         Ok(resp) => {
             let body = resp.into_string().map_err(|e| e.to_string())?;
-            Ok(ok_result(Value::Str(body)))
+            Ok(ok_result(Value::str(body)))
         }
         Err(e) => Ok(err_result(format!("{}", e))),
     }
@@ -80,7 +78,7 @@ fn net_http_get_json(args: Vec<Value>) -> Result<Value, String> {
     {
         Ok(resp) => {
             let body = resp.into_string().map_err(|e| e.to_string())?;
-            Ok(ok_result(Value::Str(body)))
+            Ok(ok_result(Value::str(body)))
         }
         Err(e) => Ok(err_result(format!("{}", e))),
     }
@@ -99,7 +97,7 @@ fn net_http_post(args: Vec<Value>) -> Result<Value, String> {
     {
         Ok(resp) => {
             let text = resp.into_string().map_err(|e| e.to_string())?;
-            Ok(ok_result(Value::Str(text)))
+            Ok(ok_result(Value::str(text)))
         }
         Err(e) => Ok(err_result(format!("{}", e))),
     }
@@ -119,7 +117,7 @@ fn net_http_post_json(args: Vec<Value>) -> Result<Value, String> {
     {
         Ok(resp) => {
             let text = resp.into_string().map_err(|e| e.to_string())?;
-            Ok(ok_result(Value::Str(text)))
+            Ok(ok_result(Value::str(text)))
         }
         Err(e) => Ok(err_result(format!("{}", e))),
     }
@@ -138,7 +136,7 @@ fn net_http_put(args: Vec<Value>) -> Result<Value, String> {
     {
         Ok(resp) => {
             let text = resp.into_string().map_err(|e| e.to_string())?;
-            Ok(ok_result(Value::Str(text)))
+            Ok(ok_result(Value::str(text)))
         }
         Err(e) => Ok(err_result(format!("{}", e))),
     }
@@ -153,7 +151,7 @@ fn net_http_delete(args: Vec<Value>) -> Result<Value, String> {
     match ureq::delete(&url).call() {
         Ok(resp) => {
             let text = resp.into_string().map_err(|e| e.to_string())?;
-            Ok(ok_result(Value::Str(text)))
+            Ok(ok_result(Value::str(text)))
         }
         Err(e) => Ok(err_result(format!("{}", e))),
     }
@@ -179,7 +177,7 @@ fn net_http_request(args: Vec<Value>) -> Result<Value, String> {
     // Apply headers from Map value
     if let Value::Map(map) = &args[2] {
         for (key, val) in map.borrow().iter() {
-            req = req.set(key, &format!("{}", val));
+            req = req.set(&key.to_string(), &format!("{}", val));
         }
     }
 
@@ -192,7 +190,7 @@ fn net_http_request(args: Vec<Value>) -> Result<Value, String> {
     match result {
         Ok(resp) => {
             let text = resp.into_string().map_err(|e| e.to_string())?;
-            Ok(ok_result(Value::Str(text)))
+            Ok(ok_result(Value::str(text)))
         }
         Err(e) => Ok(err_result(format!("{}", e))),
     }
@@ -225,7 +223,7 @@ fn net_http_status(args: Vec<Value>) -> Result<Value, String> {
 ///   // => "hello%20world%20%26%20foo%3Dbar"
 fn net_url_encode(args: Vec<Value>) -> Result<Value, String> {
     let s = require_str_arg(&args, 0, "url_encode(s)")?;
-    Ok(Value::Str(percent_encode(&s)))
+    Ok(Value::str(percent_encode(&s)))
 }
 
 /// url_decode(s: String) -> String
@@ -235,7 +233,7 @@ fn net_url_encode(args: Vec<Value>) -> Result<Value, String> {
 ///   // => "hello world"
 fn net_url_decode(args: Vec<Value>) -> Result<Value, String> {
     let s = require_str_arg(&args, 0, "url_decode(s)")?;
-    Ok(Value::Str(percent_decode(&s)))
+    Ok(Value::str(percent_decode(&s)))
 }
 
 /// url_parse(url: String) -> Map
@@ -252,7 +250,7 @@ fn net_url_decode(args: Vec<Value>) -> Result<Value, String> {
 fn net_url_parse(args: Vec<Value>) -> Result<Value, String> {
     let url = require_str_arg(&args, 0, "url_parse(url)")?;
     let parsed = parse_url(&url)?;
-    Ok(Value::Map(Rc::new(RefCell::new(parsed))))
+    Ok(Value::map(parsed))
 }
 
 /// url_join(base: String, path: String) -> String
@@ -283,7 +281,7 @@ fn net_url_join(args: Vec<Value>) -> Result<Value, String> {
         let base = base.trim_end_matches('/');
         format!("{}/{}", base, path)
     };
-    Ok(Value::Str(joined))
+    Ok(Value::str(joined))
 }
 
 /// url_query_string(params: Map) -> String
@@ -303,9 +301,9 @@ fn net_url_query_string(args: Vec<Value>) -> Result<Value, String> {
             let pairs: Vec<String> = m
                 .borrow()
                 .iter()
-                .map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(&format!("{}", v))))
+                .map(|(k, v)| format!("{}={}", percent_encode(&k.to_string()), percent_encode(&format!("{}", v))))
                 .collect();
-            Ok(Value::Str(pairs.join("&")))
+            Ok(Value::str(pairs.join("&")))
         }
         other => Err(format!("url_query_string() expects a Map, got {}", other)),
     }
@@ -315,7 +313,7 @@ fn net_url_query_string(args: Vec<Value>) -> Result<Value, String> {
 
 fn require_str_arg(args: &[Value], idx: usize, sig: &str) -> Result<String, String> {
     match args.get(idx) {
-        Some(Value::Str(s)) => Ok(s.clone()),
+        Some(Value::Str(s)) => Ok((**s).clone()),
         Some(other)         => Ok(format!("{}", other)), // coerce to string
         None                => Err(format!("{} — argument {} is required", sig, idx + 1)),
     }
@@ -326,14 +324,13 @@ fn ok_result(val: Value) -> Value {
 }
 
 fn err_result(msg: String) -> Value {
-    Value::Result(std::result::Result::Err(Box::new(Value::Str(msg))))
+    Value::Result(std::result::Result::Err(Box::new(Value::str(msg))))
 }
 
 /// Very simple URL parser — does not require an external crate.
 /// Handles:  scheme://[host[:port]][path][?query][#fragment]
-fn parse_url(url: &str) -> Result<HashMap<String, Value>, String> {
-    let mut map: HashMap<String, Value> = HashMap::new();
-    let empty = || Value::Str(String::new());
+fn parse_url(url: &str) -> Result<IndexMap<MapKey, Value>, String> {
+    let mut map: IndexMap<MapKey, Value> = IndexMap::new();
 
     // Extract scheme
     let (scheme, rest) = if let Some(idx) = url.find("://") {
@@ -341,7 +338,7 @@ fn parse_url(url: &str) -> Result<HashMap<String, Value>, String> {
     } else {
         ("", url)
     };
-    map.insert("scheme".into(), Value::Str(scheme.to_string()));
+    map.insert("scheme".into(), Value::str(scheme.to_string()));
 
     // Strip fragment
     let (rest, fragment) = if let Some(idx) = rest.find('#') {
@@ -349,7 +346,7 @@ fn parse_url(url: &str) -> Result<HashMap<String, Value>, String> {
     } else {
         (rest, "")
     };
-    map.insert("fragment".into(), Value::Str(fragment.to_string()));
+    map.insert("fragment".into(), Value::str(fragment.to_string()));
 
     // Strip query
     let (rest, query) = if let Some(idx) = rest.find('?') {
@@ -357,7 +354,7 @@ fn parse_url(url: &str) -> Result<HashMap<String, Value>, String> {
     } else {
         (rest, "")
     };
-    map.insert("query".into(), Value::Str(query.to_string()));
+    map.insert("query".into(), Value::str(query.to_string()));
 
     // Separate host[:port] from path
     let (authority, path) = if let Some(idx) = rest.find('/') {
@@ -365,7 +362,7 @@ fn parse_url(url: &str) -> Result<HashMap<String, Value>, String> {
     } else {
         (rest, "")
     };
-    map.insert("path".into(), Value::Str(path.to_string()));
+    map.insert("path".into(), Value::str(path.to_string()));
 
     // Separate host from port
     let (host, port) = if let Some(idx) = authority.rfind(':') {
@@ -378,8 +375,8 @@ fn parse_url(url: &str) -> Result<HashMap<String, Value>, String> {
     } else {
         (authority, "")
     };
-    map.insert("host".into(), Value::Str(host.to_string()));
-    map.insert("port".into(), Value::Str(port.to_string()));
+    map.insert("host".into(), Value::str(host.to_string()));
+    map.insert("port".into(), Value::str(port.to_string()));
 
     Ok(map)
 }
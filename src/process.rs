@@ -55,11 +55,9 @@
 //
 // ═══════════════════════════════════════════════════════════
 
-use std::collections::HashMap;
-use std::rc::Rc;
-use std::cell::RefCell;
 use std::process::{Command, Stdio};
 use std::env;
+use indexmap::IndexMap;
 use crate::interpreter::Value;
 
 // ── Registration ──────────────────────────────────────────────────────────────
@@ -127,7 +125,7 @@ fn proc_exec(args: Vec<Value>) -> Result<Value, String> {
     let cmd = require_str(&args, 0, "exec(cmd)")?;
     let out = run_shell(&cmd)?;
     if out.code == 0 {
-        Ok(ok_val(Value::Str(out.stdout)))
+        Ok(ok_val(Value::str(out.stdout)))
     } else {
         let msg = if out.stderr.is_empty() {
             format!("exited with code {}", out.code)
@@ -267,10 +265,10 @@ fn proc_process_run(args: Vec<Value>) -> Result<Value, String> {
     let arg_list = extract_string_list(&args, 1).unwrap_or_default();
     let env_vars = extract_string_map(&args, 2);
     let cwd      = match args.get(3) {
-        Some(Value::Str(s)) if !s.is_empty() => Some(s.clone()),
+        Some(Value::Str(s)) if !s.is_empty() => Some(s.as_str()),
         _ => None,
     };
-    let out = run_command(&program, &arg_list, &env_vars, cwd.as_deref())?;
+    let out = run_command(&program, &arg_list, &env_vars, cwd)?;
     Ok(make_output_map(out))
 }
 
@@ -321,7 +319,7 @@ fn proc_process_spawn(args: Vec<Value>) -> Result<Value, String> {
 fn proc_env_get(args: Vec<Value>) -> Result<Value, String> {
     let key = require_str(&args, 0, "env_get(key)")?;
     match env::var(&key) {
-        Ok(val) => Ok(Value::Str(val)),
+        Ok(val) => Ok(Value::str(val)),
         Err(_)  => Ok(Value::Nil),
     }
 }
@@ -349,11 +347,11 @@ fn proc_env_set(args: Vec<Value>) -> Result<Value, String> {
 ///   let env = env_all()
 ///   let path = env["PATH"]
 fn proc_env_all(_args: Vec<Value>) -> Result<Value, String> {
-    let mut map = HashMap::new();
+    let mut map = IndexMap::new();
     for (key, val) in env::vars() {
-        map.insert(key, Value::Str(val));
+        map.insert(key.into(), Value::str(val));
     }
-    Ok(Value::Map(Rc::new(RefCell::new(map))))
+    Ok(Value::map(map))
 }
 
 // ── Working directory ─────────────────────────────────────────────────────────
@@ -367,7 +365,7 @@ fn proc_env_all(_args: Vec<Value>) -> Result<Value, String> {
 ///   println("Working in: #{dir}")
 fn proc_cwd(_args: Vec<Value>) -> Result<Value, String> {
     env::current_dir()
-        .map(|p| Value::Str(p.to_string_lossy().to_string()))
+        .map(|p| Value::str(p.to_string_lossy().to_string()))
         .map_err(|e| format!("cwd(): {}", e))
 }
 
@@ -433,12 +431,12 @@ fn run_command(
     let stdout = stdout.strip_suffix('\n')
         .unwrap_or(&stdout)
         .strip_suffix('\r')
-        .unwrap_or(&stdout.strip_suffix('\n').unwrap_or(&stdout))
+        .unwrap_or(stdout.strip_suffix('\n').unwrap_or(&stdout))
         .to_string();
     let stderr = stderr.strip_suffix('\n')
         .unwrap_or(&stderr)
         .strip_suffix('\r')
-        .unwrap_or(&stderr.strip_suffix('\n').unwrap_or(&stderr))
+        .unwrap_or(stderr.strip_suffix('\n').unwrap_or(&stderr))
         .to_string();
 
     let code = output.status.code().unwrap_or(-1);
@@ -447,19 +445,19 @@ fn run_command(
 
 /// Build the standard { stdout, stderr, code, ok } Map.
 fn make_output_map(out: ProcessOutput) -> Value {
-    let mut map = HashMap::new();
-    map.insert("stdout".into(), Value::Str(out.stdout));
-    map.insert("stderr".into(), Value::Str(out.stderr));
+    let mut map = IndexMap::new();
+    map.insert("stdout".into(), Value::str(out.stdout));
+    map.insert("stderr".into(), Value::str(out.stderr));
     map.insert("code".into(),   Value::Int(out.code as i64));
     map.insert("ok".into(),     Value::Bool(out.code == 0));
-    Value::Map(Rc::new(RefCell::new(map)))
+    Value::map(map)
 }
 
 // ── Argument helpers ──────────────────────────────────────────────────────────
 
 fn require_str(args: &[Value], idx: usize, sig: &str) -> Result<String, String> {
     match args.get(idx) {
-        Some(Value::Str(s)) => Ok(s.clone()),
+        Some(Value::Str(s)) => Ok((**s).clone()),
         Some(other) => Ok(format!("{}", other)), // coerce to string
         None => Err(format!("{}: argument {} is required", sig, idx + 1)),
     }
@@ -471,7 +469,7 @@ fn extract_string_list(args: &[Value], idx: usize) -> Result<Vec<String>, String
         Some(Value::List(v)) => {
             v.borrow().iter()
                 .map(|item| match item {
-                    Value::Str(s) => Ok(s.clone()),
+                    Value::Str(s) => Ok((**s).clone()),
                     other         => Ok(format!("{}", other)), // coerce
                 })
                 .collect()
@@ -487,7 +485,7 @@ fn extract_string_map(args: &[Value], idx: usize) -> Vec<(String, String)> {
     match args.get(idx) {
         Some(Value::Map(m)) => {
             m.borrow().iter()
-                .map(|(k, v)| (k.clone(), format!("{}", v)))
+                .map(|(k, v)| (k.to_string(), format!("{}", v)))
                 .collect()
         }
         _ => vec![],
@@ -499,5 +497,5 @@ fn ok_val(v: Value) -> Value {
 }
 
 fn err_val(msg: String) -> Value {
-    Value::Result(std::result::Result::Err(Box::new(Value::Str(msg))))
+    Value::Result(std::result::Result::Err(Box::new(Value::str(msg))))
 }
\ No newline at end of file
@@ -0,0 +1,190 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr Random — random numbers, shuffling and UUIDs
+// ═══════════════════════════════════════════════════════════
+//
+// QUICK REFERENCE
+//
+//   random_int(lo, hi)             Int in lo..=hi (both ends included)
+//   random_float()                 Float in [0, 1)
+//   random_float(lo, hi)           Float in [lo, hi)
+//   random_bool(p?)                true with probability p (default 0.5)
+//   random_bytes(n)                List<Int> of n bytes
+//   shuffle(list)                  a shuffled copy; the argument is untouched
+//   choice(list)                   Option, a random element (None when empty)
+//   uuid4()                        String, a random (version 4) UUID
+//   random_seed(n?)                reseed: from n for repeatable runs, or
+//                                  from the OS when called with no argument
+//
+// Every thread starts seeded from the OS. After random_seed(n), the same
+// script draws the same numbers, UUIDs included:
+//
+//   random_seed(42)
+//   let ids = [uuid4(), uuid4()]
+//   let delay_ms = 100 * pow(2, attempt) + random_int(0, 50)
+//
+// shuffle and choice take a List, Tuple, Set, Range or String (characters).
+//
+// ═══════════════════════════════════════════════════════════
+
+use std::cell::RefCell;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use crate::interpreter::{MapKey, Value};
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+// ── Registration ──────────────────────────────────────────────────────────────
+
+pub fn random_functions() -> Vec<&'static str> {
+    vec![
+        "random_int",
+        "random_float",
+        "random_bool",
+        "random_bytes",
+        "shuffle",
+        "choice",
+        "uuid4",
+        "random_seed",
+    ]
+}
+
+// ── Dispatch ──────────────────────────────────────────────────────────────────
+
+pub fn call_random(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    match name {
+        "random_int"   => random_int(args),
+        "random_float" => random_float(args),
+        "random_bool"  => {
+            let p = match args.first() {
+                Some(v) => number(v, "random_bool(p)")?,
+                None => 0.5,
+            };
+            if !(0.0..=1.0).contains(&p) {
+                return Err(format!("random_bool(p) — p must be between 0 and 1, got {}", p));
+            }
+            Ok(Value::Bool(with_rng(|rng| rng.gen_bool(p))))
+        }
+        "random_bytes" => {
+            let n = match args.first() {
+                Some(Value::Int(n)) if *n >= 0 => *n as usize,
+                _ => return Err("random_bytes(n) requires a non-negative Int".into()),
+            };
+            let bytes = with_rng(|rng| (0..n).map(|_| Value::Int(rng.gen::<u8>() as i64)).collect());
+            Ok(Value::list(bytes))
+        }
+        "shuffle" => {
+            let mut items = elements(args.first(), "shuffle(list)")?;
+            with_rng(|rng| items.shuffle(rng));
+            Ok(Value::list(items))
+        }
+        "choice" => {
+            let items = elements(args.first(), "choice(list)")?;
+            Ok(Value::Option(with_rng(|rng| items.choose(rng).cloned()).map(Box::new)))
+        }
+        "uuid4" => {
+            let bytes = with_rng(|rng| rng.gen::<[u8; 16]>());
+            Ok(Value::str(uuid::Builder::from_random_bytes(bytes).into_uuid().to_string()))
+        }
+        "random_seed" => {
+            let rng = match args.first() {
+                Some(Value::Int(n)) => StdRng::seed_from_u64(*n as u64),
+                Some(other) => return Err(format!("random_seed(n) requires an Int, got {}", other)),
+                None => StdRng::from_entropy(),
+            };
+            RNG.with(|cell| *cell.borrow_mut() = rng);
+            Ok(Value::Nil)
+        }
+        _ => Err(format!("Unknown random function '{}'", name)),
+    }
+}
+
+// ── Functions ─────────────────────────────────────────────────────────────────
+
+fn random_int(args: Vec<Value>) -> Result<Value, String> {
+    match (args.first(), args.get(1)) {
+        (Some(Value::Int(lo)), Some(Value::Int(hi))) if lo <= hi => {
+            let (lo, hi) = (*lo, *hi);
+            Ok(Value::Int(with_rng(|rng| rng.gen_range(lo..=hi))))
+        }
+        (Some(Value::Int(lo)), Some(Value::Int(hi))) => {
+            Err(format!("random_int(lo, hi) — lo must not exceed hi, got {} and {}", lo, hi))
+        }
+        _ => Err("random_int(lo, hi) requires two Ints".into()),
+    }
+}
+
+fn random_float(args: Vec<Value>) -> Result<Value, String> {
+    let sig = "random_float(lo, hi)";
+    let (lo, hi) = match args.as_slice() {
+        [] => (0.0, 1.0),
+        [lo, hi] => (number(lo, sig)?, number(hi, sig)?),
+        _ => return Err(format!("{} takes no arguments or two", sig)),
+    };
+    if lo >= hi || !(hi - lo).is_finite() {
+        return Err(format!("{} — needs finite lo < hi, got {} and {}", sig, lo, hi));
+    }
+    Ok(Value::Float(with_rng(|rng| rng.gen_range(lo..hi))))
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    RNG.with(|cell| f(&mut cell.borrow_mut()))
+}
+
+fn elements(arg: Option<&Value>, sig: &str) -> Result<Vec<Value>, String> {
+    match arg {
+        Some(Value::List(v)) => Ok(v.borrow().clone()),
+        Some(Value::Tuple(v)) => Ok(v.clone()),
+        Some(Value::Set(s)) => Ok(s.borrow().iter().map(MapKey::to_value).collect()),
+        Some(Value::Range(start, end, step)) => {
+            Ok((0..crate::iter::range_len(*start, *end, *step)).map(|i| Value::Int(start + i * step)).collect())
+        }
+        Some(Value::Str(s)) => Ok(s.chars().map(|c| Value::str(c.to_string())).collect()),
+        Some(other) => Err(format!("{} — expected a List, Tuple, Set, Range or String, got {}", sig, other)),
+        None => Err(format!("{} requires 1 argument", sig)),
+    }
+}
+
+fn number(v: &Value, sig: &str) -> Result<f64, String> {
+    match v {
+        Value::Int(n) => Ok(*n as f64),
+        Value::Float(f) => Ok(*f),
+        other => Err(format!("{} — expected a number, got {}", sig, other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::eval;
+
+    fn show(src: &str) -> String {
+        format!("{}", eval(src).unwrap())
+    }
+
+    #[test]
+    fn a_seed_repeats_every_draw() {
+        let draws = "[random_int(1, 6), random_float(), uuid4(), shuffle(0..5), choice(\"abc\")]";
+        let src = format!("random_seed(7)\nlet a = {0}\nrandom_seed(7)\nstr(a) == str({0})", draws);
+        assert_eq!(show(&src), "true");
+    }
+
+    #[test]
+    fn draws_stay_in_range() {
+        assert_eq!(show("(0..200).map(|_| random_int(3, 4)).all(|n| n == 3 || n == 4)"), "true");
+        assert_eq!(show("(0..200).map(|_| random_float(-1, 1)).all(|x| x >= -1 && x < 1)"), "true");
+        assert_eq!(show("[random_int(5, 5), len(random_bytes(4)), choice([]), random_bool(0)]"), "[5, 4, nil, false]");
+        assert_eq!(show("let l = [1, 2, 3]\nshuffle(l)\nl"), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn bad_bounds_are_errors() {
+        assert_eq!(eval("random_int(6, 1)").unwrap_err(), "random_int(lo, hi) — lo must not exceed hi, got 6 and 1");
+        assert_eq!(eval("random_float(1, 1)").unwrap_err(), "random_float(lo, hi) — needs finite lo < hi, got 1 and 1");
+        assert_eq!(eval("random_bool(2)").unwrap_err(), "random_bool(p) — p must be between 0 and 1, got 2");
+        assert_eq!(eval("shuffle(3)").unwrap_err(), "shuffle(list) — expected a List, Tuple, Set, Range or String, got 3");
+    }
+}
@@ -0,0 +1,181 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr Regex — pattern matching over strings
+// ═══════════════════════════════════════════════════════════
+//
+// QUICK REFERENCE
+//
+//   regex_compile(pat)             String → Result<Regex, String>
+//   regex_match(re, s)             first match, or nil
+//   regex_find_all(re, s)          List of every match
+//   regex_replace(re, s, with)     String, every match replaced
+//   regex_split(re, s)             List<String>
+//
+// `re` is a compiled Regex or a pattern string; compile once and reuse
+// the value when a pattern runs in a loop. A bad pattern string is an
+// error; regex_compile hands it back as Err instead.
+//
+// A match is the matched text when the pattern has no groups, the group's
+// text for one group, a tuple of group texts for several, and a Map when
+// any group is named: names for named groups, numbers for the rest.
+// Groups that did not take part are nil.
+//
+//   let re = regex_compile("(?P<key>\\w+)=(?P<val>\\d+)").unwrap()
+//   regex_find_all(re, "a=1 b=2")         [{key: a, val: 1}, {key: b, val: 2}]
+//   regex_find_all("\\d+", "a=1 b=22")    [1, 22]
+//   regex_replace("(\\w+)@(\\w+)", s, "$2 at ${1}")
+//
+// Replacements use $1 / ${1} / $name / ${name}; $$ is a literal dollar.
+//
+// ═══════════════════════════════════════════════════════════
+
+use std::rc::Rc;
+use indexmap::IndexMap;
+use regex::{Captures, Regex};
+use crate::interpreter::{MapKey, Value};
+
+// ── Registration ──────────────────────────────────────────────────────────────
+
+pub fn regex_functions() -> Vec<&'static str> {
+    vec![
+        "regex_compile",
+        "regex_match",
+        "regex_find_all",
+        "regex_replace",
+        "regex_split",
+    ]
+}
+
+// ── Dispatch ──────────────────────────────────────────────────────────────────
+
+pub fn call_regex(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    match name {
+        "regex_compile"  => regex_compile(args),
+        "regex_match"    => regex_match(args),
+        "regex_find_all" => regex_find_all(args),
+        "regex_replace"  => regex_replace(args),
+        "regex_split"    => regex_split(args),
+        _                => Err(format!("Unknown regex function '{}'", name)),
+    }
+}
+
+// ── Functions ─────────────────────────────────────────────────────────────────
+
+fn regex_compile(args: Vec<Value>) -> Result<Value, String> {
+    match args.first() {
+        Some(Value::Str(pat)) => Ok(Value::Result(match Regex::new(pat) {
+            Ok(re) => Ok(Box::new(Value::Regex(Rc::new(re)))),
+            Err(e) => Err(Box::new(Value::str(e.to_string()))),
+        })),
+        _ => Err("regex_compile(pattern) requires a String".into()),
+    }
+}
+
+fn regex_match(args: Vec<Value>) -> Result<Value, String> {
+    let re = regex_arg(&args, "regex_match(re, text)")?;
+    let text = text_arg(&args, 1, "regex_match(re, text)")?;
+    Ok(Value::Option(re.captures(text).map(|caps| Box::new(match_value(&re, &caps)))))
+}
+
+fn regex_find_all(args: Vec<Value>) -> Result<Value, String> {
+    let re = regex_arg(&args, "regex_find_all(re, text)")?;
+    let text = text_arg(&args, 1, "regex_find_all(re, text)")?;
+    let found = re.captures_iter(text).map(|caps| match_value(&re, &caps)).collect();
+    Ok(Value::list(found))
+}
+
+fn regex_replace(args: Vec<Value>) -> Result<Value, String> {
+    let sig = "regex_replace(re, text, replacement)";
+    let re = regex_arg(&args, sig)?;
+    let text = text_arg(&args, 1, sig)?;
+    let with = text_arg(&args, 2, sig)?;
+    Ok(Value::str(re.replace_all(text, with).into_owned()))
+}
+
+fn regex_split(args: Vec<Value>) -> Result<Value, String> {
+    let re = regex_arg(&args, "regex_split(re, text)")?;
+    let text = text_arg(&args, 1, "regex_split(re, text)")?;
+    let parts = re.split(text).map(|p| Value::str(p.to_string())).collect();
+    Ok(Value::list(parts))
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+/// What a match is worth to a script; see the quick reference above.
+fn match_value(re: &Regex, caps: &Captures) -> Value {
+    let group = |i: usize| caps.get(i).map(|m| Value::str(m.as_str().to_string())).unwrap_or(Value::Nil);
+    let names: Vec<Option<&str>> = re.capture_names().skip(1).collect();
+    if names.iter().any(Option::is_some) {
+        let mut map = IndexMap::new();
+        for (i, name) in names.iter().enumerate() {
+            let key = match name {
+                Some(n) => MapKey::from(*n),
+                None => MapKey::Int(i as i64 + 1),
+            };
+            map.insert(key, group(i + 1));
+        }
+        return Value::map(map);
+    }
+    match names.len() {
+        0 => group(0),
+        1 => group(1),
+        n => Value::Tuple((1..=n).map(group).collect()),
+    }
+}
+
+fn regex_arg(args: &[Value], sig: &str) -> Result<Rc<Regex>, String> {
+    match args.first() {
+        Some(Value::Regex(re)) => Ok(re.clone()),
+        Some(Value::Str(pat)) => Regex::new(pat).map(Rc::new).map_err(|e| format!("{}: {}", sig, e)),
+        Some(other) => Err(format!("{} — expected a Regex or pattern String, got {}", sig, other)),
+        None => Err(format!("{} — argument 1 is required", sig)),
+    }
+}
+
+fn text_arg<'a>(args: &'a [Value], idx: usize, sig: &str) -> Result<&'a str, String> {
+    match args.get(idx) {
+        Some(Value::Str(s)) => Ok(s),
+        Some(other) => Err(format!("{} — argument {} must be a String, got {}", sig, idx + 1, other)),
+        None => Err(format!("{} — argument {} is required", sig, idx + 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::eval;
+
+    fn show(src: &str) -> String {
+        format!("{}", eval(src).unwrap())
+    }
+
+    #[test]
+    fn matches_take_the_shape_of_the_groups() {
+        assert_eq!(show(r#"regex_find_all("\\d+", "x1y23")"#), "[1, 23]");
+        assert_eq!(show(r#"regex_match("(\\w)(\\d)", "ab1")"#), "Some((b, 1))");
+        assert_eq!(show(r#"regex_find_all("(?P<k>\\w+)=(\\d+)", "a=1 b=22")"#), "[{k: a, 2: 1}, {k: b, 2: 22}]");
+        assert_eq!(show(r#"regex_match("z", "abc")"#), "nil");
+    }
+
+    #[test]
+    fn compiled_regexes_and_patterns_work_alike() {
+        let src = r#"let re = regex_compile(",\\s*").unwrap()
+[regex_split(re, "a, b,c"), regex_split(",\\s*", "a, b,c")]"#;
+        assert_eq!(show(src), "[[a, b, c], [a, b, c]]");
+    }
+
+    #[test]
+    fn replacements_refer_to_groups() {
+        assert_eq!(show(r#"regex_replace("(\\w+)@(\\w+)", "bob@host", "$2 at ${1}")"#), "host at bob");
+        assert_eq!(show(r#"regex_replace("(?P<n>\\d)", "a1", "$$${n}")"#), "a$1");
+    }
+
+    #[test]
+    fn bad_patterns_are_errors() {
+        assert!(show(r#"regex_compile("(")"#).starts_with("Err(regex parse error"));
+        let err = eval(r#"regex_match("(", "x")"#).unwrap_err();
+        assert!(err.starts_with("regex_match(re, text): regex parse error"), "{}", err);
+        let err = eval(r#"regex_split(1, "x")"#).unwrap_err();
+        assert_eq!(err, "regex_split(re, text) — expected a Regex or pattern String, got 1");
+        let err = eval(r#"regex_replace("a", "b")"#).unwrap_err();
+        assert_eq!(err, "regex_replace(re, text, replacement) — argument 3 is required");
+    }
+}
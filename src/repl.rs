@@ -0,0 +1,387 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr REPL — line editing, history, completion and commands
+// ═══════════════════════════════════════════════════════════
+
+use std::borrow::Cow;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Instant;
+
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper};
+
+use crate::ast::{Stmt, StmtKind};
+use crate::interpreter::{value_type_name, Interpreter, Signal, Value};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::{stdlib, typeck};
+
+const KEYWORDS: &[&str] = &[
+    "fun", "let", "var", "if", "else", "elif", "while", "for", "in", "return", "struct", "enum",
+    "impl", "trait", "match", "import", "pub", "priv", "mod", "break", "continue", "new", "ref",
+    "box", "type", "true", "false", "nil", "Int", "Float", "Bool", "String", "Nil",
+];
+
+const COMMANDS: &[&str] = &[":help", ":clear", ":quit", ":type", ":env", ":load", ":save", ":time"];
+
+const HISTORY_FILE: &str = ".zephyr_history";
+
+pub fn repl() {
+    println!("\x1b[36m");
+    println!("  ███████╗███████╗██████╗ ██╗  ██╗██╗   ██╗██████╗ ");
+    println!("  ╚══███╔╝██╔════╝██╔══██╗██║  ██║╚██╗ ██╔╝██╔══██╗");
+    println!("    ███╔╝ █████╗  ██████╔╝███████║ ╚████╔╝ ██████╔╝");
+    println!("   ███╔╝  ██╔══╝  ██╔═══╝ ██╔══██║  ╚██╔╝  ██╔══██╗");
+    println!("  ███████╗███████╗██║     ██║  ██║   ██║   ██║  ██║");
+    println!("  ╚══════╝╚══════╝╚═╝     ╚═╝  ╚═╝   ╚═╝   ╚═╝  ╚═╝");
+    println!("\x1b[0m");
+    println!("  \x1b[90mThe Zephyr Programming Language v0.9.9\x1b[0m");
+    println!("  \x1b[90mType :help for help, :quit to exit\x1b[0m");
+    println!();
+
+    let mut editor: Editor<ReplHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(e) => { eprintln!("\x1b[31m[error]\x1b[0m cannot start the line editor: {}", e); return; }
+    };
+    editor.set_helper(Some(ReplHelper::default()));
+    let history = history_path();
+    if let Some(path) = &history {
+        let _ = editor.load_history(path);
+    }
+
+    let interp = Interpreter::new();
+    let mut session = Session { interp, entries: Vec::new() };
+
+    loop {
+        if let Some(helper) = editor.helper_mut() {
+            helper.refresh(&session.interp);
+        }
+        let line = match editor.readline("zph> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => { eprintln!("Read error: {}", e); break; }
+        };
+        let input = line.trim();
+        if input.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(input);
+        if let Some(path) = &history {
+            let _ = editor.save_history(path);
+        }
+
+        let (command, arg) = match input.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (input, ""),
+        };
+        match command {
+            ":quit" | ":q" | ":exit" => break,
+            ":help" | ":h" => print_help(),
+            ":clear" => { print!("\x1b[2J\x1b[H"); io::stdout().flush().unwrap(); }
+            ":env" => session.show_env(),
+            ":type" => session.show_type(arg),
+            ":load" => session.load(arg),
+            ":save" => session.save(arg),
+            ":time" => {
+                let start = Instant::now();
+                session.eval(arg);
+                println!("\x1b[90m({:.2?})\x1b[0m", start.elapsed());
+            }
+            _ if command.starts_with(':') => {
+                eprintln!("\x1b[31m[error]\x1b[0m unknown command {}; :help lists them", command);
+            }
+            _ => session.eval(input),
+        }
+    }
+    println!("Goodbye!");
+}
+
+/// `~/.zephyr_history`, when there is a home directory to keep it in.
+fn history_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(HISTORY_FILE))
+}
+
+fn parse(source: &str) -> Result<Vec<Stmt>, String> {
+    let tokens = Lexer::new(source).tokenize().map_err(|e| format!("\x1b[31m[lex error]\x1b[0m {}", e))?;
+    Parser::new(tokens).parse_program().map_err(|e| format!("\x1b[31m[parse error]\x1b[0m {}", e))
+}
+
+/// Whether `source` fails to parse only because it stops early, as when
+/// a line opens a block that later lines close.
+fn incomplete(source: &str) -> bool {
+    let mut lexer = Lexer::new(source);
+    match lexer.tokenize() {
+        Ok(tokens) => {
+            let mut parser = Parser::new(tokens);
+            parser.parse_program().is_err() && parser.at_end()
+        }
+        Err(_) => lexer.at_end(),
+    }
+}
+
+// ── Session ──────────────────────────────────────────────────────────────────
+
+struct Session {
+    interp: Interpreter,
+    // source of every entry that ran cleanly, for :type and :save
+    entries: Vec<String>,
+}
+
+impl Session {
+    fn eval(&mut self, source: &str) {
+        if self.run(source) {
+            self.entries.push(source.to_string());
+        }
+    }
+
+    /// Run `source` and print its value or error; true if it ran cleanly.
+    fn run(&mut self, source: &str) -> bool {
+        let ast = match parse(source) {
+            Ok(ast) => ast,
+            Err(e) => { eprintln!("{}", e); return false; }
+        };
+        match self.interp.run(ast) {
+            Ok(Value::Nil)               => return true,
+            Ok(val)                      => { println!("\x1b[32m=> {}\x1b[0m", val); return true; }
+            Err(Signal::Return(v))       => { println!("\x1b[32m=> {}\x1b[0m", v); return true; }
+            Err(Signal::TailCall(_))     => return true,
+            Err(Signal::Error(e))        => eprintln!("\x1b[31m[runtime error]\x1b[0m {}", e),
+            Err(Signal::Located(e))      => eprintln!("\x1b[31m[runtime error]\x1b[0m {}", e.render(Some(source))),
+            Err(Signal::PropagateErr(v)) => eprintln!("\x1b[31m[error propagated]\x1b[0m {}", v),
+            Err(Signal::Break)           => eprintln!("\x1b[33m[warning]\x1b[0m break outside loop"),
+            Err(Signal::Continue)        => eprintln!("\x1b[33m[warning]\x1b[0m continue outside loop"),
+        }
+        false
+    }
+
+    fn show_env(&self) {
+        let global = &self.interp.global;
+        let mut names: Vec<String> = global.names();
+        if names.is_empty() {
+            println!("  \x1b[90mnothing defined yet\x1b[0m");
+        }
+        names.sort();
+        for name in names {
+            if let Some(val) = global.get(&name) {
+                println!("  {}: \x1b[33m{}\x1b[0m = {}", name, value_type_name(&val), val);
+            }
+        }
+    }
+
+    /// The static type of an expression, given everything entered so far.
+    fn show_type(&self, source: &str) {
+        let session = match parse(&self.entries.join("\n")) {
+            Ok(stmts) => stmts,
+            Err(e) => { eprintln!("{}", e); return; }
+        };
+        match parse(source).as_deref() {
+            Ok([Stmt { kind: StmtKind::Expr(expr), .. }]) => {
+                println!("\x1b[33m{}\x1b[0m", typeck::type_of(&session, expr));
+            }
+            Ok(_) => eprintln!("\x1b[31m[error]\x1b[0m usage: :type <expression>"),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    /// Run a file into the session, as if its source had been typed in.
+    fn load(&mut self, path: &str) {
+        if path.is_empty() {
+            eprintln!("\x1b[31m[error]\x1b[0m usage: :load <file.zph>");
+            return;
+        }
+        let source = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => { eprintln!("\x1b[31m[error]\x1b[0m cannot read {}: {}", path, e); return; }
+        };
+        // imports and error positions inside the file are relative to it
+        let file = Some(Rc::new(PathBuf::from(path)));
+        let outer = std::mem::replace(&mut self.interp.current_file, file);
+        if self.run(&source) {
+            self.entries.push(source.trim_end().to_string());
+            println!("\x1b[90mloaded {}\x1b[0m", path);
+        }
+        self.interp.current_file = outer;
+    }
+
+    /// Write every entry that ran cleanly to a file that replays the session.
+    fn save(&self, path: &str) {
+        if path.is_empty() {
+            eprintln!("\x1b[31m[error]\x1b[0m usage: :save <file.zph>");
+            return;
+        }
+        let mut source = self.entries.join("\n");
+        source.push('\n');
+        match fs::write(path, source) {
+            Ok(()) => println!("\x1b[90msaved {} entries to {}\x1b[0m", self.entries.len(), path),
+            Err(e) => eprintln!("\x1b[31m[error]\x1b[0m cannot write {}: {}", path, e),
+        }
+    }
+}
+
+// ── Line editor helper ───────────────────────────────────────────────────────
+
+/// Completion and multi-line input for the editor. Candidates are
+/// refreshed from the interpreter before each prompt.
+#[derive(Default)]
+struct ReplHelper {
+    globals: Vec<String>,
+    methods: Vec<String>,
+}
+
+impl ReplHelper {
+    fn refresh(&mut self, interp: &Interpreter) {
+        self.globals = interp.global.names().into_iter().chain(interp.builtins.names()).collect();
+        self.methods = stdlib::method_names().into_iter().map(String::from)
+            .chain(interp.impl_methods.values().flat_map(|methods| methods.keys().cloned()))
+            .collect();
+    }
+
+    /// Completions for the word that ends at `pos`, and where it starts.
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        if before.starts_with(':') && !before.contains(char::is_whitespace) {
+            let commands = COMMANDS.iter().filter(|c| c.starts_with(before));
+            return (0, commands.map(|c| c.to_string()).collect());
+        }
+        let start = before.char_indices().rev()
+            .find(|&(_, c)| !(c.is_alphanumeric() || c == '_'))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let word = &before[start..];
+        let pool: Vec<&str> = if before[..start].ends_with('.') {
+            self.methods.iter().map(String::as_str).collect()
+        } else {
+            KEYWORDS.iter().copied().chain(self.globals.iter().map(String::as_str)).collect()
+        };
+        let mut found: Vec<String> = pool.into_iter().filter(|c| c.starts_with(word)).map(String::from).collect();
+        found.sort();
+        found.dedup();
+        (start, found)
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let input = ctx.input();
+        // an empty line sends what there is, so a typo can't hold the prompt open
+        if input.starts_with(':') || input.ends_with('\n') || !incomplete(input) {
+            Ok(ValidationResult::Valid(None))
+        } else {
+            Ok(ValidationResult::Incomplete)
+        }
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _default: bool) -> Cow<'b, str> {
+        Cow::Owned(format!("\x1b[36m{}\x1b[0m", prompt))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Helper for ReplHelper {}
+
+// ── Help ─────────────────────────────────────────────────────────────────────
+
+fn print_help() {
+    println!();
+    println!("  \x1b[1mZephyr Language Quick Reference\x1b[0m");
+    println!();
+    println!("  \x1b[33mCLI commands:\x1b[0m");
+    println!("    zephyr run <file.zph>          Run source file");
+    println!("    zephyr <file.zph>              Shorthand for run");
+    println!("    zephyr <file.zphc>             Run compiled bytecode");
+    println!("    zephyr compile <file.zph>      Compile → .zphc + native executable");
+    println!("    zephyr compile -o <stem> <f>   Custom output name (no extension)");
+    println!("    zephyr check <file.zph>        Type-check without running");
+    println!("    zephyr repl                    Start REPL");
+    println!();
+    println!("  \x1b[33mVariables:\x1b[0m");
+    println!("    let x = 42          // immutable");
+    println!("    var y = \"hello\"     // mutable");
+    println!();
+    println!("  \x1b[33mFunctions:\x1b[0m");
+    println!("    fun add(a: Int, b: Int) -> Int {{ a + b }}");
+    println!("    fun greet(name) {{ println(\"Hello, #{{name}}!\") }}");
+    println!();
+    println!("  \x1b[33mTypes:\x1b[0m  Int, Float, Bool, String, Nil");
+    println!("  \x1b[33mNullable:\x1b[0m  Option<T>,  Result<T, E>");
+    println!();
+    println!("  \x1b[33mControl flow:\x1b[0m");
+    println!("    if x > 0 {{ ... }} elif x == 0 {{ ... }} else {{ ... }}");
+    println!("    while cond {{ ... }}     for i in 0..10 {{ ... }}");
+    println!("    match val {{ pattern => expr, _ => fallback }}");
+    println!();
+    println!("  \x1b[33mCollections:\x1b[0m");
+    println!("    let list  = [1, 2, 3]");
+    println!("    let map   = {{\"key\": value}}");
+    println!("    let tuple = (1, \"hello\", true)");
+    println!();
+    println!("  \x1b[33mStructs & Enums:\x1b[0m");
+    println!("    struct Point {{ x: Int, y: Int }}");
+    println!("    enum Shape {{ Circle(Float), Rect(Float, Float) }}");
+    println!();
+    println!("  \x1b[33mAsync:\x1b[0m");
+    println!("    let t  = async_http_get(url)       // spawn task");
+    println!("    let r  = async_await(t)             // await result");
+    println!("    let rs = async_await_all([t1, t2])  // await all");
+    println!("    let ch = channel()                  // create channel");
+    println!("    channel_send(ch, value)  channel_recv(ch)");
+    println!();
+    println!("  \x1b[33mREPL commands:\x1b[0m");
+    println!("    :type <expr>        Static type of an expression");
+    println!("    :env                Globals defined so far");
+    println!("    :load <file.zph>    Run a file into the session");
+    println!("    :save <file.zph>    Write the session's entries to a file");
+    println!("    :time <code>        Run code and show how long it took");
+    println!("    :help  :clear  :quit");
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_continues_only_while_the_parser_runs_out() {
+        for src in ["fun f() {", "if x {\n  1", "let s = \"abc", "/* note", "let xs = [1,\n 2,"] {
+            assert!(incomplete(src), "{:?} should continue", src);
+        }
+        for src in ["1 + 2", "fun f() { 1 }", "let = 3", "f(1))", "let s = \"abc\""] {
+            assert!(!incomplete(src), "{:?} should be sent", src);
+        }
+    }
+
+    #[test]
+    fn completes_commands_keywords_globals_and_methods() {
+        let mut interp = Interpreter::new();
+        interp.run(parse("var total = 1\nstruct P { x: Int }\nimpl P { fun norm(self) { 0 } }").unwrap()).unwrap();
+        let mut helper = ReplHelper::default();
+        helper.refresh(&interp);
+        assert_eq!(helper.candidates(":t", 2), (0, vec![":type".to_string(), ":time".to_string()]));
+        assert_eq!(helper.candidates("x = tot", 7), (4, vec!["total".to_string()]));
+        assert_eq!(helper.candidates("whi", 3).1, vec!["while"]);
+        assert_eq!(helper.candidates("s.starts_w", 10), (2, vec!["starts_with".to_string()]));
+        assert_eq!(helper.candidates("p.no", 4).1, vec!["norm"]);
+    }
+}
@@ -0,0 +1,407 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr Resolver — fixed slots for local variables
+// ═══════════════════════════════════════════════════════════
+//
+// Runs over a parsed script or module before the tree-walker does, so
+// that local variables live in a Vec per scope instead of a HashMap per
+// scope searched name by name. It fills in the annotations the parser
+// leaves empty:
+//
+//   Scope on every function, block, loop body and match arm
+//     Slots     the names the scope binds, in slot order (parameters
+//               first), and whether a closure made inside can keep it
+//     Inline    the scope binds nothing, so it runs in the enclosing
+//               one and costs nothing to enter
+//   VarRes on every variable
+//     Slot      how many scopes out it is, and which slot
+//     Dynamic   left to the lookup by name: globals, module items,
+//               anything past a `mod` or an `import`
+//
+// The tree-walker defines names as it runs, and the rules here follow
+// it rather than change it:
+//
+//   - `let`, patterns, `fun` and `mod` bind in the scope they are in; an
+//     assignment binds in its own scope when no enclosing scope has the
+//     name, just as an assignment to an unknown name does at run time.
+//   - A variable is the innermost binding of its name. Within one
+//     function, bindings further down the source are not there yet;
+//     from a nested function they may be, since it can run later.
+//   - A slot that has not been defined yet (a closure called before the
+//     variable it uses) falls back to the lookup by name, which gives
+//     the answer the tree-walker always gave.
+//   - A loop body that nothing can capture keeps one scope and clears it
+//     between iterations instead of making a new one for each.
+//
+// Default arguments are evaluated in the caller's scope, so they are
+// left unresolved, as is anything compiled to bytecode.
+//
+// ═══════════════════════════════════════════════════════════
+
+use std::collections::HashSet;
+
+use crate::ast::*;
+use crate::compiler::pattern_names;
+
+/// Resolve a script or module body, which binds its own names by name.
+/// `is_global` says whether a name is already bound in the global scope.
+pub fn resolve(stmts: &mut [Stmt], is_global: &dyn Fn(&str) -> bool) {
+    let mut r = Resolver { frames: Vec::new(), made: 0, is_global };
+    r.named(stmts);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    // a script, module or `mod` body: looked up by name
+    Named,
+    Fun,
+    Block,
+    // binds nothing and runs in the enclosing scope
+    Inline,
+}
+
+struct Frame {
+    kind: Kind,
+    // slot order; for Named, every name bound anywhere in it
+    names: Vec<String>,
+    // the names whose binding the walk has passed
+    bound: HashSet<String>,
+    // holds an `import`, which binds names nobody can see coming
+    opaque: bool,
+}
+
+struct Resolver<'a> {
+    frames: Vec<Frame>,
+    // closures, functions and modules made so far
+    made: usize,
+    is_global: &'a dyn Fn(&str) -> bool,
+}
+
+impl Resolver<'_> {
+    fn named(&mut self, stmts: &mut [Stmt]) {
+        let mut direct = Direct::default();
+        stmts.iter().for_each(|s| direct.stmt(s));
+        let mut names = direct.names;
+        names.extend(direct.assigned);
+        self.frames.push(Frame { kind: Kind::Named, names, bound: HashSet::new(), opaque: false });
+        self.stmts(stmts);
+        self.frames.pop();
+    }
+
+    /// Push the scope for a body that binds `params` on entry and runs
+    /// `stmts` and then `exprs`; pop it with `leave`.
+    fn enter(&mut self, fun: bool, params: Vec<String>, stmts: &[Stmt], exprs: &[&Expr]) -> Scope {
+        let mut direct = Direct::default();
+        stmts.iter().for_each(|s| direct.stmt(s));
+        exprs.iter().for_each(|e| direct.expr(e));
+
+        let mut names: Vec<String> = Vec::new();
+        for n in params.iter().chain(&direct.names) {
+            if !names.contains(n) { names.push(n.clone()); }
+        }
+        for n in &direct.assigned {
+            if !names.contains(n) && !self.exists(n) { names.push(n.clone()); }
+        }
+        // an assignment in an inline block must not be able to bind
+        let inline = !fun && names.is_empty() && !direct.other && !direct.opaque
+            && direct.assigned.iter().all(|n| self.settled(n));
+
+        let kind = if fun { Kind::Fun } else if inline { Kind::Inline } else { Kind::Block };
+        let scope = if inline { Scope::Inline } else { Scope::Slots(names.clone().into(), false) };
+        self.frames.push(Frame { kind, names, bound: params.into_iter().collect(), opaque: direct.opaque });
+        scope
+    }
+
+    /// Pop the scope `enter` pushed, noting whether anything made inside
+    /// since `made` can keep it.
+    fn leave(&mut self, scope: &mut Scope, made: usize) {
+        self.frames.pop();
+        if let Scope::Slots(_, captured) = scope {
+            *captured = self.made > made;
+        }
+    }
+
+    /// Where a variable used here lives.
+    fn lookup(&self, name: &str) -> VarRes {
+        let mut depth = 0;
+        // past a function boundary, a binding further down may have run
+        let mut crossed = false;
+        for f in self.frames.iter().rev() {
+            match f.kind {
+                Kind::Named => return VarRes::Dynamic,
+                Kind::Inline => {}
+                Kind::Fun | Kind::Block => {
+                    if let Some(slot) = f.names.iter().position(|n| n == name) {
+                        if crossed || f.bound.contains(name) {
+                            return VarRes::Slot { depth, slot: slot as u32 };
+                        }
+                    }
+                    if f.opaque { return VarRes::Dynamic; }
+                    depth += 1;
+                    crossed |= f.kind == Kind::Fun;
+                }
+            }
+        }
+        VarRes::Dynamic
+    }
+
+    /// Whether assigning `name` in a scope about to be entered could find
+    /// an existing variable instead of binding a new one.
+    fn exists(&self, name: &str) -> bool {
+        let mut crossed = false;
+        for f in self.frames.iter().rev() {
+            let has = f.names.iter().any(|n| n == name);
+            match f.kind {
+                Kind::Named => if has { return true },
+                Kind::Inline => {}
+                Kind::Fun | Kind::Block => {
+                    if has && (crossed || f.bound.contains(name)) { return true; }
+                    if f.opaque { return true; }
+                    crossed |= f.kind == Kind::Fun;
+                }
+            }
+        }
+        (self.is_global)(name)
+    }
+
+    /// Whether `name` is bound for certain at this point of the current
+    /// function, so that assigning it never binds.
+    fn settled(&self, name: &str) -> bool {
+        for f in self.frames.iter().rev() {
+            match f.kind {
+                Kind::Inline => {}
+                Kind::Fun | Kind::Block => {
+                    if f.bound.contains(name) { return true; }
+                    if f.kind == Kind::Fun || f.opaque { return false; }
+                }
+                Kind::Named => return false,
+            }
+        }
+        false
+    }
+
+    fn bind(&mut self, name: &str) {
+        if let Some(f) = self.frames.last_mut() {
+            if f.names.iter().any(|n| n == name) {
+                f.bound.insert(name.to_string());
+            }
+        }
+    }
+
+    fn stmts(&mut self, stmts: &mut [Stmt]) {
+        for s in stmts { self.stmt(s); }
+    }
+
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        match &mut stmt.kind {
+            StmtKind::Let(pat, _, value, _) => {
+                self.expr(value);
+                let mut names = Vec::new();
+                pattern_names(pat, &mut names);
+                names.iter().for_each(|n| self.bind(n));
+            }
+            StmtKind::Expr(e) | StmtKind::Return(Some(e)) => self.expr(e),
+            StmtKind::While(cond, body, scope) => {
+                self.expr(cond);
+                let made = self.made;
+                *scope = self.enter(false, Vec::new(), body, &[]);
+                self.stmts(body);
+                self.leave(scope, made);
+            }
+            StmtKind::For(pat, iter, body, scope) => {
+                self.expr(iter);
+                let mut names = Vec::new();
+                pattern_names(pat, &mut names);
+                let made = self.made;
+                *scope = self.enter(false, names, body, &[]);
+                self.stmts(body);
+                self.leave(scope, made);
+            }
+            StmtKind::FunDef(f) => {
+                self.bind(&f.name);
+                self.function(f);
+            }
+            StmtKind::ImplBlock(block) => block.methods.iter_mut().for_each(|m| self.function(m)),
+            StmtKind::TraitDef(def) => def.defaults.iter_mut().for_each(|m| self.function(m)),
+            StmtKind::ModDef(name, body, _) => {
+                self.made += 1;
+                self.named(body);
+                self.bind(name);
+            }
+            StmtKind::Return(None) | StmtKind::Break | StmtKind::Continue | StmtKind::StructDef(_)
+            | StmtKind::EnumDef(_) | StmtKind::Import(_) | StmtKind::TypeAlias(..) => {}
+        }
+    }
+
+    fn function(&mut self, f: &mut FunDef) {
+        self.made += 1;
+        let params = f.params.iter().map(|p| p.name.clone()).collect();
+        let made = self.made;
+        f.scope = self.enter(true, params, &f.body, &[]);
+        self.stmts(&mut f.body);
+        self.leave(&mut f.scope, made);
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::Var(name, res) => *res = self.lookup(name),
+            ExprKind::Assign(target, value) => {
+                self.expr(value);
+                match &mut target.kind {
+                    ExprKind::Var(name, res) => {
+                        self.bind(name);
+                        *res = self.lookup(name);
+                    }
+                    _ => self.expr(target),
+                }
+            }
+            ExprKind::Block(stmts, tail, scope) => {
+                let made = self.made;
+                *scope = self.enter(false, Vec::new(), stmts, tail.as_deref().as_slice());
+                self.stmts(stmts);
+                if let Some(t) = tail { self.expr(t); }
+                self.leave(scope, made);
+            }
+            ExprKind::Closure(params, body, scope) => {
+                self.made += 1;
+                let names = params.iter().map(|(n, _)| n.clone()).collect();
+                let made = self.made;
+                *scope = self.enter(true, names, &[], &[&**body]);
+                self.expr(body);
+                self.leave(scope, made);
+            }
+            ExprKind::Match(subject, arms) => {
+                self.expr(subject);
+                for arm in arms {
+                    let mut names = Vec::new();
+                    pattern_names(&arm.pattern, &mut names);
+                    let made = self.made;
+                    let exprs: Vec<&Expr> = arm.guard.iter().chain([&arm.body]).collect();
+                    arm.scope = self.enter(false, names, &[], &exprs);
+                    if let Some(g) = &mut arm.guard { self.expr(g); }
+                    self.expr(&mut arm.body);
+                    self.leave(&mut arm.scope, made);
+                }
+            }
+            ExprKind::InterpolatedString(parts) => for p in parts {
+                if let StringPart::Interpolated(e, _) = p { self.expr(e) }
+            },
+            ExprKind::Tuple(es) | ExprKind::List(es) | ExprKind::SetLit(es) | ExprKind::EnumVariant(_, _, es) => {
+                es.iter_mut().for_each(|e| self.expr(e));
+            }
+            ExprKind::MapLit(pairs) => for (k, v) in pairs { self.expr(k); self.expr(v); },
+            ExprKind::BinOp(l, _, r) | ExprKind::Index(l, r) | ExprKind::Range(l, r) => { self.expr(l); self.expr(r); }
+            ExprKind::UnaryOp(_, e) | ExprKind::FieldAccess(e, _) | ExprKind::Some(e) | ExprKind::Ok(e) | ExprKind::Err(e)
+            | ExprKind::Question(e) | ExprKind::BoxExpr(e) | ExprKind::RefExpr(e) | ExprKind::Await(e) => self.expr(e),
+            ExprKind::Call(f, args) | ExprKind::MethodCall(f, _, args) => {
+                self.expr(f);
+                args.iter_mut().for_each(|a| self.expr(a));
+            }
+            ExprKind::If(c, t, elifs, els) => {
+                self.expr(c);
+                self.expr(t);
+                for (c, b) in elifs { self.expr(c); self.expr(b); }
+                if let Some(e) = els { self.expr(e); }
+            }
+            ExprKind::StructCreate(_, fields) => fields.iter_mut().for_each(|(_, e)| self.expr(e)),
+            ExprKind::Int(_) | ExprKind::Float(_) | ExprKind::Bool(_) | ExprKind::StringLit(_) | ExprKind::Nil | ExprKind::Path(_) => {}
+        }
+    }
+}
+
+/// What a scope binds itself, leaving out the scopes nested in it.
+#[derive(Default)]
+struct Direct {
+    names: Vec<String>,
+    // plain variables assigned to
+    assigned: Vec<String>,
+    // binds something other than variables (enum variants)
+    other: bool,
+    opaque: bool,
+}
+
+impl Direct {
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let(pat, _, value, _) => {
+                self.expr(value);
+                pattern_names(pat, &mut self.names);
+            }
+            StmtKind::Expr(e) | StmtKind::Return(Some(e)) | StmtKind::While(e, _, _) | StmtKind::For(_, e, _, _) => self.expr(e),
+            StmtKind::FunDef(f) => self.names.push(f.name.clone()),
+            StmtKind::ModDef(name, _, _) => self.names.push(name.clone()),
+            StmtKind::EnumDef(_) => self.other = true,
+            StmtKind::Import(_) => self.opaque = true,
+            _ => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Assign(target, value) => {
+                match &target.kind {
+                    ExprKind::Var(name, _) => if !self.assigned.contains(name) { self.assigned.push(name.clone()) },
+                    _ => self.expr(target),
+                }
+                self.expr(value);
+            }
+            // scopes of their own
+            ExprKind::Block(..) | ExprKind::Closure(..) => {}
+            ExprKind::Match(subject, _) => self.expr(subject),
+            ExprKind::InterpolatedString(parts) => for p in parts {
+                if let StringPart::Interpolated(e, _) = p { self.expr(e) }
+            },
+            ExprKind::Tuple(es) | ExprKind::List(es) | ExprKind::SetLit(es) | ExprKind::EnumVariant(_, _, es) => {
+                es.iter().for_each(|e| self.expr(e));
+            }
+            ExprKind::MapLit(pairs) => for (k, v) in pairs { self.expr(k); self.expr(v); },
+            ExprKind::BinOp(l, _, r) | ExprKind::Index(l, r) | ExprKind::Range(l, r) => { self.expr(l); self.expr(r); }
+            ExprKind::UnaryOp(_, e) | ExprKind::FieldAccess(e, _) | ExprKind::Some(e) | ExprKind::Ok(e) | ExprKind::Err(e)
+            | ExprKind::Question(e) | ExprKind::BoxExpr(e) | ExprKind::RefExpr(e) | ExprKind::Await(e) => self.expr(e),
+            ExprKind::Call(f, args) | ExprKind::MethodCall(f, _, args) => {
+                self.expr(f);
+                args.iter().for_each(|a| self.expr(a));
+            }
+            ExprKind::If(c, t, elifs, els) => {
+                self.expr(c);
+                self.expr(t);
+                for (c, b) in elifs { self.expr(c); self.expr(b); }
+                if let Some(e) = els { self.expr(e); }
+            }
+            ExprKind::StructCreate(_, fields) => fields.iter().for_each(|(_, e)| self.expr(e)),
+            ExprKind::Var(..) | ExprKind::Int(_) | ExprKind::Float(_) | ExprKind::Bool(_) | ExprKind::StringLit(_)
+            | ExprKind::Nil | ExprKind::Path(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn parse(src: &str) -> Vec<Stmt> {
+        let tokens = crate::lexer::Lexer::new(src).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse_program().unwrap()
+    }
+
+    #[test]
+    fn locals_get_slots_without_changing_meaning() {
+        let src = "fun sum(n) {\n var s = 0\n var i = 0\n while i < n {\n if i % 2 == 0 { s = s + i }\n i = i + 1 }\n s }\n\
+                   fun late() {\n let g = || y\n if true { x = 1 }\n let y = 5\n let x = 2\n [g(), x] }\n\
+                   fun loops() {\n var out = []\n var fs = []\n for i in 0..3 {\n if i > 0 { out.push(seen) }\n\
+                   let seen = i\n fs.push(|| seen) }\n [out, fs.map(|f| => f())] }\n\
+                   let seen = \"outer\"\nlet got = [sum(10), late(), loops()]\n";
+        let mut stmts = parse(src);
+        resolve(&mut stmts, &|_| false);
+        let StmtKind::FunDef(sum) = &stmts[0].kind else { panic!("expected a function") };
+        assert!(matches!(&sum.scope, Scope::Slots(names, false) if names[..] == ["n", "s", "i"]));
+        let StmtKind::While(cond, _, scope) = &sum.body[2].kind else { panic!("expected a loop") };
+        assert!(matches!(scope, Scope::Inline));
+        let ExprKind::BinOp(i, _, _) = &cond.kind else { panic!("expected a comparison") };
+        assert!(matches!(i.kind, ExprKind::Var(_, VarRes::Slot { depth: 0, slot: 2 })));
+
+        let mut interp = Interpreter::new();
+        interp.run(parse(src)).unwrap();
+        assert_eq!(format!("{}", interp.global.get("got").unwrap()), "[20, [5, 2], [[outer, outer], [0, 1, 2]]]");
+    }
+}
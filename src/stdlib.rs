@@ -1,627 +1,937 @@
-// ═══════════════════════════════════════════════════════════
-// Zephyr Standard Library — built-in functions and methods
-// ═══════════════════════════════════════════════════════════
-
-use std::rc::Rc;
-use std::cell::RefCell;
-use crate::interpreter::{Value, Env, ZephyrFn};
-use crate::net;
-use crate::json;
-use crate::process;
-use crate::zfs as fs;
-use crate::async_rt;
-
-pub fn register(env: &Env) {
-    let natives = [
-        // I/O
-        "print", "println", "input", "eprint", "eprintln",
-        // Type conversion
-        "int", "float", "str", "bool",
-        // Type checking
-        "type_of",
-        // Math
-        "abs", "sqrt", "pow", "min", "max", "floor", "ceil", "round",
-        // Collections
-        "len", "push", "pop", "range",
-        // Functional
-        "map", "filter", "reduce", "zip", "enumerate", "sorted",
-        // String
-        "split", "join", "trim",
-        // Option/Result
-        "some", "ok", "err", "unwrap",
-        // Misc
-        "assert", "panic", "exit",
-    ];
-    for name in natives {
-        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
-    }
-    for name in net::net_functions() {
-        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
-    }
-    for name in json::json_functions() {
-        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
-    }
-    for name in process::process_functions() {
-        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
-    }
-    for name in fs::fs_functions() {
-        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
-    }
-    // Async runtime
-    for name in async_rt::async_functions() {
-        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
-    }
-    for name in async_rt::async_http_functions() {
-        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
-    }
-}
-
-pub fn call_native(name: &str, args: Vec<Value>, _env: &Env) -> Result<Value, String> {
-    match name {
-        // ── I/O ─────────────────────────────────────────────────────────────
-
-        "print" => {
-            let parts: Vec<String> = args.iter().map(|v| format!("{}", v)).collect();
-            print!("{}", parts.join(" "));
-            Ok(Value::Nil)
-        }
-
-        "println" => {
-            let parts: Vec<String> = args.iter().map(|v| format!("{}", v)).collect();
-            println!("{}", parts.join(" "));
-            Ok(Value::Nil)
-        }
-
-        "eprint" => {
-            let parts: Vec<String> = args.iter().map(|v| format!("{}", v)).collect();
-            eprint!("{}", parts.join(" "));
-            Ok(Value::Nil)
-        }
-
-        "eprintln" => {
-            let parts: Vec<String> = args.iter().map(|v| format!("{}", v)).collect();
-            eprintln!("{}", parts.join(" "));
-            Ok(Value::Nil)
-        }
-
-        "input" => {
-            if let Some(prompt) = args.first() {
-                print!("{}", prompt);
-                use std::io::Write;
-                std::io::stdout().flush().ok();
-            }
-            let mut line = String::new();
-            std::io::stdin().read_line(&mut line).map_err(|e| e.to_string())?;
-            Ok(Value::Str(line.trim_end_matches('\n').to_string()))
-        }
-
-        // ── Type conversion ───────────────────────────────────────────────
-
-        "int" => {
-            let arg = args.into_iter().next().ok_or("int() requires 1 argument")?;
-            match arg {
-                Value::Int(n)   => Ok(Value::Int(n)),
-                Value::Float(f) => Ok(Value::Int(f as i64)),
-                Value::Str(s)   => s.trim().parse::<i64>()
-                    .map(Value::Int)
-                    .map_err(|_| format!("Cannot convert '{}' to Int", s)),
-                Value::Bool(b)  => Ok(Value::Int(if b { 1 } else { 0 })),
-                other => Err(format!("Cannot convert {} to Int", other))
-            }
-        }
-
-        "float" => {
-            let arg = args.into_iter().next().ok_or("float() requires 1 argument")?;
-            match arg {
-                Value::Float(f) => Ok(Value::Float(f)),
-                Value::Int(n)   => Ok(Value::Float(n as f64)),
-                Value::Str(s)   => s.trim().parse::<f64>()
-                    .map(Value::Float)
-                    .map_err(|_| format!("Cannot convert '{}' to Float", s)),
-                other => Err(format!("Cannot convert {} to Float", other))
-            }
-        }
-
-        "str" => {
-            let arg = args.into_iter().next().ok_or("str() requires 1 argument")?;
-            Ok(Value::Str(format!("{}", arg)))
-        }
-
-        "bool" => {
-            let arg = args.into_iter().next().ok_or("bool() requires 1 argument")?;
-            Ok(Value::Bool(crate::interpreter::is_truthy(&arg)))
-        }
-
-        "type_of" => {
-            let arg = args.into_iter().next().ok_or("type_of() requires 1 argument")?;
-            Ok(Value::Str(crate::interpreter::value_type_name(&arg)))
-        }
-
-        // ── Math ──────────────────────────────────────────────────────────
-
-        "abs" => {
-            let n = args.into_iter().next().ok_or("abs() requires 1 argument")?;
-            match n {
-                Value::Int(v)   => Ok(Value::Int(v.abs())),
-                Value::Float(f) => Ok(Value::Float(f.abs())),
-                other => Err(format!("abs() expects number, got {}", other))
-            }
-        }
-
-        "sqrt" => {
-            let n = args.into_iter().next().ok_or("sqrt() requires 1 argument")?;
-            match n {
-                Value::Int(v)   => Ok(Value::Float((v as f64).sqrt())),
-                Value::Float(f) => Ok(Value::Float(f.sqrt())),
-                other => Err(format!("sqrt() expects number, got {}", other))
-            }
-        }
-
-        "pow" => {
-            if args.len() < 2 { return Err("pow(base, exp) requires 2 arguments".into()); }
-            let base = to_f64(&args[0])?;
-            let exp = to_f64(&args[1])?;
-            Ok(Value::Float(base.powf(exp)))
-        }
-
-        "min" => {
-            if args.len() < 2 { return Err("min() requires at least 2 arguments".into()); }
-            let a = to_f64(&args[0])?;
-            let b = to_f64(&args[1])?;
-            if matches!(args[0], Value::Int(_)) && matches!(args[1], Value::Int(_)) {
-                Ok(Value::Int(a.min(b) as i64))
-            } else {
-                Ok(Value::Float(a.min(b)))
-            }
-        }
-
-        "max" => {
-            if args.len() < 2 { return Err("max() requires at least 2 arguments".into()); }
-            let a = to_f64(&args[0])?;
-            let b = to_f64(&args[1])?;
-            if matches!(args[0], Value::Int(_)) && matches!(args[1], Value::Int(_)) {
-                Ok(Value::Int(a.max(b) as i64))
-            } else {
-                Ok(Value::Float(a.max(b)))
-            }
-        }
-
-        "floor" => {
-            let n = to_f64(&args.into_iter().next().ok_or("floor() requires 1 argument")?)?;
-            Ok(Value::Int(n.floor() as i64))
-        }
-
-        "ceil" => {
-            let n = to_f64(&args.into_iter().next().ok_or("ceil() requires 1 argument")?)?;
-            Ok(Value::Int(n.ceil() as i64))
-        }
-
-        "round" => {
-            let n = to_f64(&args.into_iter().next().ok_or("round() requires 1 argument")?)?;
-            Ok(Value::Int(n.round() as i64))
-        }
-
-        // ── Collections ───────────────────────────────────────────────────
-
-        "len" => {
-            let arg = args.into_iter().next().ok_or("len() requires 1 argument")?;
-            match arg {
-                Value::List(v)  => Ok(Value::Int(v.borrow().len() as i64)),
-                Value::Str(s)   => Ok(Value::Int(s.chars().count() as i64)),
-                Value::Map(m)   => Ok(Value::Int(m.borrow().len() as i64)),
-                Value::Tuple(t) => Ok(Value::Int(t.len() as i64)),
-                other => Err(format!("len() not supported for {}", other))
-            }
-        }
-
-        "push" => {
-            if args.len() < 2 { return Err("push(list, item) requires 2 arguments".into()); }
-            if let Value::List(v) = &args[0] {
-                v.borrow_mut().push(args[1].clone());
-                Ok(Value::Nil)
-            } else {
-                Err("push() requires a List as first argument".into())
-            }
-        }
-
-        "pop" => {
-            let arg = args.into_iter().next().ok_or("pop() requires 1 argument")?;
-            if let Value::List(v) = arg {
-                Ok(v.borrow_mut().pop().unwrap_or(Value::Nil))
-            } else {
-                Err("pop() requires a List".into())
-            }
-        }
-
-        "range" => {
-            match args.len() {
-                1 => {
-                    let end = crate::interpreter::require_int(&args[0]).map_err(|e| format!("{:?}", e))?;
-                    let list: Vec<Value> = (0..end).map(Value::Int).collect();
-                    Ok(Value::List(Rc::new(RefCell::new(list))))
-                }
-                2 => {
-                    let start = crate::interpreter::require_int(&args[0]).map_err(|e| format!("{:?}", e))?;
-                    let end = crate::interpreter::require_int(&args[1]).map_err(|e| format!("{:?}", e))?;
-                    let list: Vec<Value> = (start..end).map(Value::Int).collect();
-                    Ok(Value::List(Rc::new(RefCell::new(list))))
-                }
-                3 => {
-                    let start = crate::interpreter::require_int(&args[0]).map_err(|e| format!("{:?}", e))?;
-                    let end = crate::interpreter::require_int(&args[1]).map_err(|e| format!("{:?}", e))?;
-                    let step = crate::interpreter::require_int(&args[2]).map_err(|e| format!("{:?}", e))?;
-                    if step == 0 { return Err("range() step cannot be 0".into()); }
-                    let step_abs = step.unsigned_abs() as usize;
-                    let list: Vec<Value> = if step > 0 {
-                        (start..end).step_by(step_abs).map(Value::Int).collect()
-                    } else {
-                        (end+1..=start).rev().step_by(step_abs).map(Value::Int).collect()
-                    };
-                    Ok(Value::List(Rc::new(RefCell::new(list))))
-                }
-                _ => Err("range() takes 1-3 arguments".into())
-            }
-        }
-
-        // ── Functional ────────────────────────────────────────────────────
-
-        "map" | "filter" | "reduce" | "zip" | "enumerate" | "sorted" => {
-            Err(format!("{} is better used as a method: list.{}(fn)", name, name))
-        }
-
-        // ── String ────────────────────────────────────────────────────────
-
-        "split" => {
-            if args.len() < 2 { return Err("split(str, sep) requires 2 arguments".into()); }
-            if let (Value::Str(s), Value::Str(sep)) = (&args[0], &args[1]) {
-                let parts: Vec<Value> = s.split(sep.as_str()).map(|p| Value::Str(p.to_string())).collect();
-                Ok(Value::List(Rc::new(RefCell::new(parts))))
-            } else {
-                Err("split() requires (String, String)".into())
-            }
-        }
-
-        "join" => {
-            if args.len() < 2 { return Err("join(list, sep) requires 2 arguments".into()); }
-            if let (Value::List(v), Value::Str(sep)) = (&args[0], &args[1]) {
-                let strs: Vec<String> = v.borrow().iter().map(|x| format!("{}", x)).collect();
-                Ok(Value::Str(strs.join(sep)))
-            } else {
-                Err("join() requires (List, String)".into())
-            }
-        }
-
-        "trim" => {
-            let arg = args.into_iter().next().ok_or("trim() requires 1 argument")?;
-            if let Value::Str(s) = arg {
-                Ok(Value::Str(s.trim().to_string()))
-            } else {
-                Err("trim() requires String".into())
-            }
-        }
-
-        // ── Option/Result ─────────────────────────────────────────────────
-
-        "some" => {
-            let arg = args.into_iter().next().ok_or("some() requires 1 argument")?;
-            Ok(Value::Option(Some(Box::new(arg))))
-        }
-
-        "ok" => {
-            let arg = args.into_iter().next().ok_or("ok() requires 1 argument")?;
-            Ok(Value::Result(std::result::Result::Ok(Box::new(arg))))
-        }
-
-        "err" => {
-            let arg = args.into_iter().next().ok_or("err() requires 1 argument")?;
-            Ok(Value::Result(std::result::Result::Err(Box::new(arg))))
-        }
-
-        "unwrap" => {
-            let arg = args.into_iter().next().ok_or("unwrap() requires 1 argument")?;
-            match arg {
-                Value::Option(Some(v)) => Ok(*v),
-                Value::Option(None)    => Err("unwrap() called on nil Option".into()),
-                Value::Result(std::result::Result::Ok(v)) => Ok(*v),
-                Value::Result(std::result::Result::Err(e)) => Err(format!("unwrap() on Err: {}", e)),
-                other => Ok(other),
-            }
-        }
-
-        // ── Misc ──────────────────────────────────────────────────────────
-
-        "assert" => {
-            let cond = args.first().ok_or("assert() requires at least 1 argument")?;
-            if !crate::interpreter::is_truthy(cond) {
-                let msg = args.get(1)
-                    .map(|v| format!("{}", v))
-                    .unwrap_or_else(|| "Assertion failed".to_string());
-                Err(msg)
-            } else {
-                Ok(Value::Nil)
-            }
-        }
-
-        "panic" => {
-            let msg = args.first().map(|v| format!("{}", v)).unwrap_or_else(|| "explicit panic".to_string());
-            eprintln!("\x1b[31m[Zephyr panic]\x1b[0m {}", msg);
-            std::process::exit(1);
-        }
-
-        "exit" => {
-            let code = args.first().and_then(|v| if let Value::Int(n) = v { Some(*n) } else { None }).unwrap_or(0);
-            std::process::exit(code as i32);
-        }
-
-        // ── Net ───────────────────────────────────────────────────────────────
-        name if net::net_functions().contains(&name) => {
-            net::call_net(name, args).map_err(|e| e)
-        }
-
-        // ── JSON ──────────────────────────────────────────────────────────────
-        name if json::json_functions().contains(&name) => {
-            json::call_json(name, args).map_err(|e| e)
-        }
-
-        // ── Process ───────────────────────────────────────────────────────────
-        name if process::process_functions().contains(&name) => {
-            process::call_process(name, args).map_err(|e| e)
-        }
-
-        // ── File System ───────────────────────────────────────────────────────
-        name if fs::fs_functions().contains(&name) => {
-            fs::call_fs(name, args).map_err(|e| e)
-        }
-
-        // ── Async ─────────────────────────────────────────────────────────────
-        name if async_rt::async_functions().contains(&name) => {
-            async_rt::call_async(name, args).map_err(|e| e)
-        }
-
-        name if async_rt::async_http_functions().contains(&name) => {
-            async_rt::call_async_http(name, args).map_err(|e| e)
-        }
-
-        _ => Err(format!("Unknown native function '{}'", name))
-    }
-}
-
-// ── Built-in methods ──────────────────────────────────────────────────────────
-
-pub fn call_builtin_method(obj: Value, method: &str, args: Vec<Value>, _env: &Env) -> Result<Value, String> {
-    match (&obj, method) {
-        // ── List methods ─────────────────────────────────────────────────
-
-        (Value::List(v), "push") => {
-            let item = args.into_iter().next().ok_or("push() requires 1 argument")?;
-            v.borrow_mut().push(item);
-            Ok(Value::Nil)
-        }
-        (Value::List(v), "pop") => {
-            Ok(v.borrow_mut().pop().unwrap_or(Value::Nil))
-        }
-        (Value::List(v), "len") => Ok(Value::Int(v.borrow().len() as i64)),
-        (Value::List(v), "is_empty") => Ok(Value::Bool(v.borrow().is_empty())),
-        (Value::List(v), "first") => {
-            Ok(v.borrow().first().cloned().map(|x| Value::Option(Some(Box::new(x)))).unwrap_or(Value::Option(None)))
-        }
-        (Value::List(v), "last") => {
-            Ok(v.borrow().last().cloned().map(|x| Value::Option(Some(Box::new(x)))).unwrap_or(Value::Option(None)))
-        }
-        (Value::List(v), "contains") => {
-            let item = args.into_iter().next().ok_or("contains() requires 1 argument")?;
-            Ok(Value::Bool(v.borrow().contains(&item)))
-        }
-        (Value::List(v), "join") => {
-            let sep = args.into_iter().next().ok_or("join() requires separator")?;
-            let sep = format!("{}", sep);
-            let parts: Vec<String> = v.borrow().iter().map(|x| format!("{}", x)).collect();
-            Ok(Value::Str(parts.join(&sep)))
-        }
-        (Value::List(v), "reverse") => {
-            let mut list = v.borrow().clone();
-            list.reverse();
-            Ok(Value::List(Rc::new(RefCell::new(list))))
-        }
-        (Value::List(v), "sort") => {
-            let mut list = v.borrow().clone();
-            list.sort_by(|a, b| {
-                match (a, b) {
-                    (Value::Int(x), Value::Int(y)) => x.cmp(y),
-                    (Value::Float(x), Value::Float(y)) => x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal),
-                    (Value::Str(x), Value::Str(y)) => x.cmp(y),
-                    _ => std::cmp::Ordering::Equal,
-                }
-            });
-            Ok(Value::List(Rc::new(RefCell::new(list))))
-        }
-        (Value::List(v), "slice") => {
-            let start = args.get(0).and_then(|a| if let Value::Int(n) = a { Some(*n as usize) } else { None }).unwrap_or(0);
-            let list = v.borrow();
-            let end = args.get(1)
-                .and_then(|a| if let Value::Int(n) = a { Some(*n as usize) } else { None })
-                .unwrap_or(list.len());
-            Ok(Value::List(Rc::new(RefCell::new(list[start.min(list.len())..end.min(list.len())].to_vec()))))
-        }
-        (Value::List(v), "map") | (Value::List(v), "filter") | (Value::List(v), "reduce") => {
-            let _func = args.into_iter().next().ok_or(format!("{}() requires a function", method))?;
-            match method {
-                "map" => Err("map() on List requires the Zephyr interpreter context; use a for loop or call via global map(list, fn)".into()),
-                _ => Err(format!("{}() requires interpreter context", method))
-            }
-        }
-        (Value::List(v), "enumerate") => {
-            let pairs: Vec<Value> = v.borrow().iter().enumerate()
-                .map(|(i, x)| Value::Tuple(vec![Value::Int(i as i64), x.clone()]))
-                .collect();
-            Ok(Value::List(Rc::new(RefCell::new(pairs))))
-        }
-
-        // ── String methods ────────────────────────────────────────────────
-
-        (Value::Str(s), "len")        => Ok(Value::Int(s.chars().count() as i64)),
-        (Value::Str(s), "is_empty")   => Ok(Value::Bool(s.is_empty())),
-        (Value::Str(s), "to_upper")   => Ok(Value::Str(s.to_uppercase())),
-        (Value::Str(s), "to_lower")   => Ok(Value::Str(s.to_lowercase())),
-        (Value::Str(s), "trim")       => Ok(Value::Str(s.trim().to_string())),
-        (Value::Str(s), "trim_start") => Ok(Value::Str(s.trim_start().to_string())),
-        (Value::Str(s), "trim_end")   => Ok(Value::Str(s.trim_end().to_string())),
-        (Value::Str(s), "chars")      => {
-            let chars: Vec<Value> = s.chars().map(|c| Value::Str(c.to_string())).collect();
-            Ok(Value::List(Rc::new(RefCell::new(chars))))
-        }
-        (Value::Str(s), "split") => {
-            let sep = args.into_iter().next().ok_or("split() requires separator")?;
-            let sep = format!("{}", sep);
-            let parts: Vec<Value> = s.split(sep.as_str()).map(|p| Value::Str(p.to_string())).collect();
-            Ok(Value::List(Rc::new(RefCell::new(parts))))
-        }
-        (Value::Str(s), "starts_with") => {
-            let prefix = args.into_iter().next().ok_or("starts_with() requires argument")?;
-            Ok(Value::Bool(s.starts_with(&format!("{}", prefix))))
-        }
-        (Value::Str(s), "ends_with") => {
-            let suffix = args.into_iter().next().ok_or("ends_with() requires argument")?;
-            Ok(Value::Bool(s.ends_with(&format!("{}", suffix))))
-        }
-        (Value::Str(s), "contains") => {
-            let needle = args.into_iter().next().ok_or("contains() requires argument")?;
-            Ok(Value::Bool(s.contains(&format!("{}", needle))))
-        }
-        (Value::Str(s), "replace") => {
-            if args.len() < 2 { return Err("replace(from, to) requires 2 arguments".into()); }
-            let from = format!("{}", args[0]);
-            let to = format!("{}", args[1]);
-            Ok(Value::Str(s.replace(&from, &to)))
-        }
-        (Value::Str(s), "parse_int") => {
-            s.trim().parse::<i64>()
-                .map(|n| Value::Result(std::result::Result::Ok(Box::new(Value::Int(n)))))
-                .or_else(|_| Ok(Value::Result(std::result::Result::Err(Box::new(Value::Str(format!("Cannot parse '{}' as Int", s)))))))
-        }
-        (Value::Str(s), "parse_float") => {
-            s.trim().parse::<f64>()
-                .map(|f| Value::Result(std::result::Result::Ok(Box::new(Value::Float(f)))))
-                .or_else(|_| Ok(Value::Result(std::result::Result::Err(Box::new(Value::Str(format!("Cannot parse '{}' as Float", s)))))))
-        }
-        (Value::Str(s), "repeat") => {
-            let n = args.into_iter().next().ok_or("repeat() requires argument")?;
-            if let Value::Int(n) = n { Ok(Value::Str(s.repeat(n as usize))) }
-            else { Err("repeat() requires Int".into()) }
-        }
-        (Value::Str(s), "lines") => {
-            let lines: Vec<Value> = s.lines().map(|l| Value::Str(l.to_string())).collect();
-            Ok(Value::List(Rc::new(RefCell::new(lines))))
-        }
-
-        // ── Int methods ────────────────────────────────────────────────────
-
-        (Value::Int(n), "to_str")   => Ok(Value::Str(n.to_string())),
-        (Value::Int(n), "abs")      => Ok(Value::Int(n.abs())),
-        (Value::Int(n), "to_float") => Ok(Value::Float(*n as f64)),
-        (Value::Int(n), "pow") => {
-            let exp = args.into_iter().next().ok_or("pow() requires argument")?;
-            if let Value::Int(e) = exp { Ok(Value::Int(n.pow(e as u32))) }
-            else if let Value::Float(e) = exp { Ok(Value::Float((*n as f64).powf(e))) }
-            else { Err("pow() requires number".into()) }
-        }
-
-        // ── Float methods ──────────────────────────────────────────────────
-
-        (Value::Float(f), "to_str")   => Ok(Value::Str(format!("{}", f))),
-        (Value::Float(f), "abs")      => Ok(Value::Float(f.abs())),
-        (Value::Float(f), "floor")    => Ok(Value::Int(f.floor() as i64)),
-        (Value::Float(f), "ceil")     => Ok(Value::Int(f.ceil() as i64)),
-        (Value::Float(f), "round")    => Ok(Value::Int(f.round() as i64)),
-        (Value::Float(f), "sqrt")     => Ok(Value::Float(f.sqrt())),
-        (Value::Float(f), "to_int")   => Ok(Value::Int(*f as i64)),
-
-        // ── Map methods ────────────────────────────────────────────────────
-
-        (Value::Map(m), "get") => {
-            let key = args.into_iter().next().ok_or("get() requires key")?;
-            let key = format!("{}", key);
-            match m.borrow().get(&key) {
-                Some(v) => Ok(Value::Option(Some(Box::new(v.clone())))),
-                None    => Ok(Value::Option(None)),
-            }
-        }
-        (Value::Map(m), "set") => {
-            if args.len() < 2 { return Err("set(key, val) requires 2 arguments".into()); }
-            let key = format!("{}", args[0]);
-            m.borrow_mut().insert(key, args[1].clone());
-            Ok(Value::Nil)
-        }
-        (Value::Map(m), "contains_key") => {
-            let key = format!("{}", args.into_iter().next().ok_or("contains_key() requires key")?);
-            Ok(Value::Bool(m.borrow().contains_key(&key)))
-        }
-        (Value::Map(m), "remove") => {
-            let key = format!("{}", args.into_iter().next().ok_or("remove() requires key")?);
-            Ok(m.borrow_mut().remove(&key).unwrap_or(Value::Nil))
-        }
-        (Value::Map(m), "keys") => {
-            let keys: Vec<Value> = m.borrow().keys().map(|k| Value::Str(k.clone())).collect();
-            Ok(Value::List(Rc::new(RefCell::new(keys))))
-        }
-        (Value::Map(m), "values") => {
-            let vals: Vec<Value> = m.borrow().values().cloned().collect();
-            Ok(Value::List(Rc::new(RefCell::new(vals))))
-        }
-        (Value::Map(m), "len") => Ok(Value::Int(m.borrow().len() as i64)),
-        (Value::Map(m), "is_empty") => Ok(Value::Bool(m.borrow().is_empty())),
-
-        // ── Option methods ─────────────────────────────────────────────────
-
-        (Value::Option(inner), "is_some") => Ok(Value::Bool(inner.is_some())),
-        (Value::Option(inner), "is_none") => Ok(Value::Bool(inner.is_none())),
-        (Value::Option(Some(v)), "unwrap") => Ok(*v.clone()),
-        (Value::Option(None), "unwrap") => Err("unwrap() on nil Option".into()),
-        (Value::Option(inner), "unwrap_or") => {
-            let default = args.into_iter().next().ok_or("unwrap_or() requires default")?;
-            Ok(inner.as_ref().map(|v| *v.clone()).unwrap_or(default))
-        }
-
-        // ── Result methods ─────────────────────────────────────────────────
-
-        (Value::Result(r), "is_ok")  => Ok(Value::Bool(r.is_ok())),
-        (Value::Result(r), "is_err") => Ok(Value::Bool(r.is_err())),
-        (Value::Result(Ok(v)), "unwrap") => Ok(*v.clone()),
-        (Value::Result(Err(e)), "unwrap") => Err(format!("unwrap() on Err: {}", e)),
-        (Value::Result(r), "unwrap_or") => {
-            let default = args.into_iter().next().ok_or("unwrap_or() requires default")?;
-            Ok(match r { Ok(v) => *v.clone(), Err(_) => default })
-        }
-
-        // ── Ref methods ────────────────────────────────────────────────────
-
-        (Value::Ref(r), "get") => Ok(r.borrow().clone()),
-        (Value::Ref(r), "set") => {
-            let val = args.into_iter().next().ok_or("set() requires argument")?;
-            *r.borrow_mut() = val;
-            Ok(Value::Nil)
-        }
-
-        _ => Err(format!("No method '{}' on type {}", method, crate::interpreter::value_type_name(&obj)))
-    }
-}
-
-// ── Helpers ───────────────────────────────────────────────────────────────────
-
-fn to_f64(val: &Value) -> Result<f64, String> {
-    match val {
-        Value::Int(n)   => Ok(*n as f64),
-        Value::Float(f) => Ok(*f),
-        other => Err(format!("Expected number, got {}", other))
-    }
-}
-
-trait ValueExt { fn is_int(&self) -> bool; }
-impl ValueExt for Value {
-    fn is_int(&self) -> bool {
-        match self {
-            Value::Int(_) => true,
-            _ => false,
-        }
-    }
-}
\ No newline at end of file
+// ═══════════════════════════════════════════════════════════
+// Zephyr Standard Library — built-in functions and methods
+// ═══════════════════════════════════════════════════════════
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use indexmap::{IndexMap, IndexSet};
+use num_bigint::BigInt;
+use num_traits::Signed;
+use crate::interpreter::{int_value, is_truthy, to_big, Env, EvalResult, Interpreter, MapKey, Signal, Value, ZephyrFn};
+use crate::net;
+use crate::json;
+use crate::process;
+use crate::zfs as fs;
+use crate::async_rt;
+use crate::iter;
+use crate::regexp;
+use crate::math::{self, to_f64};
+use crate::datetime;
+use crate::random;
+use crate::digest;
+use crate::gc;
+
+pub fn register(env: &Env) {
+    let natives = [
+        // I/O
+        "print", "println", "input", "eprint", "eprintln",
+        // Type conversion
+        "int", "float", "str", "bool",
+        // Type checking
+        "type_of",
+        // Collections
+        "len", "push", "pop", "range", "set",
+        // Functional
+        "map", "filter", "reduce", "zip", "enumerate", "sorted",
+        // String
+        "split", "join", "trim", "format",
+        // Option/Result
+        "some", "ok", "err", "unwrap",
+        // Misc
+        "assert", "panic", "exit", "set_recursion_limit", "recursion_limit",
+    ];
+    for name in natives {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for name in net::net_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for name in json::json_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for name in process::process_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for name in fs::fs_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for name in math::math_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for (name, value) in math::math_constants() {
+        env.define(name, Value::Float(value));
+    }
+    for name in regexp::regex_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for name in datetime::datetime_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for name in random::random_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for name in digest::digest_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for name in gc::gc_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    // Async runtime
+    for name in async_rt::async_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+    for name in async_rt::async_http_functions() {
+        env.define(name, Value::Function(ZephyrFn::Native(name.to_string())));
+    }
+}
+
+/// Call the native `name`. The interpreter handle lets natives that take
+/// a function call back into Zephyr.
+pub fn call_native(interp: &mut Interpreter, name: &str, mut args: Vec<Value>, env: &Env) -> EvalResult {
+    match name {
+        "map" | "filter" | "reduce" | "sorted" if !args.is_empty() => {
+            let coll = args.remove(0);
+            if name == "sorted" && args.is_empty() {
+                return builtin_method(coll, "sort", args).map_err(Signal::Error);
+            }
+            let method = if name == "sorted" { "sort_by" } else { name };
+            callback_method(interp, &coll, method, args, env)
+        }
+        "set" => {
+            let items = match args.into_iter().next() {
+                Some(coll) => {
+                    let it = iter::iter_of(interp, coll, env)?;
+                    iter::collect(&it, interp, env)?
+                }
+                None => Vec::new(),
+            };
+            crate::interpreter::set_of(items).map_err(Signal::Error)
+        }
+        "set_recursion_limit" => match args.first() {
+            Some(Value::Int(n)) if *n > 0 => {
+                interp.max_depth = *n as usize;
+                Ok(Value::Nil)
+            }
+            _ => Err(Signal::Error("set_recursion_limit(n) requires a positive Int".into())),
+        },
+        "recursion_limit" => Ok(Value::Int(interp.max_depth as i64)),
+        "async_spawn" => match args.first() {
+            Some(Value::Function(f)) => async_rt::spawn_function(interp, f).map_err(Signal::Error),
+            _ => native(name, args, env).map_err(Signal::Error),
+        },
+        _ => native(name, args, env).map_err(Signal::Error),
+    }
+}
+
+fn native(name: &str, args: Vec<Value>, _env: &Env) -> Result<Value, String> {
+    match name {
+        // ── I/O ─────────────────────────────────────────────────────────────
+
+        "print" => {
+            let parts: Vec<String> = args.iter().map(|v| format!("{}", v)).collect();
+            print!("{}", parts.join(" "));
+            Ok(Value::Nil)
+        }
+
+        "println" => {
+            let parts: Vec<String> = args.iter().map(|v| format!("{}", v)).collect();
+            println!("{}", parts.join(" "));
+            Ok(Value::Nil)
+        }
+
+        "eprint" => {
+            let parts: Vec<String> = args.iter().map(|v| format!("{}", v)).collect();
+            eprint!("{}", parts.join(" "));
+            Ok(Value::Nil)
+        }
+
+        "eprintln" => {
+            let parts: Vec<String> = args.iter().map(|v| format!("{}", v)).collect();
+            eprintln!("{}", parts.join(" "));
+            Ok(Value::Nil)
+        }
+
+        "input" => {
+            if let Some(prompt) = args.first() {
+                print!("{}", prompt);
+                use std::io::Write;
+                std::io::stdout().flush().ok();
+            }
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line).map_err(|e| e.to_string())?;
+            Ok(Value::str(line.trim_end_matches('\n').to_string()))
+        }
+
+        // ── Type conversion ───────────────────────────────────────────────
+
+        "int" => {
+            let arg = args.into_iter().next().ok_or("int() requires 1 argument")?;
+            match arg {
+                Value::Int(n)   => Ok(Value::Int(n)),
+                Value::BigInt(n) => Ok(Value::BigInt(n)),
+                Value::Float(f) => Ok(Value::Int(f as i64)),
+                Value::Str(s)   => s.trim().parse::<BigInt>()
+                    .map(int_value)
+                    .map_err(|_| format!("Cannot convert '{}' to Int", s)),
+                Value::Bool(b)  => Ok(Value::Int(if b { 1 } else { 0 })),
+                other => Err(format!("Cannot convert {} to Int", other))
+            }
+        }
+
+        "float" => {
+            let arg = args.into_iter().next().ok_or("float() requires 1 argument")?;
+            match arg {
+                Value::Float(f) => Ok(Value::Float(f)),
+                Value::Int(_) | Value::BigInt(_) => Ok(Value::Float(to_f64(&arg)?)),
+                Value::Str(s)   => s.trim().parse::<f64>()
+                    .map(Value::Float)
+                    .map_err(|_| format!("Cannot convert '{}' to Float", s)),
+                other => Err(format!("Cannot convert {} to Float", other))
+            }
+        }
+
+        "str" => {
+            let arg = args.into_iter().next().ok_or("str() requires 1 argument")?;
+            Ok(Value::str(format!("{}", arg)))
+        }
+
+        "bool" => {
+            let arg = args.into_iter().next().ok_or("bool() requires 1 argument")?;
+            Ok(Value::Bool(crate::interpreter::is_truthy(&arg)))
+        }
+
+        "type_of" => {
+            let arg = args.into_iter().next().ok_or("type_of() requires 1 argument")?;
+            Ok(Value::str(crate::interpreter::value_type_name(&arg)))
+        }
+
+        // ── Collections ───────────────────────────────────────────────────
+
+        "len" => {
+            let arg = args.into_iter().next().ok_or("len() requires 1 argument")?;
+            match arg {
+                Value::List(v)  => Ok(Value::Int(v.borrow().len() as i64)),
+                Value::Str(s)   => Ok(Value::Int(s.chars().count() as i64)),
+                Value::Map(m)   => Ok(Value::Int(m.borrow().len() as i64)),
+                Value::Set(s)   => Ok(Value::Int(s.borrow().len() as i64)),
+                Value::Tuple(t) => Ok(Value::Int(t.len() as i64)),
+                Value::Range(start, end, step) => Ok(Value::Int(iter::range_len(start, end, step))),
+                other => Err(format!("len() not supported for {}", other))
+            }
+        }
+
+        "push" => {
+            if args.len() < 2 { return Err("push(list, item) requires 2 arguments".into()); }
+            if let Value::List(v) = &args[0] {
+                v.borrow_mut().push(args[1].clone());
+                Ok(Value::Nil)
+            } else {
+                Err("push() requires a List as first argument".into())
+            }
+        }
+
+        "pop" => {
+            let arg = args.into_iter().next().ok_or("pop() requires 1 argument")?;
+            if let Value::List(v) = arg {
+                Ok(v.borrow_mut().pop().unwrap_or(Value::Nil))
+            } else {
+                Err("pop() requires a List".into())
+            }
+        }
+
+        "range" => {
+            let int = |v: &Value| crate::interpreter::require_int(v).map_err(|e| format!("{:?}", e));
+            match args.len() {
+                1 => Ok(Value::Range(0, int(&args[0])?, 1)),
+                2 => Ok(Value::Range(int(&args[0])?, int(&args[1])?, 1)),
+                3 => {
+                    let step = int(&args[2])?;
+                    if step == 0 { return Err("range() step cannot be 0".into()); }
+                    Ok(Value::Range(int(&args[0])?, int(&args[1])?, step))
+                }
+                _ => Err("range() takes 1-3 arguments".into())
+            }
+        }
+
+        // ── Functional ────────────────────────────────────────────────────
+
+        "map" | "filter" | "reduce" | "sorted" => {
+            Err(format!("{}() requires a collection and a function", name))
+        }
+
+        "zip" => {
+            if let [Value::List(a), Value::List(b)] = args.as_slice() {
+                let pairs: Vec<Value> = a.borrow().iter().zip(b.borrow().iter())
+                    .map(|(x, y)| Value::Tuple(vec![x.clone(), y.clone()]))
+                    .collect();
+                Ok(Value::list(pairs))
+            } else {
+                Err("zip() requires (List, List)".into())
+            }
+        }
+
+        "enumerate" => {
+            let list = args.into_iter().next().ok_or("enumerate() requires 1 argument")?;
+            builtin_method(list, "enumerate", vec![])
+        }
+
+        // ── String ────────────────────────────────────────────────────────
+
+        "split" => {
+            if args.len() < 2 { return Err("split(str, sep) requires 2 arguments".into()); }
+            if let (Value::Str(s), Value::Str(sep)) = (&args[0], &args[1]) {
+                let parts: Vec<Value> = s.split(sep.as_str()).map(|p| Value::str(p.to_string())).collect();
+                Ok(Value::list(parts))
+            } else {
+                Err("split() requires (String, String)".into())
+            }
+        }
+
+        "join" => {
+            if args.len() < 2 { return Err("join(list, sep) requires 2 arguments".into()); }
+            if let (Value::List(v), Value::Str(sep)) = (&args[0], &args[1]) {
+                let strs: Vec<String> = v.borrow().iter().map(|x| format!("{}", x)).collect();
+                Ok(Value::str(strs.join(sep)))
+            } else {
+                Err("join() requires (List, String)".into())
+            }
+        }
+
+        "format" => {
+            match args.split_first() {
+                Some((Value::Str(template), rest)) => crate::format::format_template(template, rest).map(Value::str),
+                _ => Err("format(template, args...) requires a String template".into()),
+            }
+        }
+
+        "trim" => {
+            let arg = args.into_iter().next().ok_or("trim() requires 1 argument")?;
+            if let Value::Str(s) = arg {
+                Ok(Value::str(s.trim().to_string()))
+            } else {
+                Err("trim() requires String".into())
+            }
+        }
+
+        // ── Option/Result ─────────────────────────────────────────────────
+
+        "some" => {
+            let arg = args.into_iter().next().ok_or("some() requires 1 argument")?;
+            Ok(Value::Option(Some(Box::new(arg))))
+        }
+
+        "ok" => {
+            let arg = args.into_iter().next().ok_or("ok() requires 1 argument")?;
+            Ok(Value::Result(std::result::Result::Ok(Box::new(arg))))
+        }
+
+        "err" => {
+            let arg = args.into_iter().next().ok_or("err() requires 1 argument")?;
+            Ok(Value::Result(std::result::Result::Err(Box::new(arg))))
+        }
+
+        "unwrap" => {
+            let arg = args.into_iter().next().ok_or("unwrap() requires 1 argument")?;
+            match arg {
+                Value::Option(Some(v)) => Ok(*v),
+                Value::Option(None)    => Err("unwrap() called on nil Option".into()),
+                Value::Result(std::result::Result::Ok(v)) => Ok(*v),
+                Value::Result(std::result::Result::Err(e)) => Err(format!("unwrap() on Err: {}", e)),
+                other => Ok(other),
+            }
+        }
+
+        // ── Misc ──────────────────────────────────────────────────────────
+
+        "assert" => {
+            let cond = args.first().ok_or("assert() requires at least 1 argument")?;
+            if !crate::interpreter::is_truthy(cond) {
+                let msg = args.get(1)
+                    .map(|v| format!("{}", v))
+                    .unwrap_or_else(|| "Assertion failed".to_string());
+                Err(msg)
+            } else {
+                Ok(Value::Nil)
+            }
+        }
+
+        "panic" => {
+            let msg = args.first().map(|v| format!("{}", v)).unwrap_or_else(|| "explicit panic".to_string());
+            eprintln!("\x1b[31m[Zephyr panic]\x1b[0m {}", msg);
+            std::process::exit(1);
+        }
+
+        "exit" => {
+            let code = args.first().and_then(|v| if let Value::Int(n) = v { Some(*n) } else { None }).unwrap_or(0);
+            std::process::exit(code as i32);
+        }
+
+        // ── Net ───────────────────────────────────────────────────────────────
+        name if net::net_functions().contains(&name) => {
+            net::call_net(name, args)
+        }
+
+        // ── JSON ──────────────────────────────────────────────────────────────
+        name if json::json_functions().contains(&name) => {
+            json::call_json(name, args)
+        }
+
+        // ── Process ───────────────────────────────────────────────────────────
+        name if process::process_functions().contains(&name) => {
+            process::call_process(name, args)
+        }
+
+        // ── File System ───────────────────────────────────────────────────────
+        name if fs::fs_functions().contains(&name) => {
+            fs::call_fs(name, args)
+        }
+
+        // ── Math ──────────────────────────────────────────────────────────────
+        name if math::math_functions().contains(&name) => {
+            math::call_math(name, args)
+        }
+
+        // ── Regex ─────────────────────────────────────────────────────────────
+        name if regexp::regex_functions().contains(&name) => {
+            regexp::call_regex(name, args)
+        }
+
+        // ── Date and time ─────────────────────────────────────────────────────
+        name if datetime::datetime_functions().contains(&name) => {
+            datetime::call_datetime(name, args)
+        }
+
+        // ── Random ────────────────────────────────────────────────────────────
+        name if random::random_functions().contains(&name) => {
+            random::call_random(name, args)
+        }
+
+        // ── Hashing ───────────────────────────────────────────────────────────
+        name if digest::digest_functions().contains(&name) => {
+            digest::call_digest(name, args)
+        }
+
+        // ── Memory ────────────────────────────────────────────────────────────
+        name if gc::gc_functions().contains(&name) => {
+            gc::call_gc(name, args)
+        }
+
+        // ── Async ─────────────────────────────────────────────────────────────
+        name if async_rt::async_functions().contains(&name) => {
+            async_rt::call_async(name, args)
+        }
+
+        name if async_rt::async_http_functions().contains(&name) => {
+            async_rt::call_async_http(name, args)
+        }
+
+        _ => Err(format!("Unknown native function '{}'", name))
+    }
+}
+
+// ── Built-in methods ──────────────────────────────────────────────────────────
+
+/// Call a builtin method on `obj`.
+pub fn call_builtin_method(interp: &mut Interpreter, obj: Value, method: &str, args: Vec<Value>, env: &Env) -> EvalResult {
+    match &obj {
+        Value::Iter(_) => iter::iter_method(interp, obj, method, args, env),
+        // stepped, so a callback can stop early on a range too large to hold
+        Value::Range(..) if LAZY_RANGE_METHODS.contains(&method) => iter::iter_method(interp, obj, method, args, env),
+        Value::List(_) | Value::Map(_) | Value::Set(_) | Value::Range(..) if CALLBACK_METHODS.contains(&method) => {
+            callback_method(interp, &obj, method, args, env)
+        }
+        Value::Range(..) if method == "step_by" => builtin_method(obj, method, args).map_err(Signal::Error),
+        Value::List(_) | Value::Tuple(_) | Value::Str(_) | Value::Map(_) | Value::Set(_) | Value::Range(..)
+            if iter::ADAPTERS.contains(&method) => iter::iter_method(interp, obj, method, args, env),
+        Value::Struct(..) | Value::Enum(..) if iter::METHODS.contains(&method) => {
+            iter::iter_method(interp, obj, method, args, env)
+        }
+        Value::DateTime(_) | Value::Duration(_) => datetime::call_method(&obj, method, args).map_err(Signal::Error),
+        _ => builtin_method(obj, method, args).map_err(Signal::Error),
+    }
+}
+
+/// Every method name a built-in value answers to, for REPL completion.
+pub fn method_names() -> Vec<&'static str> {
+    let mut names: Vec<_> = BUILTIN_METHODS.iter()
+        .chain(CALLBACK_METHODS)
+        .chain(iter::METHODS)
+        .chain(datetime::METHODS)
+        .copied()
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// What `builtin_method` answers, across all types.
+const BUILTIN_METHODS: &[&str] = &[
+    // List, Map, Set, Range
+    "push", "pop", "len", "is_empty", "contains", "first", "last", "reverse", "slice", "sort",
+    "join", "enumerate", "get", "set", "remove", "keys", "values", "contains_key", "add",
+    "clear", "copy", "to_list", "collect", "union", "intersection", "difference",
+    "symmetric_difference", "is_subset", "is_superset", "is_disjoint", "step_by",
+    // String
+    "chars", "lines", "split", "trim", "trim_start", "trim_end", "to_upper", "to_lower",
+    "starts_with", "ends_with", "replace", "repeat", "parse_int", "parse_float",
+    // Numbers
+    "abs", "pow", "floor", "ceil", "round", "sqrt", "is_nan", "is_finite", "to_int", "to_float",
+    "to_str",
+    // Option, Result
+    "is_some", "is_none", "is_ok", "is_err", "unwrap", "unwrap_or",
+];
+
+fn builtin_method(obj: Value, method: &str, args: Vec<Value>) -> Result<Value, String> {
+    match (&obj, method) {
+        // ── List methods ─────────────────────────────────────────────────
+
+        (Value::List(v), "push") => {
+            let item = args.into_iter().next().ok_or("push() requires 1 argument")?;
+            v.borrow_mut().push(item);
+            Ok(Value::Nil)
+        }
+        (Value::List(v), "pop") => {
+            Ok(v.borrow_mut().pop().unwrap_or(Value::Nil))
+        }
+        (Value::List(v), "len") => Ok(Value::Int(v.borrow().len() as i64)),
+        (Value::List(v), "is_empty") => Ok(Value::Bool(v.borrow().is_empty())),
+        (Value::List(v), "first") => {
+            Ok(v.borrow().first().cloned().map(|x| Value::Option(Some(Box::new(x)))).unwrap_or(Value::Option(None)))
+        }
+        (Value::List(v), "last") => {
+            Ok(v.borrow().last().cloned().map(|x| Value::Option(Some(Box::new(x)))).unwrap_or(Value::Option(None)))
+        }
+        (Value::List(v), "contains") => {
+            let item = args.into_iter().next().ok_or("contains() requires 1 argument")?;
+            Ok(Value::Bool(v.borrow().contains(&item)))
+        }
+        (Value::List(v), "join") => {
+            let sep = args.into_iter().next().ok_or("join() requires separator")?;
+            let sep = format!("{}", sep);
+            let parts: Vec<String> = v.borrow().iter().map(|x| format!("{}", x)).collect();
+            Ok(Value::str(parts.join(&sep)))
+        }
+        (Value::List(v), "reverse") => {
+            let mut list = v.borrow().clone();
+            list.reverse();
+            Ok(Value::list(list))
+        }
+        (Value::List(v), "sort") => {
+            let mut list = v.borrow().clone();
+            list.sort_by(sort_order);
+            Ok(Value::list(list))
+        }
+        (Value::List(v), "slice") => {
+            let start = args.first().and_then(|a| if let Value::Int(n) = a { Some(*n as usize) } else { None }).unwrap_or(0);
+            let list = v.borrow();
+            let end = args.get(1)
+                .and_then(|a| if let Value::Int(n) = a { Some(*n as usize) } else { None })
+                .unwrap_or(list.len());
+            Ok(Value::list(list[start.min(list.len())..end.min(list.len())].to_vec()))
+        }
+        (Value::List(v), "enumerate") => {
+            let pairs: Vec<Value> = v.borrow().iter().enumerate()
+                .map(|(i, x)| Value::Tuple(vec![Value::Int(i as i64), x.clone()]))
+                .collect();
+            Ok(Value::list(pairs))
+        }
+
+        // ── String methods ────────────────────────────────────────────────
+
+        (Value::Str(s), "len")        => Ok(Value::Int(s.chars().count() as i64)),
+        (Value::Str(s), "is_empty")   => Ok(Value::Bool(s.is_empty())),
+        (Value::Str(s), "to_upper")   => Ok(Value::str(s.to_uppercase())),
+        (Value::Str(s), "to_lower")   => Ok(Value::str(s.to_lowercase())),
+        (Value::Str(s), "trim")       => Ok(Value::str(s.trim().to_string())),
+        (Value::Str(s), "trim_start") => Ok(Value::str(s.trim_start().to_string())),
+        (Value::Str(s), "trim_end")   => Ok(Value::str(s.trim_end().to_string())),
+        (Value::Str(s), "chars")      => {
+            let chars: Vec<Value> = s.chars().map(|c| Value::str(c.to_string())).collect();
+            Ok(Value::list(chars))
+        }
+        (Value::Str(s), "split") => {
+            let sep = args.into_iter().next().ok_or("split() requires separator")?;
+            let sep = format!("{}", sep);
+            let parts: Vec<Value> = s.split(sep.as_str()).map(|p| Value::str(p.to_string())).collect();
+            Ok(Value::list(parts))
+        }
+        (Value::Str(s), "starts_with") => {
+            let prefix = args.into_iter().next().ok_or("starts_with() requires argument")?;
+            Ok(Value::Bool(s.starts_with(&format!("{}", prefix))))
+        }
+        (Value::Str(s), "ends_with") => {
+            let suffix = args.into_iter().next().ok_or("ends_with() requires argument")?;
+            Ok(Value::Bool(s.ends_with(&format!("{}", suffix))))
+        }
+        (Value::Str(s), "contains") => {
+            let needle = args.into_iter().next().ok_or("contains() requires argument")?;
+            Ok(Value::Bool(s.contains(&format!("{}", needle))))
+        }
+        (Value::Str(s), "replace") => {
+            if args.len() < 2 { return Err("replace(from, to) requires 2 arguments".into()); }
+            let from = format!("{}", args[0]);
+            let to = format!("{}", args[1]);
+            Ok(Value::str(s.replace(&from, &to)))
+        }
+        (Value::Str(s), "parse_int") => {
+            s.trim().parse::<BigInt>()
+                .map(|n| Value::Result(std::result::Result::Ok(Box::new(int_value(n)))))
+                .or_else(|_| Ok(Value::Result(std::result::Result::Err(Box::new(Value::str(format!("Cannot parse '{}' as Int", s)))))))
+        }
+        (Value::Str(s), "parse_float") => {
+            s.trim().parse::<f64>()
+                .map(|f| Value::Result(std::result::Result::Ok(Box::new(Value::Float(f)))))
+                .or_else(|_| Ok(Value::Result(std::result::Result::Err(Box::new(Value::str(format!("Cannot parse '{}' as Float", s)))))))
+        }
+        (Value::Str(s), "repeat") => {
+            let n = args.into_iter().next().ok_or("repeat() requires argument")?;
+            if let Value::Int(n) = n { Ok(Value::str(s.repeat(n as usize))) }
+            else { Err("repeat() requires Int".into()) }
+        }
+        (Value::Str(s), "lines") => {
+            let lines: Vec<Value> = s.lines().map(|l| Value::str(l.to_string())).collect();
+            Ok(Value::list(lines))
+        }
+
+        // ── Int methods ────────────────────────────────────────────────────
+
+        (Value::Int(_) | Value::BigInt(_), "to_str")   => Ok(Value::str(obj.to_string())),
+        (Value::Int(_) | Value::BigInt(_), "abs")      => Ok(int_value(to_big(&obj).unwrap_or_default().abs())),
+        (Value::Int(_) | Value::BigInt(_), "to_float") => Ok(Value::Float(to_f64(&obj)?)),
+        (Value::Int(_) | Value::BigInt(_), "pow") => {
+            let exp = args.into_iter().next().ok_or("pow() requires argument")?;
+            match exp {
+                Value::Int(e) if e < 0 => Err("pow() exponent must not be negative; use a Float exponent".into()),
+                Value::Int(e) => {
+                    let e = u32::try_from(e).map_err(|_| "pow() exponent is too large")?;
+                    match obj {
+                        Value::Int(n) => Ok(n.checked_pow(e).map(Value::Int).unwrap_or_else(|| int_value(BigInt::from(n).pow(e)))),
+                        _ => Ok(int_value(to_big(&obj).unwrap_or_default().pow(e))),
+                    }
+                }
+                Value::Float(e) => Ok(Value::Float(to_f64(&obj)?.powf(e))),
+                _ => Err("pow() requires number".into()),
+            }
+        }
+
+        // ── Float methods ──────────────────────────────────────────────────
+
+        (Value::Float(f), "to_str")   => Ok(Value::str(format!("{}", f))),
+        (Value::Float(f), "abs")      => Ok(Value::Float(f.abs())),
+        (Value::Float(_), "floor" | "ceil" | "round" | "sqrt" | "is_nan" | "is_finite") => {
+            math::call_math(method, std::iter::once(obj.clone()).chain(args).collect())
+        }
+        (Value::Float(f), "to_int")   => Ok(Value::Int(*f as i64)),
+
+        // ── Map methods ────────────────────────────────────────────────────
+
+        (Value::Map(m), "get") => {
+            let key = args.into_iter().next().ok_or("get() requires key")?;
+            let key = MapKey::from_value(&key)?;
+            match m.borrow().get(&key) {
+                Some(v) => Ok(Value::Option(Some(Box::new(v.clone())))),
+                None    => Ok(Value::Option(None)),
+            }
+        }
+        (Value::Map(m), "set") => {
+            if args.len() < 2 { return Err("set(key, val) requires 2 arguments".into()); }
+            let key = MapKey::from_value(&args[0])?;
+            m.borrow_mut().insert(key, args[1].clone());
+            Ok(Value::Nil)
+        }
+        (Value::Map(m), "contains_key") => {
+            let key = MapKey::from_value(&args.into_iter().next().ok_or("contains_key() requires key")?)?;
+            Ok(Value::Bool(m.borrow().contains_key(&key)))
+        }
+        (Value::Map(m), "remove") => {
+            let key = MapKey::from_value(&args.into_iter().next().ok_or("remove() requires key")?)?;
+            Ok(m.borrow_mut().shift_remove(&key).unwrap_or(Value::Nil))
+        }
+        (Value::Map(m), "keys") => {
+            let keys: Vec<Value> = m.borrow().keys().map(MapKey::to_value).collect();
+            Ok(Value::list(keys))
+        }
+        (Value::Map(m), "values") => {
+            let vals: Vec<Value> = m.borrow().values().cloned().collect();
+            Ok(Value::list(vals))
+        }
+        (Value::Map(m), "len") => Ok(Value::Int(m.borrow().len() as i64)),
+        (Value::Map(m), "is_empty") => Ok(Value::Bool(m.borrow().is_empty())),
+
+        // ── Set methods ────────────────────────────────────────────────────
+
+        (Value::Set(s), "add") => {
+            let item = args.first().ok_or("add() requires a value")?;
+            Ok(Value::Bool(s.borrow_mut().insert(MapKey::from_value(item)?)))
+        }
+        (Value::Set(s), "remove") => {
+            let item = args.first().ok_or("remove() requires a value")?;
+            let removed = match MapKey::from_value(item) {
+                Ok(key) => s.borrow_mut().shift_remove(&key),
+                Err(_) => false,
+            };
+            Ok(Value::Bool(removed))
+        }
+        (Value::Set(s), "contains") => {
+            let item = args.first().ok_or("contains() requires a value")?;
+            Ok(Value::Bool(MapKey::from_value(item).is_ok_and(|key| s.borrow().contains(&key))))
+        }
+        (Value::Set(s), "len") => Ok(Value::Int(s.borrow().len() as i64)),
+        (Value::Set(s), "is_empty") => Ok(Value::Bool(s.borrow().is_empty())),
+        (Value::Set(s), "clear") => { s.borrow_mut().clear(); Ok(Value::Nil) }
+        (Value::Set(s), "copy") => Ok(Value::Set(Rc::new(RefCell::new(s.borrow().clone())))),
+        (Value::Set(s), "to_list") => {
+            Ok(Value::list(s.borrow().iter().map(MapKey::to_value).collect()))
+        }
+        (Value::Set(s), "union" | "intersection" | "difference" | "symmetric_difference") => {
+            let other = set_arg(&args, method)?;
+            let s = s.borrow();
+            let out: IndexSet<MapKey> = match method {
+                "union" => s.union(&other).cloned().collect(),
+                "intersection" => s.intersection(&other).cloned().collect(),
+                "difference" => s.difference(&other).cloned().collect(),
+                _ => s.symmetric_difference(&other).cloned().collect(),
+            };
+            Ok(Value::Set(Rc::new(RefCell::new(out))))
+        }
+        (Value::Set(s), "is_subset" | "is_superset" | "is_disjoint") => {
+            let other = set_arg(&args, method)?;
+            let s = s.borrow();
+            Ok(Value::Bool(match method {
+                "is_subset" => s.is_subset(&other),
+                "is_superset" => s.is_superset(&other),
+                _ => s.is_disjoint(&other),
+            }))
+        }
+
+        // ── Range methods ──────────────────────────────────────────────────
+
+        (Value::Range(start, end, step), "len") => Ok(Value::Int(iter::range_len(*start, *end, *step))),
+        (Value::Range(start, end, step), "is_empty") => Ok(Value::Bool(!iter::range_has(*start, *end, *step))),
+        (Value::Range(start, end, step), "contains") => {
+            let n = match args.first() {
+                Some(Value::Int(n)) => *n,
+                _ => return Ok(Value::Bool(false)),
+            };
+            let inside = if *step > 0 { *start <= n && n < *end } else { *end < n && n <= *start };
+            Ok(Value::Bool(inside && (n - start) % step == 0))
+        }
+        (Value::Range(start, end, step), "step_by") => {
+            let by = match args.first() {
+                Some(Value::Int(n)) if *n > 0 => *n,
+                _ => return Err("step_by() requires a positive Int".into()),
+            };
+            let step = step.checked_mul(by).ok_or("step_by() step is too large")?;
+            Ok(Value::Range(*start, *end, step))
+        }
+        (Value::Range(start, end, step), "to_list" | "collect") => {
+            Ok(Value::list(iter::range_items(*start, *end, *step)?))
+        }
+
+        // ── Option methods ─────────────────────────────────────────────────
+
+        (Value::Option(inner), "is_some") => Ok(Value::Bool(inner.is_some())),
+        (Value::Option(inner), "is_none") => Ok(Value::Bool(inner.is_none())),
+        (Value::Option(Some(v)), "unwrap") => Ok(*v.clone()),
+        (Value::Option(None), "unwrap") => Err("unwrap() on nil Option".into()),
+        (Value::Option(inner), "unwrap_or") => {
+            let default = args.into_iter().next().ok_or("unwrap_or() requires default")?;
+            Ok(inner.as_ref().map(|v| *v.clone()).unwrap_or(default))
+        }
+
+        // ── Result methods ─────────────────────────────────────────────────
+
+        (Value::Result(r), "is_ok")  => Ok(Value::Bool(r.is_ok())),
+        (Value::Result(r), "is_err") => Ok(Value::Bool(r.is_err())),
+        (Value::Result(Ok(v)), "unwrap") => Ok(*v.clone()),
+        (Value::Result(Err(e)), "unwrap") => Err(format!("unwrap() on Err: {}", e)),
+        (Value::Result(r), "unwrap_or") => {
+            let default = args.into_iter().next().ok_or("unwrap_or() requires default")?;
+            Ok(match r { Ok(v) => *v.clone(), Err(_) => default })
+        }
+
+        // ── Ref methods ────────────────────────────────────────────────────
+
+        (Value::Ref(r), "get") => Ok(r.borrow().clone()),
+        (Value::Ref(r), "set") => {
+            let val = args.into_iter().next().ok_or("set() requires argument")?;
+            *r.borrow_mut() = val;
+            Ok(Value::Nil)
+        }
+
+        _ => Err(format!("No method '{}' on type {}", method, crate::interpreter::value_type_name(&obj)))
+    }
+}
+
+// ── Callback-taking methods ───────────────────────────────────────────────────
+
+const CALLBACK_METHODS: &[&str] = &[
+    "map", "filter", "reduce", "sort_by", "find", "any", "all", "flat_map", "group_by",
+];
+
+/// Callback methods a range answers as an iterator rather than a List.
+const LAZY_RANGE_METHODS: &[&str] = &["map", "filter", "reduce", "find", "any", "all"];
+
+/// The other operand of a set operation: a Set, or a List or Tuple of keys.
+fn set_arg(args: &[Value], method: &str) -> Result<IndexSet<MapKey>, String> {
+    match args.first() {
+        Some(Value::Set(s)) => Ok(s.borrow().clone()),
+        Some(Value::List(v)) => v.borrow().iter().map(MapKey::from_value).collect(),
+        Some(Value::Tuple(v)) => v.iter().map(MapKey::from_value).collect(),
+        Some(other) => Err(format!("{}() requires a Set, got {}", method, crate::interpreter::value_type_name(other))),
+        None => Err(format!("{}() requires a Set", method)),
+    }
+}
+
+/// List and map methods that take a Zephyr function. A list passes each
+/// item to it; a map passes each entry as two arguments, key and value,
+/// and hands entries back as (key, value) tuples where a list is built.
+/// Sets and ranges act as lists, except that filtering a set keeps a Set.
+fn callback_method(interp: &mut Interpreter, coll: &Value, method: &str, args: Vec<Value>, env: &Env) -> EvalResult {
+    let (items, is_map) = match coll {
+        Value::List(v) => (v.borrow().clone(), false),
+        Value::Map(m) => {
+            let entries = m.borrow().iter()
+                .map(|(k, v)| Value::Tuple(vec![k.to_value(), v.clone()]))
+                .collect();
+            (entries, true)
+        }
+        Value::Set(s) => (s.borrow().iter().map(MapKey::to_value).collect(), false),
+        Value::Range(start, end, step) => (iter::range_items(*start, *end, *step)?, false),
+        other => return Err(Signal::Error(format!("{}() requires a List or Map, got {}", method, other))),
+    };
+    let mut args = args.into_iter();
+    let f = args.next().ok_or_else(|| format!("{}() requires a function", method))?;
+    let spread = |item: &Value| match item {
+        Value::Tuple(kv) if is_map => kv.clone(),
+        other => vec![other.clone()],
+    };
+    let call = |interp: &mut Interpreter, item: &Value| interp.call_value(f.clone(), spread(item), env);
+
+    let list = |items: Vec<Value>| Value::list(items);
+    let map = |entries: Vec<(MapKey, Value)>| Value::map(entries.into_iter().collect());
+    let key_of = |item: &Value| match item {
+        Value::Tuple(kv) => MapKey::from_value(&kv[0]).map_err(Signal::Error),
+        other => MapKey::from_value(other).map_err(Signal::Error),
+    };
+
+    match method {
+        "map" => {
+            if is_map {
+                let mut out = Vec::with_capacity(items.len());
+                for item in &items { out.push((key_of(item)?, call(interp, item)?)); }
+                Ok(map(out))
+            } else {
+                let mut out = Vec::with_capacity(items.len());
+                for item in &items { out.push(call(interp, item)?); }
+                Ok(list(out))
+            }
+        }
+        "filter" => {
+            let mut kept = Vec::new();
+            for item in items {
+                if is_truthy(&call(interp, &item)?) { kept.push(item); }
+            }
+            if let Value::Set(_) = coll {
+                return crate::interpreter::set_of(kept).map_err(Signal::Error);
+            }
+            if is_map {
+                let mut out = Vec::with_capacity(kept.len());
+                for item in kept {
+                    let key = key_of(&item)?;
+                    if let Value::Tuple(mut kv) = item { out.push((key, kv.pop().unwrap_or(Value::Nil))); }
+                }
+                Ok(map(out))
+            } else {
+                Ok(list(kept))
+            }
+        }
+        "reduce" => {
+            let mut rest = items.into_iter();
+            let mut acc = match args.next() {
+                Some(init) => init,
+                None if is_map => return Err(Signal::Error("reduce() on a Map requires an initial value".into())),
+                None => rest.next().ok_or_else(|| Signal::Error("reduce() of an empty List with no initial value".into()))?,
+            };
+            for item in rest {
+                let mut call_args = vec![acc];
+                call_args.extend(spread(&item));
+                acc = interp.call_value(f.clone(), call_args, env)?;
+            }
+            Ok(acc)
+        }
+        "find" => {
+            for item in items {
+                if is_truthy(&call(interp, &item)?) {
+                    return Ok(Value::Option(Some(Box::new(item))));
+                }
+            }
+            Ok(Value::Option(None))
+        }
+        "any" | "all" => {
+            let want = method == "any";
+            for item in &items {
+                if is_truthy(&call(interp, item)?) == want {
+                    return Ok(Value::Bool(want));
+                }
+            }
+            Ok(Value::Bool(!want))
+        }
+        "flat_map" => {
+            let mut out = Vec::new();
+            for item in &items {
+                match call(interp, item)? {
+                    Value::List(v) => out.extend(v.borrow().iter().cloned()),
+                    other => out.push(other),
+                }
+            }
+            Ok(list(out))
+        }
+        "sort_by" => {
+            let mut keyed = Vec::with_capacity(items.len());
+            for item in items {
+                keyed.push((call(interp, &item)?, item));
+            }
+            keyed.sort_by(|a, b| sort_order(&a.0, &b.0));
+            Ok(list(keyed.into_iter().map(|(_, item)| item).collect()))
+        }
+        "group_by" => {
+            let mut groups: IndexMap<MapKey, Value> = IndexMap::new();
+            for item in items {
+                let group = MapKey::from_value(&call(interp, &item)?).map_err(Signal::Error)?;
+                let bucket = groups.entry(group).or_insert_with(|| if is_map { map(vec![]) } else { list(vec![]) });
+                match (bucket, item) {
+                    (Value::Map(m), item) => {
+                        let key = key_of(&item)?;
+                        if let Value::Tuple(mut kv) = item { m.borrow_mut().insert(key, kv.pop().unwrap_or(Value::Nil)); }
+                    }
+                    (Value::List(l), item) => l.borrow_mut().push(item),
+                    _ => {}
+                }
+            }
+            Ok(Value::map(groups))
+        }
+        _ => Err(Signal::Error(format!("No method '{}' on type {}", method, crate::interpreter::value_type_name(coll)))),
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+/// Order used by `sort` and `sort_by`: the order of `<`, so Int, BigInt and
+/// Float compare by value; anything that `<` rejects is left in place.
+fn sort_order(a: &Value, b: &Value) -> std::cmp::Ordering {
+    crate::interpreter::value_order(a, b).unwrap_or(std::cmp::Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::eval;
+
+    #[test]
+    fn mixed_numbers_sort_by_value() {
+        let src = "let b = 9223372036854775807 + 1\n[b, 1, b + 5, 2.5, -b, 2].sort()";
+        assert_eq!(eval(src).unwrap().to_string(), "[-9223372036854775808, 1, 2, 2.5, 9223372036854775808, 9223372036854775813]");
+        let src = "let b = 9223372036854775807 + 1\n[(\"x\", b), (\"y\", 1), (\"z\", 0.5)].sort_by(|(_, v)| v).map(|(k, _)| k)";
+        assert_eq!(eval(src).unwrap().to_string(), "[z, y, x]");
+    }
+}
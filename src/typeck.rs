@@ -0,0 +1,1682 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr Type Checker — the static pass behind `zephyr check`
+// ═══════════════════════════════════════════════════════════
+//
+// Uses the annotations the parser already records (let bindings,
+// params, return types, struct fields, generics, type aliases) and
+// infers everything else locally.
+//
+// The pass is gradual: whatever it cannot see through — natives,
+// unannotated params, values read out of untyped maps — becomes
+// `Ty::Unknown`, which is compatible with every type. Only mistakes
+// that would definitely go wrong at runtime are reported.
+//
+// What is checked
+// ───────────────
+//   let x: T = e        e is assignable to T
+//   f(a, b)             arity (defaults counted) and argument types;
+//                       generic params are unified per call
+//   return e            against the declared return type
+//   Point { x: 1 }      unknown, missing and mistyped fields
+//   p.field             the field exists on a known struct
+//   E::V(a)             the variant exists, payload arity and types
+//   a + b, a < b        operand types of arithmetic / comparisons
+//   e?                  operand is Option or Result
+//   impl T for X        T exists, every required method is provided
+//                       with the trait's arity, and no others
+//   names               undefined variables and unknown type names
+//
+// ═══════════════════════════════════════════════════════════
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::ast::*;
+use crate::interpreter::{self, Env};
+use crate::stdlib;
+
+// ── Types ─────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Int,
+    Float,
+    Bool,
+    Str,
+    Nil,
+    List(Box<Ty>),
+    Map(Box<Ty>, Box<Ty>),
+    Set(Box<Ty>),
+    Tuple(Vec<Ty>),
+    Option(Box<Ty>),
+    Result(Box<Ty>, Box<Ty>),
+    Named(String),          // struct or enum
+    Fn(Vec<Ty>, Box<Ty>),
+    Param(String),          // generic type parameter
+    Unknown,
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Int          => write!(f, "Int"),
+            Ty::Float        => write!(f, "Float"),
+            Ty::Bool         => write!(f, "Bool"),
+            Ty::Str          => write!(f, "String"),
+            Ty::Nil          => write!(f, "Nil"),
+            Ty::List(t)      => write!(f, "[{}]", t),
+            Ty::Map(k, v)    => write!(f, "Map<{}, {}>", k, v),
+            Ty::Set(t)       => write!(f, "Set<{}>", t),
+            Ty::Tuple(ts)    => write!(f, "({})", join_tys(ts)),
+            Ty::Option(t)    => write!(f, "Option<{}>", t),
+            Ty::Result(t, e) => write!(f, "Result<{}, {}>", t, e),
+            Ty::Named(n)     => write!(f, "{}", n),
+            Ty::Fn(ps, r)    => write!(f, "fun({}) -> {}", join_tys(ps), r),
+            Ty::Param(n)     => write!(f, "{}", n),
+            Ty::Unknown      => write!(f, "_"),
+        }
+    }
+}
+
+fn join_tys(ts: &[Ty]) -> String {
+    ts.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn is_numeric(t: &Ty) -> bool {
+    matches!(t, Ty::Int | Ty::Float)
+}
+
+/// Can a value of type `actual` be used where `expected` is required?
+fn compatible(expected: &Ty, actual: &Ty) -> bool {
+    match (expected, actual) {
+        (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+        (Ty::Param(_), _) | (_, Ty::Param(_)) => true,
+        (Ty::Float, Ty::Int) => true,
+        (Ty::Option(_), Ty::Nil) => true,
+        (Ty::Option(a), Ty::Option(b)) => compatible(a, b),
+        (Ty::List(a), Ty::List(b)) | (Ty::Set(a), Ty::Set(b)) => compatible(a, b),
+        (Ty::Map(k1, v1), Ty::Map(k2, v2)) => compatible(k1, k2) && compatible(v1, v2),
+        (Ty::Result(t1, e1), Ty::Result(t2, e2)) => compatible(t1, t2) && compatible(e1, e2),
+        (Ty::Tuple(a), Ty::Tuple(b)) => a.len() == b.len() && a.iter().zip(b).all(|(x, y)| compatible(x, y)),
+        (Ty::Fn(p1, r1), Ty::Fn(p2, r2)) => {
+            p1.len() == p2.len() && p1.iter().zip(p2).all(|(x, y)| compatible(y, x)) && compatible(r1, r2)
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// The common type of two branches, or Unknown when they disagree.
+fn join(a: &Ty, b: &Ty) -> Ty {
+    match (a, b) {
+        (Ty::Unknown, _) | (_, Ty::Unknown) => Ty::Unknown,
+        (Ty::Option(t), Ty::Nil) | (Ty::Nil, Ty::Option(t)) => Ty::Option(t.clone()),
+        (Ty::Result(t1, e1), Ty::Result(t2, e2)) => Ty::Result(Box::new(join(t1, t2)), Box::new(join(e1, e2))),
+        (Ty::List(x), Ty::List(y)) => Ty::List(Box::new(join(x, y))),
+        (Ty::Set(x), Ty::Set(y)) => Ty::Set(Box::new(join(x, y))),
+        (Ty::Option(x), Ty::Option(y)) => Ty::Option(Box::new(join(x, y))),
+        _ if a == b => a.clone(),
+        _ => Ty::Unknown,
+    }
+}
+
+fn join_all(tys: impl IntoIterator<Item = Ty>) -> Option<Ty> {
+    tys.into_iter().reduce(|acc, t| join(&acc, &t))
+}
+
+/// Replace generic params by their bindings; unbound params become Unknown.
+fn substitute(ty: &Ty, subst: &HashMap<String, Ty>) -> Ty {
+    match ty {
+        Ty::Param(p)      => subst.get(p).cloned().unwrap_or(Ty::Unknown),
+        Ty::List(t)       => Ty::List(Box::new(substitute(t, subst))),
+        Ty::Set(t)        => Ty::Set(Box::new(substitute(t, subst))),
+        Ty::Map(k, v)     => Ty::Map(Box::new(substitute(k, subst)), Box::new(substitute(v, subst))),
+        Ty::Tuple(ts)     => Ty::Tuple(ts.iter().map(|t| substitute(t, subst)).collect()),
+        Ty::Option(t)     => Ty::Option(Box::new(substitute(t, subst))),
+        Ty::Result(t, e)  => Ty::Result(Box::new(substitute(t, subst)), Box::new(substitute(e, subst))),
+        Ty::Fn(ps, r)     => Ty::Fn(ps.iter().map(|t| substitute(t, subst)).collect(), Box::new(substitute(r, subst))),
+        other             => other.clone(),
+    }
+}
+
+/// Bind the generic params in `param` from the argument type `arg`.
+/// Returns false when a param is bound to two incompatible types.
+fn unify(param: &Ty, arg: &Ty, subst: &mut HashMap<String, Ty>) -> bool {
+    match (param, arg) {
+        (_, Ty::Unknown) => true,
+        (Ty::Param(p), _) => match subst.get(p) {
+            Some(bound) => {
+                if compatible(bound, arg) { true }
+                else if compatible(arg, bound) { subst.insert(p.clone(), arg.clone()); true }
+                else { false }
+            }
+            None => { subst.insert(p.clone(), arg.clone()); true }
+        },
+        (Ty::List(a), Ty::List(b)) | (Ty::Set(a), Ty::Set(b)) | (Ty::Option(a), Ty::Option(b)) => unify(a, b, subst),
+        (Ty::Map(k1, v1), Ty::Map(k2, v2)) | (Ty::Result(k1, v1), Ty::Result(k2, v2)) => {
+            unify(k1, k2, subst) && unify(v1, v2, subst)
+        }
+        (Ty::Tuple(a), Ty::Tuple(b)) if a.len() == b.len() => a.iter().zip(b).all(|(x, y)| unify(x, y, subst)),
+        (Ty::Fn(p1, r1), Ty::Fn(p2, r2)) if p1.len() == p2.len() => {
+            p1.iter().zip(p2).all(|(x, y)| unify(x, y, subst)) && unify(r1, r2, subst)
+        }
+        _ => true,
+    }
+}
+
+// ── Diagnostics ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+}
+
+// ── Declarations ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+struct FnSig {
+    generics: Vec<String>,
+    params: Vec<(String, Ty, bool)>, // (name, type, has_default)
+    ret: Ty,
+    annotated_ret: bool,
+}
+
+impl FnSig {
+    fn as_ty(&self) -> Ty {
+        Ty::Fn(self.params.iter().map(|(_, t, _)| t.clone()).collect(), Box::new(self.ret.clone()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct VarInfo {
+    ty: Ty,
+    annotated: bool,
+    mutable: bool,
+}
+
+// ── Entry point ───────────────────────────────────────────────────────────────
+
+/// Type-check a parsed program. `path` is used to resolve `import`s.
+pub fn check_program(stmts: &[Stmt], path: Option<&Path>) -> Vec<Diagnostic> {
+    let mut checker = Checker::new(path);
+    checker.run(stmts);
+    checker.diags.sort_by_key(|d| (d.span.line, d.span.col));
+    checker.diags
+}
+
+/// The type of `expr` once `stmts` have run, as the REPL's `:type` shows it.
+pub fn type_of(stmts: &[Stmt], expr: &Expr) -> Ty {
+    let mut checker = Checker::new(None);
+    checker.run(stmts);
+    checker.expr(expr)
+}
+
+struct Checker {
+    natives: Env,
+    file: Option<PathBuf>,
+    structs: HashMap<String, Vec<(String, Ty)>>,                 // fields, keyed by qualified name
+    enums: HashMap<String, Vec<(String, Vec<Ty>)>>,              // variants, keyed by qualified name
+    aliases: HashMap<String, (Vec<String>, Type)>,
+    fns: HashMap<String, FnSig>,                                 // keyed by qualified name
+    methods: HashMap<String, HashMap<String, FnSig>>,
+    traits: HashMap<String, HashMap<String, (FnSig, bool)>>,    // method → (signature, has default)
+    imported: Vec<(Vec<String>, Vec<Stmt>)>,
+    imported_types: HashMap<String, String>,                     // pub type → qualified name
+    scopes: Vec<HashMap<String, VarInfo>>,
+    generics: Vec<String>,
+    returns: Vec<(String, Option<Ty>)>,                          // enclosing fn name, declared return
+    prefix: Vec<String>,                                         // enclosing module path
+    span: Span,
+    quiet: bool,
+    diags: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn new(path: Option<&Path>) -> Checker {
+        let natives = Env::new();
+        stdlib::register(&natives);
+        Checker {
+            natives,
+            file: path.map(Path::to_path_buf),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            aliases: HashMap::new(),
+            fns: HashMap::new(),
+            methods: HashMap::new(),
+            traits: HashMap::new(),
+            imported: Vec::new(),
+            imported_types: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            generics: Vec::new(),
+            returns: Vec::new(),
+            prefix: Vec::new(),
+            span: Span::default(),
+            quiet: false,
+            diags: Vec::new(),
+        }
+    }
+
+    fn run(&mut self, stmts: &[Stmt]) {
+        self.declare_names(stmts, &[]);
+        let imported = std::mem::take(&mut self.imported);
+        for (prefix, body) in &imported {
+            self.quiet = true;
+            self.declare_sigs(body, prefix);
+            self.quiet = false;
+        }
+        self.declare_sigs(stmts, &[]);
+        self.imported = imported;
+
+        self.check_block(stmts);
+    }
+
+    fn error(&mut self, message: String) {
+        if self.quiet { return; }
+        self.diags.push(Diagnostic { severity: Severity::Error, span: self.span, message });
+    }
+
+    fn warning(&mut self, message: String) {
+        if self.quiet { return; }
+        self.diags.push(Diagnostic { severity: Severity::Warning, span: self.span, message });
+    }
+
+    // ── Scopes ────────────────────────────────────────────────────────────────
+
+    fn push_scope(&mut self) { self.scopes.push(HashMap::new()); }
+    fn pop_scope(&mut self) { self.scopes.pop(); }
+
+    fn define(&mut self, name: &str, ty: Ty, annotated: bool) {
+        self.define_var(name, ty, annotated, true);
+    }
+
+    fn define_var(&mut self, name: &str, ty: Ty, annotated: bool, mutable: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), VarInfo { ty, annotated, mutable });
+        }
+    }
+
+    fn lookup_var(&self, name: &str) -> Option<&VarInfo> {
+        self.scopes.iter().rev().find_map(|s| s.get(name))
+    }
+
+    /// `name` qualified by each enclosing module path, innermost first.
+    fn scoped(&self, name: &str) -> Vec<String> {
+        (0..=self.prefix.len()).rev().map(|depth| {
+            let mut key = self.prefix[..depth].join("::");
+            if !key.is_empty() { key.push_str("::"); }
+            key.push_str(name);
+            key
+        }).collect()
+    }
+
+    /// Find a function by name, trying the enclosing module paths innermost first.
+    fn lookup_fn(&self, name: &str) -> Option<&FnSig> {
+        self.scoped(name).iter().find_map(|key| self.fns.get(key))
+    }
+
+    /// The qualified name of the struct or enum `name` refers to here:
+    /// declared in an enclosing module, or a pub type of an imported file.
+    fn type_name(&self, name: &str) -> Option<String> {
+        self.scoped(name).into_iter()
+            .find(|key| self.structs.contains_key(key) || self.enums.contains_key(key))
+            .or_else(|| self.imported_types.get(name).cloned())
+    }
+
+    fn struct_name(&self, name: &str) -> Option<String> {
+        self.type_name(name).filter(|n| self.structs.contains_key(n))
+    }
+
+    fn enum_name(&self, name: &str) -> Option<String> {
+        self.type_name(name).filter(|n| self.enums.contains_key(n))
+    }
+
+    // ── Declarations ──────────────────────────────────────────────────────────
+
+    /// First pass: record struct/enum/alias names so types can refer to each
+    /// other in any order, and load imported files.
+    fn declare_names(&mut self, stmts: &[Stmt], prefix: &[String]) {
+        let qualified = |name: &str| [prefix, &[name.to_string()]].concat().join("::");
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::StructDef(s) => { self.structs.insert(qualified(&s.name), Vec::new()); }
+                StmtKind::EnumDef(e)   => { self.enums.insert(qualified(&e.name), Vec::new()); }
+                StmtKind::TraitDef(t)  => { self.traits.insert(t.name.clone(), HashMap::new()); }
+                StmtKind::TypeAlias(name, generics, ty) => {
+                    self.aliases.insert(name.clone(), (generics.clone(), ty.clone()));
+                }
+                StmtKind::ModDef(name, body, _) => {
+                    let mut inner = prefix.to_vec();
+                    inner.push(name.clone());
+                    self.declare_names(body, &inner);
+                }
+                StmtKind::Import(path) if prefix.is_empty() => {
+                    self.span = stmt.span;
+                    self.load_import(path);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn load_import(&mut self, path: &[String]) {
+        let file = match interpreter::resolve_module_file(self.file.as_deref(), path) {
+            Ok(f) => f,
+            Err(e) => { self.error(e); return; }
+        };
+        let parsed = std::fs::read_to_string(&file).map_err(|e| e.to_string())
+            .and_then(|src| crate::lexer::Lexer::new(&src).tokenize())
+            .and_then(|tokens| crate::parser::Parser::new(tokens).parse_program());
+        match parsed {
+            Ok(body) => {
+                let last = path.last().cloned().unwrap_or_default();
+                for stmt in &body {
+                    match &stmt.kind {
+                        StmtKind::StructDef(s) => {
+                            let name = format!("{}::{}", last, s.name);
+                            if s.is_pub { self.imported_types.insert(s.name.clone(), name.clone()); }
+                            self.structs.insert(name, Vec::new());
+                        }
+                        StmtKind::EnumDef(e) => {
+                            let name = format!("{}::{}", last, e.name);
+                            if e.is_pub { self.imported_types.insert(e.name.clone(), name.clone()); }
+                            self.enums.insert(name, Vec::new());
+                        }
+                        StmtKind::TraitDef(t)  => { self.traits.insert(t.name.clone(), HashMap::new()); }
+                        StmtKind::TypeAlias(name, generics, ty) => {
+                            self.aliases.insert(name.clone(), (generics.clone(), ty.clone()));
+                        }
+                        _ => {}
+                    }
+                }
+                self.imported.push((vec![last], body));
+            }
+            Err(e) => self.error(format!("Cannot load module '{}': {}", path.join("."), e)),
+        }
+    }
+
+    /// Second pass: lower struct fields, enum payloads and function signatures.
+    fn declare_sigs(&mut self, stmts: &[Stmt], prefix: &[String]) {
+        // annotations resolve type names from inside the module
+        let outer = std::mem::replace(&mut self.prefix, prefix.to_vec());
+        for stmt in stmts {
+            self.span = stmt.span;
+            match &stmt.kind {
+                StmtKind::StructDef(s) => {
+                    let fields = s.fields.iter()
+                        .map(|f| (f.name.clone(), self.lower(&f.ty, &s.generics)))
+                        .collect();
+                    self.structs.insert(self.qualified(&s.name), fields);
+                }
+                StmtKind::EnumDef(e) => {
+                    let variants = e.variants.iter()
+                        .map(|v| (v.name.clone(), v.fields.iter().map(|t| self.lower(t, &e.generics)).collect()))
+                        .collect();
+                    self.enums.insert(self.qualified(&e.name), variants);
+                }
+                StmtKind::FunDef(f) => {
+                    let sig = self.signature(f, &[]);
+                    self.fns.insert(self.qualified(&f.name), sig);
+                }
+                StmtKind::TraitDef(t) => {
+                    let mut sigs = HashMap::new();
+                    for m in &t.required { sigs.insert(m.name.clone(), (self.signature(m, &[]), false)); }
+                    for m in &t.defaults { sigs.insert(m.name.clone(), (self.signature(m, &[]), true)); }
+                    self.traits.insert(t.name.clone(), sigs);
+                }
+                StmtKind::ImplBlock(block) => {
+                    let target = self.type_name(&block.target).unwrap_or_else(|| block.target.clone());
+                    for m in &block.methods {
+                        let sig = self.signature(m, &block.generics);
+                        self.methods.entry(target.clone()).or_default().insert(m.name.clone(), sig);
+                    }
+                }
+                StmtKind::ModDef(name, body, _) => {
+                    let mut inner = prefix.to_vec();
+                    inner.push(name.clone());
+                    self.declare_sigs(body, &inner);
+                }
+                _ => {}
+            }
+        }
+        // trait defaults become methods of each implementing type; done
+        // last so an impl may come before its trait in the file
+        for stmt in stmts {
+            if let StmtKind::ImplBlock(ImplBlock { trait_name: Some(t), target, .. }) = &stmt.kind {
+                let defaults: Vec<(String, FnSig)> = self.traits.get(t).into_iter().flatten()
+                    .filter(|(_, (_, has_default))| *has_default)
+                    .map(|(name, (sig, _))| (name.clone(), sig.clone()))
+                    .collect();
+                let target = self.type_name(target).unwrap_or_else(|| target.clone());
+                let methods = self.methods.entry(target).or_default();
+                for (name, sig) in defaults {
+                    methods.entry(name).or_insert(sig);
+                }
+            }
+        }
+        self.prefix = outer;
+    }
+
+    fn signature(&mut self, f: &FunDef, outer_generics: &[String]) -> FnSig {
+        let mut generics = outer_generics.to_vec();
+        generics.extend(f.generics.iter().cloned());
+        let params = f.params.iter()
+            .map(|p| {
+                let ty = p.ty.as_ref().map(|t| self.lower(t, &generics)).unwrap_or(Ty::Unknown);
+                (p.name.clone(), ty, p.default.is_some())
+            })
+            .collect();
+        let ret = f.return_type.as_ref().map(|t| self.lower(t, &generics)).unwrap_or(Ty::Unknown);
+        FnSig { generics, params, ret, annotated_ret: f.return_type.is_some() }
+    }
+
+    /// Turn a parsed annotation into a checker type.
+    fn lower(&mut self, ty: &Type, generics: &[String]) -> Ty {
+        match ty {
+            Type::Int      => Ty::Int,
+            Type::Float    => Ty::Float,
+            Type::Bool     => Ty::Bool,
+            Type::StringT  => Ty::Str,
+            Type::Nil      => Ty::Nil,
+            Type::Inferred => Ty::Unknown,
+            Type::Option(t)    => Ty::Option(Box::new(self.lower(t, generics))),
+            Type::Result(t, e) => Ty::Result(Box::new(self.lower(t, generics)), Box::new(self.lower(e, generics))),
+            Type::List(t)      => Ty::List(Box::new(self.lower(t, generics))),
+            Type::Map(k, v)    => Ty::Map(Box::new(self.lower(k, generics)), Box::new(self.lower(v, generics))),
+            Type::Tuple(ts)    => Ty::Tuple(ts.iter().map(|t| self.lower(t, generics)).collect()),
+            Type::Function(ps, r) => Ty::Fn(
+                ps.iter().map(|t| self.lower(t, generics)).collect(),
+                Box::new(self.lower(r, generics)),
+            ),
+            Type::Named(name) => self.lower_named(name, &[], generics),
+            Type::Generic(name, args) => {
+                let args: Vec<Ty> = args.iter().map(|t| self.lower(t, generics)).collect();
+                self.lower_named(name, &args, generics)
+            }
+        }
+    }
+
+    fn lower_named(&mut self, name: &str, args: &[Ty], generics: &[String]) -> Ty {
+        let arg = |i: usize| Box::new(args.get(i).cloned().unwrap_or(Ty::Unknown));
+        if generics.iter().any(|g| g == name) {
+            return Ty::Param(name.to_string());
+        }
+        match name {
+            "List"   => return Ty::List(arg(0)),
+            "Map"    => return Ty::Map(arg(0), arg(1)),
+            "Set"    => return Ty::Set(arg(0)),
+            "Option" => return Ty::Option(arg(0)),
+            "Result" => return Ty::Result(arg(0), arg(1)),
+            "Any" | "Fun" | "Fn" | "Function" | "Regex" | "DateTime" | "Duration" => return Ty::Unknown,
+            _ => {}
+        }
+        if let Some(qualified) = self.type_name(name) {
+            return Ty::Named(qualified);
+        }
+        // values typed by a trait are dispatched at runtime
+        if self.traits.contains_key(name) {
+            return Ty::Unknown;
+        }
+        if let Some((params, body)) = self.aliases.get(name).cloned() {
+            let lowered = self.lower(&body, &params);
+            let subst = params.iter().cloned().zip(args.iter().cloned()).collect();
+            return substitute(&lowered, &subst);
+        }
+        self.error(format!("Unknown type '{}'", name));
+        Ty::Unknown
+    }
+
+    // ── Statements ────────────────────────────────────────────────────────────
+
+    fn check_block(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.check_stmt(stmt);
+        }
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        self.span = stmt.span;
+        match &stmt.kind {
+            StmtKind::Let(pat, ann, expr, mutable) => {
+                let actual = self.expr(expr);
+                self.span = stmt.span;
+                match ann {
+                    Some(t) => {
+                        let generics = self.generics.clone();
+                        let declared = self.lower(t, &generics);
+                        if !compatible(&declared, &actual) {
+                            self.error(format!("'{}' is declared as {} but initialised with {}", pat, declared, actual));
+                        }
+                        self.bind_let(pat, declared, true, *mutable);
+                    }
+                    None => self.bind_let(pat, actual, false, *mutable),
+                }
+            }
+
+            StmtKind::Expr(expr) => { self.expr(expr); }
+
+            StmtKind::Return(expr) => {
+                let actual = match expr {
+                    Some(e) => self.expr(e),
+                    None => Ty::Nil,
+                };
+                self.span = stmt.span;
+                if let Some((fname, Some(declared))) = self.returns.last().cloned() {
+                    if !compatible(&declared, &actual) {
+                        self.error(format!("'{}' returns {} but this returns {}", fname, declared, actual));
+                    }
+                }
+            }
+
+            StmtKind::Break | StmtKind::Continue => {}
+
+            StmtKind::While(cond, body, _) => {
+                self.expr(cond);
+                self.push_scope();
+                self.check_block(body);
+                self.pop_scope();
+            }
+
+            StmtKind::For(pat, iter, body, _) => {
+                let iter_ty = self.expr(iter);
+                let elem = match iter_ty {
+                    Ty::List(t) | Ty::Set(t) => *t,
+                    Ty::Str => Ty::Str,
+                    Ty::Map(k, v) => Ty::Tuple(vec![*k, *v]),
+                    // tuples, and structs or enums with a next() method
+                    Ty::Tuple(_) | Ty::Named(_) | Ty::Unknown | Ty::Param(_) => Ty::Unknown,
+                    other => {
+                        self.error(format!("Cannot iterate over {}", other));
+                        Ty::Unknown
+                    }
+                };
+                self.push_scope();
+                self.span = stmt.span;
+                self.bind_pattern(pat, &elem);
+                self.check_block(body);
+                self.pop_scope();
+            }
+
+            StmtKind::FunDef(f) => {
+                // Top-level and module functions were declared up front;
+                // functions nested in a body are only visible after this point.
+                let declared = if self.returns.is_empty() { self.fns.get(&self.qualified(&f.name)).cloned() } else { None };
+                let sig = match declared {
+                    Some(sig) => sig,
+                    None => {
+                        let generics = self.generics.clone();
+                        let sig = self.signature(f, &generics);
+                        self.define(&f.name, sig.as_ty(), false);
+                        sig
+                    }
+                };
+                self.check_fn(f, &sig, None);
+            }
+
+            StmtKind::TraitDef(t) => {
+                for m in &t.defaults {
+                    let sig = self.traits.get(&t.name)
+                        .and_then(|ms| ms.get(&m.name))
+                        .map(|(sig, _)| sig.clone())
+                        .unwrap_or_else(|| self.signature(m, &[]));
+                    self.check_fn(m, &sig, None);
+                }
+            }
+
+            StmtKind::ImplBlock(block) => {
+                if let Some(t) = &block.trait_name {
+                    self.check_trait_impl(t, block);
+                }
+                let target = self.type_name(&block.target).unwrap_or_else(|| block.target.clone());
+                let self_ty = self_type(&target);
+                for m in &block.methods {
+                    let sig = self.methods.get(&target)
+                        .and_then(|ms| ms.get(&m.name))
+                        .cloned()
+                        .unwrap_or_else(|| self.signature(m, &block.generics));
+                    self.check_fn(m, &sig, Some(self_ty.clone()));
+                }
+            }
+
+            StmtKind::ModDef(name, body, _) => {
+                self.prefix.push(name.clone());
+                self.push_scope();
+                self.check_block(body);
+                self.pop_scope();
+                self.prefix.pop();
+                self.define(name, Ty::Unknown, false);
+            }
+
+            StmtKind::Import(path) => {
+                let last = path.last().cloned().unwrap_or_default();
+                self.define(&last, Ty::Unknown, false);
+                let exported: Vec<(String, Ty)> = self.imported.iter()
+                    .filter(|(prefix, _)| prefix.first() == Some(&last))
+                    .flat_map(|(_, body)| body.iter())
+                    .filter_map(|s| match &s.kind {
+                        StmtKind::FunDef(f) if f.is_pub => {
+                            let sig = self.fns.get(&format!("{}::{}", last, f.name))?;
+                            Some((f.name.clone(), sig.as_ty()))
+                        }
+                        StmtKind::ModDef(n, _, true) => Some((n.clone(), Ty::Unknown)),
+                        _ => None,
+                    })
+                    .collect();
+                for (name, ty) in exported {
+                    self.define(&name, ty, false);
+                }
+            }
+
+            StmtKind::StructDef(_) | StmtKind::EnumDef(_) | StmtKind::TypeAlias(..) => {}
+        }
+    }
+
+    fn check_trait_impl(&mut self, trait_name: &str, block: &ImplBlock) {
+        let Some(sigs) = self.traits.get(trait_name).cloned() else {
+            self.error(format!("Unknown trait '{}'", trait_name));
+            return;
+        };
+        for m in &block.methods {
+            match sigs.get(&m.name) {
+                None => self.error(format!("Method '{}' is not a member of trait '{}'", m.name, trait_name)),
+                Some((sig, _)) if sig.params.len() != m.params.len() => self.error(format!(
+                    "Method '{}' takes {} parameter(s) in trait '{}' but {} in impl for {}",
+                    m.name, sig.params.len(), trait_name, m.params.len(), block.target
+                )),
+                Some(_) => {}
+            }
+        }
+        let mut missing: Vec<&String> = sigs.iter()
+            .filter(|(name, (_, has_default))| !has_default && !block.methods.iter().any(|m| &m.name == *name))
+            .map(|(name, _)| name)
+            .collect();
+        if !missing.is_empty() {
+            missing.sort();
+            let list = missing.iter().map(|m| format!("'{}'", m)).collect::<Vec<_>>().join(", ");
+            self.error(format!("impl {} for {} is missing {}", trait_name, block.target, list));
+        }
+    }
+
+    fn qualified(&self, name: &str) -> String {
+        let mut segs = self.prefix.clone();
+        segs.push(name.to_string());
+        segs.join("::")
+    }
+
+    fn check_fn(&mut self, f: &FunDef, sig: &FnSig, self_ty: Option<Ty>) {
+        let saved_generics = std::mem::replace(&mut self.generics, sig.generics.clone());
+        self.push_scope();
+        for (i, (name, ty, _)) in sig.params.iter().enumerate() {
+            let ty = match (&self_ty, i, ty) {
+                (Some(st), 0, Ty::Unknown) if name == "self" => st.clone(),
+                _ => ty.clone(),
+            };
+            self.define(name, ty, true);
+        }
+        for (param, (_, ty, _)) in f.params.iter().zip(&sig.params) {
+            if let Some(default) = &param.default {
+                let actual = self.expr(default);
+                if !compatible(ty, &actual) {
+                    self.error(format!("Default for '{}' is {} but the parameter is {}", param.name, actual, ty));
+                }
+            }
+        }
+        let declared = if sig.annotated_ret { Some(sig.ret.clone()) } else { None };
+        self.returns.push((f.name.clone(), declared.clone()));
+        match (f.body.split_last(), &declared) {
+            (Some((Stmt { kind: StmtKind::Expr(tail), span }, init)), Some(ret)) => {
+                self.check_block(init);
+                self.span = *span;
+                self.check_tail(tail, ret, &f.name);
+            }
+            _ => self.check_block(&f.body),
+        }
+        self.returns.pop();
+        self.pop_scope();
+        self.generics = saved_generics;
+    }
+
+    /// Check the value a function body ends with against its declared
+    /// return type, branch by branch so an `if` can't hide a mismatch.
+    fn check_tail(&mut self, tail: &Expr, declared: &Ty, fname: &str) {
+        match &tail.kind {
+            ExprKind::If(cond, then, elifs, Some(els)) => {
+                self.expr(cond);
+                self.check_tail(then, declared, fname);
+                for (c, body) in elifs {
+                    self.expr(c);
+                    self.check_tail(body, declared, fname);
+                }
+                self.check_tail(els, declared, fname);
+            }
+            // `if` branches keep their value as the last statement
+            ExprKind::Block(stmts, value, _) => {
+                self.push_scope();
+                match (value, stmts.split_last()) {
+                    (Some(value), _) => {
+                        self.check_block(stmts);
+                        self.check_tail(value, declared, fname);
+                    }
+                    (None, Some((Stmt { kind: StmtKind::Expr(last), span }, init))) => {
+                        self.check_block(init);
+                        self.span = *span;
+                        self.check_tail(last, declared, fname);
+                    }
+                    // ends in a return or another statement
+                    (None, _) => self.check_block(stmts),
+                }
+                self.pop_scope();
+            }
+            _ => {
+                let span = self.span;
+                let actual = self.expr(tail);
+                self.span = span;
+                if !compatible(declared, &actual) {
+                    self.error(format!("'{}' returns {} but its body ends with {}", fname, declared, actual));
+                }
+            }
+        }
+    }
+
+    // ── Expressions ───────────────────────────────────────────────────────────
+
+    fn exprs(&mut self, es: &[Expr]) -> Vec<Ty> {
+        es.iter().map(|e| self.expr(e)).collect()
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Ty {
+        match &expr.kind {
+            ExprKind::Int(_)   => Ty::Int,
+            ExprKind::Float(_) => Ty::Float,
+            ExprKind::Bool(_)  => Ty::Bool,
+            ExprKind::Nil      => Ty::Nil,
+            ExprKind::StringLit(_) => Ty::Str,
+
+            ExprKind::InterpolatedString(parts) => {
+                for part in parts {
+                    if let StringPart::Interpolated(e, _) = part { self.expr(e); }
+                }
+                Ty::Str
+            }
+
+            ExprKind::Var(name, _) => {
+                if let Some(v) = self.lookup_var(name) {
+                    return v.ty.clone();
+                }
+                if let Some(sig) = self.lookup_fn(name) {
+                    return sig.as_ty();
+                }
+                match self.natives.get(name) {
+                    Some(interpreter::Value::Float(_)) => return Ty::Float,
+                    Some(_) => {}
+                    None => self.error(format!("Undefined variable '{}'", name)),
+                }
+                Ty::Unknown
+            }
+
+            ExprKind::Tuple(es) => Ty::Tuple(self.exprs(es)),
+
+            ExprKind::List(es) => {
+                let tys = self.exprs(es);
+                Ty::List(Box::new(join_all(tys).unwrap_or(Ty::Unknown)))
+            }
+
+            ExprKind::SetLit(es) => {
+                let tys = self.exprs(es);
+                Ty::Set(Box::new(join_all(tys).unwrap_or(Ty::Unknown)))
+            }
+
+            ExprKind::MapLit(pairs) => {
+                let mut keys = Vec::new();
+                let mut vals = Vec::new();
+                for (k, v) in pairs {
+                    keys.push(self.expr(k));
+                    vals.push(self.expr(v));
+                }
+                Ty::Map(
+                    Box::new(join_all(keys).unwrap_or(Ty::Unknown)),
+                    Box::new(join_all(vals).unwrap_or(Ty::Unknown)),
+                )
+            }
+
+            ExprKind::Block(stmts, tail, _) => {
+                let span = self.span;
+                self.push_scope();
+                self.check_block(stmts);
+                let ty = match tail {
+                    Some(e) => self.expr(e),
+                    None => Ty::Nil,
+                };
+                self.pop_scope();
+                self.span = span;
+                ty
+            }
+
+            ExprKind::BinOp(l, op, r) => {
+                let lt = self.expr(l);
+                let rt = self.expr(r);
+                self.binop(&lt, op, &rt)
+            }
+
+            ExprKind::UnaryOp(UnaryOp::Neg, e) => {
+                let t = self.expr(e);
+                if !is_numeric(&t) && t != Ty::Unknown {
+                    self.error(format!("Cannot negate {}", t));
+                    return Ty::Unknown;
+                }
+                t
+            }
+            ExprKind::UnaryOp(UnaryOp::Not, e) => { self.expr(e); Ty::Bool }
+
+            ExprKind::Assign(target, value) => {
+                let vt = self.expr(value);
+                match &target.kind {
+                    ExprKind::Var(name, _) => match self.lookup_var(name).cloned() {
+                        Some(info) => {
+                            if !info.mutable {
+                                self.warning(format!("'{}' is declared with let; use var if it is meant to change", name));
+                            }
+                            if info.annotated && !compatible(&info.ty, &vt) {
+                                self.error(format!("Cannot assign {} to '{}' of type {}", vt, name, info.ty));
+                            }
+                        }
+                        None => self.define(name, vt, false),
+                    },
+                    ExprKind::FieldAccess(obj, field) => {
+                        let ot = self.expr(obj);
+                        if let Some(ft) = self.field_type(&ot, field) {
+                            if !compatible(&ft, &vt) {
+                                self.error(format!("Cannot assign {} to field '{}' of type {}", vt, field, ft));
+                            }
+                        }
+                    }
+                    _ => { self.expr(target); }
+                }
+                Ty::Nil
+            }
+
+            ExprKind::Call(callee, args) => self.call(callee, args),
+
+            ExprKind::MethodCall(obj, method, args) => {
+                if let ExprKind::Var(m, _) = &obj.kind {
+                    let key = format!("{}::{}", m, method);
+                    if self.lookup_var(m).is_some_and(|v| v.ty == Ty::Unknown) {
+                        if let Some(sig) = self.lookup_fn(&key).cloned() {
+                            return self.check_call(&key, &sig, args, 0);
+                        }
+                    }
+                }
+                let ot = self.expr(obj);
+                if let Some(type_name) = method_owner(&ot) {
+                    if let Some(sig) = self.methods.get(&type_name).and_then(|ms| ms.get(method)).cloned() {
+                        let skip = usize::from(sig.params.first().is_some_and(|(n, _, _)| n == "self"));
+                        return self.check_call(&format!("{}.{}", type_name, method), &sig, args, skip);
+                    }
+                }
+                self.exprs(args);
+                builtin_method_type(&ot, method)
+            }
+
+            ExprKind::FieldAccess(obj, field) => {
+                let ot = self.expr(obj);
+                match self.field_type(&ot, field) {
+                    Some(t) => t,
+                    None => match &ot {
+                        Ty::Named(n) if self.structs.contains_key(n) => {
+                            self.error(format!("Struct '{}' has no field '{}'", n, field));
+                            Ty::Unknown
+                        }
+                        Ty::Tuple(ts) => {
+                            self.error(format!("Tuple index '{}' out of range for {}", field, Ty::Tuple(ts.clone())));
+                            Ty::Unknown
+                        }
+                        Ty::Int | Ty::Float | Ty::Bool | Ty::Str | Ty::Nil | Ty::List(_) => {
+                            self.error(format!("Cannot access field '{}' on {}", field, ot));
+                            Ty::Unknown
+                        }
+                        _ => Ty::Unknown,
+                    },
+                }
+            }
+
+            ExprKind::Index(obj, idx) => {
+                let ot = self.expr(obj);
+                let it = self.expr(idx);
+                match ot {
+                    Ty::List(t) => {
+                        if !compatible(&Ty::Int, &it) || it == Ty::Float {
+                            self.error(format!("List index must be Int, got {}", it));
+                        }
+                        *t
+                    }
+                    Ty::Str => Ty::Str,
+                    Ty::Map(_, v) => *v,
+                    Ty::Unknown => Ty::Unknown,
+                    other => {
+                        self.error(format!("Cannot index {}", other));
+                        Ty::Unknown
+                    }
+                }
+            }
+
+            ExprKind::If(cond, then, elifs, els) => {
+                self.expr(cond);
+                let mut tys = vec![self.expr(then)];
+                for (c, body) in elifs {
+                    self.expr(c);
+                    tys.push(self.expr(body));
+                }
+                match els {
+                    Some(e) => {
+                        tys.push(self.expr(e));
+                        join_all(tys).unwrap_or(Ty::Unknown)
+                    }
+                    None => Ty::Unknown,
+                }
+            }
+
+            ExprKind::Match(subject, arms) => {
+                let st = self.expr(subject);
+                let span = self.span;
+                let mut tys = Vec::new();
+                for arm in arms {
+                    self.push_scope();
+                    self.bind_pattern(&arm.pattern, &st);
+                    if let Some(g) = &arm.guard { self.expr(g); }
+                    tys.push(self.expr(&arm.body));
+                    self.pop_scope();
+                    self.span = span;
+                }
+                if let Some((enum_name, missing)) = self.uncovered_variants(&st, arms) {
+                    let missing: Vec<String> = missing.iter().map(|v| format!("{}::{}", enum_name, v)).collect();
+                    self.warning(format!("Non-exhaustive match on {}: no arm for {}", enum_name, missing.join(", ")));
+                }
+                join_all(tys).unwrap_or(Ty::Unknown)
+            }
+
+            ExprKind::Closure(params, body, _) => {
+                let generics = self.generics.clone();
+                let ptys: Vec<Ty> = params.iter()
+                    .map(|(_, t)| t.as_ref().map(|t| self.lower(t, &generics)).unwrap_or(Ty::Unknown))
+                    .collect();
+                self.push_scope();
+                for ((name, _), ty) in params.iter().zip(&ptys) {
+                    self.define(name, ty.clone(), true);
+                }
+                self.returns.push(("<closure>".into(), None));
+                let rt = self.expr(body);
+                self.returns.pop();
+                self.pop_scope();
+                Ty::Fn(ptys, Box::new(rt))
+            }
+
+            ExprKind::StructCreate(name, fields) => {
+                let vals: Vec<(String, Ty)> = fields.iter().map(|(f, e)| (f.clone(), self.expr(e))).collect();
+                let Some(qualified) = self.struct_name(name) else {
+                    self.error(format!("Unknown struct '{}'", name));
+                    return Ty::Unknown;
+                };
+                let decl = self.structs[&qualified].clone();
+                for (fname, actual) in &vals {
+                    match decl.iter().find(|(n, _)| n == fname) {
+                        Some((_, declared)) if !compatible(declared, actual) => {
+                            self.error(format!("Field '{}.{}' is {} but got {}", qualified, fname, declared, actual));
+                        }
+                        Some(_) => {}
+                        None => self.error(format!("Struct '{}' has no field '{}'", qualified, fname)),
+                    }
+                }
+                for (fname, _) in &decl {
+                    if !vals.iter().any(|(n, _)| n == fname) {
+                        self.error(format!("Missing field '{}' in '{}' literal", fname, qualified));
+                    }
+                }
+                Ty::Named(qualified)
+            }
+
+            ExprKind::EnumVariant(enum_name, variant, args) => {
+                let key = format!("{}::{}", enum_name, variant);
+                let resolved = self.enum_name(enum_name);
+                if resolved.is_none() {
+                    if let Some(sig) = self.lookup_fn(&key).cloned() {
+                        return self.check_call(&key, &sig, args, 0);
+                    }
+                }
+                let tys = self.exprs(args);
+                self.variant(resolved.as_deref().unwrap_or(enum_name), variant, &tys)
+            }
+
+            ExprKind::Path(segs) => {
+                let key = segs.join("::");
+                if let Some(sig) = self.lookup_fn(&key) {
+                    return sig.as_ty();
+                }
+                match segs.split_last() {
+                    Some((v, init)) if !init.is_empty() => match self.enum_name(&init.join("::")) {
+                        Some(e) => self.variant(&e, v, &[]),
+                        None => Ty::Unknown,
+                    },
+                    _ => Ty::Unknown,
+                }
+            }
+
+            ExprKind::Range(lo, hi) => {
+                for e in [lo, hi] {
+                    let t = self.expr(e);
+                    if !compatible(&Ty::Int, &t) || t == Ty::Float {
+                        self.error(format!("Range bounds must be Int, got {}", t));
+                    }
+                }
+                Ty::List(Box::new(Ty::Int))
+            }
+
+            ExprKind::Some(e) => Ty::Option(Box::new(self.expr(e))),
+            ExprKind::Ok(e)   => Ty::Result(Box::new(self.expr(e)), Box::new(Ty::Unknown)),
+            ExprKind::Err(e)  => Ty::Result(Box::new(Ty::Unknown), Box::new(self.expr(e))),
+
+            ExprKind::Question(e) => {
+                let t = self.expr(e);
+                if let Some((fname, Some(ret))) = self.returns.last().cloned() {
+                    if !matches!(ret, Ty::Result(..) | Ty::Option(_) | Ty::Unknown | Ty::Param(_)) {
+                        self.error(format!("'?' used in '{}', which returns {}", fname, ret));
+                    }
+                }
+                match t {
+                    Ty::Result(ok, _) => *ok,
+                    Ty::Option(inner) => *inner,
+                    Ty::Unknown => Ty::Unknown,
+                    other => {
+                        self.error(format!("'?' needs an Option or Result, got {}", other));
+                        Ty::Unknown
+                    }
+                }
+            }
+
+            ExprKind::BoxExpr(e) | ExprKind::Await(e) => self.expr(e),
+            ExprKind::RefExpr(e) => { self.expr(e); Ty::Unknown }
+        }
+    }
+
+    fn binop(&mut self, l: &Ty, op: &BinOp, r: &Ty) -> Ty {
+        let unknown = *l == Ty::Unknown || *r == Ty::Unknown
+            || matches!(l, Ty::Param(_)) || matches!(r, Ty::Param(_));
+        match op {
+            BinOp::Add => match (l, r) {
+                (Ty::Int, Ty::Int) => Ty::Int,
+                _ if is_numeric(l) && is_numeric(r) => Ty::Float,
+                (Ty::Str, _) => Ty::Str,
+                (Ty::List(a), Ty::List(b)) => Ty::List(Box::new(join(a, b))),
+                _ if unknown => Ty::Unknown,
+                _ => { self.error(format!("Cannot add {} and {}", l, r)); Ty::Unknown }
+            },
+            BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => match (l, r) {
+                (Ty::Int, Ty::Int) => Ty::Int,
+                (Ty::Str, Ty::Int) if *op == BinOp::Mul => Ty::Str,
+                _ if is_numeric(l) && is_numeric(r) => Ty::Float,
+                _ if unknown => Ty::Unknown,
+                _ => {
+                    self.error(format!("Arithmetic on {} and {}", l, r));
+                    Ty::Unknown
+                }
+            },
+            BinOp::Eq | BinOp::NotEq => Ty::Bool,
+            BinOp::Lt | BinOp::LtEq | BinOp::Gt | BinOp::GtEq => {
+                let ok = unknown || (is_numeric(l) && is_numeric(r)) || (*l == Ty::Str && *r == Ty::Str);
+                if !ok { self.error(format!("Cannot compare {} and {}", l, r)); }
+                Ty::Bool
+            }
+            BinOp::And => Ty::Bool,
+            BinOp::Or => join(l, r),
+            BinOp::DotDot => Ty::List(Box::new(Ty::Int)),
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Expr]) -> Ty {
+        match &callee.kind {
+            ExprKind::Var(name, _) if self.lookup_var(name).is_none_or(|v| v.ty == Ty::Unknown) => {
+                if let Some(sig) = self.lookup_fn(name).cloned() {
+                    return self.check_call(name, &sig, args, 0);
+                }
+                if self.lookup_var(name).is_none() && self.natives.get(name).is_some() {
+                    let tys = self.exprs(args);
+                    return native_return_type(name, &tys);
+                }
+            }
+            ExprKind::Path(segs) => {
+                let key = segs.join("::");
+                if let Some(sig) = self.lookup_fn(&key).cloned() {
+                    return self.check_call(&key, &sig, args, 0);
+                }
+                if let Some((v, init)) = segs.split_last() {
+                    if let Some(e) = self.enum_name(&init.join("::")) {
+                        let tys = self.exprs(args);
+                        return self.variant(&e, v, &tys);
+                    }
+                }
+            }
+            _ => {}
+        }
+        let ct = self.expr(callee);
+        let arg_tys = self.exprs(args);
+        match ct {
+            Ty::Fn(params, ret) => {
+                if params.len() != arg_tys.len() {
+                    self.error(format!("Function expects {} argument(s), got {}", params.len(), arg_tys.len()));
+                } else {
+                    for (i, (p, a)) in params.iter().zip(&arg_tys).enumerate() {
+                        if !compatible(p, a) {
+                            self.error(format!("Argument {} has type {}, expected {}", i + 1, a, p));
+                        }
+                    }
+                }
+                *ret
+            }
+            Ty::Unknown | Ty::Param(_) => Ty::Unknown,
+            other => {
+                self.error(format!("{} is not callable", other));
+                Ty::Unknown
+            }
+        }
+    }
+
+    /// Check a call against a known signature. `skip` drops leading params
+    /// that are supplied implicitly (the receiver of a method call).
+    fn check_call(&mut self, name: &str, sig: &FnSig, args: &[Expr], skip: usize) -> Ty {
+        let arg_tys = self.exprs(args);
+        let params = &sig.params[skip.min(sig.params.len())..];
+        let required = params.iter().filter(|(_, _, has_default)| !has_default).count();
+        if arg_tys.len() < required || arg_tys.len() > params.len() {
+            let expected = if required == params.len() {
+                format!("{}", required)
+            } else {
+                format!("{} to {}", required, params.len())
+            };
+            self.error(format!("'{}' expects {} argument(s), got {}", name, expected, arg_tys.len()));
+        }
+        let mut subst = HashMap::new();
+        for ((pname, pty, _), aty) in params.iter().zip(&arg_tys) {
+            if !unify(pty, aty, &mut subst) {
+                self.error(format!("Argument '{}' of '{}' conflicts with an earlier use of its generic type: got {}", pname, name, aty));
+                continue;
+            }
+            let expected = substitute(pty, &subst);
+            if !compatible(&expected, aty) {
+                self.error(format!("Argument '{}' of '{}' expects {}, got {}", pname, name, expected, aty));
+            }
+        }
+        if !sig.generics.is_empty() && self.generics.is_empty() {
+            substitute(&sig.ret, &subst)
+        } else {
+            let generics: HashSet<&String> = sig.generics.iter().collect();
+            let keep: HashMap<String, Ty> = self.generics.iter()
+                .filter(|g| !generics.contains(g) || subst.contains_key(*g))
+                .map(|g| (g.clone(), subst.get(g).cloned().unwrap_or(Ty::Param(g.clone()))))
+                .collect();
+            substitute(&sig.ret, &subst.into_iter().chain(keep).collect())
+        }
+    }
+
+    fn variant(&mut self, enum_name: &str, variant: &str, args: &[Ty]) -> Ty {
+        let Some(variants) = self.enums.get(enum_name).cloned() else {
+            if self.lookup_var(enum_name).is_none() {
+                self.error(format!("Unknown enum '{}'", enum_name));
+            }
+            return Ty::Unknown;
+        };
+        match variants.iter().find(|(n, _)| n == variant) {
+            None => self.error(format!("Enum '{}' has no variant '{}'", enum_name, variant)),
+            Some((_, fields)) if !args.is_empty() && fields.len() != args.len() => {
+                self.error(format!("'{}::{}' takes {} value(s), got {}", enum_name, variant, fields.len(), args.len()));
+            }
+            Some((_, fields)) => {
+                for (i, (f, a)) in fields.iter().zip(args).enumerate() {
+                    if !compatible(f, a) {
+                        self.error(format!("'{}::{}' field {} is {}, got {}", enum_name, variant, i + 1, f, a));
+                    }
+                }
+            }
+        }
+        Ty::Named(enum_name.to_string())
+    }
+
+    fn field_type(&self, ty: &Ty, field: &str) -> Option<Ty> {
+        match ty {
+            Ty::Named(n) => {
+                let fields = self.structs.get(n)?;
+                fields.iter().find(|(f, _)| f == field).map(|(_, t)| substitute(t, &HashMap::new()))
+            }
+            Ty::Tuple(ts) => field.parse::<usize>().ok().and_then(|i| ts.get(i).cloned()),
+            _ => None,
+        }
+    }
+
+    // ── Patterns ──────────────────────────────────────────────────────────────
+
+    /// Bind what a `let`/`var` pattern names, mutable only under `var`.
+    fn bind_let(&mut self, pat: &Pattern, ty: Ty, annotated: bool, mutable: bool) {
+        if let Pattern::Ident(name) = pat {
+            return self.define_var(name, ty, annotated, mutable);
+        }
+        self.bind_pattern(pat, &ty);
+        let mut names = Vec::new();
+        crate::compiler::pattern_names(pat, &mut names);
+        if let Some(scope) = self.scopes.last_mut() {
+            for n in names {
+                if let Some(info) = scope.get_mut(&n) { info.mutable = mutable; }
+            }
+        }
+    }
+
+    /// The variants of the enum being matched that no unguarded arm covers,
+    /// or None when the match is not over an enum or covers all of it.
+    fn uncovered_variants(&self, subject: &Ty, arms: &[MatchArm]) -> Option<(String, Vec<String>)> {
+        let enum_name = match subject {
+            Ty::Named(n) if self.enums.contains_key(n) => n.clone(),
+            _ => arms.iter().find_map(|a| enum_of(&a.pattern)).and_then(|e| self.enum_name(&e))?,
+        };
+        let variants = self.enums.get(&enum_name)?;
+        let is_subject = |e: &str| self.enum_name(e).as_ref() == Some(&enum_name);
+        let mut covered = HashSet::new();
+        for arm in arms.iter().filter(|a| a.guard.is_none()) {
+            if covers_variants(&arm.pattern, &is_subject, &mut covered) {
+                return None;
+            }
+        }
+        let missing: Vec<String> = variants.iter()
+            .map(|(v, _)| v)
+            .filter(|v| !covered.contains(*v))
+            .cloned()
+            .collect();
+        (!missing.is_empty()).then_some((enum_name, missing))
+    }
+
+    fn bind_pattern(&mut self, pat: &Pattern, ty: &Ty) {
+        let mismatch = |this: &mut Self, what: &str| {
+            this.error(format!("{} pattern can never match a value of type {}", what, ty));
+        };
+        match pat {
+            Pattern::Wildcard | Pattern::Range(..) => {}
+            Pattern::Ident(name) => self.define(name, ty.clone(), false),
+            Pattern::Int(_) if !compatible(ty, &Ty::Int) => mismatch(self, "Int"),
+            Pattern::Float(_) if !compatible(ty, &Ty::Float) => mismatch(self, "Float"),
+            Pattern::Bool(_) if !compatible(ty, &Ty::Bool) => mismatch(self, "Bool"),
+            Pattern::StringLit(_) if !compatible(ty, &Ty::Str) => mismatch(self, "String"),
+            Pattern::Int(_) | Pattern::Float(_) | Pattern::Bool(_) | Pattern::StringLit(_) | Pattern::Nil => {}
+            Pattern::Tuple(ps) => match ty {
+                Ty::Tuple(ts) if ts.len() == ps.len() => {
+                    for (p, t) in ps.iter().zip(ts) { self.bind_pattern(p, t); }
+                }
+                Ty::Tuple(_) => mismatch(self, &format!("{}-element tuple", ps.len())),
+                Ty::Unknown | Ty::Param(_) => for p in ps { self.bind_pattern(p, &Ty::Unknown); },
+                _ => mismatch(self, "Tuple"),
+            },
+            Pattern::List(ps) => {
+                let elem = match ty {
+                    Ty::List(t) => (**t).clone(),
+                    Ty::Unknown | Ty::Param(_) => Ty::Unknown,
+                    _ => { mismatch(self, "List"); Ty::Unknown }
+                };
+                let rest = Ty::List(Box::new(elem.clone()));
+                for p in ps {
+                    self.bind_pattern(p, if matches!(p, Pattern::Rest(_)) { &rest } else { &elem });
+                }
+            }
+            Pattern::Rest(None) => {}
+            Pattern::Rest(Some(name)) => self.define(name, ty.clone(), false),
+            Pattern::Bind(name, p) => {
+                self.define(name, ty.clone(), false);
+                self.bind_pattern(p, ty);
+            }
+            Pattern::Struct(name, fields, rest) => {
+                let resolved = self.struct_name(name);
+                let st = Ty::Named(resolved.clone().unwrap_or_else(|| name.clone()));
+                if !compatible(ty, &st) { mismatch(self, name); }
+                if let Some(declared) = resolved.as_ref().and_then(|n| self.structs.get(n)).filter(|_| !rest) {
+                    let missing: Vec<&str> = declared.iter()
+                        .filter(|(f, _)| !fields.iter().any(|(n, _)| n == f))
+                        .map(|(f, _)| f.as_str())
+                        .collect();
+                    if !missing.is_empty() {
+                        let message = format!("Pattern {} does not mention field(s) {}; add '..' to ignore them", pat, missing.join(", "));
+                        self.error(message);
+                    }
+                }
+                for (f, p) in fields {
+                    let ft = match self.field_type(&st, f) {
+                        Some(t) => t,
+                        None => {
+                            if resolved.is_some() {
+                                self.error(format!("Struct '{}' has no field '{}'", name, f));
+                            }
+                            Ty::Unknown
+                        }
+                    };
+                    self.bind_pattern(p, &ft);
+                }
+            }
+            Pattern::EnumVariant(enum_name, variant, ps) => {
+                let resolved = self.enum_name(enum_name);
+                let fields = match resolved.as_ref().and_then(|e| self.enums.get(e)).cloned() {
+                    Some(variants) => match variants.into_iter().find(|(n, _)| n == variant) {
+                        Some((_, fields)) => fields,
+                        None => {
+                            self.error(format!("Enum '{}' has no variant '{}'", enum_name, variant));
+                            Vec::new()
+                        }
+                    },
+                    None => Vec::new(),
+                };
+                if let Some(e) = resolved {
+                    if !compatible(ty, &Ty::Named(e)) { mismatch(self, enum_name); }
+                }
+                for (i, p) in ps.iter().enumerate() {
+                    let t = fields.get(i).cloned().unwrap_or(Ty::Unknown);
+                    self.bind_pattern(p, &t);
+                }
+            }
+            Pattern::Some(p) => match ty {
+                Ty::Option(t) => self.bind_pattern(p, t),
+                Ty::Unknown | Ty::Param(_) => self.bind_pattern(p, &Ty::Unknown),
+                _ => { mismatch(self, "Some"); self.bind_pattern(p, &Ty::Unknown); }
+            },
+            Pattern::Ok(p) | Pattern::Err(p) => {
+                let is_ok = matches!(pat, Pattern::Ok(_));
+                match ty {
+                    Ty::Result(t, e) => self.bind_pattern(p, if is_ok { t } else { e }),
+                    Ty::Unknown | Ty::Param(_) => self.bind_pattern(p, &Ty::Unknown),
+                    _ => {
+                        mismatch(self, if is_ok { "Ok" } else { "Err" });
+                        self.bind_pattern(p, &Ty::Unknown);
+                    }
+                }
+            }
+            Pattern::Or(a, b) => {
+                self.bind_pattern(a, ty);
+                self.bind_pattern(b, ty);
+            }
+        }
+    }
+}
+
+/// The enum a pattern matches variants of, if any.
+fn enum_of(pat: &Pattern) -> Option<String> {
+    match pat {
+        Pattern::EnumVariant(e, _, _) => Some(e.clone()),
+        Pattern::Bind(_, p) => enum_of(p),
+        Pattern::Or(a, b) => enum_of(a).or_else(|| enum_of(b)),
+        _ => None,
+    }
+}
+
+/// Record the variants of the enum `is_subject` accepts that `pat` matches
+/// in full; true when the pattern matches any value at all.
+fn covers_variants(pat: &Pattern, is_subject: &dyn Fn(&str) -> bool, covered: &mut HashSet<String>) -> bool {
+    match pat {
+        Pattern::Wildcard | Pattern::Ident(_) => true,
+        Pattern::Bind(_, p) => covers_variants(p, is_subject, covered),
+        Pattern::Or(a, b) => {
+            let a = covers_variants(a, is_subject, covered);
+            covers_variants(b, is_subject, covered) || a
+        }
+        Pattern::EnumVariant(e, v, ps) if is_subject(e) && ps.iter().all(irrefutable) => {
+            covered.insert(v.clone());
+            false
+        }
+        _ => false,
+    }
+}
+
+/// Patterns that match every value of the type they are checked against.
+fn irrefutable(pat: &Pattern) -> bool {
+    match pat {
+        Pattern::Wildcard | Pattern::Ident(_) | Pattern::Rest(_) => true,
+        Pattern::Bind(_, p) => irrefutable(p),
+        Pattern::Tuple(ps) => ps.iter().all(irrefutable),
+        Pattern::Struct(_, fields, _) => fields.iter().all(|(_, p)| irrefutable(p)),
+        _ => false,
+    }
+}
+
+/// Result types of the natives whose answer is fixed or follows from the
+/// argument types. Everything else is left to runtime.
+fn native_return_type(name: &str, args: &[Ty]) -> Ty {
+    let arg = |i: usize| args.get(i).cloned().unwrap_or(Ty::Unknown);
+    match name {
+        "print" | "println" | "eprint" | "eprintln" | "assert" | "exit" | "panic" | "set_recursion_limit" => Ty::Nil,
+        "input" | "str" | "type_of" | "join" | "trim" | "format" | "regex_replace" | "format_datetime"
+            | "uuid4" | "sha256" | "sha1" | "md5" | "hmac_sha256" => Ty::Str,
+        "round" if args.len() > 1 => Ty::Float,
+        "int" | "len" | "floor" | "ceil" | "round" | "trunc" | "gcd" | "lcm" | "idiv" | "imod"
+            | "timestamp" | "timestamp_ms" | "instant" | "random_int" | "crc32" | "recursion_limit" | "gc_collect" => Ty::Int,
+        "float" | "sqrt" | "cbrt" | "pow" | "hypot" | "exp" | "ln" | "log" | "log2" | "log10"
+            | "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "atan2" | "sinh" | "cosh" | "tanh"
+            | "degrees" | "radians" | "fdiv" | "elapsed_ms" | "random_float" => Ty::Float,
+        "bool" | "is_nan" | "is_finite" | "is_inf" | "random_bool" => Ty::Bool,
+        "abs" | "sign" if is_numeric(&arg(0)) => arg(0),
+        "range" => Ty::List(Box::new(Ty::Int)),
+        "split" | "regex_split" => Ty::List(Box::new(Ty::Str)),
+        "regex_find_all" => Ty::List(Box::new(Ty::Unknown)),
+        "set" => match arg(0) {
+            Ty::List(t) | Ty::Set(t) => Ty::Set(t),
+            _ => Ty::Set(Box::new(Ty::Unknown)),
+        },
+        "filter" | "sorted" | "shuffle" if matches!(arg(0), Ty::List(_)) => arg(0),
+        "random_bytes" => Ty::List(Box::new(Ty::Int)),
+        "gc_stats" => Ty::Map(Box::new(Ty::Str), Box::new(Ty::Int)),
+        "some" => Ty::Option(Box::new(arg(0))),
+        "ok"   => Ty::Result(Box::new(arg(0)), Box::new(Ty::Unknown)),
+        "err"  => Ty::Result(Box::new(Ty::Unknown), Box::new(arg(0))),
+        "unwrap" => match arg(0) {
+            Ty::Option(t) | Ty::Result(t, _) => *t,
+            _ => Ty::Unknown,
+        },
+        _ => Ty::Unknown,
+    }
+}
+
+/// The name user methods for values of this type are registered under.
+fn method_owner(ty: &Ty) -> Option<String> {
+    let name = match ty {
+        Ty::Named(n) => return Some(n.clone()),
+        Ty::Int => "Int",
+        Ty::Float => "Float",
+        Ty::Bool => "Bool",
+        Ty::Str => "String",
+        Ty::Nil => "Nil",
+        Ty::List(_) => "List",
+        Ty::Map(..) => "Map",
+        Ty::Set(_) => "Set",
+        Ty::Tuple(_) => "Tuple",
+        Ty::Option(_) => "Option",
+        Ty::Result(..) => "Result",
+        Ty::Fn(..) => "Function",
+        Ty::Param(_) | Ty::Unknown => return None,
+    };
+    Some(name.to_string())
+}
+
+/// The type of `self` inside `impl target`.
+fn self_type(target: &str) -> Ty {
+    let unknown = || Box::new(Ty::Unknown);
+    match target {
+        "Int" => Ty::Int,
+        "Float" => Ty::Float,
+        "Bool" => Ty::Bool,
+        "String" => Ty::Str,
+        "List" => Ty::List(unknown()),
+        "Map" => Ty::Map(unknown(), unknown()),
+        "Set" => Ty::Set(unknown()),
+        "Option" => Ty::Option(unknown()),
+        "Result" => Ty::Result(unknown(), unknown()),
+        "Tuple" | "Function" => Ty::Unknown,
+        other => Ty::Named(other.to_string()),
+    }
+}
+
+/// Result types of the builtin methods whose answer does not depend on the
+/// receiver's contents. Everything else is left to runtime.
+fn builtin_method_type(recv: &Ty, method: &str) -> Ty {
+    match (recv, method) {
+        (_, "len") => Ty::Int,
+        (_, "to_string") => Ty::Str,
+        (_, "contains" | "is_empty" | "starts_with" | "ends_with" | "is_some" | "is_none"
+            | "is_ok" | "is_err" | "has_key" | "any" | "all") => Ty::Bool,
+        (Ty::Str, "upper" | "lower" | "trim" | "replace" | "reverse") => Ty::Str,
+        (Ty::Str, "split" | "chars" | "lines") => Ty::List(Box::new(Ty::Str)),
+        (Ty::List(t), "first" | "last" | "pop") => (**t).clone(),
+        (Ty::List(_), "reverse" | "sort" | "filter" | "sort_by") => recv.clone(),
+        (Ty::List(t), "find") => Ty::Option(t.clone()),
+        (Ty::Map(k, _), "keys") => Ty::List(k.clone()),
+        (Ty::Map(_, v), "values") => Ty::List(v.clone()),
+        (Ty::Set(_), "union" | "intersection" | "difference" | "symmetric_difference" | "filter") => recv.clone(),
+        (Ty::Set(_), "add" | "remove" | "is_subset" | "is_superset" | "is_disjoint") => Ty::Bool,
+        (Ty::Set(t), "to_list") => Ty::List(t.clone()),
+        (Ty::Option(t), "unwrap") | (Ty::Result(t, _), "unwrap") => (**t).clone(),
+        _ => Ty::Unknown,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════
+// Tests
+// ═══════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(src: &str) -> Vec<String> {
+        let tokens = crate::lexer::Lexer::new(src).tokenize().expect("lex");
+        let stmts = crate::parser::Parser::new(tokens).parse_program().expect("parse");
+        check_program(&stmts, None).into_iter()
+            .map(|d| format!("{}: {}", d.span.line, d.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_type_of_expression_after_program() {
+        let parse = |src: &str| {
+            let tokens = crate::lexer::Lexer::new(src).tokenize().expect("lex");
+            crate::parser::Parser::new(tokens).parse_program().expect("parse")
+        };
+        let stmts = parse("struct Point { x: Int, y: Int }\n\
+                           fun norm(p: Point) -> Float { sqrt(float(p.x * p.x + p.y * p.y)) }\n\
+                           let ps = [Point { x: 3, y: 4 }]\n");
+        let ty = |src: &str| match &parse(src)[0].kind {
+            StmtKind::Expr(e) => type_of(&stmts, e).to_string(),
+            _ => panic!("expected an expression"),
+        };
+        assert_eq!(ty("ps"), "[Point]");
+        assert_eq!(ty("norm(ps[0])"), "Float");
+        assert_eq!(ty("norm"), "fun(Point) -> Float");
+    }
+
+    #[test]
+    fn test_clean_program_has_no_diagnostics() {
+        let src = "struct Point { x: Int, y: Int }\n\
+                   fun add(a: Int, b: Int) -> Int { return a + b }\n\
+                   let p = Point { x: 1, y: 2 }\n\
+                   let n: Int = add(p.x, p.y)\n\
+                   let o: Option<Int> = nil\n\
+                   println(n)\n";
+        assert!(check(src).is_empty(), "{:?}", check(src));
+    }
+
+    #[test]
+    fn test_call_and_let_mismatches() {
+        let diags = check("fun add(a: Int, b: Int) -> Int { return a + b }\n\
+                           let s: String = add(1, 2)\n\
+                           add(1)\n\
+                           add(\"x\", 2)\n");
+        assert_eq!(diags.len(), 3, "{:?}", diags);
+        assert!(diags[0].starts_with("2:") && diags[0].contains("declared as String"));
+        assert!(diags[1].starts_with("3:") && diags[1].contains("expects 2 argument(s)"));
+        assert!(diags[2].starts_with("4:") && diags[2].contains("expects Int, got String"));
+    }
+
+    #[test]
+    fn test_struct_fields_and_returns() {
+        let diags = check("struct Point { x: Int, y: Int }\n\
+                           let p = Point { x: 1, z: 2 }\n\
+                           fun f() -> Int {\n  return \"no\"\n}\n\
+                           println(p.w)\n\
+                           fun g() -> String { 5 }\n\
+                           fun h(n: Int) -> Int { if n > 0 { \"a\" } else { 1 } }\n\
+                           fun k(n: Int) -> Int {\n  let m = n * 2\n  if n > 0 { m } elif n < 0 { return -m } else { 0 }\n}\n");
+        assert_eq!(diags.len(), 6, "{:?}", diags);
+        assert!(diags[0].contains("no field 'z'"));
+        assert!(diags[1].contains("Missing field 'y'"));
+        assert!(diags[2].starts_with("4:") && diags[2].contains("returns Int"));
+        assert!(diags[3].starts_with("6:") && diags[3].contains("no field 'w'"));
+        assert!(diags[4].starts_with("7:") && diags[4].contains("'g' returns String but its body ends with Int"));
+        assert!(diags[5].starts_with("8:") && diags[5].contains("'h' returns Int but its body ends with String"));
+    }
+
+    #[test]
+    fn test_generics_and_option_result() {
+        let diags = check("fun first<T>(xs: [T]) -> T { return xs[0] }\n\
+                           let a: Int = first([1, 2])\n\
+                           let b: String = first([1, 2])\n\
+                           fun g(r: Result<Int, String>) -> Result<Int, String> { let v = r?\n return ok(v) }\n\
+                           fun h(r: Result<Int, String>) -> Int { return r? }\n");
+        assert_eq!(diags.len(), 2, "{:?}", diags);
+        assert!(diags[0].starts_with("3:"));
+        assert!(diags[1].starts_with("6:") && diags[1].contains("'?' used in 'h'"));
+    }
+
+    #[test]
+    fn test_trait_impls() {
+        let diags = check("trait Shape {\n fun area(self) -> Float\n fun name(self) -> String { return \"shape\" }\n}\n\
+                           struct Sq { s: Float }\n\
+                           impl Shape for Sq { fun area(self) -> Float { return self.s * self.s } }\n\
+                           impl Shape for List { fun name(self) -> String { return \"list\" } }\n\
+                           let n: Int = Sq { s: 2.0 }.name()\n");
+        assert_eq!(diags.len(), 2, "{:?}", diags);
+        assert!(diags[0].starts_with("7:") && diags[0].contains("impl Shape for List is missing 'area'"));
+        assert!(diags[1].starts_with("8:") && diags[1].contains("declared as Int"));
+    }
+
+    #[test]
+    fn test_set_types() {
+        let diags = check("fun f(s: Set<Int>) -> Set<Int> { return s.union(#{1}) }
+                           let ok: Set<Int> = f(set([2, 3]))
+                           let bad: Set<String> = #{1, 2}
+                           for x in ok { let y: Int = x }
+");
+        assert_eq!(diags.len(), 1, "{:?}", diags);
+        assert!(diags[0].starts_with("3:") && diags[0].contains("Set<String>"));
+    }
+
+    #[test]
+    fn test_match_exhaustiveness() {
+        let diags = check("enum C { R, G, B(Int) }\nstruct P { x: Int, y: Int }\n\
+                           fun f(c: C) -> Int { return match c { C::R => 1, C::B(n) if n > 0 => n } }\n\
+                           fun g(c: C) -> Int { return match c { C::R | C::G => 1, all @ C::B(_) => 2 } }\n\
+                           fun h(p: P) -> Int { return match p { P { x, .. } => x, P { y } => y } }\n\
+                           fun k(xs: List<Int>) -> List<Int> { return match xs { [_, ..rest] => rest, _ => [] } }\n");
+        assert_eq!(diags.len(), 2, "{:?}", diags);
+        assert!(diags[0].starts_with("3:") && diags[0].contains("no arm for C::G, C::B"));
+        assert!(diags[1].starts_with("5:") && diags[1].contains("does not mention field(s) x"));
+    }
+
+    #[test]
+    fn test_types_are_scoped_to_their_module() {
+        let diags = check("mod a {\n pub struct P { x: Int }\n impl P { pub fun who(self) -> String { return \"a\" } }\n\
+                           pub enum E { One, Two(Int) }\n\
+                           pub fun show(e: E) -> Int { return match e { E::One => 1 } }\n}\n\
+                           mod b {\n pub struct P { y: Int }\n struct Secret { z: Int }\n}\n\
+                           let s: String = a::P { x: 1 }.who()\n\
+                           let q = b::P { y: 2 }\n\
+                           let n: Int = q.y\n\
+                           Secret { z: 1 }\n");
+        assert_eq!(diags.len(), 2, "{:?}", diags);
+        assert!(diags[0].starts_with("5:") && diags[0].contains("no arm for a::E::Two"));
+        assert!(diags[1].starts_with("14:") && diags[1].contains("Unknown struct 'Secret'"));
+    }
+}
@@ -0,0 +1,959 @@
+// ═══════════════════════════════════════════════════════════
+// Zephyr VM — runs compiled Protos
+// ═══════════════════════════════════════════════════════════
+//
+// A stack machine. Each call frame owns a window of the value stack:
+//
+//   [callee] [slot 0 .. slot n-1] [temporaries ...]
+//             ^ base
+//
+// Params occupy the first slots, the rest are locals and the hidden
+// slots the compiler allocates for loops and matches. A return
+// truncates the stack back to the callee and pushes the result.
+//
+// Every frame counts towards the interpreter's recursion limit, and a
+// `return f(...)` where f is the running closure reuses its frame.
+//
+// Values, natives, methods, patterns and modules are shared with the
+// tree-walking interpreter, which the VM holds a handle to. Calls
+// between the two work in both directions: the interpreter runs a
+// compiled closure through `call_closure`, and the VM hands any other
+// callable to `Interpreter::call_value`.
+//
+// ═══════════════════════════════════════════════════════════
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use indexmap::IndexMap;
+
+use crate::compiler::{Constant, Op, Proto};
+use crate::iter;
+use crate::interpreter::{
+    eval_binop, eval_unary, get_index, is_truthy, match_pattern, require_int, resolve_type, set_field,
+    set_index, set_of, value_type_name, Env, EvalResult, Interpreter, MapKey, PathItem, Signal, Trait, Value, ZephyrFn,
+};
+use crate::ast::{BinOp, Pattern, UnaryOp};
+
+/// A compiled function together with the cells it captured.
+#[derive(Debug)]
+pub struct Closure {
+    pub proto: Rc<Proto>,
+    pub upvals: Vec<Rc<RefCell<Value>>>,
+    // source file of the program, for error locations
+    pub file: Option<Rc<PathBuf>>,
+}
+
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    base: usize,
+    // where the result goes; everything from here up is dropped on return
+    ret_to: usize,
+    // arguments actually passed, for default parameters
+    argc: usize,
+}
+
+struct Vm<'a> {
+    interp: &'a mut Interpreter,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    // the bottom frame is a script: `?` there aborts the program
+    script: bool,
+}
+
+/// Run a compiled program as the top level of `interp`.
+pub fn run_program(interp: &mut Interpreter, proto: Rc<Proto>) -> EvalResult {
+    let slots = proto.slots as usize;
+    let closure = Rc::new(Closure { proto, upvals: Vec::new(), file: interp.current_file.clone() });
+    let mut vm = Vm { interp, stack: vec![Value::Nil; slots], frames: Vec::new(), script: true };
+    vm.push_frame(Frame { closure, ip: 0, base: 0, ret_to: 0, argc: 0 })?;
+    vm.run()
+}
+
+/// Call a compiled closure from outside the VM.
+pub fn call_closure(interp: &mut Interpreter, closure: Rc<Closure>, args: Vec<Value>) -> EvalResult {
+    let argc = args.len();
+    let outer_file = std::mem::replace(&mut interp.current_file, closure.file.clone());
+    let mut vm = Vm { interp, stack: args, frames: Vec::new(), script: false };
+    let result = match vm.enter(closure, 0, argc, 0) {
+        Ok(()) => vm.run(),
+        Err(e) => Err(e),
+    };
+    vm.interp.current_file = outer_file;
+    result
+}
+
+// frames still on the stack when the VM stops no longer count as calls
+impl Drop for Vm<'_> {
+    fn drop(&mut self) {
+        self.interp.depth -= self.frames.len();
+    }
+}
+
+impl Vm<'_> {
+    fn push_frame(&mut self, frame: Frame) -> Result<(), Signal> {
+        if self.interp.depth >= self.interp.max_depth {
+            return Err(self.interp.recursion_error());
+        }
+        self.interp.depth += 1;
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    fn pop_frame(&mut self) -> Option<Frame> {
+        let frame = self.frames.pop()?;
+        self.interp.depth -= 1;
+        Some(frame)
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap_or(Value::Nil)
+    }
+
+    fn pop_n(&mut self, n: usize) -> Vec<Value> {
+        let at = self.stack.len() - n;
+        self.stack.split_off(at)
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().unwrap()
+    }
+
+    fn closure(&self) -> Rc<Closure> {
+        self.frame().closure.clone()
+    }
+
+    fn jump(&mut self, target: u32) {
+        self.frames.last_mut().unwrap().ip = target as usize;
+    }
+
+    /// Push a frame for `closure` whose arguments start at `base`.
+    fn enter(&mut self, closure: Rc<Closure>, base: usize, argc: usize, ret_to: usize) -> Result<(), Signal> {
+        let proto = &closure.proto;
+        let nparams = proto.params.len();
+        if let Some((name, _)) = proto.params.iter().skip(argc).find(|(_, has_default)| !has_default) {
+            return Err(Signal::Error(format!("Missing argument '{}'", name)));
+        }
+        // extra arguments are ignored, as in the tree-walker
+        self.stack.truncate(base + argc.min(nparams));
+        self.stack.resize(base + proto.slots as usize, Value::Nil);
+        self.push_frame(Frame { closure, ip: 0, base, ret_to, argc: argc.min(nparams) })
+    }
+
+    /// Call whatever sits below the top `argc` values.
+    fn call(&mut self, argc: usize) -> Result<(), Signal> {
+        let callee_at = self.stack.len() - argc - 1;
+        if let Value::Function(ZephyrFn::Compiled(c)) = &self.stack[callee_at] {
+            let c = c.clone();
+            return self.enter(c, callee_at + 1, argc, callee_at);
+        }
+        let args = self.pop_n(argc);
+        let callee = self.pop();
+        let global = self.interp.global.clone();
+        let result = self.interp.call_value(callee, args, &global)?;
+        self.stack.push(result);
+        Ok(())
+    }
+
+    /// `return callee(args)`: a call to the running closure starts its
+    /// frame over, anything else is an ordinary call (the Return that
+    /// follows hands back its result).
+    fn tail_call(&mut self, argc: usize) -> Result<(), Signal> {
+        let callee_at = self.stack.len() - argc - 1;
+        let is_self = matches!(&self.stack[callee_at], Value::Function(ZephyrFn::Compiled(c)) if Rc::ptr_eq(c, &self.frame().closure));
+        if !is_self {
+            return self.call(argc);
+        }
+        let args = self.pop_n(argc);
+        let frame = self.pop_frame().unwrap();
+        self.stack.truncate(frame.base);
+        self.stack.extend(args);
+        self.enter(frame.closure, frame.base, argc, frame.ret_to)
+    }
+
+    fn invoke(&mut self, callee: Value, args: Vec<Value>) -> Result<(), Signal> {
+        let argc = args.len();
+        self.stack.push(callee);
+        self.stack.extend(args);
+        self.call(argc)
+    }
+
+    /// Pop the current frame. Returns the value once the VM's own entry
+    /// frame has returned.
+    fn leave(&mut self, result: Value) -> Option<Value> {
+        let frame = self.pop_frame().unwrap();
+        self.stack.truncate(frame.ret_to);
+        if self.frames.is_empty() {
+            return Some(result);
+        }
+        self.stack.push(result);
+        None
+    }
+
+    fn method(&mut self, name: &str, argc: usize) -> Result<(), Signal> {
+        let recv_at = self.stack.len() - argc - 1;
+        if let Value::Module(m) = &self.stack[recv_at] {
+            let m = m.clone();
+            let args = self.pop_n(argc);
+            self.pop();
+            return match self.interp.module_member(&m, &[name.to_string()])? {
+                PathItem::Value(f) => self.invoke(f, args),
+                _ => Err(Signal::Error(format!("'{}' in module '{}' is not callable", name, m.name))),
+            };
+        }
+        if !self.interp.impl_methods.is_empty() {
+            let type_name = value_type_name(&self.stack[recv_at]);
+            let found = self.interp.impl_methods.get(&type_name).and_then(|m| m.get(name)).cloned();
+            match found {
+                // the receiver becomes the first argument in place
+                Some(ZephyrFn::Compiled(c)) => return self.enter(c, recv_at, argc + 1, recv_at),
+                Some(f) => {
+                    let args = self.pop_n(argc + 1);
+                    return self.invoke(Value::Function(f), args);
+                }
+                None => {}
+            }
+        }
+        let args = self.pop_n(argc);
+        let recv = self.pop();
+        let global = self.interp.global.clone();
+        let result = self.interp.call_method(recv, name, args, &global)?;
+        self.stack.push(result);
+        Ok(())
+    }
+
+    fn global_cell(&mut self, proto: &Proto, k: u32, name: &str) -> Option<Rc<RefCell<Value>>> {
+        let mut cache = proto.globals.borrow_mut();
+        if cache.is_empty() {
+            cache.resize(proto.consts.len(), None);
+        }
+        if let Some(cell) = &cache[k as usize] {
+            return Some(cell.clone());
+        }
+        let cell = self.interp.global.cell(name)?;
+        cache[k as usize] = Some(cell.clone());
+        Some(cell)
+    }
+
+    fn binary(&mut self, op: BinOp) -> Result<(), Signal> {
+        let r = self.pop();
+        let l = self.pop();
+        let v = match (&l, &r, &op) {
+            (Value::Int(a), Value::Int(b), BinOp::Add) => match a.checked_add(*b) {
+                Some(n) => Value::Int(n),
+                None => eval_binop(l, &op, r)?,
+            },
+            (Value::Int(a), Value::Int(b), BinOp::Sub) => match a.checked_sub(*b) {
+                Some(n) => Value::Int(n),
+                None => eval_binop(l, &op, r)?,
+            },
+            (Value::Int(a), Value::Int(b), BinOp::Mul) => match a.checked_mul(*b) {
+                Some(n) => Value::Int(n),
+                None => eval_binop(l, &op, r)?,
+            },
+            (Value::Int(a), Value::Int(b), BinOp::Lt)   => Value::Bool(a < b),
+            (Value::Int(a), Value::Int(b), BinOp::LtEq) => Value::Bool(a <= b),
+            (Value::Int(a), Value::Int(b), BinOp::Gt)   => Value::Bool(a > b),
+            (Value::Int(a), Value::Int(b), BinOp::GtEq) => Value::Bool(a >= b),
+            (Value::Int(a), Value::Int(b), BinOp::Eq)    => Value::Bool(a == b),
+            (Value::Int(a), Value::Int(b), BinOp::NotEq) => Value::Bool(a != b),
+            _ => eval_binop(l, &op, r)?,
+        };
+        self.stack.push(v);
+        Ok(())
+    }
+
+    fn run(&mut self) -> EvalResult {
+        match self.execute() {
+            Err(sig @ (Signal::Error(_) | Signal::Located(_))) => Err(self.unwind(sig)),
+            other => other,
+        }
+    }
+
+    /// Locate an error at each frame's current instruction as it leaves
+    /// the VM, recording the functions it passes through.
+    fn unwind(&mut self, mut sig: Signal) -> Signal {
+        while let Some(frame) = self.pop_frame() {
+            let proto = &frame.closure.proto;
+            let span = proto.spans.get(frame.ip.wrapping_sub(1)).copied().unwrap_or_default();
+            sig = self.interp.locate(sig, span);
+            if self.frames.is_empty() && self.script {
+                break;
+            }
+            if let Signal::Located(e) = &mut sig {
+                e.leave_frame(proto.name.as_deref().unwrap_or("<closure>"));
+            }
+        }
+        sig
+    }
+
+    fn execute(&mut self) -> EvalResult {
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let op = frame.closure.proto.code[frame.ip];
+            frame.ip += 1;
+            let base = frame.base;
+
+            match op {
+                Op::Const(k) => {
+                    let v = match &self.frame().closure.proto.consts[k as usize] {
+                        Constant::Int(n)   => Value::Int(*n),
+                        Constant::Float(f) => Value::Float(*f),
+                        Constant::Str(s)   => Value::str(s.clone()),
+                        _ => Value::Nil,
+                    };
+                    self.stack.push(v);
+                }
+                Op::Nil   => self.stack.push(Value::Nil),
+                Op::True  => self.stack.push(Value::Bool(true)),
+                Op::False => self.stack.push(Value::Bool(false)),
+                Op::Pop   => { self.stack.pop(); }
+
+                Op::GetLocal(s) => {
+                    let v = self.stack[base + s as usize].clone();
+                    self.stack.push(v);
+                }
+                Op::SetLocal(s) => {
+                    let v = self.pop();
+                    self.stack[base + s as usize] = v;
+                }
+                Op::GetCell(s) => {
+                    let v = match &self.stack[base + s as usize] {
+                        Value::Ref(cell) => cell.borrow().clone(),
+                        _ => Value::Nil,
+                    };
+                    self.stack.push(v);
+                }
+                Op::SetCell(s) => {
+                    let v = self.pop();
+                    match &self.stack[base + s as usize] {
+                        Value::Ref(cell) => *cell.borrow_mut() = v,
+                        _ => self.stack[base + s as usize] = Value::Ref(Rc::new(RefCell::new(v))),
+                    }
+                }
+                Op::NewCell(s) => {
+                    let v = self.pop();
+                    self.stack[base + s as usize] = Value::Ref(Rc::new(RefCell::new(v)));
+                }
+                Op::BoxLocal(s) => {
+                    let slot = &mut self.stack[base + s as usize];
+                    let v = std::mem::replace(slot, Value::Nil);
+                    *slot = Value::Ref(Rc::new(RefCell::new(v)));
+                }
+                Op::GetUpval(i) => {
+                    let v = self.frame().closure.upvals[i as usize].borrow().clone();
+                    self.stack.push(v);
+                }
+                Op::SetUpval(i) => {
+                    let v = self.pop();
+                    *self.frame().closure.upvals[i as usize].borrow_mut() = v;
+                }
+                Op::GetGlobal(k) => {
+                    let closure = self.closure();
+                    let name = const_str(&closure.proto, k);
+                    match self.global_cell(&closure.proto, k, name) {
+                        Some(cell) => {
+                            let v = cell.borrow().clone();
+                            self.stack.push(v);
+                        }
+                        None => return Err(Signal::Error(format!("Undefined variable '{}'", name))),
+                    }
+                }
+                Op::SetGlobal(k) | Op::DefineGlobal(k) => {
+                    let v = self.pop();
+                    let closure = self.closure();
+                    let name = const_str(&closure.proto, k);
+                    match self.global_cell(&closure.proto, k, name) {
+                        Some(cell) => *cell.borrow_mut() = v,
+                        None => self.interp.global.define(name, v),
+                    }
+                }
+
+                Op::Add   => self.binary(BinOp::Add)?,
+                Op::Sub   => self.binary(BinOp::Sub)?,
+                Op::Mul   => self.binary(BinOp::Mul)?,
+                Op::Div   => self.binary(BinOp::Div)?,
+                Op::Mod   => self.binary(BinOp::Mod)?,
+                Op::Eq    => self.binary(BinOp::Eq)?,
+                Op::NotEq => self.binary(BinOp::NotEq)?,
+                Op::Lt    => self.binary(BinOp::Lt)?,
+                Op::LtEq  => self.binary(BinOp::LtEq)?,
+                Op::Gt    => self.binary(BinOp::Gt)?,
+                Op::GtEq  => self.binary(BinOp::GtEq)?,
+                Op::And   => self.binary(BinOp::And)?,
+                Op::Or    => self.binary(BinOp::Or)?,
+                Op::Neg => {
+                    let v = self.pop();
+                    self.stack.push(eval_unary(&UnaryOp::Neg, v)?);
+                }
+                Op::Not => {
+                    let v = self.pop();
+                    self.stack.push(Value::Bool(!is_truthy(&v)));
+                }
+
+                Op::Jump(t) => self.jump(t),
+                Op::JumpIfFalse(t) => {
+                    let v = self.pop();
+                    if !is_truthy(&v) { self.jump(t); }
+                }
+                Op::ArgGiven(p, t) => {
+                    if self.frame().argc > p as usize { self.jump(t); }
+                }
+
+                Op::Call(argc) => self.call(argc as usize)?,
+                Op::CallPath(k, argc) => {
+                    let closure = self.closure();
+                    let segs = const_names(&closure.proto, k);
+                    let args = self.pop_n(argc as usize);
+                    let global = self.interp.global.clone();
+                    match self.interp.resolve_path(segs, &global)? {
+                        PathItem::Value(f) => self.invoke(f, args)?,
+                        PathItem::Variant(e, v) => self.stack.push(Value::Enum(e, v, args)),
+                        PathItem::Struct(s) => return Err(Signal::Error(
+                            format!("Struct '{}' is created with '{} {{ ... }}', not called", s, s))),
+                    }
+                }
+                Op::Method(k, argc) => {
+                    let closure = self.closure();
+                    self.method(const_str(&closure.proto, k), argc as usize)?;
+                }
+                Op::TailCall(argc) => self.tail_call(argc as usize)?,
+                Op::Return => {
+                    let v = self.pop();
+                    if let Some(v) = self.leave(v) { return Ok(v); }
+                }
+
+                Op::Tuple(n) => {
+                    let items = self.pop_n(n as usize);
+                    self.stack.push(Value::Tuple(items));
+                }
+                Op::List(n) => {
+                    let items = self.pop_n(n as usize);
+                    self.stack.push(Value::list(items));
+                }
+                Op::Map(n) => {
+                    let items = self.pop_n(2 * n as usize);
+                    let mut map = IndexMap::new();
+                    let mut it = items.into_iter();
+                    while let (Some(k), Some(v)) = (it.next(), it.next()) {
+                        map.insert(MapKey::from_value(&k).map_err(Signal::Error)?, v);
+                    }
+                    self.stack.push(Value::map(map));
+                }
+                Op::Set(n) => {
+                    let items = self.pop_n(n as usize);
+                    self.stack.push(set_of(items).map_err(Signal::Error)?);
+                }
+                Op::Format(k) => {
+                    let v = self.pop();
+                    let closure = self.closure();
+                    let s = crate::format::format_value(&v, const_str(&closure.proto, k)).map_err(Signal::Error)?;
+                    self.stack.push(Value::str(s));
+                }
+                Op::Concat(n) => {
+                    let mut s = String::new();
+                    for part in self.pop_n(n as usize) {
+                        match part {
+                            Value::Str(p) => s.push_str(&p),
+                            other => s.push_str(&format!("{}", other)),
+                        }
+                    }
+                    self.stack.push(Value::str(s));
+                }
+                Op::Range => {
+                    let end = require_int(&self.pop())?;
+                    let start = require_int(&self.pop())?;
+                    self.stack.push(Value::Range(start, end, 1));
+                }
+                Op::Struct(k) => {
+                    let closure = self.closure();
+                    let names = const_names(&closure.proto, k);
+                    let values = self.pop_n(names.len() - 1);
+                    let global = self.interp.global.clone();
+                    let type_name = self.interp.struct_type_name(&names[0], &global)?;
+                    let fields: HashMap<String, Value> = names[1..].iter().cloned().zip(values).collect();
+                    self.stack.push(Value::structure(type_name, fields));
+                }
+                Op::Variant(k, argc) => {
+                    let closure = self.closure();
+                    let names = const_names(&closure.proto, k);
+                    let args = self.pop_n(argc as usize);
+                    if let Some(Value::Module(m)) = self.interp.global.get(&names[0]) {
+                        match self.interp.module_member(&m, &names[1..])? {
+                            PathItem::Value(f) => self.invoke(f, args)?,
+                            _ => return Err(Signal::Error(
+                                format!("'{}' in module '{}' is not callable", names[1], m.name))),
+                        }
+                    } else {
+                        let enum_name = resolve_type(&names[0], &self.interp.global);
+                        self.stack.push(Value::Enum(enum_name, names[1].clone(), args));
+                    }
+                }
+                Op::Path(k) => {
+                    let closure = self.closure();
+                    let global = self.interp.global.clone();
+                    let v = match self.interp.resolve_path(const_names(&closure.proto, k), &global)? {
+                        PathItem::Value(v) => v,
+                        PathItem::Variant(e, v) => Value::Enum(e, v, vec![]),
+                        PathItem::Struct(s) => return Err(Signal::Error(format!("Struct '{}' is a type, not a value", s))),
+                    };
+                    self.stack.push(v);
+                }
+                Op::Field(k) => {
+                    let closure = self.closure();
+                    let obj = self.pop();
+                    let v = self.interp.get_field(&obj, const_str(&closure.proto, k))?;
+                    self.stack.push(v);
+                }
+                Op::SetField(k) => {
+                    let closure = self.closure();
+                    let obj = self.pop();
+                    let v = self.pop();
+                    set_field(&obj, const_str(&closure.proto, k), v)?;
+                }
+                Op::Index => {
+                    let idx = self.pop();
+                    let obj = self.pop();
+                    self.stack.push(get_index(&obj, &idx)?);
+                }
+                Op::SetIndex => {
+                    let obj = self.pop();
+                    let idx = self.pop();
+                    let v = self.pop();
+                    set_index(&obj, &idx, v)?;
+                }
+
+                Op::Closure(k) => {
+                    let closure = self.closure();
+                    let Constant::Proto(proto) = &closure.proto.consts[k as usize] else {
+                        return Err(Signal::Error("Corrupt bytecode: expected a function".into()));
+                    };
+                    let mut upvals = Vec::with_capacity(proto.captures.len());
+                    for cap in &proto.captures {
+                        if !cap.from_local {
+                            upvals.push(closure.upvals[cap.index as usize].clone());
+                            continue;
+                        }
+                        let slot = &mut self.stack[base + cap.index as usize];
+                        if let Value::Ref(cell) = slot {
+                            upvals.push(cell.clone());
+                        } else {
+                            let cell = Rc::new(RefCell::new(std::mem::replace(slot, Value::Nil)));
+                            *slot = Value::Ref(cell.clone());
+                            upvals.push(cell);
+                        }
+                    }
+                    let f = Closure { proto: proto.clone(), upvals, file: self.interp.current_file.clone() };
+                    self.stack.push(Value::Function(ZephyrFn::Compiled(crate::gc::track_closure(Rc::new(f)))));
+                }
+                Op::DefMethod(k) => {
+                    let closure = self.closure();
+                    let names = const_names(&closure.proto, k);
+                    if let Value::Function(f) = self.pop() {
+                        let target = resolve_type(&names[0], &self.interp.global);
+                        self.interp.impl_methods.entry(target).or_default().insert(names[1].clone(), f);
+                    }
+                }
+                Op::DefTrait(k, n) => {
+                    let closure = self.closure();
+                    let names = const_names(&closure.proto, k);
+                    let n = n as usize;
+                    let defaults = self.pop_n(n).into_iter().zip(&names[1..=n])
+                        .filter_map(|(f, name)| match f {
+                            Value::Function(f) => Some((name.clone(), f)),
+                            _ => None,
+                        })
+                        .collect();
+                    let required = names[n + 1..].to_vec();
+                    self.interp.traits.insert(names[0].clone(), Trait { required, defaults });
+                }
+                Op::ImplTrait(k) => {
+                    let closure = self.closure();
+                    let names = const_names(&closure.proto, k);
+                    let target = resolve_type(&names[1], &self.interp.global);
+                    self.interp.impl_trait(&names[0], &target, &names[2..])?;
+                }
+
+                Op::Some => {
+                    let v = self.pop();
+                    self.stack.push(Value::Option(Some(Box::new(v))));
+                }
+                Op::Ok => {
+                    let v = self.pop();
+                    self.stack.push(Value::Result(Ok(Box::new(v))));
+                }
+                Op::Err => {
+                    let v = self.pop();
+                    self.stack.push(Value::Result(Err(Box::new(v))));
+                }
+                Op::Try => {
+                    let err = match self.pop() {
+                        Value::Result(Ok(v)) | Value::Option(Some(v)) => { self.stack.push(*v); continue; }
+                        Value::Result(Err(e)) => *e,
+                        Value::Option(None) | Value::Nil => Value::str("None"),
+                        other => { self.stack.push(other); continue; }
+                    };
+                    // like a function boundary in the tree-walker: the error becomes the result
+                    if self.script && self.frames.len() == 1 {
+                        return Err(Signal::PropagateErr(err));
+                    }
+                    if let Some(v) = self.leave(Value::Result(Err(Box::new(err)))) { return Ok(v); }
+                }
+                Op::Ref => {
+                    let v = self.pop();
+                    self.stack.push(Value::Ref(Rc::new(RefCell::new(v))));
+                }
+
+                // Lists and ranges are stepped in place, with the position
+                // in the second slot; anything else goes through crate::iter.
+                Op::IterInit(s) => {
+                    let (items, pos) = match self.pop() {
+                        Value::List(l) => (Value::list(l.borrow().clone()), 0),
+                        Value::Range(start, end, step) => (Value::Range(start, end, step), start),
+                        other => {
+                            let global = self.interp.global.clone();
+                            (Value::Iter(iter::iter_of(self.interp, other, &global)?), 0)
+                        }
+                    };
+                    self.stack[base + s as usize] = items;
+                    self.stack[base + s as usize + 1] = Value::Int(pos);
+                }
+                Op::ForNext(s, exit) => {
+                    let items = base + s as usize;
+                    let Value::Int(pos) = self.stack[items + 1] else {
+                        return Err(Signal::Error("Corrupt bytecode: loop without IterInit".into()));
+                    };
+                    let (next, after) = match &self.stack[items] {
+                        Value::List(l) => (l.borrow().get(pos as usize).cloned(), pos + 1),
+                        &Value::Range(_, end, step) if iter::range_has(pos, end, step) => {
+                            (Some(Value::Int(pos)), iter::range_step(pos, end, step))
+                        }
+                        Value::Iter(it) => {
+                            let it = it.clone();
+                            let global = self.interp.global.clone();
+                            (iter::next(&it, self.interp, &global)?, 0)
+                        }
+                        _ => (None, 0),
+                    };
+                    match next {
+                        Some(v) => {
+                            self.stack[items + 1] = Value::Int(after);
+                            self.stack.push(v);
+                        }
+                        None => self.jump(exit),
+                    }
+                }
+                Op::Match(k, fail) => {
+                    let closure = self.closure();
+                    let Constant::Pattern(pat, binds) = &closure.proto.consts[k as usize] else {
+                        return Err(Signal::Error("Corrupt bytecode: expected a pattern".into()));
+                    };
+                    let v = self.pop();
+                    if !self.bind_pattern(base, pat, binds, &v)? {
+                        self.jump(fail);
+                    }
+                }
+                Op::Destructure(k) => {
+                    let closure = self.closure();
+                    let Constant::Pattern(pat, binds) = &closure.proto.consts[k as usize] else {
+                        return Err(Signal::Error("Corrupt bytecode: expected a pattern".into()));
+                    };
+                    let v = self.pop();
+                    if !self.bind_pattern(base, pat, binds, &v)? {
+                        return Err(Signal::Error(format!("Cannot destructure {} as {}", v, pat)));
+                    }
+                }
+                Op::Exec(k) => {
+                    let closure = self.closure();
+                    let Constant::Stmt(stmt) = &closure.proto.consts[k as usize] else {
+                        return Err(Signal::Error("Corrupt bytecode: expected a statement".into()));
+                    };
+                    let global = self.interp.global.clone();
+                    self.interp.exec_stmt(stmt, &global)?;
+                }
+                Op::Fail(k) => {
+                    let closure = self.closure();
+                    return Err(Signal::Error(const_str(&closure.proto, k).to_string()));
+                }
+            }
+        }
+    }
+
+    /// Match `v` against a pattern and store what it binds; false if it does not match.
+    fn bind_pattern(&mut self, base: usize, pat: &Pattern, binds: &[crate::compiler::Binding], v: &Value) -> Result<bool, Signal> {
+        match pat {
+            Pattern::Wildcard => {}
+            Pattern::Ident(_) => self.bind(base, &binds[0], v.clone()),
+            _ => {
+                // a child of the globals, so type names in the pattern resolve
+                let env = Env::child(&self.interp.global);
+                if !match_pattern(pat, v, &env)? {
+                    return Ok(false);
+                }
+                for b in binds {
+                    let bound = env.get_local(&b.name).unwrap_or(Value::Nil);
+                    self.bind(base, b, bound);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn bind(&mut self, base: usize, b: &crate::compiler::Binding, v: Value) {
+        self.stack[base + b.slot as usize] = if b.boxed { Value::Ref(Rc::new(RefCell::new(v))) } else { v };
+    }
+}
+
+fn const_str(proto: &Proto, k: u32) -> &str {
+    match &proto.consts[k as usize] {
+        Constant::Str(s) => s,
+        _ => "",
+    }
+}
+
+fn const_names(proto: &Proto, k: u32) -> &[String] {
+    match &proto.consts[k as usize] {
+        Constant::Names(n) => n,
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // compile, round-trip through the .zphc encoding, run, and read a global
+    fn run(src: &str, global: &str) -> Value {
+        let tokens = crate::lexer::Lexer::new(src).tokenize().unwrap();
+        let stmts = crate::parser::Parser::new(tokens).parse_program().unwrap();
+        let program = crate::compiler::compile(&stmts).unwrap();
+        let bytes = crate::bytecode::encode(&program, src);
+        let (program, _) = crate::bytecode::decode(&bytes).unwrap();
+        let mut interp = Interpreter::new();
+        run_program(&mut interp, Rc::new(program)).unwrap();
+        interp.global.get(global).unwrap()
+    }
+
+    // run on the tree-walker as well, and check the engines agree
+    fn run_both(src: &str, global: &str) -> Value {
+        let tokens = crate::lexer::Lexer::new(src).tokenize().unwrap();
+        let stmts = crate::parser::Parser::new(tokens).parse_program().unwrap();
+        let mut interp = Interpreter::new();
+        interp.run(stmts).unwrap();
+        let walked = interp.global.get(global).unwrap();
+        let compiled = run(src, global);
+        assert_eq!(format!("{}", walked), format!("{}", compiled), "engines disagree on:\n{}", src);
+        compiled
+    }
+
+    // run a program that fails on the tree-walker and on the bytecode,
+    // returning each engine's error and the state it left behind
+    fn fail_both(src: &str) -> Vec<(Signal, Interpreter)> {
+        let tokens = crate::lexer::Lexer::new(src).tokenize().unwrap();
+        let stmts = crate::parser::Parser::new(tokens).parse_program().unwrap();
+        let bytes = crate::bytecode::encode(&crate::compiler::compile(&stmts).unwrap(), src);
+        let (program, _) = crate::bytecode::decode(&bytes).unwrap();
+        let mut walker = Interpreter::new();
+        let walked = walker.run(stmts).expect_err("the tree-walker should fail");
+        let mut vm = Interpreter::new();
+        let compiled = run_program(&mut vm, Rc::new(program)).expect_err("the VM should fail");
+        vec![(walked, walker), (compiled, vm)]
+    }
+
+    #[test]
+    fn closures_share_captured_locals() {
+        let src = "fun counter() {\n var n = 0\n |k| => { n = n + k\n n } }\n\
+                   let c = counter()\nc(2)\nc(3)\nvar fs = []\n\
+                   for i in 0..3 { fs.push(|x| => i + x) }\n\
+                   let got = [c(0), fs[0](0), fs[2](0)]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[5, 0, 2]");
+    }
+
+    #[test]
+    fn rebinding_and_defaults_match_the_tree_walker() {
+        let src = "fun s() { let a = 1\n let f = |x| => a\n let a = 2\n [f(0), a] }\n\
+                   fun f2(a, b = a * 2) { [a, b] }\n\
+                   fun p() { let (x, y) = (1, 2)\n let g = || x + y\n let (x, y) = (10, 20)\n [g(), x] }\n\
+                   fun q(a) { let h = || a\n let a = a + 1\n [h(), a] }\n\
+                   fun r() { fun k() { 1 }\n let c = || k()\n fun k() { 2 }\n c() }\n\
+                   let got = [s(), f2(3), f2(3, 1), p(), q(5), r()]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[[2, 2], [3, 6], [3, 1], [30, 10], [6, 6], 2]");
+    }
+
+    #[test]
+    fn calls_methods_defaults_and_try() {
+        let src = "struct P { x: Int }\nimpl P { fun get(self, add: Int = 1) -> Int { self.x + add } }\n\
+                   fun fib(n: Int) -> Int {\n if n <= 1 { return n }\n fib(n - 1) + fib(n - 2) }\n\
+                   fun first(r) { let v = r?\n ok(v + 1) }\n\
+                   let got = [P { x: 4 }.get(), P { x: 4 }.get(10), fib(15), first(err(\"no\"))]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[5, 14, 610, Err(no)]");
+    }
+
+    #[test]
+    fn builtins_call_compiled_closures() {
+        let src = "let k = 10\nlet xs = [3, 1, 2]\n\
+                   let got = [xs.map(|x| => x * k), xs.filter(|x| => x > 1), xs.reduce(|a, x| => a + x, 0),\n\
+                   sorted(xs, |x| => 0 - x), xs.any(|x| => x == 2), xs.find(|x| => x > 5)]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[[30, 10, 20], [3, 2], 6, [3, 2, 1], true, nil]");
+    }
+
+    #[test]
+    fn traits_dispatch_to_impls_and_defaults() {
+        let src = "trait Named {\n fun name(self) -> String\n fun greet(self) { \"hi \" + self.name() }\n}\n\
+                   struct P { n: String }\n\
+                   impl Named for P { fun name(self) { self.n } }\n\
+                   impl Named for Int { fun name(self) { \"int\" }\n fun greet(self) { \"yo\" } }\n\
+                   let got = [P { n: \"ann\" }.greet(), 3.greet(), 4.name()]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[hi ann, yo, int]");
+    }
+
+    #[test]
+    fn iterators_and_ranges_are_lazy() {
+        let src = "struct Up { n: Int }\n\
+                   impl Up { fun next(self) {\n self.n = self.n + 1\n some(self.n) } }\n\
+                   var seen = []\n\
+                   for (i, x) in Up { n: 0 }.enumerate().skip(1).take(2) { seen.push([i, x]) }\n\
+                   for x in range(10, 0, -4) { seen.push(x) }\n\
+                   let got = [seen, (0..1000000000000).step_by(5).take(3).collect(), [1, 2].zip(\"ab\").collect()]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[[[1, 2], [2, 3], 10, 6, 2], [0, 5, 10], [(1, a), (2, b)]]");
+    }
+
+    #[test]
+    fn map_keys_keep_their_type() {
+        let src = "enum C { Red }\n\
+                   let m = {1: \"int\", \"1\": \"str\", (1, true): \"tuple\", C::Red: \"enum\"}\n\
+                   let got = [len(m), m[1], m[\"1\"], m[(1, true)], m[C::Red], json_stringify({2: nil})]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[4, int, str, tuple, enum, Ok({\"2\":null})]");
+    }
+
+    #[test]
+    fn maps_keep_insertion_order() {
+        let src = "var m = {\"z\": 1, \"a\": 2}\nm[\"m\"] = 3\nm.remove(\"z\")\nm[\"z\"] = 4\n\
+                   let doc = json_parse(\"{\\\"y\\\": 1, \\\"b\\\": {\\\"x\\\": 2, \\\"a\\\": 3}}\").unwrap()\n\
+                   let got = [m, m.keys(), json_stringify(doc).unwrap()]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[{a: 2, m: 3, z: 4}, [a, m, z], {\"y\":1,\"b\":{\"x\":2,\"a\":3}}]");
+    }
+
+    #[test]
+    fn sets_hold_unique_keys() {
+        let src = "var s = #{3, 1, 3}\nlet added = [s.add(2), s.add(1)]\n\
+                   let got = [s, added, s.union([7]).difference(#{1}), s == #{1, 2, 3}, s.is_subset(set(0..5)),\
+                   s.filter(|x| x > 1), json_stringify(s)]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[#{3, 1, 2}, [true, false], #{3, 2, 7}, true, true, #{3, 2}, Ok([3,1,2])]");
+    }
+
+    #[test]
+    fn int_overflow_promotes_to_bigint() {
+        let src = "let max = 9223372036854775807\nlet big = max + 1\n\
+                   let got = [big, big - 1 == max, 2.pow(70), -(-max - 1), big * 0, big > max, json_stringify(big)]\n";
+        assert_eq!(
+            format!("{}", run_both(src, "got")),
+            "[9223372036854775808, true, 1180591620717411303424, 9223372036854775808, 0, true, Ok(9223372036854775808)]"
+        );
+    }
+
+    #[test]
+    fn rest_bind_and_struct_patterns() {
+        let src = "struct P { x: Int, y: Int }\nstruct L { a: P, b: P }\n\
+                   fun ends(xs) { match xs { [] => nil, [h, ..t] if len(t) > 2 => (h, t), [f, .., l] => (f, l), [o] => o } }\n\
+                   let line = L { a: P { x: 0, y: 1 }, b: P { x: 4, y: 2 } }\n\
+                   let got = [ends([1, 2, 3, 4]), ends([1, 2]), ends([]),\
+                   match line { L { a: P { x: 0, .. }, b: end @ P { y, .. } } => (end.x, y), _ => nil }]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[(1, [2, 3, 4]), (1, 2), nil, (4, 2)]");
+    }
+
+    #[test]
+    fn module_types_keep_their_own_methods() {
+        let src = "mod a {\n pub struct P { x: Int }\n impl P { pub fun who(self) { \"a\" } }\n}\n\
+                   struct P { x: Int }\nimpl P { fun who(self) { \"top\" } }\n\
+                   enum E { One, Two(Int) }\n\
+                   let got = [a::P { x: 1 }.who(), P { x: 1 }.who(), match E::Two(3) { E::Two(n) => n, _ => 0 }]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[a, top, 3]");
+    }
+
+    #[test]
+    fn let_for_and_params_destructure() {
+        let src = "struct P { x: Int, y: Int }\nlet (a, [b, ..bs]) = (1, [2, 3, 4])\nlet P { x, .. } = P { x: 5, y: 6 }\n\
+                   fun swap((l, r)) { return (r, l) }\nfun local() { let (m, n) = (7, 8)\n m * n }\n\
+                   var sums = []\nfor (i, (k, v)) in [(\"a\", 10), (\"b\", 20)].enumerate() { sums.push(i + v) }\n\
+                   let got = [a, b, bs, x, swap((1, 2)), local(), sums, [(1, 2), (3, 4)].map(|(p, q)| p * q)]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[1, 2, [3, 4], 5, (2, 1), 56, [10, 21], [2, 12]]");
+    }
+
+    #[test]
+    fn format_specs() {
+        let src = "let p = 3.14159\nlet n = 42\nlet s = \"ab\"\n\
+                   let got = \"[#{p:.2}|#{s:>4}|#{s:*^6}|#{n:05}|#{n:#x}|#{n:b}|#{n:+}|#{1500.0:.1e}|#{-7:04}]\"\n\
+                   let t = format(\"{1}-{0:<3}|{{}}\", 1, \"x\")\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[3.14|  ab|**ab**|00042|0x2a|101010|+42|1.5e3|-007]");
+        assert_eq!(format!("{}", run_both(src, "t")), "x-1  |{}");
+    }
+
+    #[test]
+    fn self_tail_calls_run_past_the_recursion_limit() {
+        let src = "fun count(n, acc) {\n if n == 0 { return acc }\n return count(n - 1, acc + 1) }\n\
+                   fun depth(n) {\n if n == 0 { return 0 }\n 1 + depth(n - 1) }\n\
+                   set_recursion_limit(500)\nlet got = [count(100000, 0), depth(400), recursion_limit()]\n\
+                   let deep = depth(1000)\n";
+        for (err, interp) in fail_both(src) {
+            let Signal::Located(e) = err else { panic!("expected the recursion limit error") };
+            assert!(e.message.starts_with("Recursion limit exceeded: call depth 501"), "{}", e.message);
+            assert!(e.render(Some(src)).contains("more calls ..."));
+            assert_eq!(format!("{}", interp.global.get("got").unwrap()), "[100000, 400, 500]");
+            assert_eq!(interp.depth, 0);
+        }
+    }
+
+    #[test]
+    fn collector_sees_the_same_heap_on_both_engines() {
+        // compiled closures are their own objects, so each engine builds the
+        // garbage differently; the collector must still find all of it
+        let src = "fun make() {\n let a = [1]\n a.push(a)\n fun down(n) { if n == 0 { return 0 }\n down(n - 1) }\n down(3)\n}\n\
+                   for i in 0..100 { make() }\nlet keep = [1]\nkeep.push(keep)\n\
+                   let got = [gc_collect(), len(keep[1])]\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "[200, 2]");
+    }
+
+    #[test]
+    fn spawned_closures_carry_their_globals() {
+        let src = "struct P { x: Int }\nfun fib(n) {\n if n < 2 { return n }\n fib(n - 1) + fib(n - 2) }\n\
+                   let p = P { x: 5 }\nlet r = ref 1\n\
+                   let got = [async_await(async_spawn(|| fib(p.x) + 1)), async_await(async_spawn(|| P { x: p.x }))]\n\
+                   let bad = async_spawn(|| r)\n";
+        for (err, interp) in fail_both(src) {
+            assert!(matches!(err, Signal::Located(e) if e.message.contains("ref cannot be shared")));
+            assert_eq!(format!("{}", interp.global.get("got").unwrap()), "[Ok(6), Ok(P {x: 5})]");
+        }
+    }
+
+    #[test]
+    fn spawned_closures_keep_each_scopes_own_bindings() {
+        let src = "let n = 1\nfun g() { n }\nfun outer() {\n let n = 100\n async_spawn(|| n + g())\n}\n\
+                   let got = async_await(outer())\n";
+        assert_eq!(format!("{}", run_both(src, "got")), "Ok(101)");
+
+        let src = "mod m { pub fun f() { 5 } }\nlet t = async_spawn(|| m::f())\n";
+        for (err, _) in fail_both(src) {
+            assert!(matches!(err, Signal::Located(e) if e.message == "cannot send 'm' to a task: module 'm' cannot be sent to another thread"));
+        }
+        let src = "let l = [1]\nl.push(l)\nlet t = async_spawn(|| len(l))\n";
+        for (err, _) in fail_both(src) {
+            assert!(matches!(err, Signal::Located(e) if e.message.contains("a value that contains itself cannot be sent")));
+        }
+    }
+
+    #[test]
+    fn errors_keep_positions_through_bytecode() {
+        let src = "fun inner(a, b) { a + b }\nfun outer() {\n  inner(1)\n}\nouter()\n";
+        for (err, _) in fail_both(src) {
+            let Signal::Located(e) = err else { panic!("expected a located error") };
+            assert_eq!((e.message.as_str(), e.span.line, e.span.col), ("Missing argument 'b'", 3, 3));
+            let rendered = e.render(Some(src));
+            assert!(rendered.contains("3 |   inner(1)\n  |   ^"), "{}", rendered);
+            assert!(rendered.ends_with("<script> at <input>:5:1\n  outer at <input>:3:3"), "{}", rendered);
+        }
+    }
+}
@@ -56,15 +56,13 @@
 //
 // ═══════════════════════════════════════════════════════════
 
-use std::collections::HashMap;
-use std::rc::Rc;
-use std::cell::RefCell;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::time::{SystemTime, UNIX_EPOCH};
+use indexmap::IndexMap;
 use crate::interpreter::Value;
-use crate::json;
 
 // ── Registration ──────────────────────────────────────────────────────────────
 
@@ -183,7 +181,7 @@ pub fn call_fs(name: &str, args: Vec<Value>) -> Result<Value, String> {
 fn fs_file_read(args: Vec<Value>) -> Result<Value, String> {
     let path = require_str(&args, 0, "file_read(path)")?;
     match fs::read(&path) {
-        Ok(bytes) => Ok(ok_val(Value::Str(String::from_utf8_lossy(&bytes).into_owned()))),
+        Ok(bytes) => Ok(ok_val(Value::str(String::from_utf8_lossy(&bytes).into_owned()))),
         Err(e)    => Ok(err_val(format!("file_read '{}': {}", path, e))),
     }
 }
@@ -211,9 +209,9 @@ fn fs_file_read_lines(args: Vec<Value>) -> Result<Value, String> {
         Ok(contents) => {
             let lines: Vec<Value> = contents
                 .lines()
-                .map(|l| Value::Str(l.to_string()))
+                .map(|l| Value::str(l.to_string()))
                 .collect();
-            Ok(ok_val(Value::List(Rc::new(RefCell::new(lines)))))
+            Ok(ok_val(Value::list(lines)))
         }
         Err(e) => Ok(err_val(format!("file_read_lines '{}': {}", path, e))),
     }
@@ -235,7 +233,7 @@ fn fs_file_read_bytes(args: Vec<Value>) -> Result<Value, String> {
     match fs::read(&path) {
         Ok(bytes) => {
             let vals: Vec<Value> = bytes.iter().map(|&b| Value::Int(b as i64)).collect();
-            Ok(ok_val(Value::List(Rc::new(RefCell::new(vals)))))
+            Ok(ok_val(Value::list(vals)))
         }
         Err(e) => Ok(err_val(format!("file_read_bytes '{}': {}", path, e))),
     }
@@ -263,7 +261,7 @@ fn fs_file_read_json(args: Vec<Value>) -> Result<Value, String> {
         Err(e) => return Ok(err_val(format!("file_read_json '{}': {}", path, e))),
     };
     // Delegate to json module
-    crate::json::call_json("json_parse", vec![Value::Str(contents)])
+    crate::json::call_json("json_parse", vec![Value::str(contents)])
 }
 
 // ═══════════════════════════════════════════════════════════
@@ -377,7 +375,7 @@ fn fs_file_write_json_pretty(args: Vec<Value>) -> Result<Value, String> {
         .ok_or_else(|| "file_write_json_pretty: value argument required".to_string())?;
     let json_str = match crate::json::call_json("json_pretty", vec![val])? {
         Value::Result(Ok(v)) => match *v {
-            Value::Str(s) => s,
+            Value::Str(s) => Rc::unwrap_or_clone(s),
             _ => return Ok(err_val("file_write_json_pretty: serialization returned non-string".into())),
         },
         Value::Result(Err(e)) => return Ok(Value::Result(Err(e))),
@@ -442,7 +440,7 @@ fn fs_file_ext(args: Vec<Value>) -> Result<Value, String> {
         .and_then(|e| e.to_str())
         .unwrap_or("")
         .to_string();
-    Ok(Value::Str(ext))
+    Ok(Value::str(ext))
 }
 
 /// file_name(path: String) -> String
@@ -460,7 +458,7 @@ fn fs_file_name(args: Vec<Value>) -> Result<Value, String> {
         .and_then(|n| n.to_str())
         .unwrap_or("")
         .to_string();
-    Ok(Value::Str(name))
+    Ok(Value::str(name))
 }
 
 /// file_stem(path: String) -> String
@@ -477,7 +475,7 @@ fn fs_file_stem(args: Vec<Value>) -> Result<Value, String> {
         .and_then(|s| s.to_str())
         .unwrap_or("")
         .to_string();
-    Ok(Value::Str(stem))
+    Ok(Value::str(stem))
 }
 
 /// file_modified(path: String) -> Result<Int, String>
@@ -725,7 +723,7 @@ fn fs_path_join(args: Vec<Value>) -> Result<Value, String> {
     let a = require_str(&args, 0, "path_join(a, b)")?;
     let b = require_str(&args, 1, "path_join(a, b)")?;
     let joined = Path::new(&a).join(&b);
-    Ok(Value::Str(joined.to_string_lossy().to_string()))
+    Ok(Value::str(joined.to_string_lossy().to_string()))
 }
 
 /// path_abs(path: String) -> Result<String, String>
@@ -742,7 +740,7 @@ fn fs_path_join(args: Vec<Value>) -> Result<Value, String> {
 fn fs_path_abs(args: Vec<Value>) -> Result<Value, String> {
     let path = require_str(&args, 0, "path_abs(path)")?;
     match fs::canonicalize(&path) {
-        Ok(p)  => Ok(ok_val(Value::Str(p.to_string_lossy().to_string()))),
+        Ok(p)  => Ok(ok_val(Value::str(p.to_string_lossy().to_string()))),
         Err(e) => Ok(err_val(format!("path_abs '{}': {}", path, e))),
     }
 }
@@ -762,7 +760,7 @@ fn fs_path_parent(args: Vec<Value>) -> Result<Value, String> {
         .parent()
         .map(|p| if p.as_os_str().is_empty() { ".".to_string() } else { p.to_string_lossy().to_string() })
         .unwrap_or_else(|| ".".to_string());
-    Ok(Value::Str(parent))
+    Ok(Value::str(parent))
 }
 
 /// path_exists(path: String) -> Bool
@@ -803,14 +801,14 @@ fn fs_path_expand(args: Vec<Value>) -> Result<Value, String> {
     let path = require_str(&args, 0, "path_expand(path)")?;
     let expanded = if path == "~" {
         home_dir()
-    } else if path.starts_with("~/") {
-        format!("{}/{}", home_dir(), &path[2..])
-    } else if path.starts_with("~\\") {
-        format!("{}\\{}", home_dir(), &path[2..])
+    } else if let Some(rest) = path.strip_prefix("~/") {
+        format!("{}/{}", home_dir(), rest)
+    } else if let Some(rest) = path.strip_prefix("~\\") {
+        format!("{}\\{}", home_dir(), rest)
     } else {
         path
     };
-    Ok(Value::Str(expanded))
+    Ok(Value::str(expanded))
 }
 
 // ═══════════════════════════════════════════════════════════
@@ -834,7 +832,7 @@ fn fs_path_expand(args: Vec<Value>) -> Result<Value, String> {
 ///       Err(e) => println(e)
 ///   }
 fn fs_temp_file(args: Vec<Value>) -> Result<Value, String> {
-    let prefix = args.get(0)
+    let prefix = args.first()
         .map(|v| format!("{}", v))
         .unwrap_or_else(|| "zephyr_".to_string());
     let tmp_dir = std::env::temp_dir();
@@ -845,7 +843,7 @@ fn fs_temp_file(args: Vec<Value>) -> Result<Value, String> {
         .unwrap_or(0);
     let path = tmp_dir.join(format!("{}{}", prefix, ts));
     match fs::File::create(&path) {
-        Ok(_)  => Ok(ok_val(Value::Str(path.to_string_lossy().to_string()))),
+        Ok(_)  => Ok(ok_val(Value::str(path.to_string_lossy().to_string()))),
         Err(e) => Ok(err_val(format!("temp_file: {}", e))),
     }
 }
@@ -865,7 +863,7 @@ fn fs_temp_file(args: Vec<Value>) -> Result<Value, String> {
 ///       Err(e) => println(e)
 ///   }
 fn fs_temp_dir(args: Vec<Value>) -> Result<Value, String> {
-    let prefix = args.get(0)
+    let prefix = args.first()
         .map(|v| format!("{}", v))
         .unwrap_or_else(|| "zephyr_".to_string());
     let tmp_dir = std::env::temp_dir();
@@ -875,7 +873,7 @@ fn fs_temp_dir(args: Vec<Value>) -> Result<Value, String> {
         .unwrap_or(0);
     let path = tmp_dir.join(format!("{}{}", prefix, ts));
     match fs::create_dir_all(&path) {
-        Ok(_)  => Ok(ok_val(Value::Str(path.to_string_lossy().to_string()))),
+        Ok(_)  => Ok(ok_val(Value::str(path.to_string_lossy().to_string()))),
         Err(e) => Ok(err_val(format!("temp_dir: {}", e))),
     }
 }
@@ -886,7 +884,7 @@ fn fs_temp_dir(args: Vec<Value>) -> Result<Value, String> {
 
 fn require_str(args: &[Value], idx: usize, sig: &str) -> Result<String, String> {
     match args.get(idx) {
-        Some(Value::Str(s)) => Ok(s.clone()),
+        Some(Value::Str(s)) => Ok((**s).clone()),
         Some(other) => Ok(format!("{}", other)),
         None => Err(format!("{}: argument {} is required", sig, idx + 1)),
     }
@@ -897,7 +895,7 @@ fn ok_val(v: Value) -> Value {
 }
 
 fn err_val(msg: String) -> Value {
-    Value::Result(std::result::Result::Err(Box::new(Value::Str(msg))))
+    Value::Result(std::result::Result::Err(Box::new(Value::str(msg))))
 }
 
 fn home_dir() -> String {
@@ -934,24 +932,24 @@ fn read_dir_entries(path: &str, full_paths: bool, with_info: bool) -> Result<Val
             let is_dir  = meta.as_ref().map(|m| m.is_dir()).unwrap_or(false);
             let is_file = meta.as_ref().map(|m| m.is_file()).unwrap_or(false);
             let size    = meta.as_ref().map(|m| m.len() as i64).unwrap_or(0);
-            let mut map = HashMap::new();
-            map.insert("name".into(),    Value::Str(name));
-            map.insert("path".into(),    Value::Str(full_path.to_string_lossy().to_string()));
+            let mut map = IndexMap::new();
+            map.insert("name".into(),    Value::str(name));
+            map.insert("path".into(),    Value::str(full_path.to_string_lossy().to_string()));
             map.insert("is_dir".into(),  Value::Bool(is_dir));
             map.insert("is_file".into(), Value::Bool(is_file));
             map.insert("size".into(),    Value::Int(size));
-            Value::Map(Rc::new(RefCell::new(map)))
+            Value::map(map)
         }).collect();
-        Ok(ok_val(Value::List(Rc::new(RefCell::new(items)))))
+        Ok(ok_val(Value::list(items)))
     } else {
         let items: Vec<Value> = entries.into_iter().map(|(name, full_path)| {
             if full_paths {
-                Value::Str(full_path.to_string_lossy().to_string())
+                Value::str(full_path.to_string_lossy().to_string())
             } else {
-                Value::Str(name)
+                Value::str(name)
             }
         }).collect();
-        Ok(ok_val(Value::List(Rc::new(RefCell::new(items)))))
+        Ok(ok_val(Value::list(items)))
     }
 }
 